@@ -0,0 +1,58 @@
+//! Compares the send paths for a stream of `u64` events.
+//!
+//! Run with `cargo bench --bench send_overwrite_copy`. Each path sends into a channel that
+//! is kept full, so every send overwrites, then into one that is drained as it goes.
+
+use flume_overwrite::{OverwriteReceiver, OverwriteSender, bounded};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SENDS: u64 = 1_000_000;
+const RUNS: usize = 5;
+
+/// Returns the fastest of several runs of `send`, per send.
+fn measure(drain: bool, mut send: impl FnMut(&OverwriteSender<u64>, u64)) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let (sender, receiver): (_, OverwriteReceiver<u64>) = bounded(64);
+            let start = Instant::now();
+            for i in 0..SENDS {
+                send(&sender, black_box(i));
+                if drain {
+                    black_box(receiver.try_recv().ok());
+                }
+            }
+            start.elapsed()
+        })
+        .min()
+        .expect("at least one run")
+        / SENDS as u32
+}
+
+fn main() {
+    for (scenario, drain) in [("overwriting", false), ("draining", true)] {
+        let paths: [(&str, Duration); 3] = [
+            (
+                "send_overwrite",
+                measure(drain, |sender, i| {
+                    black_box(sender.send_overwrite(i).ok());
+                }),
+            ),
+            (
+                "send_overwrite_discard",
+                measure(drain, |sender, i| {
+                    black_box(sender.send_overwrite_discard(i).ok());
+                }),
+            ),
+            (
+                "send_overwrite_copy",
+                measure(drain, |sender, i| {
+                    black_box(sender.send_overwrite_copy(i));
+                }),
+            ),
+        ];
+        for (path, per_send) in paths {
+            println!("{scenario:<12} {path:<24} {per_send:>10.2?}/send");
+        }
+    }
+}
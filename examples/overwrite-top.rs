@@ -0,0 +1,117 @@
+//! Renders live per-channel depth and eviction rates in a terminal table.
+//!
+//! The channels are global, declared with `static_channel!`, and listed in a static
+//! registry that the monitor reads, just as the code of an application can reach them from
+//! anywhere. Simulated producers and consumers keep each channel busy at different rates.
+//!
+//! Run with `cargo run --example overwrite-top [ticks]`, where `ticks` is the number of
+//! refreshes to render before exiting, 20 by default.
+
+use flume_overwrite::{StaticChannel, Stats, static_channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const REFRESH: Duration = Duration::from_millis(500);
+
+static_channel!(TELEMETRY: u64 = 64);
+static_channel!(FRAMES: u64 = 4);
+static_channel!(COMMANDS: u64 = 16);
+
+/// Every channel the monitor renders.
+static REGISTRY: &[&StaticChannel<u64>] = &[&TELEMETRY, &FRAMES, &COMMANDS];
+
+/// A simulated load on a channel: a producer and a consumer, each running at a fixed
+/// interval.
+struct Workload {
+    channel: &'static StaticChannel<u64>,
+    produce_every: Duration,
+    consume_every: Duration,
+}
+
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        channel: &TELEMETRY,
+        produce_every: Duration::from_millis(1),
+        consume_every: Duration::from_millis(2),
+    },
+    Workload {
+        channel: &FRAMES,
+        produce_every: Duration::from_millis(16),
+        consume_every: Duration::from_millis(20),
+    },
+    Workload {
+        channel: &COMMANDS,
+        produce_every: Duration::from_millis(50),
+        consume_every: Duration::from_millis(5),
+    },
+];
+
+/// Runs a workload until the process exits, since global channels never disconnect.
+fn spawn(workload: &Workload) {
+    let (channel, produce_every) = (workload.channel, workload.produce_every);
+    thread::spawn(move || {
+        for i in 0.. {
+            let _ = channel.tx().send_overwrite(i);
+            thread::sleep(produce_every);
+        }
+    });
+    let (channel, consume_every) = (workload.channel, workload.consume_every);
+    thread::spawn(move || {
+        while channel.rx().recv().is_ok() {
+            channel.rx().heartbeat();
+            thread::sleep(consume_every);
+        }
+    });
+}
+
+fn snapshot() -> Vec<Stats> {
+    REGISTRY
+        .iter()
+        .map(|channel| channel.rx().stats())
+        .collect()
+}
+
+/// Computes a per-second rate from two counter readings.
+fn rate(now: u64, before: u64, elapsed: Duration) -> f64 {
+    now.saturating_sub(before) as f64 / elapsed.as_secs_f64()
+}
+
+fn render(rows: &[(Stats, Stats)], elapsed: Duration) {
+    // Clear the screen and move the cursor home
+    print!("\x1b[2J\x1b[H");
+    println!(
+        "{:<12} {:>9} {:>6} {:>10} {:>12}",
+        "CHANNEL", "DEPTH", "FILL", "SENT/S", "EVICTED/S"
+    );
+    for (now, before) in rows {
+        let fill = 100.0 * now.len as f64 / now.capacity as f64;
+        println!(
+            "{:<12} {:>9} {:>5.0}% {:>10.1} {:>12.1}",
+            now.name.as_deref().unwrap_or("<unnamed>"),
+            format!("{}/{}", now.len, now.capacity),
+            fill,
+            rate(now.sent, before.sent, elapsed),
+            rate(now.evicted, before.evicted, elapsed),
+        );
+    }
+}
+
+fn main() {
+    let ticks: usize = std::env::args()
+        .nth(1)
+        .and_then(|ticks| ticks.parse().ok())
+        .unwrap_or(20);
+    WORKLOADS.iter().for_each(spawn);
+
+    let mut before = snapshot();
+    let mut last = Instant::now();
+    for _ in 0..ticks {
+        thread::sleep(REFRESH);
+        let now = snapshot();
+        let elapsed = last.elapsed();
+        last = Instant::now();
+        let rows: Vec<(Stats, Stats)> = now.iter().cloned().zip(before).collect();
+        render(&rows, elapsed);
+        before = now;
+    }
+}
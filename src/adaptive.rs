@@ -0,0 +1,130 @@
+//! Automatic resizing of a channel's capacity to its load.
+
+use crate::counter::Counter;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub(crate) type CapacityHook = Box<dyn Fn(CapacityChange) + Send + Sync>;
+
+/// A change of a channel's capacity, as reported to
+/// [`Builder::on_capacity_change`](crate::Builder::on_capacity_change).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CapacityChange {
+    /// The capacity before the change.
+    pub from: usize,
+    /// The capacity after the change.
+    pub to: usize,
+}
+
+impl CapacityChange {
+    /// Returns `true` if the capacity grew.
+    pub fn is_growth(&self) -> bool {
+        self.to > self.from
+    }
+}
+
+/// Decides, once per window, whether a channel should grow or shrink.
+///
+/// A window in which messages were evicted doubles the capacity, up to the channel's
+/// maximum. A window in which the queue never held more than a quarter of the capacity
+/// halves it, down to the capacity the channel was built with.
+pub(crate) struct Adaptive {
+    min: usize,
+    window: Duration,
+    state: Mutex<Window>,
+}
+
+struct Window {
+    start: Instant,
+    /// The channel's eviction count when the window started.
+    evicted: u64,
+    /// The longest the queue has been during the window.
+    peak: usize,
+}
+
+impl Adaptive {
+    pub(crate) fn new(min: usize, window: Duration, now: Instant) -> Self {
+        Self {
+            min,
+            window,
+            state: Mutex::new(Window {
+                start: now,
+                evicted: 0,
+                peak: 0,
+            }),
+        }
+    }
+
+    /// Records the queue's length after a send, returning the capacity to switch to if
+    /// this ended a window calling for a change.
+    pub(crate) fn observe(
+        &self,
+        now: Instant,
+        len: usize,
+        evicted: u64,
+        cap: usize,
+        max: usize,
+    ) -> Option<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.peak = state.peak.max(len);
+        if now.saturating_duration_since(state.start) < self.window {
+            return None;
+        }
+        let target = if Counter::since(evicted, state.evicted) > 0 {
+            cap.saturating_mul(2).min(max)
+        } else if state.peak <= cap / 4 {
+            (cap / 2).max(self.min)
+        } else {
+            cap
+        };
+        *state = Window {
+            start: now,
+            evicted,
+            peak: len,
+        };
+        (target != cap).then_some(target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_windows_grow_and_shrink_within_bounds() {
+        let start = Instant::now();
+        let window = Duration::from_secs(1);
+        let adaptive = Adaptive::new(4, window, start);
+
+        // Nothing changes before the window ends, however the channel is doing
+        assert_eq!(adaptive.observe(start, 8, 3, 8, 32), None);
+        // Evictions during the window double the capacity
+        assert_eq!(adaptive.observe(start + window, 8, 3, 8, 32), Some(16));
+        assert_eq!(
+            adaptive.observe(start + window * 2, 16, 9, 16, 20),
+            Some(20)
+        );
+
+        // The next window starts as full as the queue was, so only the one after is quiet
+        assert_eq!(adaptive.observe(start + window * 3, 1, 9, 20, 20), None);
+
+        // A quiet window halves the capacity, but not below the initial capacity
+        assert_eq!(adaptive.observe(start + window * 4, 1, 9, 20, 20), Some(10));
+        assert_eq!(adaptive.observe(start + window * 5, 0, 9, 10, 20), Some(5));
+        assert_eq!(adaptive.observe(start + window * 6, 0, 9, 5, 20), Some(4));
+        assert_eq!(adaptive.observe(start + window * 7, 0, 9, 4, 20), None);
+    }
+
+    #[test]
+    fn test_busy_window_keeps_the_capacity() {
+        let start = Instant::now();
+        let window = Duration::from_millis(10);
+        let adaptive = Adaptive::new(2, window, start);
+
+        // The peak, not the final length, decides whether the queue was busy
+        adaptive.observe(start, 5, 0, 8, 8);
+        assert_eq!(adaptive.observe(start + window, 0, 0, 8, 8), None);
+        assert!(CapacityChange { from: 4, to: 8 }.is_growth());
+        assert!(!CapacityChange { from: 8, to: 4 }.is_growth());
+    }
+}
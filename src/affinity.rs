@@ -0,0 +1,216 @@
+//! Messages attributed to the sender clone that sent them, and eviction that prefers a
+//! sender's own earlier messages.
+
+use crate::{Builder, OverflowPolicy, OverwriteSender, SendOverwriteError, SenderId};
+use std::ops::Deref;
+
+/// A message sent with [`OverwriteSender::send_attributed`] or
+/// [`OverwriteSender::send_overwrite_own`], along with the id of the sender clone that sent
+/// it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attributed<T> {
+    sender: SenderId,
+    value: T,
+}
+
+impl<T> Attributed<T> {
+    /// Returns the id of the sender clone that sent the message.
+    pub fn sender(&self) -> SenderId {
+        self.sender
+    }
+
+    /// Returns the message.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Attributed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> Builder<Attributed<T>> {
+    /// Counts evicted messages per sender clone that sent them, in
+    /// [`Stats::evicted_by_sender`](crate::Stats::evicted_by_sender).
+    ///
+    /// This attributes loss to specific producers, whichever way the messages were evicted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (camera, receiver) = Builder::new(2).attribute_evictions().build();
+    /// let lidar = camera.clone();
+    ///
+    /// camera.send_attributed("frame 1").unwrap();
+    /// lidar.send_attributed("scan 1").unwrap();
+    /// camera.send_attributed("frame 2").unwrap();
+    ///
+    /// let stats = receiver.stats();
+    /// assert_eq!(stats.evicted_by_sender.get(&camera.id()), Some(&1));
+    /// assert_eq!(stats.evicted_by_sender.get(&lidar.id()), None);
+    /// ```
+    pub fn attribute_evictions(mut self) -> Self {
+        self.attribute = Some(Attributed::sender);
+        self
+    }
+}
+
+impl<T> OverwriteSender<Attributed<T>> {
+    /// Sends a value with overwrite semantics, recording the [`SenderId`] of this clone in
+    /// its envelope.
+    ///
+    /// Receivers tell the producer of a message from [`Attributed::sender`], and the
+    /// returned overwritten messages keep their envelope, so loss can be attributed too.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<Attributed<T>>))` - The message was sent and the returned vector
+    ///   contains the messages that were overwritten, along with their senders
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (first, receiver) = bounded(1);
+    /// let second = first.clone();
+    ///
+    /// first.send_attributed(1).unwrap();
+    /// let overwritten = second.send_attributed(2).unwrap().unwrap();
+    /// assert_eq!(overwritten[0].sender(), first.id());
+    ///
+    /// let message = receiver.recv().unwrap();
+    /// assert_eq!((message.sender(), *message), (second.id(), 2));
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn send_attributed(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<Attributed<T>>>, SendOverwriteError<T>> {
+        let message = Attributed {
+            sender: self.id,
+            value,
+        };
+        self.send_overwrite(message)
+            .map_err(|error| error.map(Attributed::into_inner))
+    }
+
+    /// Sends a value, evicting this sender clone's own oldest queued message first if the
+    /// channel is at capacity.
+    ///
+    /// Every message records the [`SenderId`] of the clone that sent it. When the channel
+    /// is full, the oldest message sent by this clone makes room, so a producer's burst
+    /// only overwrites its own data; messages of other producers are evicted as usual only
+    /// once this clone has nothing queued. [Protected](crate::Builder::protect) messages
+    /// are never evicted, and the channel's policy and eviction budget apply as for
+    /// [`send_overwrite`](OverwriteSender::send_overwrite).
+    ///
+    /// Evicting a message from the middle of the queue drains and refills it while other
+    /// sends wait, so receivers may momentarily find the channel empty but never observe a
+    /// reordering.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sensor, receiver) = bounded(3);
+    /// let logger = sensor.clone();
+    ///
+    /// logger.send_overwrite_own("log 1").unwrap();
+    /// sensor.send_overwrite_own("reading 1").unwrap();
+    /// sensor.send_overwrite_own("reading 2").unwrap();
+    ///
+    /// // The sensor's burst overwrites its own reading, not the older log line
+    /// assert_eq!(sensor.send_overwrite_own("reading 3").unwrap(), Some(vec!["reading 1"]));
+    ///
+    /// let message = receiver.recv().unwrap();
+    /// assert_eq!((message.sender(), *message), (logger.id(), "log 1"));
+    /// ```
+    pub fn send_overwrite_own(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let message = Attributed {
+            sender: self.id,
+            value,
+        };
+        let _sending = self.shared.lock_sends();
+        let mut evicted = Vec::new();
+        let mut evicted_own = false;
+        if self.sender.len() >= self.shared.capacity.regular()
+            && !self.is_orphaned()
+            && !self.shared.is_oversized(&message)
+            && self.shared.policy.load() == OverflowPolicy::DropOldest
+        {
+            let mut queued: Vec<Attributed<T>> = self.receiver.drain().collect();
+            let protect = self.shared.protect.as_ref();
+            let own = queued.iter().position(|queued| {
+                queued.sender == self.id && protect.is_none_or(|protect| !protect(queued))
+            });
+            match own {
+                Some(index) if self.claim_evictions(1) => {
+                    let oldest = queued.remove(index);
+                    self.requeue_locked(queued);
+                    self.shared.count_evictions(1);
+                    self.shared.count_classes(std::slice::from_ref(&oldest));
+                    self.shared.check_strict(std::slice::from_ref(&oldest));
+                    evicted.push(oldest.value);
+                    evicted_own = true;
+                }
+                // Otherwise fall back to the usual eviction
+                _ => self.requeue_locked(queued),
+            }
+        }
+        if let Some(overwritten) = self
+            .send_overwrite_locked(message)
+            .map_err(|error| error.map(Attributed::into_inner))?
+        {
+            evicted.extend(overwritten.into_iter().map(Attributed::into_inner));
+        }
+        if evicted_own {
+            self.shared.check_stalled_locked();
+        }
+        Ok(if evicted.is_empty() {
+            None
+        } else {
+            Some(evicted)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    #[test]
+    fn test_falls_back_to_other_senders_messages() {
+        let (first, receiver) = bounded(2);
+        let second = first.clone();
+        first.send_overwrite_own(1).unwrap();
+        first.send_overwrite_own(2).unwrap();
+
+        // The second sender has nothing queued, so the oldest message goes
+        assert_eq!(second.send_overwrite_own(3).unwrap(), Some(vec![1]));
+        assert_eq!(second.send_overwrite_own(4).unwrap(), Some(vec![3]));
+        assert_eq!(receiver.stats().evicted, 2);
+
+        let received: Vec<_> = receiver
+            .drain()
+            .map(|message| message.into_inner())
+            .collect();
+        assert_eq!(received, vec![2, 4]);
+    }
+}
@@ -0,0 +1,94 @@
+//! Capacity that regenerates over time.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// A token bucket granting queue growth: each token lets one send add a message instead
+/// of overwriting one.
+pub(crate) struct TokenBucket {
+    per_sec: f64,
+    burst: f64,
+    state: Mutex<Option<Refill>>,
+}
+
+/// The tokens left at the last refill, which starts with a full bucket on first use.
+struct Refill {
+    tokens: f64,
+    at: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(per_sec: f64, burst: usize) -> Self {
+        Self {
+            per_sec,
+            burst: burst as f64,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Takes a token at `now`, returning `false` if the bucket is empty.
+    pub(crate) fn try_take(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let refill = self.refill(&mut state, now);
+        if refill.tokens < 1.0 {
+            return false;
+        }
+        refill.tokens -= 1.0;
+        true
+    }
+
+    /// Returns the number of whole tokens available at `now`.
+    pub(crate) fn available(&self, now: Instant) -> usize {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        self.refill(&mut state, now).tokens as usize
+    }
+
+    fn refill<'a>(&self, state: &'a mut Option<Refill>, now: Instant) -> &'a mut Refill {
+        let refill = state.get_or_insert(Refill {
+            tokens: self.burst,
+            at: now,
+        });
+        let elapsed = now.saturating_duration_since(refill.at).as_secs_f64();
+        refill.tokens = (refill.tokens + elapsed * self.per_sec).min(self.burst);
+        refill.at = now;
+        refill
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_failed_send_keeps_its_token() {
+        let (sender, receiver) = crate::Builder::new(2).token_bucket(0.001, 1).build();
+        drop(receiver);
+        assert!(sender.send_overwrite(1).unwrap_err().is_disconnected());
+        assert_eq!(sender.available_tokens(), Some(1));
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_tokens_regenerate() {
+        use crate::{Builder, MockClock};
+        use std::time::Duration;
+
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(4)
+            .token_bucket(2.0, 2)
+            .clock(clock.clone())
+            .build();
+        for i in 0..4 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert_eq!(receiver.len(), 2);
+        assert!(sender.try_send(4).is_err());
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(sender.available_tokens(), Some(1));
+        sender.try_send(4).unwrap();
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2, 3, 4]);
+
+        // An empty queue takes a message without a token
+        sender.send_overwrite(5).unwrap();
+        assert_eq!(receiver.stats().evicted, 2);
+    }
+}
@@ -0,0 +1,93 @@
+//! Limits on how many messages may be evicted within a time window.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A maximum number of evictions per fixed time window.
+pub(crate) struct EvictionBudget {
+    max: u64,
+    window: Duration,
+    state: Mutex<Option<Window>>,
+}
+
+/// The current window, starting with the first eviction claimed after the previous one ended.
+struct Window {
+    start: Instant,
+    used: u64,
+}
+
+impl EvictionBudget {
+    pub(crate) fn new(max: u64, window: Duration) -> Self {
+        Self {
+            max,
+            window,
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Claims `evictions` from the budget at `now`, returning `false` without claiming
+    /// anything if that would exceed the budget of the current window.
+    pub(crate) fn try_claim(&self, evictions: u64, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let window = match &mut *state {
+            Some(window) if now.duration_since(window.start) < self.window => window,
+            state => state.insert(Window {
+                start: now,
+                used: 0,
+            }),
+        };
+        if window.used + evictions > self.max {
+            return false;
+        }
+        window.used += evictions;
+        true
+    }
+
+    /// Returns whether [`try_claim`](EvictionBudget::try_claim) would succeed, without
+    /// claiming anything.
+    pub(crate) fn can_claim(&self, evictions: u64, now: Instant) -> bool {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let used = match &*state {
+            Some(window) if now.duration_since(window.start) < self.window => window.used,
+            _ => 0,
+        };
+        used + evictions <= self.max
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_budget_renews_with_the_window() {
+        let start = Instant::now();
+        let window = Duration::from_secs(1);
+        let budget = EvictionBudget::new(3, window);
+
+        assert!(budget.try_claim(2, start));
+        // A claim that would overdraw the budget takes nothing
+        assert!(!budget.try_claim(2, start));
+        assert!(budget.can_claim(1, start));
+        assert!(budget.try_claim(1, start + window / 2));
+        assert!(!budget.can_claim(1, start + window / 2));
+
+        // The next window starts with the first claim after the previous one ended
+        let later = start + window * 3;
+        assert!(budget.can_claim(3, later));
+        assert!(budget.try_claim(3, later));
+        assert!(!budget.try_claim(1, later + window / 2));
+        assert!(budget.try_claim(1, later + window));
+    }
+
+    #[test]
+    fn test_checking_claims_nothing() {
+        let now = Instant::now();
+        let budget = EvictionBudget::new(1, Duration::from_secs(1));
+        for _ in 0..3 {
+            assert!(budget.can_claim(1, now));
+        }
+        assert!(!budget.can_claim(2, now));
+        assert!(budget.try_claim(1, now));
+    }
+}
@@ -0,0 +1,335 @@
+//! A sender that stages messages locally and flushes them in batches.
+
+use crate::OverwriteSender;
+use crate::SendOverwriteError;
+use flume::RecvTimeoutError;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A sender that accumulates messages in a local buffer before flushing them to the channel.
+///
+/// Created by [`OverwriteSender::buffered`]. Messages are staged locally until `n` of them
+/// have been collected, at which point the whole batch is sent with overwrite semantics in
+/// a single call. This reduces contention on the shared channel for producers that emit many
+/// small messages.
+///
+/// The buffer can be flushed explicitly with [`flush`](BufferedSender::flush), periodically
+/// with [`flush_every`](BufferedSender::flush_every), and is flushed automatically when the
+/// `BufferedSender` is dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+///
+/// let (sender, receiver) = bounded(4);
+/// let mut buffered = sender.buffered(2);
+///
+/// // Staged messages count towards the channel's length, but cannot be received yet
+/// buffered.send_overwrite(1).unwrap();
+/// assert_eq!(receiver.len(), 1);
+/// assert!(receiver.try_recv().is_err());
+///
+/// // The second message fills the local buffer and flushes both
+/// buffered.send_overwrite(2).unwrap();
+/// assert_eq!(receiver.len(), 2);
+///
+/// buffered.send_overwrite(3).unwrap();
+/// drop(buffered);
+/// assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+pub struct BufferedSender<T> {
+    staging: Arc<Staging<T>>,
+    timer: Option<FlushTimer>,
+}
+
+/// The local buffer, shared with the flush timer when one is running.
+struct Staging<T> {
+    sender: OverwriteSender<T>,
+    buffer: Mutex<Vec<T>>,
+    batch_size: usize,
+}
+
+impl<T> Staging<T> {
+    fn flush(&self) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        // The lock is held while sending so that concurrent flushes cannot reorder batches.
+        let mut buffer = self.lock();
+        self.flush_locked(&mut buffer)
+    }
+
+    /// Flushes on behalf of the timer, which has no one to hand a failed batch to: the
+    /// messages that could not be sent are staged again, ahead of those staged since, for
+    /// the next flush. Returns `false` once the channel is disconnected.
+    fn flush_or_restage(&self) -> bool {
+        let mut buffer = self.lock();
+        match self.flush_locked(&mut buffer) {
+            Ok(_) => true,
+            Err(error) => {
+                let disconnected = error.is_disconnected();
+                let mut unsent = error.into_inner();
+                self.count_staged(unsent.len(), true);
+                unsent.append(&mut buffer);
+                *buffer = unsent;
+                !disconnected
+            }
+        }
+    }
+
+    fn flush_locked(
+        &self,
+        buffer: &mut Vec<T>,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        if buffer.is_empty() {
+            return Ok(None);
+        }
+        let batch = std::mem::replace(buffer, Vec::with_capacity(self.batch_size));
+        self.count_staged(batch.len(), false);
+        self.sender.send_overwrite_batch(batch)
+    }
+
+    /// Tracks the staged messages in the channel's length, see [`OverwriteSender::len`].
+    fn count_staged(&self, n: usize, staged: bool) {
+        let counter = &self.sender.shared.staged;
+        if staged {
+            counter.fetch_add(n, Ordering::AcqRel);
+        } else {
+            counter.fetch_sub(n, Ordering::AcqRel);
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<T>> {
+        self.buffer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A background thread flushing the buffer on a fixed interval.
+struct FlushTimer {
+    stop: flume::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl<T> BufferedSender<T> {
+    pub(crate) fn new(sender: OverwriteSender<T>, batch_size: usize) -> Self {
+        let batch_size = batch_size.max(1);
+        Self {
+            staging: Arc::new(Staging {
+                sender,
+                buffer: Mutex::new(Vec::with_capacity(batch_size)),
+                batch_size,
+            }),
+            timer: None,
+        }
+    }
+
+    /// Flushes the local buffer every `interval`, so staged messages never linger longer
+    /// than that before reaching the channel.
+    ///
+    /// The timer runs on a dedicated thread rather than an async runtime, so it works the
+    /// same regardless of which executor (if any) the producer uses. Messages a periodic
+    /// flush fails to send, for example because the eviction budget is exhausted, stay
+    /// staged and are retried by the next one. The thread stops when the `BufferedSender`
+    /// is dropped or the channel is disconnected. Calling this again replaces the previous
+    /// timer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let mut buffered = sender.buffered(100).flush_every(Duration::from_millis(10));
+    ///
+    /// buffered.send_overwrite("tick").unwrap();
+    /// assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), "tick");
+    /// ```
+    pub fn flush_every(mut self, interval: Duration) -> Self
+    where
+        T: Send + 'static,
+    {
+        self.stop_timer();
+        let (stop, stopped) = flume::bounded(0);
+        let staging = self.staging.clone();
+        let handle = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if !staging.flush_or_restage() {
+                    break;
+                }
+            }
+        });
+        self.timer = Some(FlushTimer { stop, handle });
+        self
+    }
+
+    /// Stages a value, flushing the local buffer once it holds a full batch.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The value was staged, or the flush did not overwrite any messages
+    /// - `Ok(Some(Vec<T>))` - The buffer was flushed and the returned vector contains
+    ///   the messages that were overwritten
+    /// - `Err(SendOverwriteError<Vec<T>>)` - The channel is disconnected or the eviction budget
+    ///   is exhausted; the error holds every message of the batch that could not be sent
+    pub fn send_overwrite(
+        &mut self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        let staged = {
+            let mut buffer = self.staging.lock();
+            buffer.push(value);
+            self.staging.count_staged(1, true);
+            buffer.len()
+        };
+        if staged >= self.staging.batch_size {
+            self.flush()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Sends every staged message to the channel with overwrite semantics.
+    ///
+    /// Returns the same results as [`send_overwrite`](BufferedSender::send_overwrite).
+    pub fn flush(&mut self) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        self.staging.flush()
+    }
+
+    /// Returns the number of messages staged in the local buffer.
+    ///
+    /// Staged messages are also counted by the channel's own
+    /// [`len`](OverwriteSender::len), so that its [`remaining`](OverwriteSender::remaining)
+    /// slots account for the next flush.
+    pub fn len(&self) -> usize {
+        self.staging.lock().len()
+    }
+
+    /// Returns `true` if no messages are staged in the local buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of messages staged before an automatic flush.
+    pub fn batch_size(&self) -> usize {
+        self.staging.batch_size
+    }
+
+    fn stop_timer(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            drop(timer.stop);
+            let _ = timer.handle.join();
+        }
+    }
+}
+
+impl<T> fmt::Debug for BufferedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferedSender")
+            .field("sender", &self.staging.sender)
+            .field("staged", &self.len())
+            .field("batch_size", &self.staging.batch_size)
+            .field("flush_timer", &self.timer.is_some())
+            .finish()
+    }
+}
+
+impl<T> Drop for BufferedSender<T> {
+    fn drop(&mut self) {
+        self.stop_timer();
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Builder, bounded};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_buffered_flushes_full_batches() {
+        let (sender, receiver) = bounded(10);
+        let mut buffered = sender.buffered(3);
+        assert_eq!(buffered.send_overwrite(1).unwrap(), None);
+        assert_eq!(buffered.send_overwrite(2).unwrap(), None);
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.remaining(), 8);
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(buffered.send_overwrite(3).unwrap(), None);
+        assert!(buffered.is_empty());
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_buffered_flush_overwrites() {
+        let (sender, receiver) = bounded(2);
+        sender.send_overwrite(0).unwrap();
+        let mut buffered = sender.buffered(3);
+        buffered.send_overwrite(1).unwrap();
+        buffered.send_overwrite(2).unwrap();
+        let drained = buffered.send_overwrite(3).unwrap();
+        assert_eq!(drained, Some(vec![0, 1]));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_buffered_flush_on_drop() {
+        let (sender, receiver) = bounded(4);
+        let mut buffered = sender.buffered(8);
+        buffered.send_overwrite(1).unwrap();
+        buffered.send_overwrite(2).unwrap();
+        assert_eq!(buffered.len(), 2);
+        drop(buffered);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_buffered_flush_every_interval() {
+        let (sender, receiver) = bounded(4);
+        let mut buffered = sender.buffered(100).flush_every(Duration::from_millis(5));
+        buffered.send_overwrite(1).unwrap();
+        buffered.send_overwrite(2).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 2);
+        assert!(buffered.is_empty());
+    }
+
+    #[test]
+    fn test_buffered_flush_every_stops_on_drop() {
+        let (sender, receiver) = bounded(4);
+        let mut buffered = sender.buffered(100).flush_every(Duration::from_secs(60));
+        buffered.send_overwrite(1).unwrap();
+        drop(buffered);
+        drop(sender);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1]);
+        // The timer thread released its sender, so the channel is disconnected
+        assert!(receiver.is_disconnected());
+    }
+
+    #[test]
+    fn test_buffered_disconnected_returns_batch() {
+        let (sender, receiver) = bounded(4);
+        let mut buffered = sender.buffered(4);
+        buffered.send_overwrite(1).unwrap();
+        buffered.send_overwrite(2).unwrap();
+        drop(receiver);
+        assert_eq!(buffered.flush().unwrap_err().into_inner(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_flush_every_keeps_rejected_batches() {
+        let (sender, receiver) = Builder::new(1).protect(|value| *value == 0).build();
+        sender.send_overwrite(0).unwrap();
+        let mut buffered = sender.buffered(100).flush_every(Duration::from_millis(5));
+        buffered.send_overwrite(1).unwrap();
+
+        // Flushes are rejected while the protected message holds the only slot
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(buffered.len(), 1);
+        assert_eq!(receiver.recv().unwrap(), 0);
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+        assert!(buffered.is_empty());
+    }
+}
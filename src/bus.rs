@@ -0,0 +1,451 @@
+//! A lightweight in-process event bus routing events to per-type overwrite channels.
+//!
+//! A [`Bus`] holds one overwrite channel per registered event type, and an [`AnySender`]
+//! routes any event to the channel of its type. An [`EventBus`] goes further and fans every
+//! event out to all subscribers of its type, each with a channel of its own. Either way, a
+//! consumer that falls behind only loses its own oldest events, so a slow subscriber never
+//! stalls the publishers. Events must be `Serialize`, so everything on the bus can also be
+//! recorded or forwarded out of process.
+//!
+//! This module is only available with the `bus` feature.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use serde::Serialize;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+type Channels = Arc<RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>;
+type Filter<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// A subscriber of an [`EventBus`], with the filter it registered, if any.
+struct Subscriber<T> {
+    sender: OverwriteSender<T>,
+    filter: Option<Filter<T>>,
+}
+
+impl<T> Subscriber<T> {
+    fn wants(&self, event: &T) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter(event))
+    }
+}
+
+impl<T> Clone for Subscriber<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            filter: self.filter.clone(),
+        }
+    }
+}
+
+/// A registry of per-type overwrite channels.
+///
+/// Cloning a bus returns another handle to the same registry.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bus::Bus;
+/// use serde::Serialize;
+///
+/// #[derive(Debug, PartialEq, Serialize)]
+/// struct Moved(i32, i32);
+///
+/// #[derive(Debug, PartialEq, Serialize)]
+/// struct Clicked;
+///
+/// let bus = Bus::new();
+/// let moves = bus.register::<Moved>(1);
+/// let clicks = bus.register::<Clicked>(8);
+///
+/// let sender = bus.sender();
+/// sender.send_overwrite(Moved(0, 0)).unwrap();
+/// sender.send_overwrite(Clicked).unwrap();
+///
+/// // Only the latest position is kept
+/// let overwritten = sender.send_overwrite(Moved(4, 2)).unwrap();
+/// assert_eq!(overwritten, Some(vec![Moved(0, 0)]));
+///
+/// assert_eq!(moves.recv().unwrap(), Moved(4, 2));
+/// assert_eq!(clicks.recv().unwrap(), Clicked);
+///
+/// // Nobody subscribed to strings
+/// assert!(sender.send_overwrite("ignored").unwrap_err().is_disconnected());
+/// ```
+#[derive(Clone, Default)]
+pub struct Bus {
+    channels: Channels,
+}
+
+impl Bus {
+    /// Creates an empty bus.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an overwrite channel of capacity `cap` for events of type `T`, returning its
+    /// receiver.
+    ///
+    /// Registering a type again replaces its channel, so the receivers of the previous one
+    /// observe the disconnection once they have drained it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is 0.
+    pub fn register<T>(&self, cap: usize) -> OverwriteReceiver<T>
+    where
+        T: Serialize + Send + 'static,
+    {
+        let (sender, receiver) = bounded::<T>(cap);
+        self.channels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(TypeId::of::<T>(), Box::new(sender));
+        receiver
+    }
+
+    /// Returns `true` if a channel is registered for events of type `T`.
+    pub fn is_registered<T: 'static>(&self) -> bool {
+        self.channels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns a sender routing events to the channels of this bus.
+    pub fn sender(&self) -> AnySender {
+        AnySender {
+            channels: self.channels.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channels = self.channels.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("Bus")
+            .field("channels", &channels.len())
+            .finish()
+    }
+}
+
+/// A type-erased sender accepting events of any registered type.
+///
+/// Channels stay connected as long as their [`Bus`] or any `AnySender` is alive.
+#[derive(Clone)]
+pub struct AnySender {
+    channels: Channels,
+}
+
+impl AnySender {
+    /// Sends an event to the channel registered for its type, overwriting the oldest events
+    /// if that channel is at capacity.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The event was sent without overwriting any existing events
+    /// - `Ok(Some(Vec<T>))` - The event was sent and the returned vector contains the events
+    ///   that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - No channel is registered for `T`, or every receiver
+    ///   of its channel has been dropped
+    pub fn send_overwrite<T>(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>>
+    where
+        T: Serialize + Send + 'static,
+    {
+        // Release the registry before sending, so hooks may register channels
+        let sender = self
+            .channels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&TypeId::of::<T>())
+            .and_then(|sender| sender.downcast_ref::<OverwriteSender<T>>())
+            .cloned();
+        match sender {
+            Some(sender) => sender.send_overwrite(value),
+            None => Err(SendOverwriteError::Disconnected(value)),
+        }
+    }
+}
+
+impl fmt::Debug for AnySender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnySender").finish_non_exhaustive()
+    }
+}
+
+/// A publish/subscribe bus fanning events out to every subscriber of their type.
+///
+/// Each subscriber gets its own overwrite channel, so it only lags behind on its own: when
+/// its channel is full, its oldest events are evicted and counted in
+/// [`Stats::evicted`](crate::Stats::evicted) of its receiver's
+/// [`stats`](OverwriteReceiver::stats). Dropping a receiver unsubscribes it.
+///
+/// Cloning a bus returns another handle to the same subscriptions.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bus::EventBus;
+/// use serde::Serialize;
+///
+/// #[derive(Clone, Debug, PartialEq, Serialize)]
+/// struct Resized(u32, u32);
+///
+/// let bus = EventBus::new();
+/// let layout = bus.subscribe::<Resized>(8);
+/// let minimap = bus.subscribe::<Resized>(1);
+///
+/// assert_eq!(bus.publish(Resized(640, 480)), 2);
+/// assert_eq!(bus.publish(Resized(800, 600)), 2);
+///
+/// assert_eq!(layout.recv().unwrap(), Resized(640, 480));
+/// assert_eq!(minimap.recv().unwrap(), Resized(800, 600));
+///
+/// // The minimap lagged behind by one event
+/// assert_eq!(layout.stats().evicted, 0);
+/// assert_eq!(minimap.stats().evicted, 1);
+/// ```
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Channels,
+}
+
+impl EventBus {
+    /// Creates a bus without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Subscribes to events of type `T` through a new overwrite channel of capacity `cap`.
+    ///
+    /// The subscription lasts until every clone of the returned receiver is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is 0.
+    pub fn subscribe<T>(&self, cap: usize) -> OverwriteReceiver<T>
+    where
+        T: Serialize + Clone + Send + 'static,
+    {
+        self.add_subscriber(cap, None)
+    }
+
+    /// Subscribes to the events of type `T` matching `filter`, through a new overwrite
+    /// channel of capacity `cap`.
+    ///
+    /// The filter runs at publish time, so events the subscriber does not care about never
+    /// take up room in its channel, nor count towards its lag.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bus::EventBus;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Clone, Debug, PartialEq, Serialize)]
+    /// struct Log {
+    ///     level: u8,
+    ///     line: &'static str,
+    /// }
+    ///
+    /// let bus = EventBus::new();
+    /// let alerts = bus.subscribe_filtered::<Log, _>(1, |log| log.level >= 4);
+    ///
+    /// bus.publish(Log { level: 4, line: "disk full" });
+    /// assert_eq!(bus.publish(Log { level: 1, line: "tick" }), 0);
+    ///
+    /// assert_eq!(alerts.recv().unwrap().line, "disk full");
+    /// assert_eq!(alerts.stats().evicted, 0);
+    /// ```
+    pub fn subscribe_filtered<T, F>(&self, cap: usize, filter: F) -> OverwriteReceiver<T>
+    where
+        T: Serialize + Clone + Send + 'static,
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.add_subscriber(cap, Some(Arc::new(filter)))
+    }
+
+    fn add_subscriber<T>(&self, cap: usize, filter: Option<Filter<T>>) -> OverwriteReceiver<T>
+    where
+        T: Send + 'static,
+    {
+        let (sender, receiver) = bounded::<T>(cap);
+        self.subscribers
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Vec::<Subscriber<T>>::new()))
+            .downcast_mut::<Vec<Subscriber<T>>>()
+            .expect("subscribers are keyed by their type")
+            .push(Subscriber { sender, filter });
+        receiver
+    }
+
+    /// Returns the number of live subscribers to events of type `T`.
+    pub fn subscriber_count<T: 'static>(&self) -> usize {
+        self.subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&TypeId::of::<T>())
+            .and_then(|subscribers| subscribers.downcast_ref::<Vec<Subscriber<T>>>())
+            .map_or(0, |subscribers| {
+                subscribers
+                    .iter()
+                    .filter(|subscriber| !subscriber.sender.is_disconnected())
+                    .count()
+            })
+    }
+
+    /// Publishes an event to every subscriber of its type whose filter, if any, it matches,
+    /// overwriting the oldest events of subscribers that are at capacity.
+    ///
+    /// Overwritten events are dropped; the lag they represent is counted by each subscriber.
+    /// Subscribers whose receivers were all dropped are removed.
+    ///
+    /// # Returns
+    ///
+    /// The number of subscribers the event was delivered to.
+    pub fn publish<T>(&self, event: T) -> usize
+    where
+        T: Serialize + Clone + Send + 'static,
+    {
+        // Release the registry before sending, so hooks may subscribe
+        let subscribers = self
+            .subscribers
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&TypeId::of::<T>())
+            .and_then(|subscribers| subscribers.downcast_ref::<Vec<Subscriber<T>>>())
+            .cloned()
+            .unwrap_or_default();
+        let mut delivered = 0;
+        let mut pruned = false;
+        for subscriber in &subscribers {
+            if !subscriber.wants(&event) {
+                pruned |= subscriber.sender.is_disconnected();
+                continue;
+            }
+            match subscriber.sender.send_overwrite_discard(event.clone()) {
+                Ok(_) => delivered += 1,
+                Err(error) => pruned |= error.is_disconnected(),
+            }
+        }
+        if pruned
+            && let Some(subscribers) = self
+                .subscribers
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .get_mut(&TypeId::of::<T>())
+                .and_then(|subscribers| subscribers.downcast_mut::<Vec<Subscriber<T>>>())
+        {
+            subscribers.retain(|subscriber| !subscriber.sender.is_disconnected());
+        }
+        delivered
+    }
+
+    /// Publishes a shared event to every subscriber of `Arc<T>` whose filter, if any, it
+    /// matches.
+    ///
+    /// Every subscriber receives a clone of the same `Arc`, so a large event is stored once
+    /// however many subscribers there are, and converting an owned value with `Into` spares
+    /// even the initial copy. See [`publish`](EventBus::publish).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bus::EventBus;
+    /// use std::sync::Arc;
+    ///
+    /// let bus = EventBus::new();
+    /// let encoder = bus.subscribe::<Arc<Vec<u8>>>(4);
+    /// let archive = bus.subscribe::<Arc<Vec<u8>>>(4);
+    ///
+    /// assert_eq!(bus.send_shared::<Vec<u8>>(vec![0; 1 << 20]), 2);
+    ///
+    /// let (frame, archived) = (encoder.recv().unwrap(), archive.recv().unwrap());
+    /// assert!(Arc::ptr_eq(&frame, &archived));
+    /// ```
+    pub fn send_shared<T>(&self, event: impl Into<Arc<T>>) -> usize
+    where
+        T: Serialize + Send + Sync + 'static,
+    {
+        self.publish(event.into())
+    }
+}
+
+impl fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers = self.subscribers.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("EventBus")
+            .field("event_types", &subscribers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_register_replaces_channel() {
+        let bus = Bus::new();
+        let sender = bus.sender();
+        assert!(!bus.is_registered::<u8>());
+
+        let first = bus.register::<u8>(2);
+        sender.send_overwrite(1u8).unwrap();
+        let second = bus.register::<u8>(2);
+        sender.send_overwrite(2u8).unwrap();
+
+        assert_eq!(first.recv().unwrap(), 1);
+        assert!(first.recv().is_err());
+        assert_eq!(second.recv().unwrap(), 2);
+
+        // Channels outlive the bus while a sender is alive
+        drop(bus);
+        sender.send_overwrite(3u8).unwrap();
+        assert_eq!(second.recv().unwrap(), 3);
+        drop(sender);
+        assert!(second.recv().is_err());
+    }
+
+    #[test]
+    fn test_publish_prunes_dropped_subscribers() {
+        let bus = EventBus::new();
+        assert_eq!(bus.publish(1u8), 0);
+
+        let kept = bus.subscribe::<u8>(1);
+        let dropped = bus.subscribe::<u8>(1);
+        let other = bus.subscribe::<u16>(1);
+        assert_eq!(bus.subscriber_count::<u8>(), 2);
+
+        drop(dropped);
+        assert_eq!(bus.subscriber_count::<u8>(), 1);
+        assert_eq!(bus.publish(2u8), 1);
+        assert_eq!(kept.recv().unwrap(), 2);
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn test_send_shared_stores_the_event_once() {
+        let bus = EventBus::new();
+        let all = bus.subscribe::<Arc<String>>(2);
+        let long = bus.subscribe_filtered::<Arc<String>, _>(2, |event| event.len() > 4);
+
+        assert_eq!(bus.send_shared::<String>("tick".to_owned()), 1);
+        let event = Arc::new("resized".to_owned());
+        assert_eq!(bus.send_shared::<String>(event.clone()), 2);
+
+        assert_eq!(*all.recv().unwrap(), "tick");
+        let (first, second) = (all.recv().unwrap(), long.recv().unwrap());
+        assert!(Arc::ptr_eq(&first, &event) && Arc::ptr_eq(&second, &event));
+        // Subscribers of the bare type are a separate subscription
+        assert_eq!(bus.subscriber_count::<String>(), 0);
+    }
+}
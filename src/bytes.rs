@@ -0,0 +1,249 @@
+//! Byte payloads whose evicted buffers are recycled instead of freed.
+//!
+//! This module is only available with the `bytes` feature.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use ::bytes::{Bytes, BytesMut};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Creates an overwrite channel of [`Bytes`] payloads backed by a buffer arena.
+///
+/// Buffers evicted from the full channel are handed back to the sender's [`BytesArena`]
+/// rather than freed, and [`BytesSender::buffer`] reuses them for the next payload. Receivers
+/// can return consumed payloads with [`BytesArena::recycle`] as well, so a steady stream of
+/// similarly sized messages settles into reusing the same allocations.
+///
+/// The arena keeps at most `cap` idle buffers.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bytes::bounded_bytes;
+///
+/// let (sender, receiver) = bounded_bytes(1);
+/// let arena = sender.arena().clone();
+///
+/// for packet in [b"first", b"other", b"third"] {
+///     let mut buffer = sender.buffer(packet.len());
+///     buffer.extend_from_slice(packet);
+///     sender.send_overwrite(buffer.freeze()).unwrap();
+/// }
+///
+/// // The buffer of the evicted "first" was reused for "third"
+/// assert_eq!(arena.allocated(), 2);
+/// assert_eq!(arena.reused(), 1);
+///
+/// let packet = receiver.recv().unwrap();
+/// assert_eq!(&packet[..], b"third");
+/// ```
+pub fn bounded_bytes(cap: usize) -> (BytesSender, OverwriteReceiver<Bytes>) {
+    let (sender, receiver) = bounded(cap);
+    let sender = BytesSender {
+        sender,
+        arena: BytesArena::new(cap),
+    };
+    (sender, receiver)
+}
+
+/// A pool of reusable byte buffers.
+///
+/// Cloning an arena returns another handle to the same pool.
+#[derive(Clone)]
+pub struct BytesArena {
+    inner: Arc<ArenaInner>,
+}
+
+struct ArenaInner {
+    buffers: Mutex<Vec<BytesMut>>,
+    max_buffers: usize,
+    reused: AtomicU64,
+    allocated: AtomicU64,
+}
+
+impl BytesArena {
+    /// Creates an empty arena keeping at most `max_buffers` idle buffers.
+    pub fn new(max_buffers: usize) -> Self {
+        Self {
+            inner: Arc::new(ArenaInner {
+                buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+                max_buffers,
+                reused: AtomicU64::new(0),
+                allocated: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns an empty buffer with room for at least `len` bytes, reusing an idle buffer
+    /// when one is available.
+    pub fn take(&self, len: usize) -> BytesMut {
+        let reused = self
+            .inner
+            .buffers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop();
+        match reused {
+            Some(mut buffer) => {
+                self.inner.reused.fetch_add(1, Ordering::Relaxed);
+                buffer.reserve(len);
+                buffer
+            }
+            None => {
+                self.inner.allocated.fetch_add(1, Ordering::Relaxed);
+                BytesMut::with_capacity(len)
+            }
+        }
+    }
+
+    /// Returns a payload's buffer to the arena.
+    ///
+    /// The buffer can only be reclaimed if `bytes` is its last handle and the arena is not
+    /// already holding its maximum number of idle buffers; otherwise it is simply dropped.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the buffer was kept for reuse.
+    pub fn recycle(&self, bytes: Bytes) -> bool {
+        let Ok(mut buffer) = bytes.try_into_mut() else {
+            return false;
+        };
+        let mut buffers = self.inner.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() >= self.inner.max_buffers {
+            return false;
+        }
+        buffer.clear();
+        buffers.push(buffer);
+        true
+    }
+
+    /// Returns the number of idle buffers held by the arena.
+    pub fn len(&self) -> usize {
+        self.inner
+            .buffers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Returns `true` if the arena holds no idle buffers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of buffers handed out by [`take`](BytesArena::take) that were
+    /// reused.
+    pub fn reused(&self) -> u64 {
+        self.inner.reused.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of buffers handed out by [`take`](BytesArena::take) that had to
+    /// be allocated.
+    pub fn allocated(&self) -> u64 {
+        self.inner.allocated.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for BytesArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BytesArena")
+            .field("len", &self.len())
+            .field("max_buffers", &self.inner.max_buffers)
+            .field("reused", &self.reused())
+            .field("allocated", &self.allocated())
+            .finish()
+    }
+}
+
+/// The sending half of a [`bounded_bytes`] channel.
+#[derive(Clone, Debug)]
+pub struct BytesSender {
+    sender: OverwriteSender<Bytes>,
+    arena: BytesArena,
+}
+
+impl BytesSender {
+    /// Returns an empty buffer with room for at least `len` bytes, taken from the arena.
+    pub fn buffer(&self, len: usize) -> BytesMut {
+        self.arena.take(len)
+    }
+
+    /// Sends a payload, overwriting the oldest payload if the channel is at capacity.
+    ///
+    /// Evicted payloads are recycled into the arena instead of being returned.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)` - The payload was sent; the value is the number of payloads evicted
+    /// - `Err(SendOverwriteError<Bytes>)` - All receivers have been dropped
+    pub fn send_overwrite(&self, bytes: Bytes) -> Result<usize, SendOverwriteError<Bytes>> {
+        let evicted = self.sender.send_overwrite(bytes)?;
+        Ok(self.recycle(evicted.unwrap_or_default()))
+    }
+
+    /// Asynchronously sends a payload.
+    ///
+    /// This is the async version of [`send_overwrite`](BytesSender::send_overwrite).
+    pub async fn send_overwrite_async(
+        &self,
+        bytes: Bytes,
+    ) -> Result<usize, SendOverwriteError<Bytes>> {
+        let evicted = self.sender.send_overwrite_async(bytes).await?;
+        Ok(self.recycle(evicted.unwrap_or_default()))
+    }
+
+    /// Recycles evicted payloads into the arena, returning how many there were.
+    fn recycle(&self, evicted: Vec<Bytes>) -> usize {
+        let count = evicted.len();
+        for bytes in evicted {
+            self.arena.recycle(bytes);
+        }
+        count
+    }
+
+    /// Returns the arena evicted buffers are recycled into.
+    pub fn arena(&self) -> &BytesArena {
+        &self.arena
+    }
+
+    /// Returns the underlying overwrite sender.
+    pub fn sender(&self) -> &OverwriteSender<Bytes> {
+        &self.sender
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_evicted_buffers_are_reused() {
+        let (sender, receiver) = bounded_bytes(2);
+        for i in 0..100u8 {
+            let mut buffer = sender.buffer(64);
+            buffer.extend_from_slice(&[i; 64]);
+            sender.send_overwrite(buffer.freeze()).unwrap();
+        }
+        let arena = sender.arena();
+        // The first three buffers are allocated, every later one recycles an evicted buffer
+        assert_eq!(arena.allocated(), 3);
+        assert_eq!(arena.reused(), 97);
+        assert_eq!(receiver.recv().unwrap()[0], 98);
+        assert_eq!(receiver.recv().unwrap()[0], 99);
+    }
+
+    #[test]
+    fn test_shared_buffers_are_not_recycled() {
+        let arena = BytesArena::new(1);
+        let bytes = Bytes::from(vec![1, 2, 3]);
+        let clone = bytes.clone();
+        assert!(!arena.recycle(bytes));
+        assert!(arena.recycle(clone));
+        // The arena is full
+        assert!(!arena.recycle(Bytes::from(vec![4])));
+        assert_eq!(arena.len(), 1);
+        assert!(arena.take(16).is_empty());
+        assert!(arena.is_empty());
+    }
+}
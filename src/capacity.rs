@@ -0,0 +1,157 @@
+//! A validated channel capacity.
+
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The maximum number of messages an overwrite channel can hold.
+///
+/// A capacity is never zero: a channel that cannot hold a single message has nothing to
+/// overwrite, so every send would fail. Plain `usize` capacities given to [`bounded`] or
+/// [`Builder::new`] are validated the same way and panic on zero, while
+/// [`Capacity::new`] lets callers handle an invalid value themselves.
+///
+/// [`bounded`]: crate::bounded
+/// [`Builder::new`]: crate::Builder::new
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{Builder, Capacity};
+/// use std::time::Duration;
+///
+/// assert!(Capacity::new(0).is_none());
+///
+/// // Retain the last 2 seconds of a 100 Hz sensor
+/// let capacity = Capacity::per_second(100.0, Duration::from_secs(2));
+/// assert_eq!(capacity.get(), 200);
+///
+/// let (sender, _receiver) = Builder::<f32>::with_capacity(capacity).build();
+/// assert_eq!(sender.capacity(), 200);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Capacity(NonZeroUsize);
+
+impl Capacity {
+    /// A capacity of one message, for channels that only keep the latest value.
+    pub const ONE: Capacity = Capacity(NonZeroUsize::MIN);
+
+    /// Returns a capacity of `cap` messages, or `None` if `cap` is zero.
+    pub const fn new(cap: usize) -> Option<Self> {
+        match NonZeroUsize::new(cap) {
+            Some(cap) => Some(Self(cap)),
+            None => None,
+        }
+    }
+
+    /// Returns the capacity needed to retain `horizon` worth of messages arriving at `rate`
+    /// messages per second.
+    ///
+    /// The result is rounded up and is at least one message.
+    pub fn per_second(rate: f64, horizon: Duration) -> Self {
+        // Float to integer casts saturate, and NaN becomes zero
+        let cap = (rate * horizon.as_secs_f64()).ceil() as usize;
+        Self::new(cap).unwrap_or(Self::ONE)
+    }
+
+    /// Returns the capacity as a number of messages.
+    pub const fn get(self) -> usize {
+        self.0.get()
+    }
+
+    /// Validates a raw capacity, panicking on zero.
+    #[track_caller]
+    pub(crate) fn expect(cap: usize) -> Self {
+        Self::new(cap).expect("channel capacity must be non-zero")
+    }
+}
+
+impl From<NonZeroUsize> for Capacity {
+    fn from(cap: NonZeroUsize) -> Self {
+        Self(cap)
+    }
+}
+
+impl From<Capacity> for usize {
+    fn from(cap: Capacity) -> Self {
+        cap.get()
+    }
+}
+
+impl fmt::Display for Capacity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A channel's current capacity, which can change at runtime up to the maximum the channel
+/// was allocated for.
+pub(crate) struct AtomicCapacity {
+    current: AtomicUsize,
+    max: Capacity,
+    /// Slots kept as burst headroom, which regular sends do not use.
+    reserved: AtomicUsize,
+}
+
+impl AtomicCapacity {
+    pub(crate) fn new(cap: Capacity, max: Capacity) -> Self {
+        Self {
+            current: AtomicUsize::new(cap.get()),
+            max: max.max(cap),
+            reserved: AtomicUsize::new(0),
+        }
+    }
+
+    pub(crate) fn get(&self) -> usize {
+        self.current.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn max(&self) -> usize {
+        self.max.get()
+    }
+
+    /// Sets the capacity, returning the previous one.
+    pub(crate) fn swap(&self, cap: Capacity) -> usize {
+        self.current.swap(cap.get(), Ordering::AcqRel)
+    }
+
+    pub(crate) fn reserved(&self) -> usize {
+        self.reserved.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_reserved(&self, reserved: usize) {
+        self.reserved.store(reserved, Ordering::Release);
+    }
+
+    /// Returns the capacity available to regular sends, which always keep at least one slot
+    /// even if the channel was shrunk below its burst reserve.
+    pub(crate) fn regular(&self) -> usize {
+        self.get().saturating_sub(self.reserved()).max(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_per_second_rounds_up() {
+        assert_eq!(
+            Capacity::per_second(3.0, Duration::from_millis(500)).get(),
+            2
+        );
+        assert_eq!(
+            Capacity::per_second(0.0, Duration::from_secs(10)),
+            Capacity::ONE
+        );
+        assert_eq!(
+            Capacity::per_second(f64::NAN, Duration::from_secs(1)),
+            Capacity::ONE
+        );
+        assert_eq!(
+            Capacity::per_second(f64::INFINITY, Duration::from_secs(1)).get(),
+            usize::MAX
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! A single-slot mailbox of conflated state.
+
+use crate::{OverwriteReceiver, OverwriteSender, bounded};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A state read from a [`StateCell`], along with its version.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateUpdate<T> {
+    version: u64,
+    skipped: u64,
+    value: T,
+}
+
+impl<T> StateUpdate<T> {
+    /// Returns the version of the state, counting publications from `1`.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns how many states were published since the previous read of the cell and
+    /// overwritten before being read.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Returns the state.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for StateUpdate<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// A mailbox holding the newest published state, built on a channel of capacity one.
+///
+/// Writers [`publish`](StateCell::publish) full states, each overwriting the previous one
+/// if it has not been read yet, and readers wait for the next state with
+/// [`await_update`](StateCell::await_update) or [`recv_update`](StateCell::recv_update).
+/// A reader thus always gets the newest state, and its version tells how many intermediate
+/// states were skipped. Clones share the mailbox, so each state is read at most once.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::StateCell;
+/// use futures::executor::block_on;
+///
+/// let cell = StateCell::new();
+/// let reader = cell.clone();
+///
+/// cell.publish("connecting");
+/// cell.publish("connected");
+///
+/// let update = block_on(reader.await_update());
+/// assert_eq!(*update, "connected");
+/// assert_eq!((update.version(), update.skipped()), (2, 1));
+/// ```
+pub struct StateCell<T> {
+    sender: OverwriteSender<StateUpdate<T>>,
+    receiver: OverwriteReceiver<StateUpdate<T>>,
+    versions: Arc<Versions>,
+}
+
+struct Versions {
+    /// The version of the last published state, locked while publishing it.
+    published: Mutex<u64>,
+    /// The version of the last state read.
+    read: AtomicU64,
+}
+
+impl<T> StateCell<T> {
+    /// Creates an empty cell.
+    pub fn new() -> Self {
+        let (sender, receiver) = bounded(1);
+        Self {
+            sender,
+            receiver,
+            versions: Arc::new(Versions {
+                published: Mutex::new(0),
+                read: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Publishes a new state, overwriting the previous one if it is unread, and returns
+    /// its version.
+    pub fn publish(&self, state: T) -> u64 {
+        let mut published = self
+            .versions
+            .published
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        *published += 1;
+        let update = StateUpdate {
+            version: *published,
+            skipped: 0,
+            value: state,
+        };
+        // The cell holds a receiver, so the channel stays connected
+        let _ = self.sender.send_overwrite_discard(update);
+        *published
+    }
+
+    /// Returns the version of the last published state, or `0` if none was.
+    pub fn version(&self) -> u64 {
+        *self
+            .versions
+            .published
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns `true` if a published state is waiting to be read.
+    pub fn has_update(&self) -> bool {
+        !self.receiver.is_empty()
+    }
+
+    /// Takes the unread state, if any, without waiting.
+    pub fn try_update(&self) -> Option<StateUpdate<T>> {
+        self.receiver
+            .try_recv()
+            .ok()
+            .map(|update| self.read(update))
+    }
+
+    /// Blocks until a state is published, unless one is already unread, and takes it.
+    pub fn recv_update(&self) -> StateUpdate<T> {
+        let update = self
+            .receiver
+            .recv()
+            .expect("the cell holds a sender, so the channel stays connected");
+        self.read(update)
+    }
+
+    /// Waits asynchronously until a state is published, unless one is already unread, and
+    /// takes it.
+    ///
+    /// This is the async version of [`recv_update`](StateCell::recv_update).
+    pub async fn await_update(&self) -> StateUpdate<T> {
+        let update = self
+            .receiver
+            .recv_async()
+            .await
+            .expect("the cell holds a sender, so the channel stays connected");
+        self.read(update)
+    }
+
+    /// Records `update` as read, counting the states skipped since the previous read.
+    fn read(&self, mut update: StateUpdate<T>) -> StateUpdate<T> {
+        let previous = self
+            .versions
+            .read
+            .fetch_max(update.version, Ordering::AcqRel);
+        update.skipped = update.version.saturating_sub(previous + 1);
+        update
+    }
+}
+
+impl<T> Default for StateCell<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for StateCell<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            versions: self.versions.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for StateCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StateCell")
+            .field("version", &self.version())
+            .field("has_update", &self.has_update())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_reader_sees_every_state_or_counts_it_skipped() {
+        let cell = StateCell::new();
+        let writer = cell.clone();
+        let handle = thread::spawn(move || {
+            for state in 0..1000 {
+                writer.publish(state);
+            }
+        });
+
+        let mut seen = 0;
+        loop {
+            let update = cell.recv_update();
+            seen += 1 + update.skipped();
+            assert_eq!(*update as u64 + 1, update.version());
+            if update.version() == 1000 {
+                break;
+            }
+        }
+        handle.join().unwrap();
+        assert_eq!(seen, 1000);
+        assert!(cell.try_update().is_none());
+    }
+}
@@ -0,0 +1,112 @@
+//! Positions in a channel's stream, for consumers resuming after a restart.
+
+use crate::counter::Counter;
+use crate::{ChannelId, OverwriteReceiver};
+
+/// An opaque position in a channel's stream, taken with [`OverwriteReceiver::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    channel: ChannelId,
+    sent: u64,
+    evicted: u64,
+}
+
+/// What happened on a channel since a [`Checkpoint`], as returned by
+/// [`OverwriteReceiver::resume_from`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    /// The number of messages sent since the checkpoint.
+    pub sent: u64,
+    /// The number of messages evicted since the checkpoint, which no receiver will get.
+    pub evicted: u64,
+    /// The number of messages now queued, which the consumer has yet to receive.
+    pub queued: usize,
+}
+
+impl Gap {
+    /// Returns `true` if no message was evicted since the checkpoint.
+    pub fn is_lossless(&self) -> bool {
+        self.evicted == 0
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Returns a token marking the current position in the channel's stream.
+    ///
+    /// A consumer stopping mid-stream keeps the token, and hands it to
+    /// [`resume_from`](OverwriteReceiver::resume_from) when it restarts to learn exactly
+    /// how much it missed. Positions are counted over the whole channel: messages taken by
+    /// other receivers in the meantime are not reported as missed.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            channel: self.shared.id,
+            sent: self.shared.sent.get(),
+            evicted: self.shared.evicted.get(),
+        }
+    }
+
+    /// Reports what happened on the channel since `checkpoint` was taken.
+    ///
+    /// Returns `None` if the checkpoint was taken on another channel.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    /// sender.send_overwrite(0).unwrap();
+    /// receiver.recv().unwrap();
+    /// let checkpoint = receiver.checkpoint();
+    ///
+    /// // The consumer is away while three more messages arrive
+    /// for i in 1..4 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    ///
+    /// let gap = receiver.resume_from(checkpoint).unwrap();
+    /// assert_eq!((gap.sent, gap.evicted, gap.queued), (3, 1, 2));
+    /// assert!(!gap.is_lossless());
+    /// ```
+    pub fn resume_from(&self, checkpoint: Checkpoint) -> Option<Gap> {
+        if checkpoint.channel != self.shared.id {
+            return None;
+        }
+        let now = self.checkpoint();
+        Some(Gap {
+            sent: Counter::since(now.sent, checkpoint.sent),
+            evicted: Counter::since(now.evicted, checkpoint.evicted),
+            queued: self.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    #[test]
+    fn test_gap_of_a_consumer_keeping_up() {
+        let (sender, receiver) = bounded(2);
+        let checkpoint = receiver.checkpoint();
+        for i in 0..4 {
+            sender.send_overwrite(i).unwrap();
+            receiver.recv().unwrap();
+        }
+        sender.send_overwrite(4).unwrap();
+
+        let gap = receiver.resume_from(checkpoint).unwrap();
+        assert_eq!((gap.sent, gap.evicted, gap.queued), (5, 0, 1));
+        assert!(gap.is_lossless());
+
+        // Positions are shared by every receiver of the channel
+        assert_eq!(receiver.clone().checkpoint(), receiver.checkpoint());
+    }
+
+    #[test]
+    fn test_checkpoint_of_another_channel() {
+        let (_sender, receiver) = bounded::<u8>(1);
+        let (_other_sender, other) = bounded::<u8>(1);
+        assert_eq!(receiver.resume_from(other.checkpoint()), None);
+    }
+}
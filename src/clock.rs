@@ -0,0 +1,148 @@
+//! Time sources for time-based channel features.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+/// The future returned by [`Clock::sleep_async`].
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A source of time.
+///
+/// Time-based features read the current time through a `Clock` instead of calling
+/// [`Instant::now`] directly, so tests can substitute a clock they control. The default is
+/// [`SystemClock`]; the `test-util` feature provides [`MockClock`], whose time only moves
+/// when the test advances it.
+pub trait Clock: Send + Sync + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the current thread for `duration` as measured by this clock.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    /// Returns a future completing once `duration` has passed as measured by this clock.
+    ///
+    /// This is the async version of [`sleep`](Clock::sleep).
+    fn sleep_async(&self, duration: Duration) -> Sleep {
+        Box::pin(futures_timer::Delay::new(duration))
+    }
+}
+
+/// The system's monotonic clock, backed by [`Instant::now`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A manually advanced clock for deterministic tests.
+///
+/// Time starts at the moment the clock is created and only moves forward when
+/// [`advance`](MockClock::advance) is called. Sleeping on a `MockClock` advances it by the
+/// requested duration and returns immediately, asynchronously too. Clones share the same time.
+///
+/// Available with the `test-util` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{Clock, MockClock};
+/// use std::time::Duration;
+///
+/// let clock = MockClock::new();
+/// let start = clock.now();
+///
+/// clock.advance(Duration::from_secs(5));
+/// assert_eq!(clock.now() - start, Duration::from_secs(5));
+///
+/// clock.sleep(Duration::from_secs(1));
+/// assert_eq!(clock.elapsed(), Duration::from_secs(6));
+/// ```
+#[cfg(feature = "test-util")]
+#[derive(Clone)]
+pub struct MockClock {
+    start: Instant,
+    elapsed: std::sync::Arc<std::sync::Mutex<Duration>>,
+}
+
+#[cfg(feature = "test-util")]
+impl MockClock {
+    /// Creates a clock starting at the current time.
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed: Default::default(),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner()) += duration;
+    }
+
+    /// Returns how far the clock has been advanced since it was created.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    fn sleep_async(&self, duration: Duration) -> Sleep {
+        self.advance(duration);
+        Box::pin(std::future::ready(()))
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl std::fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockClock")
+            .field("elapsed", &self.elapsed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_system_clock_moves_forward() {
+        let clock = SystemClock;
+        let before = clock.now();
+        clock.sleep(Duration::from_millis(1));
+        assert!(clock.now() > before);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let clock = MockClock::new();
+        let clone = clock.clone();
+        let start = clock.now();
+        clone.advance(Duration::from_millis(250));
+        assert_eq!(clock.now() - start, Duration::from_millis(250));
+        assert_eq!(clock.now(), clone.now());
+    }
+}
@@ -0,0 +1,297 @@
+//! Combining several overwrite receivers into a single stream.
+//!
+//! [`combine_latest`] merges N receivers into a stream of tuples holding the latest message
+//! from each source, emitting whenever any source produces a new message once every source
+//! has produced at least one. The crate keeps the latest message of every source in an
+//! intermediate buffer and counts, per source, how many messages were superseded there
+//! before they could be emitted.
+
+use crate::OverwriteReceiver;
+use futures_core::Stream;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Combines receivers into a stream of their latest messages.
+///
+/// `sources` is a tuple of up to eight [`OverwriteReceiver`]s, possibly of different message
+/// types. The returned stream yields a tuple of clones of the latest message from each
+/// source every time at least one source has produced a new message, starting once every
+/// source has produced one. If a source produces several messages between two emissions,
+/// only the newest is used and the others are counted as superseded.
+///
+/// The stream ends once every source has ended, or as soon as a source ends without ever
+/// producing a message.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+/// use flume_overwrite::combine::combine_latest;
+/// use futures::executor::block_on;
+/// use futures::StreamExt;
+///
+/// let (position_tx, position) = bounded(4);
+/// let (heading_tx, heading) = bounded(4);
+/// let mut combined = combine_latest((position, heading));
+///
+/// position_tx.send_overwrite((0, 0)).unwrap();
+/// position_tx.send_overwrite((1, 0)).unwrap();
+/// heading_tx.send_overwrite(90).unwrap();
+/// assert_eq!(block_on(combined.next()), Some(((1, 0), 90)));
+///
+/// heading_tx.send_overwrite(180).unwrap();
+/// assert_eq!(block_on(combined.next()), Some(((1, 0), 180)));
+///
+/// // The first position was superseded before the first emission
+/// assert_eq!(combined.source_stats()[0].superseded, 1);
+/// ```
+pub fn combine_latest<S: Sources>(sources: S) -> CombineLatest<S> {
+    CombineLatest {
+        sources,
+        latest: Default::default(),
+        state: vec![SourceState::default(); S::LEN],
+    }
+}
+
+/// A stream of the latest messages from several receivers, created by [`combine_latest`].
+pub struct CombineLatest<S: Sources> {
+    sources: S,
+    latest: S::Latest,
+    state: Vec<SourceState>,
+}
+
+/// Per-source bookkeeping of a [`CombineLatest`] stream.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceState {
+    /// The latest message has not been emitted yet.
+    fresh: bool,
+    /// The source has ended.
+    ended: bool,
+    received: u64,
+    superseded: u64,
+}
+
+impl SourceState {
+    fn record(&mut self) {
+        self.received += 1;
+        if self.fresh {
+            self.superseded += 1;
+        }
+        self.fresh = true;
+    }
+}
+
+/// Counters describing one source of a [`CombineLatest`] stream.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceStats {
+    /// Messages received from the source.
+    pub received: u64,
+    /// Messages replaced by a newer message from the same source before being emitted.
+    pub superseded: u64,
+    /// Messages overwritten in the source channel itself.
+    pub evicted: u64,
+}
+
+/// A tuple of receivers that can be combined with [`combine_latest`].
+///
+/// Implemented for tuples of up to eight [`OverwriteReceiver`]s whose message types are
+/// `Clone + Send + 'static`.
+pub trait Sources: Unpin {
+    /// The number of sources.
+    const LEN: usize;
+
+    /// The latest message of every source, if any.
+    #[doc(hidden)]
+    type Latest: Default;
+
+    /// A tuple with one message per source.
+    type Item;
+
+    #[doc(hidden)]
+    fn poll_sources(
+        &mut self,
+        latest: &mut Self::Latest,
+        state: &mut [SourceState],
+        cx: &mut Context<'_>,
+    );
+
+    #[doc(hidden)]
+    fn snapshot(latest: &Self::Latest) -> Option<Self::Item>;
+
+    #[doc(hidden)]
+    fn evicted(&self) -> Vec<u64>;
+}
+
+macro_rules! impl_sources {
+    ($len:literal; $($T:ident $index:tt),+) => {
+        impl<$($T: Clone + Send + 'static),+> Sources for ($(OverwriteReceiver<$T>,)+) {
+            const LEN: usize = $len;
+            type Latest = ($(Option<$T>,)+);
+            type Item = ($($T,)+);
+
+            fn poll_sources(
+                &mut self,
+                latest: &mut Self::Latest,
+                state: &mut [SourceState],
+                cx: &mut Context<'_>,
+            ) {
+                $(
+                    while !state[$index].ended {
+                        match Pin::new(&mut self.$index).poll_next(cx) {
+                            Poll::Ready(Some(value)) => {
+                                latest.$index = Some(value);
+                                state[$index].record();
+                            }
+                            Poll::Ready(None) => state[$index].ended = true,
+                            Poll::Pending => break,
+                        }
+                    }
+                )+
+            }
+
+            fn snapshot(latest: &Self::Latest) -> Option<Self::Item> {
+                Some(($(latest.$index.clone()?,)+))
+            }
+
+            fn evicted(&self) -> Vec<u64> {
+                vec![$(self.$index.stats().evicted),+]
+            }
+        }
+    };
+}
+
+impl_sources!(1; A 0);
+impl_sources!(2; A 0, B 1);
+impl_sources!(3; A 0, B 1, C 2);
+impl_sources!(4; A 0, B 1, C 2, D 3);
+impl_sources!(5; A 0, B 1, C 2, D 3, E 4);
+impl_sources!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_sources!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_sources!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+impl<S: Sources> CombineLatest<S> {
+    /// Returns counters for every source, in the order the sources were given.
+    pub fn source_stats(&self) -> Vec<SourceStats> {
+        self.state
+            .iter()
+            .zip(self.sources.evicted())
+            .map(|(state, evicted)| SourceStats {
+                received: state.received,
+                superseded: state.superseded,
+                evicted,
+            })
+            .collect()
+    }
+
+    /// Returns a reference to the combined receivers.
+    pub fn get_ref(&self) -> &S {
+        &self.sources
+    }
+
+    /// Consumes the stream, returning the combined receivers.
+    pub fn into_inner(self) -> S {
+        self.sources
+    }
+}
+
+// The latest messages are never pinned, only the receivers are, and those are `Unpin`.
+impl<S: Sources> Unpin for CombineLatest<S> {}
+
+impl<S: Sources> Stream for CombineLatest<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = &mut *self;
+        this.sources
+            .poll_sources(&mut this.latest, &mut this.state, cx);
+        let snapshot = S::snapshot(&this.latest);
+        if snapshot.is_some() && this.state.iter().any(|state| state.fresh) {
+            this.state.iter_mut().for_each(|state| state.fresh = false);
+            return Poll::Ready(snapshot);
+        }
+        let starved = snapshot.is_none() && this.state.iter().any(|state| state.ended);
+        if starved || this.state.iter().all(|state| state.ended) {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S: Sources> fmt::Debug for CombineLatest<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CombineLatest")
+            .field("sources", &S::LEN)
+            .field("source_stats", &self.source_stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    use futures::StreamExt;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_combine_latest_emits_on_any_update() {
+        let (tx_a, rx_a) = bounded(4);
+        let (tx_b, rx_b) = bounded(4);
+        let (tx_c, rx_c) = bounded(4);
+        let mut combined = combine_latest((rx_a, rx_b, rx_c));
+        tx_a.send_overwrite(1).unwrap();
+        tx_b.send_overwrite("x").unwrap();
+        tx_c.send_overwrite(1.5).unwrap();
+        assert_eq!(block_on(combined.next()), Some((1, "x", 1.5)));
+        tx_b.send_overwrite("y").unwrap();
+        assert_eq!(block_on(combined.next()), Some((1, "y", 1.5)));
+        tx_a.send_overwrite(2).unwrap();
+        tx_a.send_overwrite(3).unwrap();
+        assert_eq!(block_on(combined.next()), Some((3, "y", 1.5)));
+        let stats = combined.source_stats();
+        assert_eq!(stats[0].received, 3);
+        assert_eq!(stats[0].superseded, 1);
+        assert_eq!(stats[1].superseded, 0);
+    }
+
+    #[test]
+    fn test_combine_latest_counts_channel_evictions() {
+        let (tx_a, rx_a) = bounded(1);
+        let (tx_b, rx_b) = bounded(1);
+        tx_a.send_overwrite(1).unwrap();
+        tx_a.send_overwrite(2).unwrap();
+        tx_b.send_overwrite(1).unwrap();
+        let mut combined = combine_latest((rx_a, rx_b));
+        assert_eq!(block_on(combined.next()), Some((2, 1)));
+        assert_eq!(combined.source_stats()[0].evicted, 1);
+        assert_eq!(combined.source_stats()[1].evicted, 0);
+    }
+
+    #[test]
+    fn test_combine_latest_ends() {
+        let (tx_a, rx_a) = bounded(4);
+        let (tx_b, rx_b) = bounded::<u8>(4);
+        let mut combined = combine_latest((rx_a, rx_b));
+        tx_a.send_overwrite(1).unwrap();
+        drop(tx_b);
+        // The second source ended without a message, so nothing can be emitted
+        assert_eq!(block_on(combined.next()), None);
+        drop(tx_a);
+
+        let (tx_a, rx_a) = bounded(4);
+        let (tx_b, rx_b) = bounded(4);
+        let mut combined = combine_latest((rx_a, rx_b));
+        tx_a.send_overwrite(1).unwrap();
+        tx_b.send_overwrite(2).unwrap();
+        drop(tx_a);
+        assert_eq!(block_on(combined.next()), Some((1, 2)));
+        tx_b.send_overwrite(3).unwrap();
+        assert_eq!(block_on(combined.next()), Some((1, 3)));
+        drop(tx_b);
+        assert_eq!(block_on(combined.next()), None);
+    }
+}
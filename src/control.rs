@@ -0,0 +1,206 @@
+//! Separation of sending from controlling a channel.
+
+use crate::{OverflowPolicy, OverwriteSender, SendOverwriteError, Shared, Stats};
+use flume::Receiver;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+
+/// The sending half of [`OverwriteSender::split_control`], which can do nothing but send.
+///
+/// A `Producer` can be handed to untrusted code, such as plugins, without giving it the
+/// power to change the channel's policy, inspect queued messages or remove them.
+pub struct Producer<T> {
+    sender: OverwriteSender<T>,
+}
+
+impl<T> Producer<T> {
+    /// Sends a value, overwriting old messages if the channel is at capacity.
+    ///
+    /// See [`OverwriteSender::send_overwrite`].
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.sender.send_overwrite(value)
+    }
+
+    /// Asynchronously sends a value, overwriting old messages if the channel is at capacity.
+    ///
+    /// See [`OverwriteSender::send_overwrite_async`].
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.sender.send_overwrite_async(value).await
+    }
+
+    /// Returns `true` if every receiver of the channel has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.sender.is_disconnected()
+    }
+}
+
+impl<T> Clone for Producer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Producer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Producer").finish_non_exhaustive()
+    }
+}
+
+/// The controlling half of [`OverwriteSender::split_control`].
+///
+/// A `Controller` reports on the channel and changes how it behaves, but cannot send.
+/// It does not count as a sender, so receivers still observe the disconnection once every
+/// [`Producer`] is dropped. The capacity is fixed when the channel is built and can only be
+/// read.
+pub struct Controller<T> {
+    shared: Arc<Shared<T>>,
+    receiver: Receiver<T>,
+}
+
+impl<T> Controller<T> {
+    pub(crate) fn new(sender: &OverwriteSender<T>) -> Self {
+        Self {
+            shared: sender.shared.clone(),
+            receiver: sender.receiver.clone(),
+        }
+    }
+
+    /// Returns the maximum number of messages the channel can hold.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity.get()
+    }
+
+    /// Returns the number of messages in the channel.
+    ///
+    /// See [`OverwriteSender::len`].
+    pub fn len(&self) -> usize {
+        self.shared.len(&self.receiver)
+    }
+
+    /// Returns `true` if the channel holds no messages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a snapshot of the channel's state.
+    pub fn stats(&self) -> Stats {
+        self.shared.stats(&self.receiver)
+    }
+
+    /// Returns `true` if every producer has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.receiver.is_disconnected()
+    }
+
+    /// Returns the number of receivers connected to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.receiver_count.load(Ordering::Acquire)
+    }
+
+    /// Returns the channel's current [`OverflowPolicy`].
+    pub fn policy(&self) -> OverflowPolicy {
+        self.shared.policy.load()
+    }
+
+    /// Switches the channel to `policy`, returning the previous one.
+    ///
+    /// See [`OverwriteSender::swap_policy`].
+    pub fn swap_policy(&self, policy: OverflowPolicy) -> OverflowPolicy {
+        self.shared.policy.swap(policy)
+    }
+
+    /// Removes the oldest queued message, counting it as evicted.
+    pub(crate) fn evict_oldest(&self) -> Option<T> {
+        let _sending = self.shared.lock_sends();
+        let oldest = self.receiver.try_recv().ok()?;
+        self.shared.count_evictions(1);
+        self.shared.count_classes(std::slice::from_ref(&oldest));
+        self.shared.check_strict(std::slice::from_ref(&oldest));
+        Some(oldest)
+    }
+
+    /// Removes and returns every queued message matching `pred`, oldest first.
+    ///
+    /// See [`OverwriteSender::cancel_where`]. Once every producer has been dropped the
+    /// queue can no longer be refilled, so nothing is removed.
+    pub fn cancel_where<F>(&self, mut pred: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let _sending = self.shared.lock_sends();
+        let Some(sender) = self.shared.weak_sender.upgrade() else {
+            return Vec::new();
+        };
+        let (cancelled, kept) = self.receiver.drain().partition(|value| pred(value));
+        self.shared.requeue_locked(&sender, &self.receiver, kept);
+        cancelled
+    }
+}
+
+impl<T> Clone for Controller<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Controller<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.shared.fmt_debug("Controller", &self.receiver, f)
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Splits the sender into a [`Controller`], which reports on the channel and changes its
+    /// policy, and a [`Producer`], which can only send.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{OverflowPolicy, bounded};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// let (controller, producer) = sender.split_control();
+    ///
+    /// // Hand the producer to a plugin, keep the controller
+    /// producer.send_overwrite("sample").unwrap();
+    /// controller.swap_policy(OverflowPolicy::RejectNew);
+    /// assert!(producer.send_overwrite("burst").is_err());
+    /// assert_eq!(controller.stats().sent, 1);
+    ///
+    /// drop(producer);
+    /// assert_eq!(receiver.recv().unwrap(), "sample");
+    /// assert!(receiver.recv().is_err());
+    /// ```
+    pub fn split_control(self) -> (Controller<T>, Producer<T>) {
+        (Controller::new(&self), Producer { sender: self })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    #[test]
+    fn test_controller_cancel_where() {
+        let (sender, receiver) = bounded(4);
+        let (controller, producer) = sender.split_control();
+        for i in 0..4 {
+            producer.send_overwrite(i).unwrap();
+        }
+        assert_eq!(controller.cancel_where(|value| value % 2 == 0), vec![0, 2]);
+        assert_eq!(controller.len(), 2);
+
+        drop(producer);
+        assert_eq!(controller.cancel_where(|_| true), Vec::<i32>::new());
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 3]);
+    }
+}
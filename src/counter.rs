@@ -0,0 +1,75 @@
+//! Message counters, optionally narrowed to 32 bits.
+
+#[cfg(feature = "narrow-counters")]
+use std::sync::atomic::AtomicU32 as AtomicRaw;
+#[cfg(not(feature = "narrow-counters"))]
+use std::sync::atomic::AtomicU64 as AtomicRaw;
+use std::sync::atomic::Ordering;
+
+#[cfg(feature = "narrow-counters")]
+type Raw = u32;
+#[cfg(not(feature = "narrow-counters"))]
+type Raw = u64;
+
+/// A monotonic counter of messages, reported as `u64`.
+///
+/// The counter is 64 bits wide. With the `narrow-counters` feature it is 32 bits wide
+/// instead, halving the footprint of the channel's message counters, and wraps around after
+/// `u32::MAX` messages: differences between two readings must then be taken with
+/// [`since`](Counter::since).
+///
+/// The feature only narrows these counters. The crate needs `std`, as flume does, and
+/// keeps relying on `std::time::Instant` and 64-bit atomics elsewhere, so it does not
+/// target platforms with a 16-bit `usize`, which have no `std`, nor ones without
+/// `Instant`.
+pub(crate) struct Counter(AtomicRaw);
+
+// Conversions to `u64` are only useful with narrow counters
+#[allow(clippy::useless_conversion)]
+impl Counter {
+    pub(crate) fn new() -> Self {
+        Self(AtomicRaw::new(0))
+    }
+
+    /// Returns the current count.
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed).into()
+    }
+
+    /// Adds `n` to the count, returning the new count.
+    pub(crate) fn add(&self, n: usize) -> u64 {
+        // Truncating is the wrap-around of a narrow counter
+        let n = n as _;
+        u64::from(self.0.fetch_add(n, Ordering::Relaxed).wrapping_add(n))
+    }
+
+    /// Returns how much the count grew from the reading `earlier` to the reading `now`,
+    /// across a wrap-around of the counter's native width.
+    pub(crate) fn since(now: u64, earlier: u64) -> u64 {
+        // Readings fit the native width, so the casts are lossless
+        u64::from((now as Raw).wrapping_sub(earlier as Raw))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counter_accumulates() {
+        let counter = Counter::new();
+        assert_eq!(counter.add(2), 2);
+        assert_eq!(counter.add(3), 5);
+        assert_eq!(counter.get(), 5);
+        assert_eq!(Counter::since(counter.get(), 2), 3);
+    }
+
+    #[test]
+    #[cfg(feature = "narrow-counters")]
+    fn test_narrow_counter_wraps() {
+        let counter = Counter::new();
+        let earlier = counter.add(u32::MAX as usize);
+        assert_eq!(counter.add(3), 2);
+        assert_eq!(Counter::since(counter.get(), earlier), 3);
+    }
+}
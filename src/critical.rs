@@ -0,0 +1,179 @@
+//! Critical messages mixed with lossy traffic, acknowledged once received.
+
+use crate::{Builder, OverwriteSender, SendOverwriteError};
+use std::fmt;
+use std::ops::Deref;
+
+/// A message of a channel mixing critical and lossy traffic.
+///
+/// Critical messages are sent with [`OverwriteSender::send_critical`], and lossy ones with
+/// [`OverwriteSender::send_lossy`]. Taking the value out of a critical message with
+/// [`into_inner`](Critical::into_inner) acknowledges it to its sender; a critical message
+/// dropped without being taken, such as one still queued when the channel is dropped, is
+/// never acknowledged.
+pub struct Critical<T> {
+    value: T,
+    ack: Option<flume::Sender<()>>,
+}
+
+impl<T> Critical<T> {
+    /// Returns `true` if the message was sent with [`OverwriteSender::send_critical`].
+    pub fn is_critical(&self) -> bool {
+        self.ack.is_some()
+    }
+
+    /// Returns the message, acknowledging it to its sender if it is critical.
+    pub fn into_inner(self) -> T {
+        let (value, ack) = self.into_parts();
+        if let Some(ack) = ack {
+            let _ = ack.send(());
+        }
+        value
+    }
+
+    /// Returns the message without acknowledging it.
+    fn into_parts(self) -> (T, Option<flume::Sender<()>>) {
+        (self.value, self.ack)
+    }
+}
+
+impl<T> Deref for Critical<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Critical<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Critical")
+            .field("value", &self.value)
+            .field("critical", &self.is_critical())
+            .finish()
+    }
+}
+
+/// Reports whether a critical message was received, as returned by
+/// [`OverwriteSender::send_critical`].
+#[must_use = "an acknowledgement does nothing unless waited on"]
+#[derive(Debug)]
+pub struct Acknowledgement {
+    ack: flume::Receiver<()>,
+}
+
+impl Acknowledgement {
+    /// Returns `true` if the consumer has already taken the message.
+    pub fn is_acknowledged(&self) -> bool {
+        !self.ack.is_empty()
+    }
+
+    /// Blocks until the consumer takes the message, returning `true`, or until the message
+    /// is dropped without being taken, returning `false`.
+    pub fn wait(self) -> bool {
+        self.ack.recv().is_ok()
+    }
+
+    /// Waits asynchronously for the consumer to take the message.
+    ///
+    /// This is the async version of [`wait`](Acknowledgement::wait).
+    pub async fn wait_async(self) -> bool {
+        self.ack.recv_async().await.is_ok()
+    }
+}
+
+impl<T> Builder<Critical<T>> {
+    /// Protects critical messages from being evicted, while lossy messages remain lossy.
+    ///
+    /// This is [`protect`](Builder::protect) with [`Critical::is_critical`], and is what
+    /// keeps [`send_critical`](OverwriteSender::send_critical) from being overwritten.
+    pub fn protect_critical(self) -> Self
+    where
+        T: 'static,
+    {
+        self.protect(Critical::is_critical)
+    }
+}
+
+impl<T> OverwriteSender<Critical<T>> {
+    /// Sends a value that must reach the consumer, returning an [`Acknowledgement`] of its
+    /// receipt along with any lossy message it overwrote.
+    ///
+    /// On a channel built with [`protect_critical`](Builder::protect_critical), the message
+    /// is never overwritten, and a send finding the channel full of critical messages is
+    /// rejected. On other channels it is only as safe as a lossy message, which the
+    /// acknowledgement reveals by resolving to `false` if it is evicted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::new(2).protect_critical().build();
+    ///
+    /// let (ack, _) = sender.send_critical("shutdown").unwrap();
+    /// for position in ["x=1", "x=2", "x=3"] {
+    ///     sender.send_lossy(position).unwrap();
+    /// }
+    ///
+    /// // The command outlived the position updates that came after it
+    /// assert!(!ack.is_acknowledged());
+    /// assert_eq!(receiver.recv().unwrap().into_inner(), "shutdown");
+    /// assert!(ack.wait());
+    /// ```
+    #[allow(clippy::type_complexity)]
+    pub fn send_critical(
+        &self,
+        value: T,
+    ) -> Result<(Acknowledgement, Option<Vec<T>>), SendOverwriteError<T>> {
+        let (ack, acknowledgement) = flume::bounded(1);
+        let message = Critical {
+            value,
+            ack: Some(ack),
+        };
+        match self.send_overwrite(message) {
+            Ok(evicted) => Ok((
+                Acknowledgement {
+                    ack: acknowledgement,
+                },
+                evicted.map(values),
+            )),
+            Err(err) => Err(err.map(|message| message.into_parts().0)),
+        }
+    }
+
+    /// Sends a value that may be overwritten, with overwrite semantics.
+    ///
+    /// See [`send_overwrite`](OverwriteSender::send_overwrite) for the returned values.
+    pub fn send_lossy(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let message = Critical { value, ack: None };
+        self.send_overwrite(message)
+            .map(|evicted| evicted.map(values))
+            .map_err(|err| err.map(|message| message.into_parts().0))
+    }
+}
+
+/// Unwraps evicted messages without acknowledging them.
+fn values<T>(messages: Vec<Critical<T>>) -> Vec<T> {
+    messages
+        .into_iter()
+        .map(|message| message.into_parts().0)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Builder;
+
+    #[test]
+    fn test_critical_messages_fill_the_channel() {
+        let (sender, receiver) = Builder::new(1).protect_critical().build();
+        let (first, _) = sender.send_critical(1).unwrap();
+        assert!(sender.send_critical(2).unwrap_err().is_rejected());
+        assert!(sender.send_lossy(3).unwrap_err().is_rejected());
+
+        // Dropped unreceived, the message is never acknowledged
+        drop(receiver);
+        assert!(!first.wait());
+    }
+}
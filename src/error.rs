@@ -0,0 +1,148 @@
+//! Errors returned when sending with overwrite semantics.
+
+use flume::SendError;
+use std::error::Error;
+use std::fmt;
+
+/// An error returned by `send_overwrite` and its variants.
+///
+/// Like flume's `SendError`, the error hands back the message that could not be sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SendOverwriteError<T> {
+    /// All receivers have been dropped.
+    Disconnected(T),
+    /// The channel is full and cannot evict anything to make room: either the eviction budget
+    /// of the current window is spent, see
+    /// [`Builder::eviction_budget`](crate::Builder::eviction_budget), every queued message
+    /// is protected, see [`Builder::protect`](crate::Builder::protect), or the channel's
+    /// policy is [`OverflowPolicy::RejectNew`](crate::OverflowPolicy::RejectNew).
+    Rejected(T),
+    /// The message weighs more than the channel accepts, see
+    /// [`Builder::max_message_weight`](crate::Builder::max_message_weight), or needs more
+    /// chunks than a [`bounded_split`](crate::bounded_split) channel has slots.
+    Oversized(T),
+    /// The channel stayed full while every attempt to evict from it found the queue empty,
+    /// as happens when receivers keep racing the sender for the same messages.
+    Contended(T),
+}
+
+impl<T> SendOverwriteError<T> {
+    /// Returns the message that could not be sent.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::Disconnected(value)
+            | Self::Rejected(value)
+            | Self::Oversized(value)
+            | Self::Contended(value) => value,
+        }
+    }
+
+    /// Returns `true` if the send failed because all receivers have been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self, Self::Disconnected(_))
+    }
+
+    /// Returns `true` if the send was rejected because nothing could be evicted.
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Self::Rejected(_))
+    }
+
+    /// Returns `true` if the send was rejected because the message is too heavy.
+    pub fn is_oversized(&self) -> bool {
+        matches!(self, Self::Oversized(_))
+    }
+
+    /// Returns `true` if the send gave up because eviction could not make progress.
+    pub fn is_contended(&self) -> bool {
+        matches!(self, Self::Contended(_))
+    }
+
+    /// Maps the message held by the error, keeping the kind of failure.
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> SendOverwriteError<U> {
+        match self {
+            Self::Disconnected(value) => SendOverwriteError::Disconnected(f(value)),
+            Self::Rejected(value) => SendOverwriteError::Rejected(f(value)),
+            Self::Oversized(value) => SendOverwriteError::Oversized(f(value)),
+            Self::Contended(value) => SendOverwriteError::Contended(f(value)),
+        }
+    }
+}
+
+impl<T> From<SendError<T>> for SendOverwriteError<T> {
+    fn from(SendError(value): SendError<T>) -> Self {
+        Self::Disconnected(value)
+    }
+}
+
+impl<T> fmt::Debug for SendOverwriteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected(_) => f.write_str("Disconnected(..)"),
+            Self::Rejected(_) => f.write_str("Rejected(..)"),
+            Self::Oversized(_) => f.write_str("Oversized(..)"),
+            Self::Contended(_) => f.write_str("Contended(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendOverwriteError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected(_) => f.write_str("sending on a closed channel"),
+            Self::Rejected(_) => f.write_str("channel is full and nothing can be evicted"),
+            Self::Oversized(_) => f.write_str("message exceeds the maximum message weight"),
+            Self::Contended(_) => f.write_str("eviction made no progress"),
+        }
+    }
+}
+
+impl<T> Error for SendOverwriteError<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A message type implementing neither `Debug` nor `Display`.
+    #[derive(PartialEq, Eq)]
+    struct Opaque(u8);
+
+    #[test]
+    fn test_every_kind_hands_the_message_back() {
+        let errors = [
+            SendOverwriteError::Disconnected(Opaque(0)),
+            SendOverwriteError::Rejected(Opaque(1)),
+            SendOverwriteError::Oversized(Opaque(2)),
+            SendOverwriteError::Contended(Opaque(3)),
+        ];
+        for (i, error) in errors.into_iter().enumerate() {
+            let kinds = [
+                error.is_disconnected(),
+                error.is_rejected(),
+                error.is_oversized(),
+                error.is_contended(),
+            ];
+            assert_eq!(kinds.iter().position(|&kind| kind), Some(i));
+            // Mapping the message keeps the kind
+            let mapped = error.map(|Opaque(value)| value * 10);
+            assert_eq!(mapped.is_rejected(), i == 1);
+            assert_eq!(mapped.into_inner(), i as u8 * 10);
+        }
+    }
+
+    #[test]
+    fn test_formatting_does_not_need_the_message() {
+        let error = SendOverwriteError::Rejected(Opaque(0));
+        assert_eq!(format!("{error:?}"), "Rejected(..)");
+        assert_eq!(
+            error.to_string(),
+            "channel is full and nothing can be evicted"
+        );
+
+        let error: SendOverwriteError<Opaque> = SendError(Opaque(7)).into();
+        assert!(error.is_disconnected());
+        assert_eq!(error.to_string(), "sending on a closed channel");
+        let boxed: Box<dyn Error> = Box::new(error);
+        assert!(boxed.source().is_none());
+    }
+}
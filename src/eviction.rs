@@ -0,0 +1,197 @@
+//! Evictions as an asynchronous stream of events.
+
+use crate::{Builder, ChannelId, OverwriteReceiver, OverwriteSender, Shared};
+use futures_core::Stream;
+use futures_core::stream::FusedStream;
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// A report that messages were evicted from a channel, yielded by an [`EvictionStream`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EvictionEvent {
+    /// The channel the messages were evicted from.
+    pub channel: ChannelId,
+    /// The number of messages evicted at once.
+    pub count: usize,
+    /// The number of messages evicted from the channel so far, this eviction included.
+    ///
+    /// Comparing totals tells how many evictions a lagging stream missed.
+    pub total: u64,
+    /// When the messages were evicted, on the channel's [`Clock`](crate::Clock).
+    pub at: Instant,
+}
+
+/// A stream of the evictions of a channel, created with
+/// [`OverwriteReceiver::evictions`] or [`OverwriteSender::evictions`].
+///
+/// Unlike a callback, the stream can be polled along with other signals, for instance in
+/// a `select!`, from a single monitoring task. It keeps only the latest events up to its
+/// capacity, so a slow monitor never holds up senders, and it ends once every sender of
+/// the channel has been dropped. The stream is fused, so it can be used with `select!`
+/// directly.
+#[derive(Debug)]
+pub struct EvictionStream {
+    receiver: OverwriteReceiver<EvictionEvent>,
+    terminated: bool,
+}
+
+impl EvictionStream {
+    /// Returns the underlying receiver of events.
+    pub fn receiver(&self) -> &OverwriteReceiver<EvictionEvent> {
+        &self.receiver
+    }
+}
+
+impl Stream for EvictionStream {
+    type Item = EvictionEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<EvictionEvent>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+        let event = Pin::new(&mut self.receiver).poll_next(cx);
+        if let Poll::Ready(None) = event {
+            self.terminated = true;
+        }
+        event
+    }
+}
+
+impl FusedStream for EvictionStream {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl<T> Shared<T> {
+    /// Counts evicted messages, reporting them to every eviction stream.
+    pub(crate) fn count_evictions(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let total = self.evicted.add(count);
+        let mut listeners = self.lock_eviction_listeners();
+        if listeners.is_empty() {
+            return;
+        }
+        let event = EvictionEvent {
+            channel: self.id,
+            count,
+            total,
+            at: self.clock.now(),
+        };
+        listeners.retain(|listener| listener.send_overwrite(event.clone()).is_ok());
+    }
+
+    fn evictions(&self, cap: usize) -> EvictionStream {
+        let mut builder = Builder::new(cap);
+        if let Some(name) = &self.name {
+            builder = builder.name(format!("{name}.evictions"));
+        }
+        let (sender, receiver) = builder.build();
+        let mut listeners = self.lock_eviction_listeners();
+        // A channel whose senders are all gone will not evict anything
+        if self.sender_count.load(Ordering::Acquire) > 0 {
+            listeners.push(sender);
+        }
+        EvictionStream {
+            receiver,
+            terminated: false,
+        }
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Creates a stream of the channel's evictions from now on, keeping up to `cap` of the
+    /// latest events.
+    ///
+    /// If the channel is named, the stream's channel is named after it with an
+    /// `.evictions` suffix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::StreamExt;
+    /// use futures::executor::block_on;
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// let mut evictions = receiver.evictions(16);
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// sender.send_overwrite(2).unwrap();
+    /// sender.send_overwrite(3).unwrap();
+    /// drop(sender);
+    ///
+    /// let totals: Vec<u64> = block_on(evictions.map(|event| event.total).collect());
+    /// assert_eq!(totals, vec![1, 2]);
+    /// ```
+    #[track_caller]
+    pub fn evictions(&self, cap: usize) -> EvictionStream {
+        self.shared.evictions(cap)
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Creates a stream of the channel's evictions from now on, keeping up to `cap` of the
+    /// latest events.
+    ///
+    /// See [`OverwriteReceiver::evictions`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn evictions(&self, cap: usize) -> EvictionStream {
+        self.shared.evictions(cap)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Builder;
+    use futures::executor::block_on;
+    use futures::{FutureExt, StreamExt, select};
+
+    #[test]
+    fn test_select_over_evictions() {
+        let (sender, receiver) = Builder::new(1).name("frames").build();
+        let mut evictions = receiver.evictions(1);
+        assert_eq!(
+            evictions.receiver().stats().name.as_deref(),
+            Some("frames.evictions")
+        );
+        let (shutdown, stop) = flume::bounded::<()>(1);
+
+        for i in 0..4 {
+            sender.send_overwrite(i).unwrap();
+        }
+        // Only the latest event is kept
+        let event = block_on(async {
+            select! {
+                event = evictions.next() => event,
+                _ = stop.recv_async().fuse() => None,
+            }
+        });
+        let event = event.unwrap();
+        assert_eq!(
+            (event.channel, event.count, event.total),
+            (sender.channel_id(), 1, 3)
+        );
+
+        shutdown.send(()).unwrap();
+        let event = block_on(async {
+            select! {
+                event = evictions.next() => event,
+                _ = stop.recv_async().fuse() => None,
+            }
+        });
+        assert_eq!(event, None);
+    }
+}
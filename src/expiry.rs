@@ -0,0 +1,312 @@
+//! Messages with a time to live or a deadline, and the sweeping of expired ones.
+
+use crate::{Controller, OverwriteSender, SendOverwriteError};
+use flume::RecvTimeoutError;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// A message sent with [`OverwriteSender::send_expiring`] or
+/// [`OverwriteSender::send_with_deadline`], along with its expiry time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expiring<T> {
+    expires_at: Instant,
+    value: T,
+}
+
+impl<T> Expiring<T> {
+    /// Returns the time after which the message is stale, as measured by the channel's
+    /// [`Clock`](crate::Clock).
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    /// Returns `true` if the message is stale at `now`.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+
+    /// Returns the message.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Expiring<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> OverwriteSender<Expiring<T>> {
+    /// Sends a value that goes stale after `ttl`, with overwrite semantics.
+    ///
+    /// When the channel is full, expired messages are swept before any live message is
+    /// overwritten, so stale messages never push out fresh ones.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without removing any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were swept or overwritten, oldest first
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    /// sender.send_expiring("quote", Duration::ZERO).unwrap();
+    /// sender.send_expiring("order", Duration::from_secs(60)).unwrap();
+    ///
+    /// // The expired quote makes room, the order survives
+    /// let removed = sender.send_expiring("fill", Duration::from_secs(60)).unwrap();
+    /// assert_eq!(removed, Some(vec!["quote"]));
+    /// assert_eq!(*receiver.recv().unwrap(), "order");
+    /// ```
+    pub fn send_expiring(
+        &self,
+        value: T,
+        ttl: Duration,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.send_with_deadline(value, self.shared.clock.now() + ttl)
+    }
+
+    /// Sends a value that is useless once `deadline` has passed, with overwrite semantics.
+    ///
+    /// This is [`send_expiring`](Self::send_expiring) with an absolute deadline, as
+    /// measured by the channel's [`Clock`](crate::Clock), for requests whose caller gives
+    /// up at a fixed time. To drop undelivered messages at their deadline regardless of
+    /// capacity pressure, run a sweeper, see
+    /// [`spawn_sweeper_with`](Self::spawn_sweeper_with).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let deadline = Instant::now() + Duration::from_secs(5);
+    /// sender.send_with_deadline("lookup user 42", deadline).unwrap();
+    ///
+    /// let request = receiver.recv().unwrap();
+    /// assert_eq!(request.expires_at(), deadline);
+    /// ```
+    pub fn send_with_deadline(
+        &self,
+        value: T,
+        deadline: Instant,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut removed = if self.is_full() {
+            self.sweep()
+        } else {
+            Vec::new()
+        };
+        let message = Expiring {
+            expires_at: deadline,
+            value,
+        };
+        if let Some(overwritten) = self
+            .send_overwrite(message)
+            .map_err(|error| error.map(Expiring::into_inner))?
+        {
+            removed.extend(overwritten.into_iter().map(Expiring::into_inner));
+        }
+        Ok(if removed.is_empty() {
+            None
+        } else {
+            Some(removed)
+        })
+    }
+
+    /// Removes and returns every expired message, oldest first.
+    ///
+    /// Expiry is otherwise only enforced when a send finds the channel full, so sweeping
+    /// frees the capacity stale messages occupy in between. Swept messages are not counted
+    /// as evicted. See [`spawn_sweeper`](Self::spawn_sweeper) for sweeping periodically.
+    pub fn sweep(&self) -> Vec<T> {
+        let now = self.shared.clock.now();
+        self.cancel_where(|message| message.is_expired(now))
+            .into_iter()
+            .map(Expiring::into_inner)
+            .collect()
+    }
+
+    /// Spawns a thread sweeping the channel every `interval`, returning a guard that stops it.
+    ///
+    /// The sweeper does not count as a sender and exits on its own once every sender has
+    /// been dropped. Swept messages are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let sweeper = sender.spawn_sweeper(Duration::from_millis(1));
+    ///
+    /// sender.send_expiring("heartbeat", Duration::ZERO).unwrap();
+    /// while sweeper.swept() == 0 {
+    ///     thread::sleep(Duration::from_millis(1));
+    /// }
+    /// assert!(receiver.is_empty());
+    /// ```
+    pub fn spawn_sweeper(&self, interval: Duration) -> Sweeper
+    where
+        T: Send + 'static,
+    {
+        self.spawn_sweeper_with(interval, drop)
+    }
+
+    /// Spawns a thread sweeping the channel every `interval` and reporting every swept
+    /// message to `on_expired`, returning a guard that stops it.
+    ///
+    /// Messages that were not received by their deadline are thus dropped automatically,
+    /// independently of capacity pressure, and their sender can be told, for instance to
+    /// fail the request they carried. `on_expired` runs on the sweeper's thread. See
+    /// [`spawn_sweeper`](Self::spawn_sweeper).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Instant;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let (timed_out_tx, timed_out) = flume::unbounded();
+    /// let _sweeper = sender.spawn_sweeper_with(std::time::Duration::from_millis(1), move |request| {
+    ///     timed_out_tx.send(request).unwrap();
+    /// });
+    ///
+    /// sender.send_with_deadline("lookup user 42", Instant::now()).unwrap();
+    /// assert_eq!(timed_out.recv().unwrap(), "lookup user 42");
+    /// assert!(receiver.is_empty());
+    /// ```
+    pub fn spawn_sweeper_with<F>(&self, interval: Duration, mut on_expired: F) -> Sweeper
+    where
+        T: Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        let controller = Controller::new(self);
+        let clock = self.shared.clock.clone();
+        let swept = Arc::new(AtomicU64::new(0));
+        let counter = swept.clone();
+        let (stop, stopped) = flume::bounded::<()>(0);
+        let handle = thread::spawn(move || {
+            while stopped.recv_timeout(interval) == Err(RecvTimeoutError::Timeout)
+                && !controller.is_disconnected()
+            {
+                let now = clock.now();
+                let removed = controller.cancel_where(|message| message.is_expired(now));
+                counter.fetch_add(removed.len() as u64, Ordering::Relaxed);
+                removed
+                    .into_iter()
+                    .for_each(|message| on_expired(message.into_inner()));
+            }
+        });
+        Sweeper {
+            stop: Some(stop),
+            handle: Some(handle),
+            swept,
+        }
+    }
+}
+
+/// A guard for a sweeper started with [`OverwriteSender::spawn_sweeper`].
+///
+/// Dropping the guard stops the sweeper, blocking until its thread has exited.
+#[must_use = "dropping the sweeper immediately stops it"]
+pub struct Sweeper {
+    stop: Option<flume::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    swept: Arc<AtomicU64>,
+}
+
+impl Sweeper {
+    /// Returns the number of expired messages swept so far.
+    pub fn swept(&self) -> u64 {
+        self.swept.load(Ordering::Relaxed)
+    }
+
+    /// Returns `true` if the sweeper has exited because every sender was dropped.
+    pub fn is_finished(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+    }
+}
+
+impl fmt::Debug for Sweeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sweeper")
+            .field("swept", &self.swept())
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sweep_keeps_live_messages() {
+        let (sender, receiver) = bounded(4);
+        sender.send_expiring(1, Duration::ZERO).unwrap();
+        sender.send_expiring(2, Duration::from_secs(60)).unwrap();
+        sender.send_expiring(3, Duration::ZERO).unwrap();
+
+        assert_eq!(sender.sweep(), vec![1, 3]);
+        assert_eq!(sender.sweep(), Vec::<i32>::new());
+        assert_eq!(receiver.recv().unwrap().into_inner(), 2);
+        assert_eq!(receiver.stats().evicted, 0);
+    }
+
+    #[test]
+    fn test_sweeper_exits_once_disconnected() {
+        let (sender, _receiver) = bounded::<crate::Expiring<()>>(1);
+        let sweeper = sender.spawn_sweeper(Duration::from_millis(1));
+        drop(sender);
+        while !sweeper.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+    #[test]
+    fn test_sweeper_reports_missed_deadlines() {
+        let (sender, receiver) = bounded(4);
+        let (expired_tx, expired) = flume::unbounded();
+        let sweeper = sender.spawn_sweeper_with(Duration::from_millis(1), move |value| {
+            expired_tx.send(value).unwrap();
+        });
+        let now = std::time::Instant::now();
+        sender.send_with_deadline(1, now).unwrap();
+        sender
+            .send_with_deadline(2, now + Duration::from_secs(60))
+            .unwrap();
+
+        assert_eq!(expired.recv().unwrap(), 1);
+        drop(sweeper);
+        assert!(expired.try_recv().is_err());
+        assert_eq!(receiver.recv().unwrap().into_inner(), 2);
+    }
+}
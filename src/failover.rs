@@ -0,0 +1,163 @@
+//! Senders failing over to a secondary channel.
+
+use crate::{OverwriteSender, SendOverwriteError};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+type FailoverHook = Box<dyn Fn() + Send + Sync>;
+
+/// A sender failing over to a secondary channel once its primary is disconnected, created
+/// with [`OverwriteSender::with_fallback`].
+///
+/// Failing over is permanent: once a send finds every receiver of the primary channel
+/// gone, all later sends go to the secondary. Clones share the failover, so the hook fires
+/// once for all of them.
+pub struct FailoverSender<T> {
+    primary: OverwriteSender<T>,
+    fallback: OverwriteSender<T>,
+    state: Arc<Failover>,
+}
+
+struct Failover {
+    failed_over: AtomicBool,
+    hook: Option<FailoverHook>,
+}
+
+impl<T> OverwriteSender<T> {
+    /// Pairs this sender with a `fallback` sender that takes over once this channel is
+    /// disconnected.
+    ///
+    /// Producers keep sending through the returned [`FailoverSender`] while the consumer is
+    /// replaced: the old consumer drops its receiver, and sends transparently go to the
+    /// channel the new consumer reads from.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (primary, old_consumer) = bounded(4);
+    /// let (secondary, new_consumer) = bounded(4);
+    /// let sender = primary.with_fallback(secondary);
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// drop(old_consumer);
+    /// sender.send_overwrite(2).unwrap();
+    ///
+    /// assert!(sender.is_failed_over());
+    /// assert_eq!(new_consumer.recv().unwrap(), 2);
+    /// ```
+    pub fn with_fallback(self, fallback: OverwriteSender<T>) -> FailoverSender<T> {
+        FailoverSender {
+            primary: self,
+            fallback,
+            state: Arc::new(Failover {
+                failed_over: AtomicBool::new(false),
+                hook: None,
+            }),
+        }
+    }
+}
+
+impl<T> FailoverSender<T> {
+    /// Calls `f` once, when sends first fail over to the secondary channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sender was cloned, since the clones would not share the hook.
+    #[track_caller]
+    pub fn on_failover<F>(mut self, f: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        Arc::get_mut(&mut self.state)
+            .expect("the failover hook must be set before cloning the sender")
+            .hook = Some(Box::new(f));
+        self
+    }
+
+    /// Sends a value with overwrite semantics to the primary channel, or to the secondary
+    /// one once the primary is disconnected.
+    ///
+    /// See [`OverwriteSender::send_overwrite`] for the returned values. The send only fails
+    /// as disconnected once both channels are.
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        if !self.is_failed_over() {
+            match self.primary.send_overwrite(value) {
+                Err(SendOverwriteError::Disconnected(value)) => {
+                    self.fail_over();
+                    return self.fallback.send_overwrite(value);
+                }
+                result => return result,
+            }
+        }
+        self.fallback.send_overwrite(value)
+    }
+
+    /// Returns `true` once sends go to the secondary channel.
+    pub fn is_failed_over(&self) -> bool {
+        self.state.failed_over.load(Ordering::Acquire)
+    }
+
+    /// Returns the sender currently in use: the primary one, or the secondary one once
+    /// failed over.
+    pub fn active(&self) -> &OverwriteSender<T> {
+        if self.is_failed_over() {
+            &self.fallback
+        } else {
+            &self.primary
+        }
+    }
+
+    fn fail_over(&self) {
+        if !self.state.failed_over.swap(true, Ordering::AcqRel)
+            && let Some(hook) = &self.state.hook
+        {
+            hook();
+        }
+    }
+}
+
+impl<T> Clone for FailoverSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            fallback: self.fallback.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for FailoverSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailoverSender")
+            .field("active", self.active())
+            .field("failed_over", &self.is_failed_over())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_hook_fires_once_across_clones() {
+        let failovers = Arc::new(AtomicUsize::new(0));
+        let count = failovers.clone();
+        let (primary, consumer) = bounded(1);
+        let (secondary, _standby) = bounded(1);
+        let sender = primary.with_fallback(secondary).on_failover(move || {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+        let clone = sender.clone();
+
+        drop(consumer);
+        sender.send_overwrite(1).unwrap();
+        clone.send_overwrite(2).unwrap();
+        assert_eq!(failovers.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,331 @@
+//! Fair dispatch of messages across several receivers.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Creates a channel that dispatches messages round-robin across its receivers.
+///
+/// Cloning the returned [`FairReceiver`] registers a new consumer. Every consumer owns a
+/// lane holding at most `cap` messages, and each message is handed to exactly one lane in
+/// strict rotation. A fast consumer therefore cannot take messages meant for a slow one;
+/// when a slow consumer falls behind, only its own lane overwrites old messages.
+///
+/// When a receiver is dropped, the messages still queued in its lane are dispatched to the
+/// remaining receivers.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::fair;
+///
+/// let (sender, receiver_a) = fair(4);
+/// let receiver_b = receiver_a.clone();
+///
+/// for i in 0..4 {
+///     sender.send_overwrite(i).unwrap();
+/// }
+///
+/// assert_eq!(receiver_a.drain().collect::<Vec<_>>(), vec![0, 2]);
+/// assert_eq!(receiver_b.drain().collect::<Vec<_>>(), vec![1, 3]);
+/// ```
+pub fn fair<T>(cap: usize) -> (FairSender<T>, FairReceiver<T>) {
+    let (lane_sender, receiver) = bounded(cap);
+    let dispatch = Arc::new(Dispatch {
+        cap,
+        state: Mutex::new(DispatchState {
+            lanes: vec![Lane {
+                id: 0,
+                sender: lane_sender,
+            }],
+            next: 0,
+            next_id: 1,
+            senders: 1,
+        }),
+    });
+    let sender = FairSender {
+        dispatch: dispatch.clone(),
+    };
+    let receiver = FairReceiver {
+        id: 0,
+        receiver,
+        dispatch,
+    };
+    (sender, receiver)
+}
+
+struct Dispatch<T> {
+    cap: usize,
+    state: Mutex<DispatchState<T>>,
+}
+
+struct DispatchState<T> {
+    lanes: Vec<Lane<T>>,
+    next: usize,
+    next_id: u64,
+    senders: usize,
+}
+
+struct Lane<T> {
+    id: u64,
+    sender: OverwriteSender<T>,
+}
+
+impl<T> Dispatch<T> {
+    fn lock(&self) -> MutexGuard<'_, DispatchState<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> DispatchState<T> {
+    /// Returns the lane whose turn it is and advances the rotation.
+    fn next_lane(&mut self) -> Option<&Lane<T>> {
+        if self.lanes.is_empty() {
+            return None;
+        }
+        let index = self.next % self.lanes.len();
+        self.next = index + 1;
+        Some(&self.lanes[index])
+    }
+}
+
+/// The sending half of a [`fair`] channel.
+///
+/// Each message is delivered to the next receiver in rotation, overwriting the oldest
+/// message in that receiver's lane if it is full.
+pub struct FairSender<T> {
+    dispatch: Arc<Dispatch<T>>,
+}
+
+impl<T> FairSender<T> {
+    /// Sends a value to the next receiver in rotation, overwriting its oldest message if
+    /// its lane is at capacity.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages that were overwritten in the receiving lane
+    /// - `Err(SendOverwriteError<T>)` - Every receiver has been dropped
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut state = self.dispatch.lock();
+        match state.next_lane() {
+            Some(lane) => lane.sender.send_overwrite(value),
+            None => Err(SendOverwriteError::Disconnected(value)),
+        }
+    }
+
+    /// Asynchronously sends a value to the next receiver in rotation.
+    ///
+    /// This is the async version of [`send_overwrite`](FairSender::send_overwrite).
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let lane = match self.dispatch.lock().next_lane() {
+            Some(lane) => lane.sender.clone(),
+            None => return Err(SendOverwriteError::Disconnected(value)),
+        };
+        lane.send_overwrite_async(value).await
+    }
+
+    /// Returns the number of receivers messages are currently dispatched to.
+    pub fn receiver_count(&self) -> usize {
+        self.dispatch.lock().lanes.len()
+    }
+}
+
+impl<T> Clone for FairSender<T> {
+    fn clone(&self) -> Self {
+        self.dispatch.lock().senders += 1;
+        Self {
+            dispatch: self.dispatch.clone(),
+        }
+    }
+}
+
+impl<T> Drop for FairSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.dispatch.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            // Dropping the lane senders disconnects every receiver.
+            state.lanes.clear();
+        }
+    }
+}
+
+impl<T> fmt::Debug for FairSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.dispatch.lock();
+        f.debug_struct("FairSender")
+            .field("cap", &self.dispatch.cap)
+            .field("receivers", &state.lanes.len())
+            .finish()
+    }
+}
+
+/// A receiving half of a [`fair`] channel, owning its own lane.
+///
+/// `FairReceiver<T>` implements `Deref` to [`OverwriteReceiver<T>`] for its lane, so all
+/// receiving methods are available. Cloning a `FairReceiver` registers a new consumer with
+/// an empty lane rather than sharing this one.
+pub struct FairReceiver<T> {
+    id: u64,
+    receiver: OverwriteReceiver<T>,
+    dispatch: Arc<Dispatch<T>>,
+}
+
+impl<T> Clone for FairReceiver<T> {
+    fn clone(&self) -> Self {
+        let (lane_sender, receiver) = bounded(self.dispatch.cap);
+        let mut state = self.dispatch.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        // Without senders the new lane is left disconnected, like every other one.
+        if state.senders > 0 {
+            state.lanes.push(Lane {
+                id,
+                sender: lane_sender,
+            });
+        }
+        Self {
+            id,
+            receiver,
+            dispatch: self.dispatch.clone(),
+        }
+    }
+}
+
+impl<T> Deref for FairReceiver<T> {
+    type Target = OverwriteReceiver<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+impl<T> Drop for FairReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.dispatch.lock();
+        let Some(position) = state.lanes.iter().position(|lane| lane.id == self.id) else {
+            return;
+        };
+        let lane = state.lanes.remove(position);
+        if position < state.next {
+            state.next -= 1;
+        }
+        for value in self.receiver.drain() {
+            if let Some(lane) = state.next_lane() {
+                let _ = lane.sender.send_overwrite(value);
+            }
+        }
+        drop(lane);
+    }
+}
+
+impl<T> fmt::Debug for FairReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FairReceiver")
+            .field("id", &self.id)
+            .field("lane", &self.receiver)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::thread;
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_fair_round_robin() {
+        let (sender, receiver_a) = fair(8);
+        let receiver_b = receiver_a.clone();
+        let receiver_c = receiver_a.clone();
+        assert_eq!(sender.receiver_count(), 3);
+        for i in 0..6 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert_eq!(receiver_a.drain().collect::<Vec<_>>(), vec![0, 3]);
+        assert_eq!(receiver_b.drain().collect::<Vec<_>>(), vec![1, 4]);
+        assert_eq!(receiver_c.drain().collect::<Vec<_>>(), vec![2, 5]);
+    }
+
+    #[test]
+    fn test_fair_overwrites_per_lane() {
+        let (sender, receiver_a) = fair(1);
+        let receiver_b = receiver_a.clone();
+        assert_eq!(sender.send_overwrite(0).unwrap(), None);
+        assert_eq!(sender.send_overwrite(1).unwrap(), None);
+        assert_eq!(receiver_b.recv().unwrap(), 1);
+        assert_eq!(sender.send_overwrite(2).unwrap(), Some(vec![0]));
+        assert_eq!(sender.send_overwrite(3).unwrap(), None);
+        assert_eq!(receiver_a.recv().unwrap(), 2);
+        assert_eq!(receiver_b.recv().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_fair_redispatches_on_drop() {
+        let (sender, receiver_a) = fair(4);
+        let receiver_b = receiver_a.clone();
+        for i in 0..4 {
+            sender.send_overwrite(i).unwrap();
+        }
+        drop(receiver_a);
+        assert_eq!(sender.receiver_count(), 1);
+        assert_eq!(receiver_b.drain().collect::<Vec<_>>(), vec![1, 3, 0, 2]);
+        drop(receiver_b);
+        assert_eq!(sender.send_overwrite(4).unwrap_err().into_inner(), 4);
+    }
+
+    #[test]
+    fn test_fair_disconnects_when_senders_dropped() {
+        let (sender, receiver) = fair(2);
+        block_on(sender.send_overwrite_async(1)).unwrap();
+        drop(sender);
+        assert_eq!(receiver.recv().unwrap(), 1);
+        assert!(receiver.recv().is_err());
+        assert!(receiver.clone().recv().is_err());
+    }
+
+    #[test]
+    fn test_fair_distribution_bounds_with_slow_consumer() {
+        const MESSAGES: usize = 300;
+        let (sender, receiver) = fair(MESSAGES);
+        let consumers: Vec<_> = (0..3)
+            .map(|i| {
+                let receiver = receiver.clone();
+                thread::spawn(move || {
+                    let mut received = 0;
+                    while receiver.recv().is_ok() {
+                        received += 1;
+                        // The first consumer is much slower than the others
+                        if i == 0 {
+                            thread::sleep(Duration::from_micros(200));
+                        }
+                    }
+                    received
+                })
+            })
+            .collect();
+        drop(receiver);
+        for i in 0..MESSAGES {
+            sender.send_overwrite(i).unwrap();
+        }
+        drop(sender);
+        let counts: Vec<usize> = consumers.into_iter().map(|c| c.join().unwrap()).collect();
+        assert_eq!(counts.iter().sum::<usize>(), MESSAGES);
+        for count in counts {
+            assert!(
+                count.abs_diff(MESSAGES / 3) <= 1,
+                "unfair distribution: {count}"
+            );
+        }
+    }
+}
@@ -0,0 +1,396 @@
+//! Merging of several sources into one channel with age-ordered eviction.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use flume::{Receiver, RecvError, Selector, TryRecvError};
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::{Future, poll_fn};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::Poll;
+
+/// A message tagged with its position in the global enqueue order.
+type Stamped<T> = (u64, T);
+
+/// Creates a channel merging `sources` producers into one receiver, sharing a total
+/// capacity of `cap` messages.
+///
+/// Every source has its own sub-queue and the receiver takes from them in rotation, so a
+/// bursty source cannot starve the others. When the channel is full, the oldest message
+/// across all sources is evicted, by enqueue order rather than by position within its
+/// source: a burst from one source evicts its own older data before the newer data of a
+/// quiet source. Finding that message costs one check per source, however large the
+/// capacity.
+///
+/// Returns one [`FanInSender`] per source, in order.
+///
+/// # Panics
+///
+/// Panics if `cap` or `sources` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::fan_in;
+///
+/// let (senders, receiver) = fan_in(3, 2);
+/// let (camera, lidar) = (&senders[0], &senders[1]);
+///
+/// camera.send_overwrite("frame 1").unwrap();
+/// lidar.send_overwrite("scan 1").unwrap();
+/// camera.send_overwrite("frame 2").unwrap();
+///
+/// // The oldest message overall is evicted, even though it came from another source
+/// assert_eq!(lidar.send_overwrite("scan 2").unwrap(), Some(vec!["frame 1"]));
+///
+/// // Sources are received in rotation
+/// assert_eq!(receiver.recv().unwrap(), "frame 2");
+/// assert_eq!(receiver.recv().unwrap(), "scan 1");
+/// assert_eq!(receiver.recv().unwrap(), "scan 2");
+/// ```
+#[track_caller]
+pub fn fan_in<T>(cap: usize, sources: usize) -> (Vec<FanInSender<T>>, FanInReceiver<T>) {
+    assert!(sources > 0, "at least one source is required");
+    // Every sub-queue can hold the whole capacity, which the senders enforce in total
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..sources).map(|_| bounded(cap)).unzip();
+    let state = Arc::new(Mutex::new(State {
+        queued: vec![VecDeque::new(); sources],
+        next_stamp: 0,
+    }));
+    let senders = senders
+        .into_iter()
+        .enumerate()
+        .map(|(source, sender)| FanInSender {
+            source,
+            sender,
+            all: receivers
+                .iter()
+                .map(|receiver| Receiver::clone(receiver))
+                .collect(),
+            cap,
+            state: state.clone(),
+        })
+        .collect();
+    let receiver = FanInReceiver {
+        sources: receivers,
+        next: Arc::new(AtomicUsize::new(0)),
+        state,
+    };
+    (senders, receiver)
+}
+
+/// Enqueue order of the messages queued by each source, oldest first.
+struct State {
+    queued: Vec<VecDeque<u64>>,
+    next_stamp: u64,
+}
+
+impl State {
+    /// Forgets a message that left the queue of `source`.
+    fn remove(&mut self, source: usize, stamp: u64) {
+        let queued = &mut self.queued[source];
+        if let Some(position) = queued.iter().position(|queued| *queued == stamp) {
+            queued.remove(position);
+        }
+    }
+
+    /// Returns the source holding the oldest queued message.
+    fn oldest(&self) -> Option<usize> {
+        (0..self.queued.len())
+            .filter_map(|source| Some((self.queued[source].front()?, source)))
+            .min()
+            .map(|(_, source)| source)
+    }
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The sending half of one source of a [`fan_in`] channel.
+pub struct FanInSender<T> {
+    source: usize,
+    sender: OverwriteSender<Stamped<T>>,
+    /// Raw receivers of every source, for evicting from them. They do not count as
+    /// receivers, so sends still fail once the [`FanInReceiver`] is dropped.
+    all: Vec<Receiver<Stamped<T>>>,
+    cap: usize,
+    state: Arc<Mutex<State>>,
+}
+
+impl<T> FanInSender<T> {
+    /// Sends a value, evicting the oldest messages across all sources if the channel is at
+    /// capacity.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages that were overwritten, from any source
+    /// - `Err(SendOverwriteError<T>)` - The receiver has been dropped
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut state = lock(&self.state);
+        if self.sender.is_disconnected() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        let mut drained = Vec::new();
+        while self.all.iter().map(|source| source.len()).sum::<usize>() >= self.cap {
+            let Some(oldest) = state.oldest() else {
+                break;
+            };
+            match self.all[oldest].try_recv() {
+                Ok((stamp, evicted)) => {
+                    state.remove(oldest, stamp);
+                    drained.push(evicted);
+                }
+                // Received in the meantime, the receiver will forget it
+                Err(_) => {
+                    state.queued[oldest].pop_front();
+                }
+            }
+        }
+        let stamp = state.next_stamp;
+        state.next_stamp += 1;
+        self.sender
+            .send_overwrite((stamp, value))
+            .map_err(|error| error.map(|(_, value)| value))?;
+        state.queued[self.source].push_back(stamp);
+        Ok(if drained.is_empty() {
+            None
+        } else {
+            Some(drained)
+        })
+    }
+
+    /// Asynchronously sends a value.
+    ///
+    /// This is the async version of [`send_overwrite`](FanInSender::send_overwrite).
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.send_overwrite(value)
+    }
+
+    /// Returns the index of this sender's source.
+    pub fn source(&self) -> usize {
+        self.source
+    }
+}
+
+impl<T> Clone for FanInSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source,
+            sender: self.sender.clone(),
+            all: self.all.clone(),
+            cap: self.cap,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for FanInSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanInSender")
+            .field("source", &self.source)
+            .field("capacity", &self.cap)
+            .finish()
+    }
+}
+
+/// The receiving half of a [`fan_in`] channel.
+///
+/// Receive operations take the oldest message of the next non-empty source in rotation.
+pub struct FanInReceiver<T> {
+    sources: Vec<OverwriteReceiver<Stamped<T>>>,
+    next: Arc<AtomicUsize>,
+    state: Arc<Mutex<State>>,
+}
+
+impl<T> FanInReceiver<T> {
+    /// Attempts to receive a message without blocking.
+    ///
+    /// Returns `Err(TryRecvError::Empty)` if every source is empty, and
+    /// `Err(TryRecvError::Disconnected)` once every source is empty and all senders have
+    /// been dropped.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let count = self.sources.len();
+        let start = self.next.load(Ordering::Relaxed);
+        for offset in 0..count {
+            let source = (start + offset) % count;
+            if let Ok(stamped) = self.sources[source].try_recv() {
+                self.next.store((source + 1) % count, Ordering::Relaxed);
+                return Ok(self.received(source, stamped));
+            }
+        }
+        if self.is_disconnected() {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Receives a message, blocking until one is available from any source.
+    ///
+    /// Returns an error once every source is empty and all senders have been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            // Every source was empty, so take whichever message arrives first from a source
+            // that can still produce one
+            let selected = self
+                .sources
+                .iter()
+                .enumerate()
+                .filter(|(_, source)| !source.is_disconnected())
+                .fold(Selector::new(), |selector, (index, source)| {
+                    selector.recv(source, move |result| {
+                        result.ok().map(|value| (index, value))
+                    })
+                })
+                .wait();
+            if let Some((source, stamped)) = selected {
+                return Ok(self.received(source, stamped));
+            }
+        }
+    }
+
+    /// Asynchronously receives a message, waiting until one is available from any source.
+    ///
+    /// This is the async version of [`recv`](FanInReceiver::recv).
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let mut pending: Vec<_> = self
+            .sources
+            .iter()
+            .map(|source| Some(source.recv_async()))
+            .collect();
+        poll_fn(|cx| {
+            match self.try_recv() {
+                Ok(value) => return Poll::Ready(Ok(value)),
+                Err(TryRecvError::Disconnected) => {
+                    return Poll::Ready(Err(RecvError::Disconnected));
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+            for (source, slot) in pending.iter_mut().enumerate() {
+                let Some(future) = slot else {
+                    continue;
+                };
+                match Pin::new(future).poll(cx) {
+                    Poll::Ready(Ok(stamped)) => {
+                        return Poll::Ready(Ok(self.received(source, stamped)));
+                    }
+                    // This source went away, but others may still produce messages
+                    Poll::Ready(Err(_)) => {
+                        *slot = None;
+                        cx.waker().wake_by_ref();
+                    }
+                    Poll::Pending => {}
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Returns the total number of messages queued across all sources.
+    pub fn len(&self) -> usize {
+        self.sources.iter().map(|source| source.len()).sum()
+    }
+
+    /// Returns `true` if every source is empty.
+    pub fn is_empty(&self) -> bool {
+        self.sources.iter().all(|source| source.is_empty())
+    }
+
+    /// Returns `true` if the senders of every source have been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.sources.iter().all(|source| source.is_disconnected())
+    }
+
+    /// Returns the number of sources.
+    pub fn source_count(&self) -> usize {
+        self.sources.len()
+    }
+
+    fn received(&self, source: usize, (stamp, value): Stamped<T>) -> T {
+        lock(&self.state).remove(source, stamp);
+        value
+    }
+}
+
+impl<T> Clone for FanInReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sources: self.sources.clone(),
+            next: self.next.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for FanInReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FanInReceiver")
+            .field("sources", &self.sources.len())
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor::block_on;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_fan_in_burst_evicts_own_older_data() {
+        let (senders, receiver) = fan_in(4, 2);
+        senders[1].send_overwrite(100).unwrap();
+        for i in 0..3 {
+            senders[0].send_overwrite(i).unwrap();
+        }
+        // The quiet source's message is the oldest and goes first, then the burst's own
+        assert_eq!(senders[0].send_overwrite(3).unwrap(), Some(vec![100]));
+        assert_eq!(senders[0].send_overwrite(4).unwrap(), Some(vec![0]));
+        senders[1].send_overwrite(101).unwrap();
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(received, vec![2, 101, 3, 4]);
+    }
+
+    #[test]
+    fn test_fan_in_recv_outlives_one_source() {
+        let (mut senders, receiver) = fan_in(2, 2);
+        drop(senders.remove(0));
+        let sender = senders.remove(0);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send_overwrite(1).unwrap();
+            block_on(sender.send_overwrite_async(2)).unwrap();
+        });
+        assert_eq!(receiver.recv(), Ok(1));
+        assert_eq!(block_on(receiver.recv_async()), Ok(2));
+        handle.join().unwrap();
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+        assert_eq!(
+            block_on(receiver.recv_async()),
+            Err(RecvError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_fan_in_send_fails_without_receiver() {
+        let (senders, receiver) = fan_in(1, 1);
+        drop(receiver);
+        assert!(senders[0].send_overwrite(1).unwrap_err().is_disconnected());
+    }
+}
@@ -0,0 +1,118 @@
+//! Feeding whole sources into a channel.
+
+use crate::{OverwriteSender, SendOverwriteError};
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::pin::pin;
+
+/// What happened while feeding a source into a channel, as returned by
+/// [`OverwriteSender::feed_iter`] and [`OverwriteSender::feed_stream`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Fed {
+    /// The number of messages sent.
+    pub sent: u64,
+    /// The number of queued messages evicted to make room for them.
+    pub evicted: u64,
+    /// The number of messages the channel refused, for instance because they were
+    /// [oversized](SendOverwriteError::Oversized), and which were dropped.
+    pub dropped: u64,
+    /// Whether feeding stopped early because every receiver had been dropped.
+    pub disconnected: bool,
+}
+
+impl Fed {
+    /// Records the outcome of a send, returning `false` once the channel is disconnected.
+    fn record<T>(&mut self, result: Result<usize, SendOverwriteError<T>>) -> bool {
+        match result {
+            Ok(evicted) => {
+                self.sent += 1;
+                self.evicted += evicted as u64;
+            }
+            Err(error) if error.is_disconnected() => {
+                self.disconnected = true;
+                return false;
+            }
+            Err(_) => self.dropped += 1,
+        }
+        true
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Sends every item of `iter` with overwrite semantics, blocking until the source is
+    /// exhausted, and returns the cumulative outcome.
+    ///
+    /// Overwritten messages are dropped and counted. Feeding stops early once every
+    /// receiver has been dropped; messages the channel refuses are dropped and counted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(3);
+    /// let fed = sender.feed_iter(1..=5);
+    /// assert_eq!((fed.sent, fed.evicted), (5, 2));
+    /// assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![3, 4, 5]);
+    /// ```
+    pub fn feed_iter<I>(&self, iter: I) -> Fed
+    where
+        I: IntoIterator<Item = T>,
+    {
+        let mut fed = Fed::default();
+        for value in iter {
+            if !fed.record(self.send_overwrite_discard(value)) {
+                break;
+            }
+        }
+        fed
+    }
+
+    /// Sends every item of `stream` with overwrite semantics until it ends, and returns
+    /// the cumulative outcome.
+    ///
+    /// This is the async version of [`feed_iter`](OverwriteSender::feed_iter).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::executor::block_on;
+    /// use futures::stream;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    /// let fed = block_on(sender.feed_stream(stream::iter(["a", "b", "c"])));
+    /// assert_eq!((fed.sent, fed.evicted), (3, 1));
+    ///
+    /// drop(receiver);
+    /// assert!(block_on(sender.feed_stream(stream::iter(["d"]))).disconnected);
+    /// ```
+    pub async fn feed_stream<S>(&self, stream: S) -> Fed
+    where
+        S: Stream<Item = T>,
+    {
+        let mut stream = pin!(stream);
+        let mut fed = Fed::default();
+        while let Some(value) = poll_fn(|cx| stream.as_mut().poll_next(cx)).await {
+            if !fed.record(self.send_overwrite_discard(value)) {
+                break;
+            }
+        }
+        fed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Builder;
+
+    #[test]
+    fn test_refused_messages_are_dropped() {
+        let (sender, receiver) = Builder::new(4)
+            .max_message_weight(3, |word: &&str| word.len())
+            .build();
+        let fed = sender.feed_iter(["ok", "too long", "yes"]);
+        assert_eq!((fed.sent, fed.dropped, fed.disconnected), (2, 1, false));
+        assert_eq!(receiver.len(), 2);
+    }
+}
@@ -0,0 +1,209 @@
+//! Freezing a channel for consistent snapshots.
+
+use crate::{OverwriteReceiver, OverwriteSender, SenderId, Shared, Stats};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::MutexGuard;
+use std::sync::atomic::Ordering;
+
+impl<T> Shared<T> {
+    /// Returns `true` while at least one [`FreezeGuard`] is alive.
+    ///
+    /// Freezes only start and end under the send lock, so this is stable while it is held.
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.freezes.load(Ordering::Acquire) > 0
+    }
+
+    /// Returns the messages sent while the channel is frozen.
+    pub(crate) fn lock_frozen(&self) -> MutexGuard<'_, VecDeque<T>> {
+        self.frozen.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A guard keeping a channel frozen, returned by [`OverwriteReceiver::freeze`].
+///
+/// While it is alive, sends neither deliver nor evict from the queue: their messages wait in
+/// a side queue. Dropping the guard releases them, in order and with overwrite semantics,
+/// so any eviction they cause happens then, and so does counting them as sent, passing them
+/// to taps and taking their tokens. Receiving is unaffected.
+#[must_use = "dropping the guard immediately unfreezes the channel"]
+pub struct FreezeGuard<'a, T> {
+    receiver: &'a OverwriteReceiver<T>,
+}
+
+impl<T> FreezeGuard<'_, T> {
+    /// Returns a snapshot of the channel's state, which sends leave unchanged while frozen.
+    pub fn stats(&self) -> Stats {
+        self.receiver.stats()
+    }
+
+    /// Calls `f` with an iterator over the queued messages, oldest first, without consuming
+    /// them.
+    ///
+    /// See [`OverwriteReceiver::inspect`].
+    pub fn inspect<R>(&self, f: impl FnOnce(std::slice::Iter<'_, T>) -> R) -> Option<R> {
+        self.receiver.inspect(f)
+    }
+
+    /// Returns the number of messages sent since the channel was frozen, waiting to be
+    /// released.
+    pub fn buffered(&self) -> usize {
+        self.receiver.shared.lock_frozen().len()
+    }
+
+    /// Unfreezes the channel, releasing the buffered messages.
+    ///
+    /// This is equivalent to dropping the guard.
+    pub fn unfreeze(self) {}
+}
+
+impl<T> fmt::Debug for FreezeGuard<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FreezeGuard")
+            .field("channel", &self.receiver.channel_id())
+            .field("buffered", &self.buffered())
+            .finish()
+    }
+}
+
+impl<T> Drop for FreezeGuard<'_, T> {
+    fn drop(&mut self) {
+        let shared = &self.receiver.shared;
+        let _sending = shared.lock_sends();
+        if shared.freezes.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        // The buffer is put back once emptied, keeping its allocation for the next freeze
+        let mut buffered = std::mem::take(&mut *shared.lock_frozen());
+        if buffered.is_empty() {
+            *shared.lock_frozen() = buffered;
+            return;
+        }
+        // Once every sender is gone, the buffered messages go with them
+        let Some(sender) = shared.weak_sender.upgrade() else {
+            return;
+        };
+        shared.sender_count.fetch_add(1, Ordering::AcqRel);
+        let sender = OverwriteSender {
+            id: SenderId::next(),
+            sender,
+            receiver: self.receiver.receiver.clone(),
+            shared: shared.clone(),
+        };
+        for value in buffered.drain(..) {
+            let _ = sender.send_overwrite_with(value, drop);
+        }
+        *shared.lock_frozen() = buffered;
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Freezes the channel until the returned guard is dropped.
+    ///
+    /// Sends made in the meantime are set aside instead of being delivered or evicting
+    /// anything, so the queue and the [`Stats`] only change through receives. This lets a
+    /// consumer take a consistent snapshot of both while producers keep running. Freezes
+    /// nest: the channel thaws once every guard is dropped.
+    ///
+    /// The side queue is bounded by the capacity. Once it is full, a send with overwrite
+    /// semantics overwrites its oldest message, which would be evicted on release anyway,
+    /// and counts it neither as sent nor as evicted. Under [`OverflowPolicy::RejectNew`],
+    /// or for [`try_send`](crate::OverwriteSender::try_send), a send is refused instead
+    /// once the queue and the side queue together reach the capacity.
+    ///
+    /// [`OverflowPolicy::RejectNew`]: crate::OverflowPolicy::RejectNew
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    /// sender.send_overwrite("a").unwrap();
+    ///
+    /// let frozen = receiver.freeze();
+    /// sender.send_overwrite("b").unwrap();
+    /// sender.send_overwrite("c").unwrap();
+    /// let queued = frozen.inspect(|queued| queued.copied().collect::<Vec<_>>());
+    /// assert_eq!(queued, Some(vec!["a"]));
+    /// assert_eq!((frozen.stats().sent, frozen.buffered()), (1, 2));
+    ///
+    /// frozen.unfreeze();
+    /// assert_eq!(receiver.drain().collect::<Vec<_>>(), vec!["b", "c"]);
+    /// assert_eq!(receiver.stats().evicted, 1);
+    /// ```
+    pub fn freeze(&self) -> FreezeGuard<'_, T> {
+        let _sending = self.shared.lock_sends();
+        self.shared.freezes.fetch_add(1, Ordering::AcqRel);
+        FreezeGuard { receiver: self }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Builder, bounded};
+    use flume::TrySendError;
+
+    #[test]
+    fn test_try_send_while_frozen() {
+        let (sender, receiver) = bounded(2);
+        sender.try_send(1).unwrap();
+        let frozen = receiver.freeze();
+        sender.try_send(2).unwrap();
+        assert!(matches!(sender.try_send(3), Err(TrySendError::Full(3))));
+
+        let nested = receiver.freeze();
+        drop(frozen);
+        assert_eq!(receiver.len(), 1);
+        drop(nested);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_side_queue_is_bounded() {
+        let (sender, receiver) = bounded(2);
+        sender.send_overwrite(0).unwrap();
+        let frozen = receiver.freeze();
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+
+        // The oldest buffered message makes room, leaving the queue and the stats alone
+        assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+        assert_eq!(frozen.buffered(), 2);
+        let stats = frozen.stats();
+        assert_eq!((stats.sent, stats.evicted, receiver.len()), (1, 0, 1));
+
+        // Released messages are counted then
+        drop(frozen);
+        let stats = receiver.stats();
+        assert_eq!((stats.sent, stats.evicted), (3, 1));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_reject_new_bounds_queue_and_side_queue() {
+        let (sender, receiver) = Builder::new(2)
+            .policy(crate::OverflowPolicy::RejectNew)
+            .build();
+        sender.send_overwrite(0).unwrap();
+        let frozen = receiver.freeze();
+        sender.send_overwrite(1).unwrap();
+        assert!(sender.send_overwrite(2).unwrap_err().is_rejected());
+        drop(frozen);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_preallocated_buffer_outlives_the_freeze() {
+        let (sender, receiver) = Builder::<u16>::new(4).preallocate(|| 0).build();
+        assert_eq!(sender.allocated_bytes(), 16);
+        assert_eq!(receiver.stats().sent, 0);
+
+        let frozen = receiver.freeze();
+        sender.send_overwrite_batch(vec![1, 2, 3]).unwrap();
+        drop(frozen);
+        assert_eq!(receiver.len(), 3);
+        assert_eq!(sender.shared.lock_frozen().capacity(), 4);
+        assert_eq!(bounded::<u16>(4).0.allocated_bytes(), 0);
+    }
+}
@@ -0,0 +1,225 @@
+//! Grouping of messages into generations that are evicted as a whole.
+
+use crate::{OverwriteSender, SendOverwriteError};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A message sent by a [`GenerationSender`], along with its generation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Generational<T> {
+    generation: u64,
+    /// Position in send order, to tell where a generation ends.
+    seq: u64,
+    value: T,
+}
+
+impl<T> Generational<T> {
+    /// Returns the generation the message was sent in.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the message.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Generational<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// A sender grouping messages into generations, evicting older generations as a whole.
+///
+/// Messages belong to the current generation until
+/// [`new_generation`](GenerationSender::new_generation) marks a boundary. When the channel
+/// is full and its oldest message belongs to an older generation, the whole remainder of
+/// that generation is evicted at once, since a partial frame is rarely of use; messages of
+/// the current generation are evicted one at a time as usual. Receivers get each message
+/// wrapped in a [`Generational`] telling its generation.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{GenerationSender, bounded};
+///
+/// let (sender, receiver) = bounded(3);
+/// let sender = GenerationSender::new(sender);
+///
+/// sender.send_overwrite("frame 0, entity a").unwrap();
+/// sender.send_overwrite("frame 0, entity b").unwrap();
+/// sender.new_generation();
+/// sender.send_overwrite("frame 1, entity a").unwrap();
+///
+/// // Making room drops frame 0 entirely
+/// let evicted = sender.send_overwrite("frame 1, entity b").unwrap();
+/// assert_eq!(evicted, Some(vec!["frame 0, entity a", "frame 0, entity b"]));
+///
+/// let message = receiver.recv().unwrap();
+/// assert_eq!((message.generation(), *message), (1, "frame 1, entity a"));
+/// ```
+pub struct GenerationSender<T> {
+    sender: OverwriteSender<Generational<T>>,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    current: u64,
+    next_seq: u64,
+    /// The generation of the last message sent, if any, with its position.
+    last: Option<(u64, u64)>,
+    /// The position of the last message of every recent closed generation, oldest first.
+    ends: VecDeque<(u64, u64)>,
+}
+
+impl<T> GenerationSender<T> {
+    /// Wraps a sender of generational messages, starting at generation `0`.
+    pub fn new(sender: OverwriteSender<Generational<T>>) -> Self {
+        Self {
+            sender,
+            state: Arc::new(Mutex::new(State {
+                current: 0,
+                next_seq: 0,
+                last: None,
+                ends: VecDeque::new(),
+            })),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts a new generation, returning its number.
+    ///
+    /// Messages sent from now on belong to the new generation.
+    pub fn new_generation(&self) -> u64 {
+        let mut state = self.lock();
+        if let Some(last) = state.last.take() {
+            state.ends.push_back(last);
+            // A generation with queued messages takes up at least one slot, so older
+            // ones are known to have left the queue
+            if state.ends.len() > self.sender.capacity() {
+                state.ends.pop_front();
+            }
+        }
+        state.current += 1;
+        state.current
+    }
+
+    /// Returns the current generation.
+    pub fn generation(&self) -> u64 {
+        self.lock().current
+    }
+
+    /// Sends a value in the current generation with overwrite semantics.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten, which may be a whole older generation
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut state = self.lock();
+        let mut evicted = Vec::new();
+        if self.sender.is_full()
+            && let Some(oldest) = self.sender.evict_oldest()
+        {
+            let generation = oldest.generation;
+            let end = state
+                .ends
+                .iter()
+                .find(|(ended, _)| *ended == generation)
+                .map(|(_, end)| *end);
+            let mut seq = oldest.seq;
+            evicted.push(oldest.value);
+            if let Some(end) = end {
+                // Evict the rest of the older generation
+                while seq < end {
+                    let Some(next) = self.sender.evict_oldest() else {
+                        break;
+                    };
+                    seq = next.seq;
+                    let done = next.generation != generation;
+                    evicted.push(next.value);
+                    if done {
+                        break;
+                    }
+                }
+                state.ends.retain(|(ended, _)| *ended > generation);
+            }
+        }
+        let message = Generational {
+            generation: state.current,
+            seq: state.next_seq,
+            value,
+        };
+        if let Some(overwritten) = self
+            .sender
+            .send_overwrite(message)
+            .map_err(|error| error.map(Generational::into_inner))?
+        {
+            evicted.extend(overwritten.into_iter().map(Generational::into_inner));
+        }
+        state.last = Some((state.current, state.next_seq));
+        state.next_seq += 1;
+        Ok(if evicted.is_empty() {
+            None
+        } else {
+            Some(evicted)
+        })
+    }
+
+    /// Returns the underlying sender.
+    pub fn sender(&self) -> &OverwriteSender<Generational<T>> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for GenerationSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for GenerationSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GenerationSender")
+            .field("generation", &self.generation())
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    #[test]
+    fn test_current_generation_evicts_one_at_a_time() {
+        let (sender, receiver) = bounded(2);
+        let sender = GenerationSender::new(sender);
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+
+        // Once the older generation is partly received, only its remainder is evicted
+        assert_eq!(sender.new_generation(), 1);
+        assert_eq!(receiver.recv().unwrap().into_inner(), 2);
+        sender.send_overwrite(4).unwrap();
+        assert_eq!(sender.send_overwrite(5).unwrap(), Some(vec![3]));
+        assert_eq!(sender.send_overwrite(6).unwrap(), Some(vec![4]));
+        assert_eq!(receiver.stats().evicted, 3);
+    }
+}
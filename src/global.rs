@@ -0,0 +1,150 @@
+//! Global channels, created lazily on first use.
+
+use crate::{Builder, OverwriteReceiver, OverwriteSender};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// A named overwrite channel that can live in a `static`, created on first access.
+///
+/// Declaring a channel globally spares wiring its endpoints through deeply nested code:
+/// any module can reach the channel through [`tx`](StaticChannel::tx) and
+/// [`rx`](StaticChannel::rx). The channel holds on to one sender and one receiver for the
+/// life of the program, so it never disconnects and never reports being orphaned.
+///
+/// See [`static_channel!`](crate::static_channel) for declaring one.
+pub struct StaticChannel<T> {
+    name: &'static str,
+    cap: usize,
+    channel: OnceLock<(OverwriteSender<T>, OverwriteReceiver<T>)>,
+}
+
+impl<T> StaticChannel<T> {
+    /// Declares a channel named `name` holding at most `cap` messages.
+    ///
+    /// Nothing is allocated until the channel is first accessed.
+    pub const fn new(name: &'static str, cap: usize) -> Self {
+        Self {
+            name,
+            cap,
+            channel: OnceLock::new(),
+        }
+    }
+
+    /// Returns the channel, creating it on first use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel was declared with a capacity of zero.
+    #[track_caller]
+    fn get(&self) -> &(OverwriteSender<T>, OverwriteReceiver<T>) {
+        self.channel
+            .get_or_init(|| Builder::new(self.cap).name(self.name).build())
+    }
+
+    /// Returns the channel's sender.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel was declared with a capacity of zero.
+    #[track_caller]
+    pub fn tx(&self) -> &OverwriteSender<T> {
+        &self.get().0
+    }
+
+    /// Returns the channel's receiver.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the channel was declared with a capacity of zero.
+    #[track_caller]
+    pub fn rx(&self) -> &OverwriteReceiver<T> {
+        &self.get().1
+    }
+
+    /// Returns the channel's name.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T> fmt::Debug for StaticChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StaticChannel")
+            .field("name", &self.name)
+            .field("cap", &self.cap)
+            .field("initialized", &self.channel.get().is_some())
+            .finish()
+    }
+}
+
+/// Declares a global overwrite channel, named after the static.
+///
+/// `static_channel!(EVENTS: Event = 64)` declares a [`StaticChannel`] named `EVENTS` of
+/// capacity 64. Attributes and visibility are passed through.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::static_channel;
+///
+/// static_channel! {
+///     /// Progress reports from every plugin.
+///     pub PROGRESS: (&'static str, u8) = 16;
+/// }
+///
+/// fn deep_in_a_plugin() {
+///     PROGRESS.tx().send_overwrite(("import", 50)).unwrap();
+/// }
+///
+/// deep_in_a_plugin();
+/// assert_eq!(PROGRESS.rx().recv().unwrap(), ("import", 50));
+/// assert_eq!(PROGRESS.rx().stats().name.as_deref(), Some("PROGRESS"));
+/// ```
+#[macro_export]
+macro_rules! static_channel {
+    ($(#[$attr:meta])* $vis:vis $name:ident: $ty:ty = $cap:expr $(;)?) => {
+        $(#[$attr])*
+        $vis static $name: $crate::StaticChannel<$ty> =
+            $crate::StaticChannel::new(stringify!($name), $cap);
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static_channel!(TEST_EVENTS: u32 = 2);
+
+    #[test]
+    fn test_channel_is_created_on_first_use() {
+        static LAZY: StaticChannel<u8> = StaticChannel::new("LAZY", 1);
+        assert!(format!("{LAZY:?}").contains("initialized: false"));
+        assert_eq!(LAZY.name(), "LAZY");
+        assert!(LAZY.rx().is_empty());
+        assert!(format!("{LAZY:?}").contains("initialized: true"));
+    }
+
+    #[test]
+    fn test_channel_outlives_cloned_endpoints() {
+        let (sender, receiver) = (TEST_EVENTS.tx().clone(), TEST_EVENTS.rx().clone());
+        for event in 0..3 {
+            sender.send_overwrite(event).unwrap();
+        }
+        drop((sender, receiver));
+
+        // The static keeps both ends, so the channel stays connected and shares one queue
+        assert!(!TEST_EVENTS.tx().is_disconnected());
+        assert_eq!(TEST_EVENTS.rx().try_iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            TEST_EVENTS.rx().stats().name.as_deref(),
+            Some("TEST_EVENTS")
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_zero_capacity_panics_on_access() {
+        static EMPTY: StaticChannel<u8> = StaticChannel::new("EMPTY", 0);
+        EMPTY.tx();
+    }
+}
@@ -0,0 +1,197 @@
+//! Consumer groups sharing the work of a stream.
+
+use crate::{Builder, Capacity, OverwriteReceiver, OverwriteSender, ReceiverId, Stats};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A dispatcher delivering every message to exactly one member of each consumer group.
+///
+/// Each group is backed by its own overwrite channel of the same capacity, named after the
+/// group, whose members compete for messages. Groups thus see every message, while the
+/// members of a group share the work. A slow group only overwrites its own backlog, and
+/// [`stats`](ConsumerGroups::stats) reports the lag of each group separately.
+///
+/// Messages published while a group has no member are not kept for it: a group starts
+/// from the messages published after its first member joined, and the backlog of a group
+/// is dropped when its last member leaves.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::ConsumerGroups;
+///
+/// let groups = ConsumerGroups::new(2);
+/// let indexer = groups.join("indexer");
+/// let indexer_2 = groups.join("indexer");
+/// let audit = groups.join("audit");
+///
+/// for order in 0..3 {
+///     assert_eq!(groups.publish(order), 2);
+/// }
+///
+/// // The indexers share the orders
+/// assert_eq!(indexer.recv().unwrap(), 1);
+/// assert_eq!(indexer_2.recv().unwrap(), 2);
+///
+/// // The audit group lagged behind on its own
+/// let audit_stats = &groups.stats()[0];
+/// assert_eq!(audit_stats.name.as_deref(), Some("audit"));
+/// assert_eq!(audit_stats.evicted, 1);
+/// assert_eq!(audit.recv().unwrap(), 1);
+/// ```
+pub struct ConsumerGroups<T> {
+    cap: Capacity,
+    groups: Arc<RwLock<BTreeMap<String, OverwriteSender<T>>>>,
+}
+
+impl<T> ConsumerGroups<T> {
+    /// Creates a dispatcher whose groups each hold at most `cap` messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap: Capacity::expect(cap),
+            groups: Arc::default(),
+        }
+    }
+
+    /// Joins the group named `group`, creating it if needed, and returns the new member's
+    /// receiver.
+    ///
+    /// A member leaves the group once every clone of its receiver is dropped.
+    pub fn join(&self, group: &str) -> OverwriteReceiver<T> {
+        let mut groups = self.groups.write().unwrap_or_else(|e| e.into_inner());
+        match groups.get(group) {
+            Some(sender) => member(sender),
+            None => {
+                let (sender, receiver) = Builder::with_capacity(self.cap).name(group).build();
+                groups.insert(group.to_string(), sender);
+                receiver
+            }
+        }
+    }
+
+    /// Publishes a message to every group with at least one member, overwriting the oldest
+    /// message of groups that are at capacity.
+    ///
+    /// Overwritten messages are dropped; each group counts its own.
+    ///
+    /// # Returns
+    ///
+    /// The number of groups the message was delivered to.
+    pub fn publish(&self, value: T) -> usize
+    where
+        T: Clone,
+    {
+        let groups = self.groups.read().unwrap_or_else(|e| e.into_inner());
+        groups
+            .values()
+            .filter(|sender| sender.send_overwrite_discard(value.clone()).is_ok())
+            .count()
+    }
+
+    /// Returns the number of members of `group`, counting each receiver clone.
+    pub fn members(&self, group: &str) -> usize {
+        self.groups
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(group)
+            .map_or(0, |sender| sender.receiver_count())
+    }
+
+    /// Returns a snapshot of each group's channel, in group name order.
+    pub fn stats(&self) -> Vec<Stats> {
+        self.groups
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .map(|sender| sender.stats())
+            .collect()
+    }
+}
+
+impl<T> ConsumerGroups<Arc<T>> {
+    /// Publishes a shared message to every group with at least one member.
+    ///
+    /// Every group receives a clone of the same `Arc`, so a large payload is stored once
+    /// however many groups there are, and converting an owned value with `Into` spares
+    /// even the initial copy. See [`publish`](ConsumerGroups::publish).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::ConsumerGroups;
+    /// use std::sync::Arc;
+    ///
+    /// let groups = ConsumerGroups::<Arc<Vec<u8>>>::new(4);
+    /// let encoder = groups.join("encoder");
+    /// let archive = groups.join("archive");
+    ///
+    /// assert_eq!(groups.send_shared(vec![0; 1 << 20]), 2);
+    ///
+    /// let (frame, archived) = (encoder.recv().unwrap(), archive.recv().unwrap());
+    /// assert!(Arc::ptr_eq(&frame, &archived));
+    /// ```
+    pub fn send_shared(&self, value: impl Into<Arc<T>>) -> usize {
+        self.publish(value.into())
+    }
+}
+
+impl<T> Clone for ConsumerGroups<T> {
+    fn clone(&self) -> Self {
+        Self {
+            cap: self.cap,
+            groups: self.groups.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ConsumerGroups<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups = self.groups.read().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("ConsumerGroups")
+            .field("cap", &self.cap)
+            .field("groups", &groups.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Returns a new receiver of the channel `sender` feeds, reconnecting it if every receiver
+/// was dropped.
+fn member<T>(sender: &OverwriteSender<T>) -> OverwriteReceiver<T> {
+    sender.shared.receiver_count.fetch_add(1, Ordering::AcqRel);
+    OverwriteReceiver {
+        id: ReceiverId::next(),
+        receiver: sender.receiver.clone(),
+        shared: sender.shared.clone(),
+        conflate: false,
+        epoch: sender.shared.receiver_epoch.load(Ordering::Acquire),
+        selected: AtomicU64::new(sender.shared.evicted.get()),
+        stream: Mutex::new(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rejoining_an_abandoned_group() {
+        let groups = ConsumerGroups::new(4);
+        let member = groups.join("billing");
+        groups.publish(1);
+        drop(member);
+        assert_eq!(groups.members("billing"), 0);
+        assert_eq!(groups.publish(2), 0);
+
+        let member = groups.join("billing");
+        assert_eq!(groups.publish(3), 1);
+        assert_eq!(member.recv().unwrap(), 3);
+        assert_eq!(groups.stats()[0].sent, 2);
+    }
+}
@@ -0,0 +1,164 @@
+//! Handles to queued messages, for correcting them before they are received.
+
+use crate::{MessageHandle, OverwriteSender, SendOverwriteError};
+use std::fmt;
+use std::ops::Deref;
+
+/// A message sent by a [`HandleSender`], along with its handle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Handled<T> {
+    handle: MessageHandle,
+    value: T,
+}
+
+impl<T> Handled<T> {
+    /// Returns the handle the message was sent with.
+    pub fn handle(&self) -> MessageHandle {
+        self.handle
+    }
+
+    /// Returns the message.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Handled<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// A sender returning a [`MessageHandle`] for every message, through which the message can
+/// be replaced or cancelled while it is still queued.
+///
+/// This allows correcting stale commands before the consumer sees them. Receivers get
+/// each message wrapped in a [`Handled`]. Replacing or cancelling a message drains and
+/// refills the queue while other sends wait, so receivers may momentarily find the channel
+/// empty but never observe a reordering.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{HandleSender, bounded};
+///
+/// let (sender, receiver) = bounded(4);
+/// let sender = HandleSender::new(sender);
+///
+/// let (target, _) = sender.send_overwrite("move to A").unwrap();
+/// sender.send_overwrite("open gripper").unwrap();
+///
+/// // The target changed before the consumer got to it
+/// assert_eq!(sender.replace(target, "move to B"), Ok("move to A"));
+///
+/// assert_eq!(*receiver.recv().unwrap(), "move to B");
+/// assert_eq!(*receiver.recv().unwrap(), "open gripper");
+///
+/// // Once received, a message can no longer be corrected
+/// assert_eq!(sender.replace(target, "move to C"), Err("move to C"));
+/// ```
+pub struct HandleSender<T> {
+    sender: OverwriteSender<Handled<T>>,
+}
+
+impl<T> HandleSender<T> {
+    /// Wraps a sender of handled messages.
+    pub fn new(sender: OverwriteSender<Handled<T>>) -> Self {
+        Self { sender }
+    }
+
+    /// Sends a value with overwrite semantics, returning its handle.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok((MessageHandle, None))` - The message was sent without overwriting any existing
+    ///   messages
+    /// - `Ok((MessageHandle, Some(Vec<T>)))` - The message was sent and the returned vector
+    ///   contains the messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    #[allow(clippy::type_complexity)]
+    pub fn send_overwrite(
+        &self,
+        value: T,
+    ) -> Result<(MessageHandle, Option<Vec<T>>), SendOverwriteError<T>> {
+        let handle = MessageHandle::next();
+        let overwritten = self
+            .sender
+            .send_overwrite(Handled { handle, value })
+            .map_err(|error| error.map(Handled::into_inner))?;
+        let overwritten = overwritten
+            .map(|overwritten| overwritten.into_iter().map(Handled::into_inner).collect());
+        Ok((handle, overwritten))
+    }
+
+    /// Replaces the queued message sent with `handle`, keeping its place in the queue.
+    ///
+    /// Returns the replaced message, or gives `value` back if the message is no longer
+    /// queued because it was received, overwritten or cancelled.
+    pub fn replace(&self, handle: MessageHandle, value: T) -> Result<T, T> {
+        self.sender.with_queue(|queued| {
+            match queued.iter_mut().find(|queued| queued.handle == handle) {
+                Some(queued) => Ok(std::mem::replace(&mut queued.value, value)),
+                None => Err(value),
+            }
+        })
+    }
+
+    /// Removes the queued message sent with `handle`, returning it if it was still queued.
+    ///
+    /// A cancelled message is not counted as evicted.
+    pub fn cancel(&self, handle: MessageHandle) -> Option<T> {
+        self.sender
+            .cancel_where(|queued| queued.handle == handle)
+            .pop()
+            .map(Handled::into_inner)
+    }
+
+    /// Returns the underlying sender.
+    pub fn sender(&self) -> &OverwriteSender<Handled<T>> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for HandleSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for HandleSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandleSender")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    #[test]
+    fn test_cancel_queued_message() {
+        let (sender, receiver) = bounded(2);
+        let sender = HandleSender::new(sender);
+        let (first, _) = sender.send_overwrite(1).unwrap();
+        let (second, _) = sender.send_overwrite(2).unwrap();
+        assert_eq!(sender.cancel(first), Some(1));
+        assert_eq!(sender.cancel(first), None);
+
+        // The cancelled message freed its slot
+        let (third, overwritten) = sender.send_overwrite(3).unwrap();
+        assert_eq!(overwritten, None);
+        assert_eq!(receiver.stats().evicted, 0);
+
+        let received = receiver.recv().unwrap();
+        assert_eq!((received.handle(), received.into_inner()), (second, 2));
+        assert_eq!(receiver.recv().unwrap().handle(), third);
+    }
+}
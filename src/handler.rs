@@ -0,0 +1,294 @@
+//! Managed consumer threads that survive panicking handlers.
+
+use crate::{OverwriteReceiver, Subscription, bounded};
+use std::any::Any;
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// How many of the most recent panics a supervisor keeps.
+const PANIC_HISTORY: usize = 16;
+
+/// A panic raised by a handler attached with [`OverwriteReceiver::attach_handler`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandlerPanic {
+    message: String,
+}
+
+impl HandlerPanic {
+    fn from_payload(payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "Box<dyn Any>".to_string(),
+            },
+        };
+        Self { message }
+    }
+
+    /// Returns the panic message, or `"Box<dyn Any>"` if the payload was not a string.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "handler panicked: {}", self.message)
+    }
+}
+
+impl Error for HandlerPanic {}
+
+/// A handle supervising a handler attached with [`OverwriteReceiver::attach_handler`].
+///
+/// A panic in the handler is caught, recorded here, and the handler keeps running with the
+/// next message, so one bad message cannot take a pipeline down silently. The supervisor
+/// keeps the most recent panics for inspection. Dropping it stops the handler, like
+/// dropping a [`Subscription`].
+#[must_use = "dropping the supervisor immediately stops the handler"]
+pub struct HandlerSupervisor {
+    subscription: Subscription,
+    panics: OverwriteReceiver<HandlerPanic>,
+    panic_count: Arc<AtomicU64>,
+}
+
+impl HandlerSupervisor {
+    pub(crate) fn spawn<T, F>(receiver: OverwriteReceiver<T>, mut f: F) -> Self
+    where
+        T: Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        let (panic_tx, panics) = bounded(PANIC_HISTORY);
+        let panic_count = Arc::new(AtomicU64::new(0));
+        let count = panic_count.clone();
+        let subscription = Subscription::spawn(receiver, move |value| {
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| f(value))) {
+                count.fetch_add(1, Ordering::Relaxed);
+                let _ = panic_tx.send_overwrite(HandlerPanic::from_payload(payload));
+            }
+        });
+        Self {
+            subscription,
+            panics,
+            panic_count,
+        }
+    }
+
+    /// Returns how many times the handler has panicked.
+    pub fn panic_count(&self) -> u64 {
+        self.panic_count.load(Ordering::Relaxed)
+    }
+
+    /// Takes the oldest recorded panic that has not been taken yet, if any.
+    ///
+    /// Only the most recent panics are kept; older ones are still reflected in
+    /// [`panic_count`](HandlerSupervisor::panic_count).
+    pub fn take_panic(&self) -> Option<HandlerPanic> {
+        self.panics.try_recv().ok()
+    }
+
+    /// Returns `true` once the handler thread has exited because the channel was
+    /// disconnected.
+    pub fn is_finished(&self) -> bool {
+        self.subscription.is_finished()
+    }
+
+    /// Stops the handler, waiting for it to exit.
+    ///
+    /// This is equivalent to dropping the supervisor.
+    pub fn stop(self) {}
+}
+
+impl fmt::Debug for HandlerSupervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandlerSupervisor")
+            .field("finished", &self.is_finished())
+            .field("panic_count", &self.panic_count())
+            .finish()
+    }
+}
+
+type PoisonedHook<T> = Box<dyn FnMut(T, HandlerPanic) + Send>;
+
+/// How [`OverwriteReceiver::for_each_resilient`] retries a message whose handler panicked.
+pub struct RetryPolicy<T> {
+    max_attempts: u32,
+    backoff: Duration,
+    on_poisoned: Option<PoisonedHook<T>>,
+}
+
+impl<T> RetryPolicy<T> {
+    /// Handles each message at most `max_attempts` times in total.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_attempts` is zero.
+    #[track_caller]
+    pub fn attempts(max_attempts: u32) -> Self {
+        assert!(max_attempts > 0, "a message needs at least one attempt");
+        Self {
+            max_attempts,
+            backoff: Duration::ZERO,
+            on_poisoned: None,
+        }
+    }
+
+    /// Waits `backoff` before each retry, as measured by the channel's
+    /// [`Clock`](crate::Clock). Defaults to retrying at once.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Hands messages that failed every attempt to `f`, along with their last panic.
+    ///
+    /// Without it, such poisoned messages are dropped.
+    pub fn on_poisoned<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(T, HandlerPanic) + Send + 'static,
+    {
+        self.on_poisoned = Some(Box::new(f));
+        self
+    }
+}
+
+impl<T> fmt::Debug for RetryPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Calls `f` with every message on the current thread, retrying messages whose handler
+    /// panicked according to `policy`, until the channel is empty and disconnected.
+    ///
+    /// `f` borrows each message, so a panic does not lose it. A failed message keeps its
+    /// place at the head of this consumer's stream: it is retried before any newer message
+    /// is taken, while the channel keeps absorbing and overwriting sends as usual. Once
+    /// every attempt has failed, the message is poisoned and handed to
+    /// [`on_poisoned`](RetryPolicy::on_poisoned) so that it cannot block the stream. Panics
+    /// are still reported by the process's panic hook.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{RetryPolicy, bounded};
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.send_overwrite("flaky").unwrap();
+    /// sender.send_overwrite("broken").unwrap();
+    /// drop(sender);
+    ///
+    /// let mut flaky_failures = 0;
+    /// let mut handled = Vec::new();
+    /// let (poisoned_tx, poisoned) = flume::unbounded();
+    /// let policy = RetryPolicy::attempts(3).on_poisoned(move |message, _panic| {
+    ///     poisoned_tx.send(message).unwrap();
+    /// });
+    ///
+    /// receiver.for_each_resilient(
+    ///     |message| {
+    ///         if *message == "flaky" && flaky_failures < 2 {
+    ///             flaky_failures += 1;
+    ///             panic!("transient failure");
+    ///         }
+    ///         assert_ne!(*message, "broken");
+    ///         handled.push(*message);
+    ///     },
+    ///     policy,
+    /// );
+    ///
+    /// assert_eq!(handled, vec!["flaky"]);
+    /// assert_eq!(poisoned.try_recv(), Ok("broken"));
+    /// ```
+    pub fn for_each_resilient<F>(&self, mut f: F, mut policy: RetryPolicy<T>)
+    where
+        F: FnMut(&T),
+    {
+        while !self.is_retired()
+            && let Ok(value) = self.recv()
+        {
+            let mut attempt = 1;
+            loop {
+                match panic::catch_unwind(AssertUnwindSafe(|| f(&value))) {
+                    Ok(()) => break,
+                    Err(payload) if attempt >= policy.max_attempts => {
+                        if let Some(on_poisoned) = &mut policy.on_poisoned {
+                            on_poisoned(value, HandlerPanic::from_payload(payload));
+                        }
+                        break;
+                    }
+                    Err(_) => {
+                        attempt += 1;
+                        if !policy.backoff.is_zero() {
+                            self.shared.clock.sleep(policy.backoff);
+                        }
+                    }
+                }
+            }
+            self.heartbeat();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    use std::time::Duration;
+
+    #[test]
+    fn test_handler_survives_panics() {
+        let (sender, receiver) = bounded(4);
+        let (seen_tx, seen) = flume::unbounded();
+        let supervisor = receiver.attach_handler(move |value: u32| {
+            if value == 1 {
+                panic!("bad message {value}");
+            }
+            seen_tx.send(value).unwrap();
+        });
+        for i in 0..3 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert_eq!(seen.recv_timeout(Duration::from_secs(1)), Ok(0));
+        assert_eq!(seen.recv_timeout(Duration::from_secs(1)), Ok(2));
+        assert_eq!(supervisor.panic_count(), 1);
+        assert_eq!(supervisor.take_panic().unwrap().message(), "bad message 1");
+        assert_eq!(supervisor.take_panic(), None);
+
+        drop(sender);
+        while !supervisor.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_retries_wait_for_backoff() {
+        use crate::{Builder, MockClock, RetryPolicy};
+
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(2).clock(clock.clone()).build();
+        sender.send_overwrite(1).unwrap();
+        drop(sender);
+
+        let mut attempts = 0;
+        receiver.for_each_resilient(
+            |_| {
+                attempts += 1;
+                panic!("always fails");
+            },
+            RetryPolicy::attempts(3).backoff(Duration::from_secs(1)),
+        );
+        assert_eq!(attempts, 3);
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,239 @@
+//! A conflating handoff of the latest value between tasks.
+
+use crate::waker::WakerSlot;
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use flume::{RecvError, TryRecvError};
+use std::fmt;
+use std::future;
+use std::sync::Arc;
+use std::task::{Poll, Waker};
+
+/// Creates a conflating handoff: a slot holding at most the latest unclaimed value.
+///
+/// A send never waits for the receiver: it replaces any value still unclaimed and completes
+/// at once, handing back the value it replaced. The receiver gets whatever was offered
+/// last, waiting for an offer if there is none. Unlike a oneshot, the handoff can be used
+/// repeatedly, and unlike a rendezvous channel, the sender never waits.
+///
+/// A single task awaiting [`recv_async`](HandoffReceiver::recv_async) is woken through a
+/// dedicated waker slot rather than flume's wait queue, which spares each await a wait hook,
+/// for render loops where wakeup latency matters. Registering and waking only take atomic
+/// operations on the slot, but the offer itself is queued like any
+/// [overwriting send](OverwriteSender::send_overwrite), under the channel's locks. Further
+/// tasks awaiting at the same time wait through flume as usual.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::handoff_overwrite;
+/// use futures::executor::block_on;
+///
+/// let (sender, receiver) = handoff_overwrite();
+///
+/// assert_eq!(sender.send("draft 1").unwrap(), None);
+/// assert_eq!(sender.send("draft 2").unwrap(), Some("draft 1"));
+///
+/// assert_eq!(block_on(receiver.recv_async()), Ok("draft 2"));
+/// assert_eq!(receiver.try_recv(), None);
+/// ```
+pub fn handoff_overwrite<T>() -> (HandoffSender<T>, HandoffReceiver<T>) {
+    let (sender, receiver) = bounded(1);
+    let slot = Arc::new(WakerSlot::new());
+    (
+        HandoffSender {
+            sender,
+            wake: WakeOnDrop(slot.clone()),
+        },
+        HandoffReceiver { receiver, slot },
+    )
+}
+
+/// The offering side of a handoff, see [`handoff_overwrite`].
+pub struct HandoffSender<T> {
+    // Declared first, so that the channel is disconnected by the time the waiting task
+    // is woken to notice it
+    sender: OverwriteSender<T>,
+    wake: WakeOnDrop,
+}
+
+/// Wakes the task waiting in the slot once dropped.
+struct WakeOnDrop(Arc<WakerSlot>);
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        self.0.wake();
+    }
+}
+
+impl<T> HandoffSender<T> {
+    /// Offers a value, replacing the unclaimed value, if any, which is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns the value if every receiver has been dropped.
+    pub fn send(&self, value: T) -> Result<Option<T>, SendOverwriteError<T>> {
+        let replaced = self.sender.send_overwrite(value)?;
+        self.wake.0.wake();
+        Ok(replaced.and_then(|replaced| replaced.into_iter().next()))
+    }
+
+    /// Returns `true` if an offered value has not been claimed yet.
+    pub fn is_pending(&self) -> bool {
+        !self.sender.is_empty()
+    }
+
+    /// Returns `true` once every receiver has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.sender.is_disconnected()
+    }
+}
+
+impl<T> Clone for HandoffSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            wake: WakeOnDrop(self.wake.0.clone()),
+        }
+    }
+}
+
+impl<T> fmt::Debug for HandoffSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandoffSender")
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
+
+/// The claiming side of a handoff, see [`handoff_overwrite`].
+///
+/// Clones compete for offered values, each claimed by exactly one of them.
+pub struct HandoffReceiver<T> {
+    receiver: OverwriteReceiver<T>,
+    slot: Arc<WakerSlot>,
+}
+
+/// Frees the waker slot when a [`HandoffReceiver::recv_async`] future completes or is
+/// cancelled.
+struct Registration<'a> {
+    slot: &'a WakerSlot,
+    waker: Option<Waker>,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        if let Some(waker) = &self.waker {
+            self.slot.unregister(waker);
+        }
+    }
+}
+
+impl<T> HandoffReceiver<T> {
+    /// Claims the latest offered value, blocking until one is offered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once nothing is offered and every sender has been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Claims the latest offered value, waiting asynchronously until one is offered.
+    ///
+    /// This is the async version of [`recv`](HandoffReceiver::recv).
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let mut registration = Registration {
+            slot: &self.slot,
+            waker: None,
+        };
+        let claimed = future::poll_fn(|cx| {
+            if let Some(claimed) = self.try_claim() {
+                return Poll::Ready(Some(claimed));
+            }
+            if !registration.slot.register(cx.waker()) {
+                return Poll::Ready(None);
+            }
+            registration.waker = Some(cx.waker().clone());
+            // An offer made before registering would not have woken us
+            self.try_claim()
+                .map_or(Poll::Pending, |claimed| Poll::Ready(Some(claimed)))
+        })
+        .await;
+        match claimed {
+            Some(claimed) => claimed,
+            // Another task holds the slot
+            None => self.receiver.recv_async().await,
+        }
+    }
+
+    /// Claims the latest offered value, if any, without waiting.
+    pub fn try_recv(&self) -> Option<T> {
+        self.receiver.try_recv().ok()
+    }
+
+    /// Returns `true` once nothing is offered and every sender has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.receiver.is_disconnected() && self.receiver.is_empty()
+    }
+
+    /// Claims the offered value or reports the disconnection, returning `None` if the
+    /// handoff is merely empty.
+    fn try_claim(&self) -> Option<Result<T, RecvError>> {
+        match self.receiver.try_recv() {
+            Ok(value) => Some(Ok(value)),
+            Err(TryRecvError::Disconnected) => Some(Err(RecvError::Disconnected)),
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+}
+
+impl<T> Clone for HandoffReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            receiver: self.receiver.clone(),
+            slot: self.slot.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for HandoffReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandoffReceiver")
+            .field("pending", &!self.receiver.is_empty())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn test_waiting_receiver_gets_the_offer() {
+        let (sender, receiver) = handoff_overwrite();
+        let waiting = thread::spawn(move || block_on(receiver.recv_async()));
+        sender.send(1).unwrap();
+        assert_eq!(waiting.join().unwrap(), Ok(1));
+        assert!(sender.send(2).unwrap_err().is_disconnected());
+    }
+
+    #[test]
+    fn test_concurrent_waiters_share_the_slot() {
+        let (sender, receiver) = handoff_overwrite();
+        let waiters: Vec<_> = (0..2)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || block_on(receiver.recv_async()))
+            })
+            .collect();
+        thread::sleep(std::time::Duration::from_millis(20));
+
+        sender.send(1).unwrap();
+        drop(sender);
+        let claimed: Vec<_> = waiters.into_iter().map(|w| w.join().unwrap()).collect();
+        assert!(claimed.contains(&Ok(1)));
+        assert!(claimed.contains(&Err(RecvError::Disconnected)));
+    }
+}
@@ -0,0 +1,113 @@
+//! Capacity hints sent back from the receiving side.
+
+use crate::{Builder, OverwriteReceiver, OverwriteSender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub(crate) type CapacityRequestHook = Box<dyn Fn(Option<usize>) + Send + Sync>;
+
+/// The capacity last requested by a receiver, and the hook told about requests.
+pub(crate) struct CapacityRequests {
+    /// The requested capacity, or zero if none is.
+    requested: AtomicUsize,
+    hook: Option<CapacityRequestHook>,
+}
+
+impl CapacityRequests {
+    pub(crate) fn new(hook: Option<CapacityRequestHook>) -> Self {
+        Self {
+            requested: AtomicUsize::new(0),
+            hook,
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<usize> {
+        Some(self.requested.load(Ordering::Acquire)).filter(|&hint| hint > 0)
+    }
+
+    fn request(&self, hint: usize) {
+        let previous = self.requested.swap(hint, Ordering::AcqRel);
+        if previous != hint
+            && let Some(hook) = &self.hook
+        {
+            hook(self.get());
+        }
+    }
+}
+
+impl<T> Builder<T> {
+    /// Registers a hook invoked whenever a receiver requests a different capacity with
+    /// [`OverwriteReceiver::request_capacity`], with `None` once the request is withdrawn.
+    ///
+    /// The hook runs on the receiving thread, so an adaptive producer typically only
+    /// records the hint there and adjusts its rate, batch size or the channel's capacity
+    /// on its own thread.
+    pub fn on_capacity_request<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Option<usize>) + Send + Sync + 'static,
+    {
+        self.on_capacity_request = Some(Box::new(f));
+        self
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Tells the sending side how many messages the consumer would like the channel to
+    /// hold, such as the number it can process between two bursts.
+    ///
+    /// The hint is advisory: it changes nothing by itself, and is only reported by
+    /// [`OverwriteSender::requested_capacity`], by [`Stats::requested_capacity`] and to the
+    /// [`on_capacity_request`](Builder::on_capacity_request) hook, for producers to react
+    /// as they see fit. A later hint replaces the previous one, and a hint of zero
+    /// withdraws it.
+    ///
+    /// [`Stats::requested_capacity`]: crate::Stats::requested_capacity
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::<Vec<u8>>::new(4).max_capacity(16).build();
+    ///
+    /// // The consumer found it processes bursts of up to 12 frames
+    /// receiver.request_capacity(12);
+    ///
+    /// if let Some(hint) = sender.requested_capacity() {
+    ///     sender.set_capacity(hint.min(sender.max_capacity()));
+    /// }
+    /// assert_eq!(sender.capacity(), 12);
+    /// ```
+    pub fn request_capacity(&self, hint: usize) {
+        self.shared.capacity_requests.request(hint);
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Returns the capacity last requested by a receiver with
+    /// [`OverwriteReceiver::request_capacity`], if any.
+    pub fn requested_capacity(&self) -> Option<usize> {
+        self.shared.capacity_requests.get()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Builder;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_hook_sees_changed_requests() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let (sender, receiver) = Builder::<u8>::new(4)
+            .on_capacity_request(move |hint| seen.lock().unwrap().push(hint))
+            .build();
+
+        receiver.request_capacity(8);
+        receiver.clone().request_capacity(8);
+        receiver.request_capacity(0);
+        assert_eq!(*requests.lock().unwrap(), vec![Some(8), None]);
+        assert_eq!(sender.requested_capacity(), None);
+        assert_eq!(sender.stats().requested_capacity, None);
+    }
+}
@@ -0,0 +1,193 @@
+//! A channel offering both a FIFO history and a conflated snapshot of the latest message.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, Stats, bounded};
+use flume::{RecvError, TryRecvError};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Creates a channel whose receivers can either consume the history of the last `cap`
+/// messages in order, or read a snapshot of the latest message.
+///
+/// Both views are fed by the same sends, so they never drift apart: the snapshot is always
+/// the newest message sent, whether or not the history still holds it, and evictions are
+/// only counted once, by the history. This replaces a watch channel maintained alongside
+/// an overwrite channel.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::latest_with_history;
+///
+/// let (sender, receiver) = latest_with_history(2);
+/// for i in 0..3 {
+///     sender.send_overwrite(i).unwrap();
+/// }
+///
+/// // A dashboard only needs the snapshot
+/// assert_eq!(receiver.latest(), Some(2));
+///
+/// // A logger consumes the history, minus what was overwritten
+/// assert_eq!(receiver.recv().unwrap(), 1);
+/// assert_eq!(receiver.recv().unwrap(), 2);
+/// assert_eq!(receiver.stats().evicted, 1);
+///
+/// // Consuming the history leaves the snapshot in place
+/// assert_eq!(receiver.latest(), Some(2));
+/// ```
+#[track_caller]
+pub fn latest_with_history<T: Clone>(cap: usize) -> (HistorySender<T>, HistoryReceiver<T>) {
+    let (sender, receiver) = bounded(cap);
+    let latest = Arc::new(Mutex::new(None));
+    let sender = HistorySender {
+        sender,
+        latest: latest.clone(),
+    };
+    let receiver = HistoryReceiver { receiver, latest };
+    (sender, receiver)
+}
+
+fn lock<T>(latest: &Mutex<Option<T>>) -> MutexGuard<'_, Option<T>> {
+    latest.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The sending half of a [`latest_with_history`] channel.
+pub struct HistorySender<T> {
+    sender: OverwriteSender<T>,
+    latest: Arc<Mutex<Option<T>>>,
+}
+
+impl<T: Clone> HistorySender<T> {
+    /// Sends a value to the history with overwrite semantics, and makes it the latest
+    /// snapshot.
+    ///
+    /// A failed send leaves the snapshot unchanged.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        // Holding the snapshot across the send keeps both views in the same order
+        let mut latest = lock(&self.latest);
+        let snapshot = value.clone();
+        let overwritten = self.sender.send_overwrite(value)?;
+        *latest = Some(snapshot);
+        Ok(overwritten)
+    }
+}
+
+impl<T> HistorySender<T> {
+    /// Returns the sender of the history, whose sends do not update the snapshot.
+    pub fn sender(&self) -> &OverwriteSender<T> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for HistorySender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            latest: self.latest.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for HistorySender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistorySender")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+/// The receiving half of a [`latest_with_history`] channel.
+pub struct HistoryReceiver<T> {
+    receiver: OverwriteReceiver<T>,
+    latest: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> HistoryReceiver<T> {
+    /// Receives the next message of the history, blocking until one arrives.
+    ///
+    /// Returns an error once the history is empty and every sender has been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Receives the next message of the history without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Asynchronously receives the next message of the history.
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        self.receiver.recv_async().await
+    }
+
+    /// Returns a clone of the latest message sent, or `None` if nothing was sent yet.
+    ///
+    /// Reading the snapshot does not consume the history, and receiving from the history
+    /// does not clear the snapshot.
+    pub fn latest(&self) -> Option<T>
+    where
+        T: Clone,
+    {
+        lock(&self.latest).clone()
+    }
+
+    /// Returns a snapshot of the history's state.
+    pub fn stats(&self) -> Stats {
+        self.receiver.stats()
+    }
+
+    /// Returns the receiver of the history.
+    pub fn receiver(&self) -> &OverwriteReceiver<T> {
+        &self.receiver
+    }
+}
+
+impl<T> Clone for HistoryReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            receiver: self.receiver.clone(),
+            latest: self.latest.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for HistoryReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistoryReceiver")
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_failed_send_keeps_snapshot() {
+        let (sender, receiver) = latest_with_history(1);
+        sender.send_overwrite("first").unwrap();
+        let snapshot = receiver.clone();
+        drop(receiver);
+        assert_eq!(snapshot.latest(), Some("first"));
+
+        drop(snapshot);
+        assert!(
+            sender
+                .send_overwrite("second")
+                .unwrap_err()
+                .is_disconnected()
+        );
+        assert_eq!(*lock(&sender.latest), Some("first"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Identity handles for channels and their endpoints.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn next_id() -> u64 {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident, $prefix:literal) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        pub struct $name(u64);
+
+        impl $name {
+            pub(crate) fn next() -> Self {
+                Self(next_id())
+            }
+
+            /// Returns the raw numeric value of this id.
+            pub fn as_u64(self) -> u64 {
+                self.0
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.0)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}{}", $prefix, self.0)
+            }
+        }
+    };
+}
+
+id_type!(
+    /// Identifies a channel.
+    ///
+    /// Every endpoint of a channel reports the same `ChannelId`, which makes it a suitable
+    /// key for registries that need to group endpoints by channel.
+    ChannelId,
+    "channel#"
+);
+
+id_type!(
+    /// Identifies a single sender handle.
+    ///
+    /// Every clone of an `OverwriteSender` receives a fresh id, so registries can tell apart
+    /// producers sharing a channel and recognise a handle they have already seen.
+    SenderId,
+    "sender#"
+);
+
+id_type!(
+    /// Identifies a single receiver handle.
+    ///
+    /// Every clone of an `OverwriteReceiver` receives a fresh id.
+    ReceiverId,
+    "receiver#"
+);
+
+id_type!(
+    /// Identifies a single message sent through a [`HandleSender`](crate::HandleSender).
+    ///
+    /// Handles are unique across channels, so a handle of another channel never matches.
+    MessageHandle,
+    "message#"
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ids_are_unique() {
+        let a = SenderId::next();
+        let b = SenderId::next();
+        assert_ne!(a, b);
+        assert!(a < b);
+        assert_eq!(format!("{a}"), format!("sender#{}", a.as_u64()));
+        assert_eq!(format!("{b:?}"), format!("SenderId({})", b.as_u64()));
+    }
+}
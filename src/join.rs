@@ -0,0 +1,157 @@
+//! Waiting for a fresh message from each of several channels.
+
+use crate::OverwriteReceiver;
+use flume::RecvError;
+use futures_core::future::BoxFuture;
+
+/// Waits until every receiver has at least one message, then returns the newest message
+/// from each.
+///
+/// `receivers` is a tuple of up to eight `&OverwriteReceiver` references, possibly of
+/// different message types, or a slice of receivers of the same type. Backlogs are
+/// conflated: once every channel has produced a message, each one is drained and only its
+/// newest message is kept, so the result is as fresh as possible even when one source was
+/// much slower than the others.
+///
+/// # Returns
+///
+/// - `Ok(output)` - A tuple (or `Vec` for slices) with the newest message from each receiver
+/// - `Err(RecvError)` - One of the channels was disconnected before producing a message
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{bounded, join_latest};
+/// use futures::executor::block_on;
+///
+/// let (temperature_tx, temperature) = bounded(8);
+/// let (humidity_tx, humidity) = bounded(8);
+///
+/// temperature_tx.send_overwrite(20.5).unwrap();
+/// temperature_tx.send_overwrite(21.0).unwrap();
+/// humidity_tx.send_overwrite(40u8).unwrap();
+///
+/// let fused = block_on(join_latest((&temperature, &humidity))).unwrap();
+/// assert_eq!(fused, (21.0, 40));
+/// ```
+pub fn join_latest<'a, J: JoinLatest<'a>>(
+    receivers: J,
+) -> BoxFuture<'a, Result<J::Output, RecvError>> {
+    receivers.join_latest()
+}
+
+/// A set of receivers that [`join_latest`] can wait on.
+///
+/// Implemented for tuples of up to eight `&OverwriteReceiver` references and for slices
+/// of receivers.
+pub trait JoinLatest<'a> {
+    /// The newest message from each receiver.
+    type Output;
+
+    /// Waits until every receiver has a message, then returns the newest one from each.
+    fn join_latest(self) -> BoxFuture<'a, Result<Self::Output, RecvError>>;
+}
+
+macro_rules! impl_join_latest {
+    ($($T:ident $receiver:ident $value:ident),+) => {
+        impl<'a, $($T: Send + 'a),+> JoinLatest<'a> for ($(&'a OverwriteReceiver<$T>,)+) {
+            type Output = ($($T,)+);
+
+            fn join_latest(self) -> BoxFuture<'a, Result<Self::Output, RecvError>> {
+                let ($($receiver,)+) = self;
+                Box::pin(async move {
+                    $(let mut $value = $receiver.recv_async().await?;)+
+                    $(
+                        while let Ok(newer) = $receiver.try_recv() {
+                            $value = newer;
+                        }
+                    )+
+                    Ok(($($value,)+))
+                })
+            }
+        }
+    };
+}
+
+impl_join_latest!(A ra va);
+impl_join_latest!(A ra va, B rb vb);
+impl_join_latest!(A ra va, B rb vb, C rc vc);
+impl_join_latest!(A ra va, B rb vb, C rc vc, D rd vd);
+impl_join_latest!(A ra va, B rb vb, C rc vc, D rd vd, E re ve);
+impl_join_latest!(A ra va, B rb vb, C rc vc, D rd vd, E re ve, F rf vf);
+impl_join_latest!(A ra va, B rb vb, C rc vc, D rd vd, E re ve, F rf vf, G rg vg);
+impl_join_latest!(A ra va, B rb vb, C rc vc, D rd vd, E re ve, F rf vf, G rg vg, H rh vh);
+
+impl<'a, T: Send + 'a> JoinLatest<'a> for &'a [OverwriteReceiver<T>] {
+    type Output = Vec<T>;
+
+    fn join_latest(self) -> BoxFuture<'a, Result<Self::Output, RecvError>> {
+        Box::pin(async move {
+            let mut values = Vec::with_capacity(self.len());
+            for receiver in self {
+                values.push(receiver.recv_async().await?);
+            }
+            for (receiver, value) in self.iter().zip(&mut values) {
+                while let Ok(newer) = receiver.try_recv() {
+                    *value = newer;
+                }
+            }
+            Ok(values)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    use std::thread;
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_join_latest_waits_for_every_source() {
+        let (tx_a, rx_a) = bounded(4);
+        let (tx_b, rx_b) = bounded(4);
+        let (tx_c, rx_c) = bounded(4);
+        tx_a.send_overwrite(1).unwrap();
+        tx_a.send_overwrite(2).unwrap();
+        tx_b.send_overwrite("b").unwrap();
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            tx_a.send_overwrite(3).unwrap();
+            tx_c.send_overwrite('c').unwrap();
+        });
+        let fused = block_on(join_latest((&rx_a, &rx_b, &rx_c))).unwrap();
+        producer.join().unwrap();
+        assert_eq!(fused, (3, "b", 'c'));
+        assert!(rx_a.is_empty());
+    }
+
+    #[test]
+    fn test_join_latest_slice() {
+        let channels: Vec<_> = (0..3).map(|_| bounded(4)).collect();
+        for (i, (sender, _)) in channels.iter().enumerate() {
+            sender.send_overwrite(i).unwrap();
+            sender.send_overwrite(i * 10).unwrap();
+        }
+        let (senders, receivers): (Vec<_>, Vec<_>) = channels.into_iter().unzip();
+        let fused = block_on(join_latest(receivers.as_slice())).unwrap();
+        assert_eq!(fused, vec![0, 10, 20]);
+        drop(senders);
+    }
+
+    #[test]
+    fn test_join_latest_disconnected() {
+        let (tx_a, rx_a) = bounded(1);
+        let (tx_b, rx_b) = bounded::<u8>(1);
+        tx_a.send_overwrite(1).unwrap();
+        drop(tx_b);
+        assert_eq!(
+            block_on(join_latest((&rx_a, &rx_b))),
+            Err(RecvError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Priority lanes sharing a single channel.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use flume::{RecvError, Selector, TryRecvError};
+use std::fmt;
+use std::future::{Future, poll_fn};
+use std::pin::Pin;
+use std::task::Poll;
+
+/// Creates a channel made of several priority lanes, each with its own capacity.
+///
+/// Lanes are ordered from highest to lowest priority: `capacities[0]` is the capacity of
+/// the highest priority lane. Messages are sent to a specific lane with
+/// [`LaneSender::send_lane`], and receivers always drain higher lanes before lower ones.
+/// Overwriting is per lane, so a burst of low priority messages can never evict a high
+/// priority one.
+///
+/// This lets a single channel carry, for example, both a control plane and a data plane.
+///
+/// # Panics
+///
+/// Panics if `capacities` is empty.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded_overwrite_lanes;
+///
+/// const CONTROL: usize = 0;
+/// const DATA: usize = 1;
+///
+/// let (sender, receiver) = bounded_overwrite_lanes(&[1, 2]);
+/// sender.send_lane(DATA, "sample 1").unwrap();
+/// sender.send_lane(DATA, "sample 2").unwrap();
+/// sender.send_lane(CONTROL, "pause").unwrap();
+///
+/// // Only the data lane is full, so only data is overwritten
+/// assert_eq!(sender.send_lane(DATA, "sample 3").unwrap(), Some(vec!["sample 1"]));
+///
+/// assert_eq!(receiver.recv().unwrap(), "pause");
+/// assert_eq!(receiver.recv().unwrap(), "sample 2");
+/// assert_eq!(receiver.recv().unwrap(), "sample 3");
+/// ```
+pub fn bounded_overwrite_lanes<T>(capacities: &[usize]) -> (LaneSender<T>, LaneReceiver<T>) {
+    assert!(!capacities.is_empty(), "at least one lane is required");
+    let (senders, receivers) = capacities.iter().map(|&cap| bounded(cap)).unzip();
+    (
+        LaneSender { lanes: senders },
+        LaneReceiver { lanes: receivers },
+    )
+}
+
+/// The sending half of a [`bounded_overwrite_lanes`] channel.
+pub struct LaneSender<T> {
+    lanes: Vec<OverwriteSender<T>>,
+}
+
+impl<T> LaneSender<T> {
+    /// Sends a value to a lane, overwriting the oldest message of that lane if it is at
+    /// capacity.
+    ///
+    /// # Arguments
+    ///
+    /// * `lane` - The index of the lane, `0` being the highest priority
+    /// * `value` - The value to send
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages of the same lane that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - All receivers have been dropped
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lane` is out of range.
+    pub fn send_lane(
+        &self,
+        lane: usize,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.lanes[lane].send_overwrite(value)
+    }
+
+    /// Asynchronously sends a value to a lane.
+    ///
+    /// This is the async version of [`send_lane`](LaneSender::send_lane).
+    pub async fn send_lane_async(
+        &self,
+        lane: usize,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.lanes[lane].send_overwrite_async(value).await
+    }
+
+    /// Returns the number of lanes.
+    pub fn lane_count(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Returns the sender of a single lane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lane` is out of range.
+    pub fn lane(&self, lane: usize) -> &OverwriteSender<T> {
+        &self.lanes[lane]
+    }
+}
+
+impl<T> Clone for LaneSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            lanes: self.lanes.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for LaneSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LaneSender")
+            .field("lanes", &self.lanes)
+            .finish()
+    }
+}
+
+/// The receiving half of a [`bounded_overwrite_lanes`] channel.
+///
+/// Every receive operation takes the oldest message of the highest priority lane that is
+/// not empty.
+pub struct LaneReceiver<T> {
+    lanes: Vec<OverwriteReceiver<T>>,
+}
+
+impl<T> LaneReceiver<T> {
+    pub(crate) fn new(lanes: Vec<OverwriteReceiver<T>>) -> Self {
+        Self { lanes }
+    }
+
+    /// Attempts to receive a message without blocking.
+    ///
+    /// Returns `Err(TryRecvError::Empty)` if every lane is empty, and
+    /// `Err(TryRecvError::Disconnected)` once every lane is empty and all senders have been
+    /// dropped.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        for lane in &self.lanes {
+            if let Ok(value) = lane.try_recv() {
+                return Ok(value);
+            }
+        }
+        if self.is_disconnected() {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Receives a message, blocking until one is available in any lane.
+    ///
+    /// Returns an error once every lane is empty and all senders have been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+            // Every lane was empty, so take whichever message arrives first
+            let selected = self
+                .lanes
+                .iter()
+                .fold(Selector::new(), |selector, lane| {
+                    selector.recv(lane, |result| result.ok())
+                })
+                .wait();
+            if let Some(value) = selected {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Asynchronously receives a message, waiting until one is available in any lane.
+    ///
+    /// This is the async version of [`recv`](LaneReceiver::recv).
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let mut pending: Vec<_> = self.lanes.iter().map(|lane| lane.recv_async()).collect();
+        poll_fn(|cx| {
+            match self.try_recv() {
+                Ok(value) => return Poll::Ready(Ok(value)),
+                Err(TryRecvError::Disconnected) => {
+                    return Poll::Ready(Err(RecvError::Disconnected));
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+            // Register interest in every lane, taking whichever message arrives first
+            for future in &mut pending {
+                match Pin::new(future).poll(cx) {
+                    Poll::Ready(Ok(value)) => return Poll::Ready(Ok(value)),
+                    // The senders went away since the check above, so check again
+                    Poll::Ready(Err(_)) => cx.waker().wake_by_ref(),
+                    Poll::Pending => {}
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Returns the total number of messages queued across all lanes.
+    pub fn len(&self) -> usize {
+        self.lanes.iter().map(|lane| lane.len()).sum()
+    }
+
+    /// Returns `true` if every lane is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lanes.iter().all(|lane| lane.is_empty())
+    }
+
+    /// Returns `true` if all senders have been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.lanes[0].is_disconnected()
+    }
+
+    /// Returns the number of lanes.
+    pub fn lane_count(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Returns the receiver of a single lane.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lane` is out of range.
+    pub fn lane(&self, lane: usize) -> &OverwriteReceiver<T> {
+        &self.lanes[lane]
+    }
+}
+
+impl<T> Clone for LaneReceiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            lanes: self.lanes.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for LaneReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LaneReceiver")
+            .field("lanes", &self.lanes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor::block_on;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lanes_drain_by_priority() {
+        let (sender, receiver) = bounded_overwrite_lanes(&[2, 2, 2]);
+        sender.send_lane(2, "low").unwrap();
+        sender.send_lane(1, "mid").unwrap();
+        sender.send_lane(0, "high").unwrap();
+        sender.send_lane(1, "mid 2").unwrap();
+        assert_eq!(receiver.len(), 4);
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(received, vec!["high", "mid", "mid 2", "low"]);
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn test_lanes_overwrite_per_lane() {
+        let (sender, receiver) = bounded_overwrite_lanes(&[1, 1]);
+        sender.send_lane(0, 1).unwrap();
+        assert_eq!(sender.send_lane(1, 2).unwrap(), None);
+        assert_eq!(sender.send_lane(1, 3).unwrap(), Some(vec![2]));
+        assert_eq!(receiver.lane(0).len(), 1);
+        assert_eq!(receiver.lane(1).len(), 1);
+    }
+
+    #[test]
+    fn test_lanes_recv_waits_for_any_lane() {
+        let (sender, receiver) = bounded_overwrite_lanes(&[1, 1]);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send_lane(1, 7).unwrap();
+        });
+        assert_eq!(receiver.recv(), Ok(7));
+        handle.join().unwrap();
+        assert_eq!(receiver.recv(), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_lanes_recv_async() {
+        let (sender, receiver) = bounded_overwrite_lanes(&[1, 1]);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send_lane(1, 1).unwrap();
+            sender.send_lane(0, 0).unwrap();
+        });
+        let first = block_on(receiver.recv_async()).unwrap();
+        handle.join().unwrap();
+        let second = block_on(receiver.recv_async()).unwrap();
+        assert_eq!(first + second, 1);
+        assert_eq!(
+            block_on(receiver.recv_async()),
+            Err(RecvError::Disconnected)
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Leased receives, redelivering messages that are not acknowledged in time.
+
+use crate::{OverwriteReceiver, Shared};
+use flume::{RecvError, RecvTimeoutError};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// The messages of a channel currently leased to consumers.
+pub(crate) struct Leases<T> {
+    next_id: u64,
+    pending: Vec<Leased<T>>,
+}
+
+struct Leased<T> {
+    id: u64,
+    expires_at: Instant,
+    value: T,
+}
+
+impl<T> Default for Leases<T> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl<T> Leases<T> {
+    /// Returns the earliest time a lease expires, if any is pending.
+    fn next_expiry(&self) -> Option<Instant> {
+        self.pending.iter().map(|leased| leased.expires_at).min()
+    }
+
+    /// Removes and returns the messages whose lease has expired at `now`, in the order
+    /// they were leased.
+    fn take_expired(&mut self, now: Instant) -> Vec<T> {
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|leased| leased.expires_at <= now);
+        self.pending = pending;
+        expired
+            .into_iter()
+            .map(|leased: Leased<T>| leased.value)
+            .collect()
+    }
+}
+
+/// A message received with [`OverwriteReceiver::recv_leased`], which returns to the
+/// channel unless acknowledged before its lease expires.
+///
+/// Dropping a lease does not release it: like a worker crashing mid-task, the message
+/// reappears once the lease expires.
+pub struct Lease<T> {
+    id: u64,
+    expires_at: Instant,
+    value: T,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Lease<T> {
+    /// Returns the time the lease expires, as measured by the channel's
+    /// [`Clock`](crate::Clock).
+    pub fn expires_at(&self) -> Instant {
+        self.expires_at
+    }
+
+    /// Acknowledges the message, removing it from the channel for good.
+    ///
+    /// Returns `false` if the lease had already expired and the message was put back, in
+    /// which case it will be, or has been, received again.
+    pub fn ack(self) -> bool {
+        let mut leases = self.shared.lock_leases();
+        let before = leases.pending.len();
+        leases.pending.retain(|leased| leased.id != self.id);
+        leases.pending.len() < before
+    }
+}
+
+impl<T> Deref for Lease<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Lease<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lease")
+            .field("value", &self.value)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl<T> Shared<T> {
+    fn lock_leases(&self) -> MutexGuard<'_, Leases<T>> {
+        self.leases.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Receives a message under a lease of `visibility`, blocking until one is available.
+    ///
+    /// The message is hidden from other consumers rather than removed: unless the returned
+    /// [`Lease`] is [acknowledged](Lease::ack) before it expires, the message reappears at
+    /// the front of the channel, so a worker dying mid-task does not lose it. A message
+    /// reappearing in a full channel evicts the newest queued messages to make room.
+    ///
+    /// Expired leases are reclaimed by consumers calling this method, which also wakes up
+    /// for them while waiting. Messages received otherwise are not leased.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    /// Messages whose lease expires after that are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.send_overwrite("resize image 7").unwrap();
+    ///
+    /// // A worker takes the job and crashes before acknowledging it
+    /// let job = receiver.recv_leased(Duration::ZERO).unwrap();
+    /// drop(job);
+    ///
+    /// let job = receiver.recv_leased(Duration::from_secs(30)).unwrap();
+    /// assert_eq!(*job, "resize image 7");
+    /// assert!(job.ack());
+    /// assert!(receiver.is_empty());
+    /// ```
+    pub fn recv_leased(&self, visibility: Duration) -> Result<Lease<T>, RecvError>
+    where
+        T: Clone,
+    {
+        let receiver = self.live().ok_or(RecvError::Disconnected)?;
+        let value = loop {
+            self.reclaim_leases();
+            let next_expiry = self.shared.lock_leases().next_expiry();
+            let Some(expires_at) = next_expiry else {
+                break receiver.recv()?;
+            };
+            let timeout = expires_at.saturating_duration_since(self.shared.clock.now());
+            match receiver.recv_timeout(timeout) {
+                Ok(value) => break value,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Err(RecvError::Disconnected),
+            }
+        };
+        self.shared.observe_watermarks(receiver);
+        let expires_at = self.shared.clock.now() + visibility;
+        let mut leases = self.shared.lock_leases();
+        let id = leases.next_id;
+        leases.next_id += 1;
+        leases.pending.push(Leased {
+            id,
+            expires_at,
+            value: value.clone(),
+        });
+        Ok(Lease {
+            id,
+            expires_at,
+            value,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Puts the messages whose lease expired back at the front of the channel.
+    fn reclaim_leases(&self) {
+        let shared = &self.shared;
+        let _sending = shared.lock_sends();
+        let expired = shared.lock_leases().take_expired(shared.clock.now());
+        if expired.is_empty() {
+            return;
+        }
+        // Without a sender the messages cannot be put back, and go with their leases
+        let Some(sender) = shared.weak_sender.upgrade() else {
+            return;
+        };
+        let mut queue = expired;
+        queue.extend(self.receiver.drain());
+        let evicted = queue.split_off(queue.len().min(shared.capacity.get()));
+        shared.count_evictions(evicted.len());
+        shared.count_classes(&evicted);
+        shared.requeue_locked(&sender, &self.receiver, queue);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_expired_lease_evicts_the_newest() {
+        use crate::{Builder, MockClock};
+        use std::time::Duration;
+
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(2).clock(clock.clone()).build();
+        sender.send_overwrite(1).unwrap();
+        let lease = receiver.recv_leased(Duration::from_secs(10)).unwrap();
+        sender.send_overwrite(2).unwrap();
+        sender.send_overwrite(3).unwrap();
+
+        clock.advance(Duration::from_secs(10));
+        let redelivered = receiver.recv_leased(Duration::from_secs(10)).unwrap();
+        assert_eq!(*redelivered, 1);
+        assert!(!lease.ack());
+        assert!(redelivered.ack());
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(receiver.stats().evicted, 1);
+    }
+}
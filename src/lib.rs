@@ -31,14 +31,597 @@
 //! assert_eq!(receiver.recv().unwrap(), 3);
 //! assert_eq!(receiver.recv().unwrap(), 4);
 //! ```
+//!
+//! ## Unsafe code
+//!
+//! The default build forbids unsafe code. Internal optimizations that need it may only be
+//! compiled in with the `fast` feature, so that safety-critical users can rule them out by
+//! leaving it disabled, and check the build they got with [`UNSAFE_FREE`].
+
+#![cfg_attr(not(feature = "fast"), forbid(unsafe_code))]
+
+mod adaptive;
+mod affinity;
+mod bucket;
+mod budget;
+mod buffered;
+#[cfg(feature = "bus")]
+pub mod bus;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+mod capacity;
+mod cell;
+mod checkpoint;
+mod clock;
+pub mod combine;
+mod control;
+mod counter;
+mod critical;
+mod error;
+mod eviction;
+mod expiry;
+mod failover;
+mod fair;
+mod fan_in;
+mod feed;
+mod freeze;
+mod generation;
+mod global;
+mod group;
+mod handle;
+mod handler;
+mod handoff;
+mod hint;
+mod history;
+mod id;
+mod join;
+mod lanes;
+mod lease;
+mod ordered;
+pub mod pipe;
+mod pipeline;
+mod plan;
+mod policy;
+mod pool;
+mod prefetch;
+mod priority;
+mod projection;
+mod rate;
+pub mod record;
+mod replace;
+mod routed;
+mod rpc;
+mod runs;
+mod scoped;
+mod select;
+mod send_lock;
+mod signal;
+mod split;
+mod stats;
+mod status;
+mod subscription;
+mod summary;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tower")]
+pub mod tower;
+pub mod ui;
+mod wait;
+mod waker;
+mod watchdog;
+mod watermark;
 
-use flume::{Receiver, SendError, Sender};
+pub use adaptive::CapacityChange;
+pub use affinity::Attributed;
+pub use buffered::BufferedSender;
+pub use capacity::Capacity;
+pub use cell::{StateCell, StateUpdate};
+pub use checkpoint::{Checkpoint, Gap};
+#[cfg(feature = "test-util")]
+pub use clock::MockClock;
+pub use clock::{Clock, Sleep, SystemClock};
+pub use control::{Controller, Producer};
+pub use critical::{Acknowledgement, Critical};
+pub use error::SendOverwriteError;
+pub use eviction::{EvictionEvent, EvictionStream};
+pub use expiry::{Expiring, Sweeper};
+pub use failover::FailoverSender;
+pub use fair::{FairReceiver, FairSender, fair};
+pub use fan_in::{FanInReceiver, FanInSender, fan_in};
+pub use feed::Fed;
+pub use freeze::FreezeGuard;
+pub use generation::{GenerationSender, Generational};
+pub use global::StaticChannel;
+pub use group::ConsumerGroups;
+pub use handle::{HandleSender, Handled};
+pub use handler::{HandlerPanic, HandlerSupervisor, RetryPolicy};
+pub use handoff::{HandoffReceiver, HandoffSender, handoff_overwrite};
+pub use history::{HistoryReceiver, HistorySender, latest_with_history};
+pub use id::{ChannelId, MessageHandle, ReceiverId, SenderId};
+pub use join::{JoinLatest, join_latest};
+pub use lanes::{LaneReceiver, LaneSender, bounded_overwrite_lanes};
+pub use lease::Lease;
+pub use ordered::{OrderedSender, ReorderReceiver, Stamped, bounded_ordered};
+pub use pipeline::{Pipeline, PipelineStages};
+pub use plan::EvictionPlan;
+pub use policy::OverflowPolicy;
+pub use pool::{PoolSender, SharedPool};
+pub use prefetch::Prefetch;
+pub use priority::{PrioritySender, bounded_priority};
+pub use projection::{Projection, project};
+pub use rate::RateLimited;
+pub use replace::RecvFuture;
+pub use routed::{RoutedSender, routed};
+pub use rpc::{CallError, Caller, Request, Responder, rpc};
+pub use runs::{CoalesceRuns, Run};
+pub use scoped::scope_producers;
+pub use select::{Select, Select2, Select3, Selected, select_overwrite};
+pub use signal::{Control, Message};
+pub use split::{Split, SplitReceiver, SplitSender, bounded_split};
+pub use stats::Stats;
+pub use status::SendStatus;
+pub use subscription::Subscription;
+pub use summary::{Aggregator, MinMaxMean, Summary};
+pub use wait::WaitTimes;
+pub use watermark::Watermark;
+
+use flume::{Receiver, RecvError, Sender, TryRecvError, TrySendError, WeakSender};
+use futures_core::Stream;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::task::{Context, Poll};
+use std::thread;
+use std::time::Duration;
+
+use adaptive::{Adaptive, CapacityHook};
+use bucket::TokenBucket;
+use budget::EvictionBudget;
+use capacity::AtomicCapacity;
+use counter::Counter;
+use hint::{CapacityRequestHook, CapacityRequests};
+use lease::Leases;
+use plan::Protected;
+use policy::AtomicPolicy;
+use send_lock::{SendLock, SendLockGuard};
+use wait::WaitHistogram;
+use watchdog::{StalledHook, Watchdog};
+use watermark::{WatermarkHook, Watermarks};
+
+/// Whether this build of the crate is free of unsafe code.
+///
+/// This is `true` unless the `fast` feature is enabled, in which case the crate is
+/// compiled without `#![forbid(unsafe_code)]` and may use unsafe internal optimizations.
+///
+/// # Examples
+///
+/// A safety-critical binary can refuse to build if a dependency enabled the feature, with
+/// `const _: () = assert!(flume_overwrite::UNSAFE_FREE);`, or report it at startup:
+///
+/// ```rust
+/// if !flume_overwrite::UNSAFE_FREE {
+///     eprintln!("flume-overwrite was built with unsafe optimizations");
+/// }
+/// ```
+pub const UNSAFE_FREE: bool = !cfg!(feature = "fast");
+
+/// How often a lingering sender checks whether the queue has been drained, and a paused
+/// one whether it may resume.
+const LINGER_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How many times in a row eviction may find the queue empty while it still looks full
+/// before a send gives up with [`SendOverwriteError::Contended`].
+const MAX_EVICTION_ATTEMPTS: u32 = 1024;
+
+type OrphanedHook<T> = Box<dyn Fn(Vec<T>) + Send + Sync>;
+type LingerHook<T> = Box<dyn Fn(Sender<T>, Receiver<T>, Arc<dyn Clock>) + Send + Sync>;
+type RecvStream<T> = Box<dyn Stream<Item = T> + Send + Unpin>;
+/// Returns `true` for messages that must never be evicted.
+type Protect<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+/// Returns `true` if two messages are identical.
+type Identical<T> = Box<dyn Fn(&T, &T) -> bool + Send + Sync>;
+/// Formats evicted messages for the panic of a strict channel.
+type Strict<T> = Box<dyn Fn(&[T]) -> String + Send + Sync>;
+/// The maximum weight of a single message, along with the function weighing messages.
+type MaxWeight<T> = (usize, Box<dyn Fn(&T) -> usize + Send + Sync>);
+/// Turns an evicted message into a cheaper representative, or drops it.
+type EvictTransform<T> = Box<dyn Fn(T) -> Option<T> + Send + Sync>;
+/// Names the class of a message, for per-class eviction counts.
+type Classify<T> = Box<dyn Fn(&T) -> &'static str + Send + Sync>;
+/// Forwards a clone of a sent message, returning `false` once the tap has no receivers.
+type Tap<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// State shared by every endpoint of a channel.
+struct Shared<T> {
+    id: ChannelId,
+    name: Option<String>,
+    capacity: AtomicCapacity,
+    in_flight: AtomicUsize,
+    /// Messages staged by [`BufferedSender`]s and not flushed yet.
+    staged: AtomicUsize,
+    send_lock: SendLock,
+    /// Hook calls held back while the send lock is held, see [`Shared::lock_sends`].
+    deferred: Mutex<Vec<Deferred>>,
+    /// Held by the thread running the deferred hook calls.
+    notifying: Mutex<()>,
+    sender_count: AtomicUsize,
+    receiver_count: AtomicUsize,
+    sent: Counter,
+    evicted: Counter,
+    on_orphaned: Option<OrphanedHook<T>>,
+    linger: Option<LingerHook<T>>,
+    taps: Mutex<Vec<Tap<T>>>,
+    eviction_listeners: Mutex<Vec<OverwriteSender<EvictionEvent>>>,
+    clock: Arc<dyn Clock>,
+    eviction_budget: Option<EvictionBudget>,
+    token_bucket: Option<TokenBucket>,
+    protect: Option<Protect<T>>,
+    /// Where the protected messages are in the queue, tracked only if some can be.
+    protected: Mutex<Protected>,
+    policy: AtomicPolicy,
+    watchdog: Watchdog,
+    skip_identical: Option<Identical<T>>,
+    strict: Option<Strict<T>>,
+    max_weight: Option<MaxWeight<T>>,
+    evict_transform: Option<EvictTransform<T>>,
+    classify: Option<Classify<T>>,
+    evicted_by_class: Mutex<BTreeMap<&'static str, u64>>,
+    attribute: Option<fn(&T) -> SenderId>,
+    evicted_by_sender: Mutex<BTreeMap<SenderId, u64>>,
+    aggregator: Option<Mutex<Box<dyn Aggregator<T>>>>,
+    adaptive: Option<Adaptive>,
+    on_capacity_change: Option<CapacityHook>,
+    /// Capacity hints from receivers, see [`OverwriteReceiver::request_capacity`].
+    capacity_requests: CapacityRequests,
+    watermarks: Option<Watermarks>,
+    /// The current generation of receivers; older ones are retired.
+    receiver_epoch: AtomicU64,
+    /// The number of live [`FreezeGuard`]s.
+    freezes: AtomicUsize,
+    /// Messages sent while the channel is frozen, released once it thaws.
+    frozen: Mutex<VecDeque<T>>,
+    /// How long blocking receives waited.
+    recv_waits: WaitHistogram,
+    /// Control messages sent with [`OverwriteSender::signal`], ahead of the data.
+    signals: (Sender<Control>, Receiver<Control>),
+    /// Messages dropped by [`OverwriteSender::send_overwrite_detached`].
+    detached_failures: AtomicU64,
+    /// The bytes reserved for messages when the channel was built, see
+    /// [`Builder::preallocate`].
+    reserved: usize,
+    /// Messages handed out by [`OverwriteReceiver::recv_leased`] and not yet acknowledged.
+    leases: Mutex<Leases<T>>,
+    /// Lets receivers put drained messages back without keeping the channel connected.
+    weak_sender: WeakSender<T>,
+}
+
+/// A hook call held back until the send lock is released.
+enum Deferred {
+    CapacityChange(CapacityChange),
+    Watermark(Watermark),
+    Stalled(Duration),
+}
+
+/// Holds the send lock, running the hook calls deferred meanwhile once it is released.
+struct Sending<'a, T> {
+    shared: &'a Shared<T>,
+    lock: Option<SendLockGuard<'a>>,
+}
+
+impl<T> Drop for Sending<'_, T> {
+    fn drop(&mut self) {
+        drop(self.lock.take());
+        self.shared.run_deferred();
+    }
+}
+
+impl<T> Shared<T> {
+    /// Serializes operations that modify the queue from the sending side.
+    ///
+    /// Hooks reacting to a send, such as [`Builder::on_capacity_change`], are deferred
+    /// with [`defer`](Shared::defer) and only run once the lock is released, so that they
+    /// can take their time or send on the channel without holding up other senders.
+    fn lock_sends(&self) -> Sending<'_, T> {
+        self.sending(self.send_lock.lock())
+    }
+
+    /// Waits for the send lock without blocking the executor thread.
+    async fn lock_sends_async(&self) -> Sending<'_, T> {
+        self.sending(self.send_lock.lock_async().await)
+    }
+
+    /// Takes the send lock if no other send holds it.
+    fn try_lock_sends(&self) -> Option<Sending<'_, T>> {
+        self.send_lock.try_lock().map(|lock| self.sending(lock))
+    }
+
+    /// Takes the send lock if no other send holds it, or registers the task to be woken
+    /// once it is released.
+    fn poll_lock_sends(&self, cx: &mut Context<'_>) -> Poll<Sending<'_, T>> {
+        self.send_lock.poll_lock(cx).map(|lock| self.sending(lock))
+    }
+
+    fn sending<'a>(&'a self, lock: SendLockGuard<'a>) -> Sending<'a, T> {
+        Sending {
+            shared: self,
+            lock: Some(lock),
+        }
+    }
+
+    /// Holds back a hook call until the send lock is released.
+    fn defer(&self, call: Deferred) {
+        self.deferred
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(call);
+    }
+
+    /// Runs the deferred hook calls in order.
+    ///
+    /// A single thread runs them at a time, taking over the calls deferred by others
+    /// meanwhile, which also lets a hook send on the channel without running hooks
+    /// re-entrantly.
+    fn run_deferred(&self) {
+        loop {
+            let _notifying = match self.notifying.try_lock() {
+                Ok(notifying) => notifying,
+                Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+                Err(TryLockError::WouldBlock) => return,
+            };
+            loop {
+                let calls =
+                    std::mem::take(&mut *self.deferred.lock().unwrap_or_else(|e| e.into_inner()));
+                if calls.is_empty() {
+                    break;
+                }
+                calls.into_iter().for_each(|call| self.notify(call));
+            }
+            drop(_notifying);
+            // Calls deferred while the previous thread was done but still holding the lock
+            if self
+                .deferred
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_empty()
+            {
+                return;
+            }
+        }
+    }
+
+    fn notify(&self, call: Deferred) {
+        match call {
+            Deferred::CapacityChange(change) => {
+                if let Some(hook) = &self.on_capacity_change {
+                    hook(change);
+                }
+            }
+            Deferred::Watermark(crossed) => {
+                if let Some(watermarks) = &self.watermarks {
+                    watermarks.notify(crossed);
+                }
+            }
+            Deferred::Stalled(silence) => self.watchdog.notify(silence),
+        }
+    }
+
+    /// Checks whether the consumer stalled, deferring the stall hook, while holding the send
+    /// lock.
+    fn check_stalled_locked(&self) {
+        if let Some(silence) = self.watchdog.stalled(self.clock.now()) {
+            self.defer(Deferred::Stalled(silence));
+        }
+    }
+
+    /// Puts previously drained messages back in order, while holding the send lock.
+    ///
+    /// See [`OverwriteSender::requeue_locked`].
+    fn requeue_locked(&self, sender: &Sender<T>, receiver: &Receiver<T>, values: Vec<T>) {
+        for mut value in values {
+            let protected = self.is_protected(&value);
+            loop {
+                match sender.try_send(value) {
+                    Err(TrySendError::Full(returned)) => value = returned,
+                    Ok(()) => {
+                        self.queued_locked(protected);
+                        break;
+                    }
+                    Err(TrySendError::Disconnected(_)) => break,
+                }
+                if let Ok(oldest) = receiver.try_recv() {
+                    self.count_evictions(1);
+                    self.count_classes(std::slice::from_ref(&oldest));
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if `value` is [protected](Builder::protect) from eviction.
+    fn is_protected(&self, value: &T) -> bool {
+        self.protect.as_ref().is_some_and(|protect| protect(value))
+    }
+
+    /// Records that a message was queued, while holding the send lock.
+    fn queued_locked(&self, protected: bool) {
+        if self.protect.is_some() {
+            self.lock_protected().queued(protected);
+        }
+    }
+
+    /// Returns how many of the `len` queued messages are protected, while holding the
+    /// send lock.
+    fn protected_locked(&self, len: usize) -> usize {
+        if self.protect.is_none() {
+            return 0;
+        }
+        self.lock_protected().count(len)
+    }
+
+    fn lock_protected(&self) -> MutexGuard<'_, Protected> {
+        self.protected.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns `true` if the channel limits the weight of messages and `value` exceeds it.
+    fn is_oversized(&self, value: &T) -> bool {
+        self.max_weight
+            .as_ref()
+            .is_some_and(|(max, weigh)| weigh(value) > *max)
+    }
+
+    /// Panics if the channel is strict and messages were evicted to make room.
+    #[track_caller]
+    fn check_strict(&self, evicted: &[T]) {
+        if let Some(format) = &self.strict
+            && !evicted.is_empty()
+        {
+            let name = self.name.as_deref().unwrap_or("unnamed");
+            panic!(
+                "strict channel `{name}` evicted {} message(s): {}",
+                evicted.len(),
+                format(evicted)
+            );
+        }
+    }
+
+    /// Counts evicted messages per class and per sender, and summarizes them, if the
+    /// channel classifies, attributes or aggregates them.
+    ///
+    /// See [`Builder::classify_evictions`], [`Builder::attribute_evictions`] and
+    /// [`Builder::aggregate_evictions`].
+    fn count_classes(&self, evicted: &[T]) {
+        if let Some(classify) = &self.classify {
+            self.tally_classes(evicted.iter().map(classify));
+        }
+        if let Some(attribute) = self.attribute {
+            self.tally_senders(evicted.iter().map(attribute));
+        }
+        if let Some(aggregator) = &self.aggregator
+            && !evicted.is_empty()
+        {
+            let mut aggregator = aggregator.lock().unwrap_or_else(|e| e.into_inner());
+            evicted.iter().for_each(|evicted| aggregator.add(evicted));
+        }
+    }
+
+    fn tally_classes(&self, classes: impl IntoIterator<Item = &'static str>) {
+        let mut counts = self
+            .evicted_by_class
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        for class in classes {
+            *counts.entry(class).or_default() += 1;
+        }
+    }
+
+    fn tally_senders(&self, senders: impl IntoIterator<Item = SenderId>) {
+        let mut counts = self
+            .evicted_by_sender
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        for sender in senders {
+            *counts.entry(sender).or_default() += 1;
+        }
+    }
+
+    fn lock_taps(&self) -> MutexGuard<'_, Vec<Tap<T>>> {
+        self.taps.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn lock_eviction_listeners(&self) -> MutexGuard<'_, Vec<OverwriteSender<EvictionEvent>>> {
+        self.eviction_listeners
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Forwards a message about to be sent to every tap, dropping taps without receivers.
+    fn tap(&self, value: &T) {
+        let mut taps = self.lock_taps();
+        if !taps.is_empty() {
+            taps.retain(|tap| tap(value));
+        }
+    }
+
+    /// Reports any watermark the queue crossed.
+    fn observe_watermarks(&self, receiver: &Receiver<T>) {
+        if let Some(watermarks) = &self.watermarks {
+            watermarks.observe(receiver.len());
+        }
+    }
+
+    /// Records any watermark the queue crossed, deferring the hook, while holding the send
+    /// lock.
+    fn observe_watermarks_locked(&self, receiver: &Receiver<T>) {
+        if let Some(crossed) = self
+            .watermarks
+            .as_ref()
+            .and_then(|watermarks| watermarks.cross(receiver.len()))
+        {
+            self.defer(Deferred::Watermark(crossed));
+        }
+    }
+
+    /// Counts queued messages plus those being sent, which have already claimed a slot by
+    /// evicting an older message but are not queued yet, and those staged by buffered
+    /// senders, which will claim one on their next flush.
+    fn len(&self, receiver: &Receiver<T>) -> usize {
+        let in_flight = self.in_flight.load(Ordering::Acquire);
+        let staged = self.staged.load(Ordering::Acquire);
+        (receiver.len() + in_flight + staged).min(self.capacity.get())
+    }
+
+    fn stats(&self, receiver: &Receiver<T>) -> Stats {
+        Stats {
+            name: self.name.clone(),
+            capacity: self.capacity.get(),
+            len: self.len(receiver),
+            sent: self.sent.get(),
+            evicted: self.evicted.get(),
+            evicted_by_class: self
+                .evicted_by_class
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            evicted_by_sender: self
+                .evicted_by_sender
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            evicted_summary: self.aggregator.as_ref().map(|aggregator| {
+                aggregator
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .summary()
+            }),
+            recv_wait: self.recv_waits.snapshot(),
+            requested_capacity: self.capacity_requests.get(),
+        }
+    }
+
+    fn fmt_debug(
+        &self,
+        kind: &str,
+        receiver: &Receiver<T>,
+        f: &mut fmt::Formatter<'_>,
+    ) -> fmt::Result {
+        let stats = self.stats(receiver);
+        f.debug_struct(kind)
+            .field("name", &stats.name)
+            .field("capacity", &stats.capacity)
+            .field("len", &stats.len)
+            .field("sent", &stats.sent)
+            .field("evicted", &stats.evicted)
+            .finish()
+    }
+}
 
 /// Creates a bounded channel with overwrite capability.
 ///
-/// Returns a tuple of `(OverwriteSender<T>, Receiver<T>)` where the sender can overwrite
-/// old messages when the channel reaches capacity, and the receiver is a standard flume receiver.
+/// Returns a tuple of `(OverwriteSender<T>, OverwriteReceiver<T>)` where the sender can overwrite
+/// old messages when the channel reaches capacity, and the receiver dereferences to a standard
+/// flume receiver.
 ///
 /// # Arguments
 ///
@@ -48,7 +631,11 @@ use std::ops::Deref;
 ///
 /// A tuple containing:
 /// - `OverwriteSender<T>` - A sender that can overwrite old messages when at capacity
-/// - `Receiver<T>` - A standard flume receiver for reading messages
+/// - `OverwriteReceiver<T>` - A receiver for reading messages
+///
+/// # Panics
+///
+/// Panics if `cap` is zero; see [`Capacity`].
 ///
 /// # Examples
 ///
@@ -62,161 +649,2814 @@ use std::ops::Deref;
 /// assert_eq!(receiver.recv().unwrap(), "hello");
 /// assert_eq!(receiver.recv().unwrap(), "world");
 /// ```
-pub fn bounded<T>(cap: usize) -> (OverwriteSender<T>, Receiver<T>) {
-    let (tx, rx) = flume::bounded(cap);
-    let overwrite_sender = OverwriteSender {
-        sender: tx,
-        receiver: rx.clone(),
-    };
-    (overwrite_sender, rx)
+#[track_caller]
+pub fn bounded<T>(cap: usize) -> (OverwriteSender<T>, OverwriteReceiver<T>) {
+    Builder::new(cap).build()
 }
 
-/// A sender that can overwrite old messages when the channel reaches capacity.
+/// A builder for overwrite channels with optional hooks.
 ///
-/// `OverwriteSender<T>` wraps a flume `Sender<T>` and provides additional functionality
-/// to automatically remove old messages when sending would block due to a full channel.
-///
-/// This struct implements `Deref` to `Sender<T>`, so all standard sender methods are available.
-/// Additionally, it provides `send_overwrite` and `send_overwrite_async` methods that will
-/// never block due to a full channel.
+/// Use [`bounded`] for a plain channel; reach for the builder when the channel needs
+/// additional configuration such as an [`on_orphaned`](Builder::on_orphaned) hook.
 ///
 /// # Examples
 ///
 /// ```rust
-/// use flume_overwrite::bounded;
+/// use flume_overwrite::Builder;
+/// use std::sync::{Arc, Mutex};
 ///
-/// let (sender, receiver) = bounded(1);
+/// let orphaned = Arc::new(Mutex::new(Vec::new()));
+/// let sink = orphaned.clone();
 ///
-/// // First message goes through normally
-/// sender.send_overwrite("first").unwrap();
+/// let (sender, receiver) = Builder::new(4)
+///     .on_orphaned(move |pending| sink.lock().unwrap().extend(pending))
+///     .build();
 ///
-/// // Second message overwrites the first
-/// let overwritten = sender.send_overwrite("second").unwrap();
-/// assert_eq!(overwritten, Some(vec!["first"]));
+/// sender.send_overwrite(1).unwrap();
+/// sender.send_overwrite(2).unwrap();
+/// drop(receiver);
+///
+/// assert_eq!(*orphaned.lock().unwrap(), vec![1, 2]);
 /// ```
-#[derive(Clone)]
-pub struct OverwriteSender<T> {
-    sender: Sender<T>,
-    receiver: Receiver<T>,
+pub struct Builder<T> {
+    cap: Capacity,
+    name: Option<String>,
+    on_orphaned: Option<OrphanedHook<T>>,
+    linger: Option<LingerHook<T>>,
+    clock: Arc<dyn Clock>,
+    eviction_budget: Option<EvictionBudget>,
+    token_bucket: Option<TokenBucket>,
+    protect: Option<Protect<T>>,
+    policy: OverflowPolicy,
+    on_stalled: Option<(Duration, StalledHook)>,
+    skip_identical: Option<Identical<T>>,
+    strict: Option<Strict<T>>,
+    max_weight: Option<MaxWeight<T>>,
+    evict_transform: Option<EvictTransform<T>>,
+    classify: Option<Classify<T>>,
+    /// Tells which sender clone sent a message, see [`Builder::attribute_evictions`].
+    attribute: Option<fn(&T) -> SenderId>,
+    aggregator: Option<Box<dyn Aggregator<T>>>,
+    max_cap: Option<Capacity>,
+    adaptive_window: Option<Duration>,
+    on_capacity_change: Option<CapacityHook>,
+    on_capacity_request: Option<CapacityRequestHook>,
+    watermarks: Option<(usize, usize)>,
+    on_watermark: Option<WatermarkHook>,
+    /// Creates the placeholder messages that warm up the queue, see [`Builder::preallocate`].
+    preallocate: Option<Box<dyn Fn() -> T + Send + Sync>>,
 }
 
-impl<T> Deref for OverwriteSender<T> {
-    type Target = Sender<T>;
+impl<T> Builder<T> {
+    /// Creates a builder for a channel holding at most `cap` messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero; use [`with_capacity`](Builder::with_capacity) with a
+    /// validated [`Capacity`] to rule that out statically.
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        Self::with_capacity(Capacity::expect(cap))
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.sender
+    /// Creates a builder for a channel holding at most `cap` messages.
+    pub fn with_capacity(cap: Capacity) -> Self {
+        Self {
+            cap,
+            name: None,
+            on_orphaned: None,
+            linger: None,
+            clock: Arc::new(SystemClock),
+            eviction_budget: None,
+            token_bucket: None,
+            protect: None,
+            policy: OverflowPolicy::DropOldest,
+            on_stalled: None,
+            skip_identical: None,
+            strict: None,
+            max_weight: None,
+            evict_transform: None,
+            classify: None,
+            attribute: None,
+            aggregator: None,
+            max_cap: None,
+            adaptive_window: None,
+            on_capacity_change: None,
+            on_capacity_request: None,
+            watermarks: None,
+            on_watermark: None,
+            preallocate: None,
+        }
     }
-}
 
-impl<T> OverwriteSender<T> {
-    /// Sends a value, overwriting old messages if the channel is at capacity.
+    /// Names the channel.
     ///
-    /// This method will never block. If the channel is at capacity, it will remove
-    /// old messages from the front of the queue until there's space for the new message.
+    /// The name is reported by [`Stats`] and the `Debug` output of both endpoints, which
+    /// helps tell channels apart when diagnosing a pipeline.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Registers a hook invoked when the last receiver is dropped while messages remain.
     ///
-    /// # Arguments
+    /// The hook receives every message still queued at that point, in queue order, so
+    /// pending work can be persisted or re-routed instead of being silently destroyed.
+    /// It is not invoked if the channel is empty when the last receiver goes away.
+    pub fn on_orphaned<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Vec<T>) + Send + Sync + 'static,
+    {
+        self.on_orphaned = Some(Box::new(f));
+        self
+    }
+
+    /// Delays disconnection by up to `period` after the last sender is dropped.
     ///
-    /// * `value` - The value to send through the channel
+    /// Normally receivers observe the channel as disconnected as soon as the last sender
+    /// goes away, although they can still drain any queued messages. With a linger period,
+    /// the channel stays connected until the queue has been drained or the period elapses,
+    /// whichever comes first. This mirrors socket linger semantics and gives consumers that
+    /// stop on disconnection, such as those polling `is_disconnected`, a chance to process
+    /// the remaining messages.
     ///
-    /// # Returns
+    /// The sender is held by a background thread for the duration of the linger period,
+    /// which is measured on the channel's [`clock`](Builder::clock).
     ///
-    /// - `Ok(None)` - The message was sent without overwriting any existing messages
-    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
-    ///   the messages that were overwritten (removed from the channel)
-    /// - `Err(SendError<T>)` - The channel is disconnected
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = Builder::new(4).linger(Duration::from_secs(5)).build();
+    /// sender.send_overwrite("last words").unwrap();
+    /// drop(sender);
+    ///
+    /// assert!(!receiver.is_disconnected());
+    /// assert_eq!(receiver.recv().unwrap(), "last words");
+    /// // Once drained, the channel disconnects without waiting for the full period
+    /// assert!(receiver.recv().is_err());
+    /// ```
+    pub fn linger(mut self, period: Duration) -> Self
+    where
+        T: Send + 'static,
+    {
+        self.linger = Some(Box::new(move |sender, receiver, clock: Arc<dyn Clock>| {
+            let deadline = clock.now() + period;
+            thread::spawn(move || {
+                while !receiver.is_empty() {
+                    let now = clock.now();
+                    if now >= deadline {
+                        break;
+                    }
+                    thread::sleep((deadline - now).min(LINGER_POLL_INTERVAL));
+                }
+                drop(sender);
+            });
+        }));
+        self
+    }
+
+    /// Sets the clock used by time-based features such as
+    /// [`eviction_budget`](Builder::eviction_budget).
+    ///
+    /// Defaults to [`SystemClock`]; tests can substitute a `MockClock` from the `test-util`
+    /// feature to control time.
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Limits evictions to `max` per `window`.
+    ///
+    /// Once a send would evict more messages than the budget has left for the current
+    /// window, the channel stops overwriting: sends that need to evict are rejected with
+    /// [`SendOverwriteError::Rejected`] until the window ends, while sends that fit without
+    /// evicting still go through. This caps how much data can be lost in a burst while the
+    /// consumer recovers. A window starts with the first eviction after the previous window
+    /// ended, and time is read from the channel's [`clock`](Builder::clock).
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use flume_overwrite::bounded;
+    /// use flume_overwrite::Builder;
+    /// use std::time::Duration;
     ///
-    /// let (sender, receiver) = bounded(2);
+    /// let (sender, receiver) = Builder::new(1)
+    ///     .eviction_budget(1, Duration::from_secs(60))
+    ///     .build();
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// assert_eq!(sender.send_overwrite(2).unwrap(), Some(vec![1]));
+    ///
+    /// // The budget is spent, so the queued message is kept
+    /// assert!(sender.send_overwrite(3).unwrap_err().is_rejected());
+    ///
+    /// // Once the consumer catches up there is room again without evicting
+    /// assert_eq!(receiver.recv().unwrap(), 2);
+    /// assert_eq!(sender.send_overwrite(4).unwrap(), None);
+    /// ```
+    pub fn eviction_budget(mut self, max: u64, window: Duration) -> Self {
+        self.eviction_budget = Some(EvictionBudget::new(max, window));
+        self
+    }
+
+    /// Lets the queue grow by at most `burst` messages at once, regenerating at `per_sec`
+    /// messages per second.
+    ///
+    /// Each send that adds a message to the queue takes a token from a bucket holding up to
+    /// `burst` tokens, which starts full and refills continuously. Once the bucket is
+    /// empty, sends overwrite the oldest queued message instead of growing the queue, even
+    /// below capacity; a send into an empty queue always goes through. This rate limits
+    /// and bounds the queue in one primitive: producers faster than `per_sec` conflate
+    /// rather than pile up. [`try_send`](OverwriteSender::try_send) reports the channel as
+    /// full while the bucket is empty. Time is read from the channel's
+    /// [`clock`](Builder::clock).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `per_sec` is not a positive, finite number or if `burst` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::new(8).token_bucket(1.0, 2).build();
     ///
-    /// // Send without overwriting
     /// assert_eq!(sender.send_overwrite(1).unwrap(), None);
     /// assert_eq!(sender.send_overwrite(2).unwrap(), None);
+    /// // The burst is spent: the newest message replaces the oldest
+    /// assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+    /// assert_eq!(sender.available_tokens(), Some(0));
     ///
-    /// // This will overwrite the first message
-    /// let overwritten = sender.send_overwrite(3).unwrap();
-    /// assert_eq!(overwritten, Some(vec![1]));
+    /// assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2, 3]);
     /// ```
-    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendError<T>> {
-        if let Some(capacity) = self.sender.capacity() {
-            let mut drained = Vec::new();
-            while self.sender.len() >= capacity {
-                match self.receiver.try_recv() {
-                    Ok(old_value) => drained.push(old_value),
-                    Err(flume::TryRecvError::Empty) => (),
-                    Err(_) => {
-                        return Err(SendError(value));
-                    }
-                }
-            }
-            self.sender.send(value)?;
-            Ok(if drained.is_empty() {
-                None
-            } else {
-                Some(drained)
-            })
-        } else {
-            self.sender.send(value)?;
-            Ok(None)
-        }
+    #[track_caller]
+    pub fn token_bucket(mut self, per_sec: f64, burst: usize) -> Self {
+        assert!(
+            per_sec > 0.0 && per_sec.is_finite(),
+            "rate must be positive and finite"
+        );
+        assert!(burst > 0, "burst must be positive");
+        self.token_bucket = Some(TokenBucket::new(per_sec, burst));
+        self
     }
 
-    /// Asynchronously sends a value, overwriting old messages if the channel is at capacity.
+    /// Protects messages matching `f` from being evicted.
     ///
-    /// This is the async version of `send_overwrite`. Like its synchronous counterpart,
-    /// this method will never block due to a full channel - it will instead remove old
-    /// messages to make space.
+    /// When the channel is full, the oldest message for which `f` returns `false` is evicted
+    /// instead of the oldest message overall, so protected messages are guaranteed to reach
+    /// the consumer while the others remain lossy. If the channel is full of protected
+    /// messages, the new message is rejected with [`SendOverwriteError::Rejected`].
     ///
-    /// # Arguments
+    /// Making room for a message then means draining and refilling the queue while other
+    /// sends wait, so receivers may momentarily find the channel empty but never observe a
+    /// reordering.
     ///
-    /// * `value` - The value to send through the channel
+    /// # Deadlocks
     ///
-    /// # Returns
+    /// `f` runs while the channel's send lock is held, which is not reentrant: it must not
+    /// send on, inspect or freeze the same channel, or the send calling it never returns.
+    pub fn protect<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.protect = Some(Box::new(f));
+        self
+    }
+
+    /// Sets what the channel does with a new message when it is full.
     ///
-    /// A future that resolves to:
-    /// - `Ok(None)` - The message was sent without overwriting any existing messages
-    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
-    ///   the messages that were overwritten (removed from the channel)
-    /// - `Err(SendError<T>)` - The channel is disconnected
+    /// Defaults to [`OverflowPolicy::DropOldest`]. The policy can be changed later with
+    /// [`OverwriteSender::swap_policy`].
+    pub fn policy(mut self, policy: OverflowPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Registers a hook invoked when a send evicts messages while the consumer has not sent
+    /// a [`heartbeat`](OverwriteReceiver::heartbeat) for at least `threshold`.
+    ///
+    /// A dead consumer otherwise goes unnoticed, since overwriting keeps every send
+    /// succeeding. The hook receives the time since the last heartbeat, or since the channel
+    /// was created if there was none, and runs once per stall: it is invoked again only
+    /// after the consumer has sent a heartbeat and stalled anew. It runs once the send is
+    /// done and other sends are let through again, so it may send on the channel itself.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use flume_overwrite::bounded;
-    /// use futures::executor::block_on;
+    /// use flume_overwrite::Builder;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    ///
+    /// let stalls = Arc::new(AtomicUsize::new(0));
+    /// let counter = stalls.clone();
+    /// let (sender, _receiver) = Builder::new(1)
+    ///     .on_consumer_stalled(Duration::ZERO, move |_silence| {
+    ///         counter.fetch_add(1, Ordering::Relaxed);
+    ///     })
+    ///     .build();
+    ///
+    /// for i in 0..4 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    /// assert_eq!(stalls.load(Ordering::Relaxed), 1);
+    /// ```
+    pub fn on_consumer_stalled<F>(mut self, threshold: Duration, f: F) -> Self
+    where
+        F: Fn(Duration) + Send + Sync + 'static,
+    {
+        self.on_stalled = Some((threshold, Box::new(f)));
+        self
+    }
+
+    /// Makes conflating sends that would replace a queued message with an identical one
+    /// a no-op.
+    ///
+    /// By default, [`send_conflate_variant`](OverwriteSender::send_conflate_variant)
+    /// replaces the queued message of the same variant even if it equals the new one, and
+    /// counts the replacement as an eviction in [`Stats`]. For idempotent updates that
+    /// inflates loss metrics although nothing was lost. With this option, such a send
+    /// leaves the queue untouched, returns `Ok(None)`, and is counted neither as sent nor as
+    /// evicted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Status {
+    ///     Online(u32),
+    /// }
+    ///
+    /// let (sender, receiver) = Builder::new(4).skip_identical_replacements().build();
+    /// sender.send_conflate_variant(Status::Online(1)).unwrap();
+    /// assert_eq!(sender.send_conflate_variant(Status::Online(1)).unwrap(), None);
+    ///
+    /// let stats = receiver.stats();
+    /// assert_eq!((stats.sent, stats.evicted, stats.len), (1, 0, 1));
+    /// ```
+    ///
+    /// # Deadlocks
+    ///
+    /// Messages are compared with `T`'s [`PartialEq`] implementation while the channel's
+    /// send lock is held. The lock is not reentrant, so that implementation must not send
+    /// on, inspect or freeze the same channel.
+    pub fn skip_identical_replacements(mut self) -> Self
+    where
+        T: PartialEq + 'static,
+    {
+        self.skip_identical = Some(Box::new(|queued, value| queued == value));
+        self
+    }
+
+    /// Makes every eviction panic, with the evicted messages in the panic message.
+    ///
+    /// Meant for test suites: a system under test that unexpectedly overloads a channel
+    /// then fails loudly instead of silently losing data. Only evictions made to free
+    /// capacity panic; explicit removals such as
+    /// [`send_conflate_variant`](OverwriteSender::send_conflate_variant) replacements or
+    /// [`cancel_where`](OverwriteSender::cancel_where) do not. The panic happens on the
+    /// sending thread, before the new message is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,should_panic
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, _receiver) = Builder::new(1).name("frames").strict().build();
+    /// sender.send_overwrite(1).unwrap();
+    ///
+    /// // Panics with "strict channel `frames` evicted 1 message(s): [1]"
+    /// sender.send_overwrite(2).unwrap();
+    /// ```
+    pub fn strict(mut self) -> Self
+    where
+        T: fmt::Debug + 'static,
+    {
+        self.strict = Some(Box::new(|evicted| format!("{evicted:?}")));
+        self
+    }
+
+    /// Rejects messages weighing more than `max`, as measured by `weigh`.
+    ///
+    /// Sending an oversized message fails with [`SendOverwriteError::Oversized`] before
+    /// anything is evicted, so a single huge message cannot flush the queue. A message
+    /// weighing exactly `max` is accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::new(4)
+    ///     .max_message_weight(8, |payload: &Vec<u8>| payload.len())
+    ///     .build();
+    ///
+    /// sender.send_overwrite(vec![0; 8]).unwrap();
+    /// assert!(sender.send_overwrite(vec![0; 9]).unwrap_err().is_oversized());
+    /// assert_eq!(receiver.len(), 1);
+    /// ```
+    ///
+    /// # Deadlocks
+    ///
+    /// `weigh` is called under the channel's send lock, which is not reentrant, so it must
+    /// not send on, inspect or freeze the same channel.
+    pub fn max_message_weight<F>(mut self, max: usize, weigh: F) -> Self
+    where
+        F: Fn(&T) -> usize + Send + Sync + 'static,
+    {
+        self.max_weight = Some((max, Box::new(weigh)));
+        self
+    }
+
+    /// Registers a hook that downsamples messages instead of dropping them when they are
+    /// evicted.
+    ///
+    /// When a send must free a slot, the oldest message is handed to `f`. If `f` returns a
+    /// cheaper representative, such as a summary of the message, the representative takes
+    /// the message's place at the head of the queue and the next oldest message is evicted
+    /// instead; if `f` returns `None`, the message is dropped. Representatives are handed to
+    /// `f` again when they are the oldest in turn, so a message can be summarized
+    /// progressively until `f` drops it. A representative that has nothing left to make
+    /// room behind it, as in a channel of capacity one, is evicted.
+    ///
+    /// Messages handed to `f` are not returned by the send, since `f` takes them; only the
+    /// messages evicted without transformation are. Channels with
+    /// [protected](Builder::protect) messages evict as usual, without the hook. The queue is
+    /// drained and refilled while other sends wait, so receivers may momentarily find the
+    /// channel empty but never observe a reordering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Sample {
+    ///     Full(Vec<u32>),
+    ///     Mean(u32),
+    /// }
+    ///
+    /// let (sender, receiver) = Builder::new(2)
+    ///     .on_evict_transform(|sample| match sample {
+    ///         Sample::Full(values) => {
+    ///             let mean = values.iter().sum::<u32>() / values.len() as u32;
+    ///             Some(Sample::Mean(mean))
+    ///         }
+    ///         Sample::Mean(_) => None,
+    ///     })
+    ///     .build();
+    ///
+    /// sender.send_overwrite(Sample::Full(vec![1, 3])).unwrap();
+    /// sender.send_overwrite(Sample::Full(vec![5, 7])).unwrap();
+    /// // The oldest sample is summarized, the next one makes room
+    /// let evicted = sender.send_overwrite(Sample::Full(vec![9])).unwrap();
+    /// assert_eq!(evicted, Some(vec![Sample::Full(vec![5, 7])]));
+    ///
+    /// assert_eq!(receiver.recv().unwrap(), Sample::Mean(2));
+    /// assert_eq!(receiver.recv().unwrap(), Sample::Full(vec![9]));
+    /// ```
+    ///
+    /// # Deadlocks
+    ///
+    /// Unlike the hooks that run once a send is done, such as
+    /// [`on_capacity_change`](Builder::on_capacity_change), `f` transforms messages in the
+    /// middle of an eviction, under the channel's send lock. The lock is not reentrant, so
+    /// `f` must not send on, inspect or freeze the same channel.
+    pub fn on_evict_transform<F>(mut self, f: F) -> Self
+    where
+        F: Fn(T) -> Option<T> + Send + Sync + 'static,
+    {
+        self.evict_transform = Some(Box::new(f));
+        self
+    }
+
+    /// Counts evicted messages per class, as named by `f`, in
+    /// [`Stats::evicted_by_class`].
+    ///
+    /// For enum messages, classifying by variant tells which kinds of messages are being
+    /// dropped. Messages evicted by an [eviction transform](Builder::on_evict_transform)
+    /// are classified as they were before being transformed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// enum Update {
+    ///     Position(f32, f32),
+    ///     Command(&'static str),
+    /// }
+    ///
+    /// let (sender, _receiver) = Builder::new(2)
+    ///     .classify_evictions(|update| match update {
+    ///         Update::Position(..) => "position",
+    ///         Update::Command(_) => "command",
+    ///     })
+    ///     .build();
+    ///
+    /// sender.send_overwrite(Update::Command("land")).unwrap();
+    /// for i in 0..3 {
+    ///     sender.send_overwrite(Update::Position(i as f32, 0.0)).unwrap();
+    /// }
+    ///
+    /// let stats = sender.stats();
+    /// assert_eq!(stats.evicted_by_class.get("command"), Some(&1));
+    /// assert_eq!(stats.evicted_by_class.get("position"), Some(&1));
+    /// ```
+    ///
+    /// # Deadlocks
+    ///
+    /// Messages are classified as they are evicted, while the channel's send lock is held.
+    /// The lock is not reentrant, so `f` must not send on, inspect or freeze the same
+    /// channel.
+    pub fn classify_evictions<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> &'static str + Send + Sync + 'static,
+    {
+        self.classify = Some(Box::new(f));
+        self
+    }
+
+    /// Feeds every evicted message to `aggregator`, whose summary is reported in
+    /// [`Stats::evicted_summary`].
+    ///
+    /// Lost data is then at least summarized: the range and mean of dropped readings, say,
+    /// with [`MinMaxMean`]. Messages an [eviction transform](Builder::on_evict_transform)
+    /// drops are not summarized, while the representatives it keeps are once evicted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{Builder, MinMaxMean};
+    ///
+    /// let (sender, _receiver) = Builder::new(1)
+    ///     .aggregate_evictions(MinMaxMean::new())
+    ///     .build();
+    /// for reading in [20.5, 23.0, 21.5, 22.0] {
+    ///     sender.send_overwrite(reading).unwrap();
+    /// }
+    ///
+    /// let summary = sender.stats().evicted_summary.unwrap();
+    /// assert_eq!(summary.get("count"), Some(3.0));
+    /// assert_eq!(summary.get("max"), Some(23.0));
+    /// assert_eq!(summary.get("mean"), Some(21.666666666666668));
+    /// ```
+    ///
+    /// # Deadlocks
+    ///
+    /// [`Aggregator::add`] is called as messages are evicted, while the channel's send lock
+    /// is held. The lock is not reentrant, so the aggregator must not send on, inspect or
+    /// freeze the same channel.
+    pub fn aggregate_evictions<A>(mut self, aggregator: A) -> Self
+    where
+        A: Aggregator<T> + 'static,
+    {
+        self.aggregator = Some(Box::new(aggregator));
+        self
+    }
+
+    /// Allows the capacity to be changed at runtime, up to `max` messages.
+    ///
+    /// See [`OverwriteSender::set_capacity`]. A maximum below the channel's capacity is
+    /// raised to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    #[track_caller]
+    pub fn max_capacity(mut self, max: usize) -> Self {
+        self.max_cap = Some(Capacity::expect(max));
+        self
+    }
+
+    /// Allocates the queue for the maximum capacity when the channel is built, so that it
+    /// never allocates afterwards.
+    ///
+    /// The queue belongs to flume and only grows by queueing messages, so it is warmed up
+    /// by filling it with placeholders made by `placeholder`, which are received and dropped
+    /// before the channel is handed out: they are never delivered, counted or seen by any
+    /// hook. The buffer holding messages set aside while the channel is
+    /// [frozen](OverwriteReceiver::freeze) is reserved for the maximum capacity too.
+    ///
+    /// Sends and receives then run without allocating, as long as overwritten messages are
+    /// not collected: use
+    /// [`send_overwrite_discard`](OverwriteSender::send_overwrite_discard) or
+    /// [`send_overwrite_copy`](OverwriteSender::send_overwrite_copy) rather than
+    /// [`send_overwrite`](OverwriteSender::send_overwrite). Operations that rebuild the
+    /// queue, such as [`protect`](Builder::protect)ed evictions or
+    /// [`drain`](flume::Receiver::drain), still reallocate it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::<u64>::new(1024).preallocate(u64::default).build();
+    /// assert_eq!(sender.allocated_bytes(), 2 * 1024 * size_of::<u64>());
+    /// # drop(receiver);
+    /// ```
+    pub fn preallocate<F>(mut self, placeholder: F) -> Self
+    where
+        F: Fn() -> T + Send + Sync + 'static,
+    {
+        self.preallocate = Some(Box::new(placeholder));
+        self
+    }
+
+    /// Resizes the channel to its load, between its capacity and `max` messages.
+    ///
+    /// Load is assessed over consecutive windows of `window`, at the first send after each
+    /// window ends. A window in which messages were evicted doubles the capacity, up to
+    /// `max`; a window in which the queue never held more than a quarter of the capacity
+    /// halves it, down to the capacity the channel was built with. Every change is reported
+    /// to the [`on_capacity_change`](Builder::on_capacity_change) hook.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, _receiver) = Builder::new(2)
+    ///     .adaptive_capacity(8, Duration::ZERO)
+    ///     .build();
+    ///
+    /// for i in 0..3 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    /// // The eviction made the channel grow
+    /// assert_eq!(sender.capacity(), 4);
+    /// ```
+    #[track_caller]
+    pub fn adaptive_capacity(self, max: usize, window: Duration) -> Self {
+        let mut builder = self.max_capacity(max);
+        builder.adaptive_window = Some(window);
+        builder
+    }
+
+    /// Registers a hook invoked whenever the channel's capacity changes.
+    ///
+    /// The hook runs once the resize is done and other sends are let through again, so it
+    /// may send on the channel itself. Changes are reported in order.
+    pub fn on_capacity_change<F>(mut self, f: F) -> Self
+    where
+        F: Fn(CapacityChange) + Send + Sync + 'static,
+    {
+        self.on_capacity_change = Some(Box::new(f));
+        self
+    }
+
+    /// Sets queue length watermarks for cooperative backpressure.
+    ///
+    /// Once the queue holds `high` messages, the channel is paused until it drains down to
+    /// `low`. Pausing stops nothing by itself: producers that can hold back check
+    /// [`OverwriteSender::is_paused`], or wait with
+    /// [`wait_resumed`](OverwriteSender::wait_resumed) or
+    /// [`resumed`](OverwriteSender::resumed), so that a slow consumer catches up before any
+    /// message is lost. Crossings are reported to the [`on_watermark`](Builder::on_watermark)
+    /// hook.
+    ///
+    /// Receives do not go through the channel, so draining is noticed on the next send,
+    /// consumer [heartbeat](OverwriteReceiver::heartbeat) or pause check.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `low` is not less than `high`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::new(10).watermarks(5, 9).build();
+    /// for i in 0..9 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    /// assert!(sender.is_paused());
+    ///
+    /// // Still paused until the queue is down to the low watermark
+    /// for _ in 0..3 {
+    ///     receiver.recv().unwrap();
+    /// }
+    /// assert!(sender.is_paused());
+    /// receiver.recv().unwrap();
+    /// assert!(!sender.is_paused());
+    /// ```
+    #[track_caller]
+    pub fn watermarks(mut self, low: usize, high: usize) -> Self {
+        assert!(low < high, "low watermark must be below the high watermark");
+        self.watermarks = Some((low, high));
+        self
+    }
+
+    /// Registers a hook invoked whenever the queue crosses one of the channel's
+    /// [watermarks](Builder::watermarks).
+    ///
+    /// The hook runs on the thread that noticed the crossing; crossings noticed by a send
+    /// are reported once the send is done, so the hook may send on the channel itself.
+    pub fn on_watermark<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Watermark) + Send + Sync + 'static,
+    {
+        self.on_watermark = Some(Box::new(f));
+        self
+    }
+
+    /// Creates the channel, returning its sender and receiver halves.
+    pub fn build(self) -> (OverwriteSender<T>, OverwriteReceiver<T>) {
+        let capacity = AtomicCapacity::new(self.cap, self.max_cap.unwrap_or(self.cap));
+        // The queue is allocated for the maximum, the current capacity is enforced on send
+        let (tx, rx) = flume::bounded(capacity.max());
+        let mut frozen = VecDeque::new();
+        let mut reserved = 0;
+        if let Some(placeholder) = &self.preallocate {
+            // Receiving one by one keeps the queue's allocation, unlike draining it
+            for _ in 0..capacity.max() {
+                let _ = tx.try_send(placeholder());
+            }
+            while rx.try_recv().is_ok() {}
+            frozen.reserve_exact(capacity.max());
+            reserved = (capacity.max() + frozen.capacity()) * size_of::<T>();
+        }
+        let watchdog = Watchdog::new(self.clock.now(), self.on_stalled);
+        let adaptive = self
+            .adaptive_window
+            .map(|window| Adaptive::new(self.cap.get(), window, self.clock.now()));
+        let shared = Arc::new(Shared {
+            id: ChannelId::next(),
+            name: self.name,
+            capacity,
+            in_flight: AtomicUsize::new(0),
+            staged: AtomicUsize::new(0),
+            send_lock: SendLock::new(),
+            deferred: Mutex::default(),
+            notifying: Mutex::new(()),
+            sender_count: AtomicUsize::new(1),
+            receiver_count: AtomicUsize::new(1),
+            sent: Counter::new(),
+            evicted: Counter::new(),
+            on_orphaned: self.on_orphaned,
+            linger: self.linger,
+            taps: Mutex::new(Vec::new()),
+            eviction_listeners: Mutex::new(Vec::new()),
+            clock: self.clock,
+            eviction_budget: self.eviction_budget,
+            token_bucket: self.token_bucket,
+            protect: self.protect,
+            protected: Mutex::new(Protected::new()),
+            policy: AtomicPolicy::new(self.policy),
+            watchdog,
+            skip_identical: self.skip_identical,
+            strict: self.strict,
+            max_weight: self.max_weight,
+            evict_transform: self.evict_transform,
+            classify: self.classify,
+            evicted_by_class: Mutex::default(),
+            attribute: self.attribute,
+            evicted_by_sender: Mutex::default(),
+            aggregator: self.aggregator.map(Mutex::new),
+            adaptive,
+            on_capacity_change: self.on_capacity_change,
+            capacity_requests: CapacityRequests::new(self.on_capacity_request),
+            watermarks: self
+                .watermarks
+                .map(|(low, high)| Watermarks::new(low, high, self.on_watermark)),
+            receiver_epoch: AtomicU64::new(0),
+            freezes: AtomicUsize::new(0),
+            recv_waits: WaitHistogram::new(),
+            signals: flume::unbounded(),
+            detached_failures: AtomicU64::new(0),
+            frozen: Mutex::new(frozen),
+            reserved,
+            leases: Mutex::default(),
+            weak_sender: tx.downgrade(),
+        });
+        let overwrite_sender = OverwriteSender {
+            id: SenderId::next(),
+            sender: tx,
+            receiver: rx.clone(),
+            shared: shared.clone(),
+        };
+        let overwrite_receiver = OverwriteReceiver {
+            id: ReceiverId::next(),
+            receiver: rx,
+            shared,
+            conflate: false,
+            epoch: 0,
+            selected: AtomicU64::new(0),
+            stream: Mutex::new(None),
+        };
+        (overwrite_sender, overwrite_receiver)
+    }
+}
+
+impl<T, E> Builder<Result<T, E>> {
+    /// Protects `Err` messages from being evicted, while `Ok` messages remain lossy.
+    ///
+    /// This is [`protect`](Builder::protect) with [`Result::is_err`], for channels where
+    /// every error must reach the consumer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::<Result<u32, String>>::new(2)
+    ///     .protect_errors()
+    ///     .build();
+    ///
+    /// sender.send_err("sensor offline".to_string()).unwrap();
+    /// sender.send_ok(1).unwrap();
+    /// // The error is older, but the previous reading is evicted instead
+    /// assert_eq!(sender.send_ok(2).unwrap(), Some(vec![Ok(1)]));
+    ///
+    /// assert_eq!(receiver.recv().unwrap(), Err("sensor offline".to_string()));
+    /// assert_eq!(receiver.recv().unwrap(), Ok(2));
+    /// ```
+    pub fn protect_errors(self) -> Self
+    where
+        T: 'static,
+        E: 'static,
+    {
+        self.protect(Result::is_err)
+    }
+}
+
+/// A sender that can overwrite old messages when the channel reaches capacity.
+///
+/// `OverwriteSender<T>` wraps a flume `Sender<T>` and provides additional functionality
+/// to automatically remove old messages when sending would block due to a full channel.
+///
+/// It provides `send_overwrite` and `send_overwrite_async` methods that will never block due
+/// to a full channel. The flume sender is deliberately not exposed, since its blocking `send`
+/// would wait forever on a full channel; the non-blocking parts of its API are delegated
+/// explicitly, and [`into_inner`](OverwriteSender::into_inner) is available as an escape hatch.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+///
+/// let (sender, receiver) = bounded(1);
+///
+/// // First message goes through normally
+/// sender.send_overwrite("first").unwrap();
+///
+/// // Second message overwrites the first
+/// let overwritten = sender.send_overwrite("second").unwrap();
+/// assert_eq!(overwritten, Some(vec!["first"]));
+/// ```
+pub struct OverwriteSender<T> {
+    id: SenderId,
+    sender: Sender<T>,
+    receiver: Receiver<T>,
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for OverwriteSender<T> {
+    fn clone(&self) -> Self {
+        self.shared.sender_count.fetch_add(1, Ordering::AcqRel);
+        Self {
+            id: SenderId::next(),
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for OverwriteSender<T> {
+    fn drop(&mut self) {
+        if self.shared.sender_count.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        // Taps and eviction streams disconnect along with the channel itself
+        self.shared.lock_taps().clear();
+        self.shared.lock_eviction_listeners().clear();
+        if let Some(linger) = &self.shared.linger
+            && !self.receiver.is_empty()
+        {
+            linger(
+                self.sender.clone(),
+                self.receiver.clone(),
+                self.shared.clock.clone(),
+            );
+        }
+    }
+}
+
+impl<T> fmt::Debug for OverwriteSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.shared.fmt_debug("OverwriteSender", &self.receiver, f)
+    }
+}
+
+/// The receiving half of an overwrite channel.
+///
+/// `OverwriteReceiver<T>` wraps a flume `Receiver<T>` and implements `Deref` to it, so all
+/// standard receiver methods are available. The wrapper tracks how many receivers are alive:
+/// once the last one is dropped, sends fail with [`SendOverwriteError::Disconnected`] and any messages still queued
+/// are handed to the [`on_orphaned`](Builder::on_orphaned) hook, if one was registered.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+///
+/// let (sender, receiver) = bounded(1);
+/// sender.send_overwrite(1).unwrap();
+/// drop(receiver);
+///
+/// assert!(sender.send_overwrite(2).is_err());
+/// ```
+pub struct OverwriteReceiver<T> {
+    id: ReceiverId,
+    receiver: Receiver<T>,
+    shared: Arc<Shared<T>>,
+    conflate: bool,
+    /// The generation of receivers this one belongs to, see
+    /// [`OverwriteSender::replace_receiver`].
+    epoch: u64,
+    /// The channel's eviction total when this receiver last took part in a
+    /// [`select_overwrite`].
+    selected: AtomicU64,
+    /// Created on the first poll of the `Stream` implementation or of
+    /// [`poll_recv`](OverwriteReceiver::poll_recv). The mutex keeps the receiver `Sync`.
+    stream: Mutex<Option<RecvStream<T>>>,
+}
+
+impl<T> Clone for OverwriteReceiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.receiver_count.fetch_add(1, Ordering::AcqRel);
+        Self {
+            id: ReceiverId::next(),
+            receiver: self.receiver.clone(),
+            shared: self.shared.clone(),
+            conflate: self.conflate,
+            epoch: self.epoch,
+            selected: AtomicU64::new(self.shared.evicted.get()),
+            stream: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> fmt::Debug for OverwriteReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.shared
+            .fmt_debug("OverwriteReceiver", &self.receiver, f)
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Sets whether this receiver's `Stream` implementation conflates backlogs.
+    ///
+    /// With conflation enabled, each item yielded by the stream is the newest message
+    /// available when it is polled: if the consumer has fallen behind, the older queued
+    /// messages are discarded rather than yielded one by one. Blocking and async `recv`
+    /// calls are unaffected. The setting is inherited by clones.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::executor::block_on;
+    /// use futures::StreamExt;
+    ///
+    /// let (sender, receiver) = bounded(8);
+    /// let mut receiver = receiver.conflate(true);
+    ///
+    /// for i in 0..5 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    ///
+    /// assert_eq!(block_on(receiver.next()), Some(4));
+    /// ```
+    pub fn conflate(mut self, enabled: bool) -> Self {
+        self.conflate = enabled;
+        self
+    }
+
+    /// Returns the id of this receiver handle.
+    ///
+    /// Each clone of a receiver has its own id; see [`ReceiverId`].
+    pub fn id(&self) -> ReceiverId {
+        self.id
+    }
+
+    /// Returns the id of the channel this receiver belongs to.
+    pub fn channel_id(&self) -> ChannelId {
+        self.shared.id
+    }
+
+    /// Returns `true` if both receivers belong to the same channel.
+    pub fn same_channel(&self, other: &OverwriteReceiver<T>) -> bool {
+        self.shared.id == other.shared.id
+    }
+
+    /// Returns the maximum number of messages the channel can hold.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity.get()
+    }
+
+    /// Returns the number of messages in the channel.
+    ///
+    /// See [`OverwriteSender::len`] for how messages being sent are accounted for.
+    pub fn len(&self) -> usize {
+        self.shared.len(&self.receiver)
+    }
+
+    /// Returns `true` if the channel holds no messages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the next send will overwrite an older message.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.shared.capacity.regular()
+    }
+
+    /// Returns how many more messages can be sent before the channel starts overwriting.
+    ///
+    /// Slots reserved with [`reserve_burst`](OverwriteSender::reserve_burst) are not
+    /// counted.
+    pub fn remaining(&self) -> usize {
+        self.shared.capacity.regular().saturating_sub(self.len())
+    }
+
+    /// Returns a snapshot of the channel's state.
+    ///
+    /// See [`Stats`] for the reported fields.
+    pub fn stats(&self) -> Stats {
+        self.shared.stats(&self.receiver)
+    }
+
+    /// Calls `f` with an iterator over the queued messages, oldest first, without consuming
+    /// them.
+    ///
+    /// This lets a debug endpoint show what is stuck in a pipeline. The queue is briefly
+    /// drained and refilled while sends wait, so other receivers may momentarily find the
+    /// channel empty but never observe a reordering.
+    ///
+    /// Returns `None` without calling `f` once every sender has been dropped, since the
+    /// queue could then not be refilled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.send_overwrite("decode").unwrap();
+    /// sender.send_overwrite("resize").unwrap();
+    ///
+    /// let stuck = receiver.inspect(|queued| queued.copied().collect::<Vec<_>>());
+    /// assert_eq!(stuck, Some(vec!["decode", "resize"]));
+    /// assert_eq!(receiver.len(), 2);
+    /// ```
+    pub fn inspect<R>(&self, f: impl FnOnce(std::slice::Iter<'_, T>) -> R) -> Option<R> {
+        let _sending = self.shared.lock_sends();
+        let sender = self.shared.weak_sender.upgrade()?;
+        let queued: Vec<T> = self.receiver.drain().collect();
+        let result = f(queued.iter());
+        self.shared.requeue_locked(&sender, &self.receiver, queued);
+        Some(result)
+    }
+
+    /// Takes every queued message at once, oldest first, leaving the channel empty.
+    ///
+    /// The channel's buffer is swapped for an empty one in a single step, so producers are
+    /// held up for constant time however long the backlog is; the messages are only moved
+    /// into the returned vector afterwards. This makes it suited to snapshotting large
+    /// queues. Like any receive, it records a [`heartbeat`](Self::heartbeat).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(3);
+    /// for i in 0..5 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    ///
+    /// assert_eq!(receiver.take_backlog(), vec![2, 3, 4]);
+    /// assert!(receiver.is_empty());
+    /// assert_eq!(receiver.take_backlog(), Vec::<i32>::new());
+    /// ```
+    pub fn take_backlog(&self) -> Vec<T> {
+        let backlog = self.drain().collect();
+        self.heartbeat();
+        backlog
+    }
+
+    /// Records that the consumer is alive.
+    ///
+    /// Receives made through the `Stream` implementation record a heartbeat automatically;
+    /// consumers using the flume receive methods should call this after handling each
+    /// message so that senders can tell a slow consumer from a dead one, see
+    /// [`OverwriteSender::consumer_stalled`].
+    pub fn heartbeat(&self) {
+        self.shared.watchdog.beat(self.shared.clock.now());
+        self.shared.observe_watermarks(&self.receiver);
+    }
+
+    /// Spawns a consumer calling `f` with every message, returning a guard that stops it.
+    ///
+    /// The consumer runs on a dedicated thread with its own clone of this receiver, so it
+    /// competes for messages with other receivers like any clone would. Dropping the returned
+    /// [`Subscription`] stops the consumer and releases that clone, which makes temporary
+    /// listeners safe to attach and detach without leaking threads or receivers.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let (seen_tx, seen) = flume::unbounded();
+    ///
+    /// let subscription = receiver.subscribe_scoped(move |value| seen_tx.send(value).unwrap());
+    /// sender.send_overwrite("event").unwrap();
+    /// assert_eq!(seen.recv_timeout(Duration::from_secs(1)), Ok("event"));
+    ///
+    /// drop(subscription);
+    /// assert_eq!(sender.receiver_count(), 1);
+    /// ```
+    pub fn subscribe_scoped<F>(&self, f: F) -> Subscription
+    where
+        T: Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        Subscription::spawn(self.clone(), f)
+    }
+
+    /// Spawns a managed consumer calling `f` with every message, catching its panics.
+    ///
+    /// This is [`subscribe_scoped`](OverwriteReceiver::subscribe_scoped) for handlers that
+    /// may fail: a panicking call is caught and recorded in the returned
+    /// [`HandlerSupervisor`], and the consumer moves on to the next message instead of
+    /// dying. Panics are still reported by the process's panic hook. Dropping the
+    /// supervisor stops the consumer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let (done_tx, done) = flume::unbounded();
+    ///
+    /// let supervisor = receiver.attach_handler(move |value: i32| {
+    ///     assert!(value >= 0, "negative value");
+    ///     done_tx.send(value).unwrap();
+    /// });
+    /// sender.send_overwrite(-1).unwrap();
+    /// sender.send_overwrite(1).unwrap();
+    ///
+    /// assert_eq!(done.recv_timeout(Duration::from_secs(1)), Ok(1));
+    /// assert_eq!(supervisor.take_panic().unwrap().message(), "negative value");
+    /// ```
+    pub fn attach_handler<F>(&self, f: F) -> HandlerSupervisor
+    where
+        T: Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        HandlerSupervisor::spawn(self.clone(), f)
+    }
+
+    /// Creates a secondary channel receiving a clone of every message sent from now on.
+    ///
+    /// The tap has its own capacity and overwrites its own oldest messages, so observing
+    /// traffic never takes messages from the main receivers nor slows down senders beyond
+    /// the cost of the clone. The tap disconnects along with the main channel once every
+    /// sender is dropped, and is detached when its receivers are dropped. If the channel is
+    /// named, the tap is named after it with a `.tap` suffix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// let tap = receiver.tap(1);
+    ///
+    /// sender.send_overwrite("a").unwrap();
+    /// sender.send_overwrite("b").unwrap();
+    ///
+    /// // The tap only keeps the latest message, the main receiver is unaffected
+    /// assert_eq!(tap.drain().collect::<Vec<_>>(), vec!["b"]);
+    /// assert_eq!(receiver.drain().collect::<Vec<_>>(), vec!["a", "b"]);
+    /// ```
+    #[track_caller]
+    pub fn tap(&self, cap: usize) -> OverwriteReceiver<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        let mut builder = Builder::new(cap);
+        if let Some(name) = &self.shared.name {
+            builder = builder.name(format!("{name}.tap"));
+        }
+        let (tap_sender, tap_receiver) = builder.build();
+        let mut taps = self.shared.lock_taps();
+        // A channel whose senders are all gone will not send anything to tap
+        if self.shared.sender_count.load(Ordering::Acquire) > 0 {
+            taps.push(Box::new(move |value: &T| {
+                tap_sender.send_overwrite(value.clone()).is_ok()
+            }));
+        }
+        tap_receiver
+    }
+
+    /// Collects messages until none arrives for `idle`, then returns them in order.
+    ///
+    /// Every message received resets the idle timer, so this waits for the channel to go
+    /// quiet rather than for a fixed duration. It returns early, with whatever was collected,
+    /// once the channel is empty and every sender has been dropped. This suits tests waiting
+    /// for a pipeline to settle, and debounced rebuilds triggered by bursts of changes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(8);
+    /// thread::spawn(move || {
+    ///     for i in 0..3 {
+    ///         sender.send_overwrite(i).unwrap();
+    ///         thread::sleep(Duration::from_millis(1));
+    ///     }
+    /// });
+    ///
+    /// assert_eq!(receiver.recv_quiescent(Duration::from_millis(100)), vec![0, 1, 2]);
+    /// ```
+    pub fn recv_quiescent(&self, idle: Duration) -> Vec<T> {
+        let mut batch: Vec<T> = self.drain().collect();
+        while let Ok(value) = self.recv_timeout(idle) {
+            batch.push(value);
+            batch.extend(self.drain());
+        }
+        batch
+    }
+
+    /// Receives a message, busy-polling up to `max_spins` times before blocking.
+    ///
+    /// Parking and waking a thread costs microseconds, which dominates for consumers that
+    /// expect the next message almost immediately. Spinning first picks such messages up
+    /// without parking, at the cost of burning CPU for the duration of the spins; once they
+    /// are exhausted this behaves like a blocking `recv`. With `max_spins` of zero it is
+    /// exactly a blocking `recv`.
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// sender.send_overwrite(1).unwrap();
+    /// assert_eq!(receiver.recv_spin(100), Ok(1));
+    ///
+    /// drop(sender);
+    /// assert!(receiver.recv_spin(100).is_err());
+    /// ```
+    pub fn recv_spin(&self, max_spins: u32) -> Result<T, RecvError> {
+        for _ in 0..max_spins {
+            match self.try_recv() {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(TryRecvError::Empty) => std::hint::spin_loop(),
+            }
+        }
+        self.recv()
+    }
+
+    /// Polls for a message, registering the waker of `cx` if none is available.
+    ///
+    /// This is the building block of the async receive methods, for custom executors and
+    /// GUI event loops that drive the channel by hand instead of awaiting a future. The
+    /// registration is kept by the receiver between polls, so polling does not allocate
+    /// after the first call. Unlike the `Stream` implementation, messages are never
+    /// conflated.
+    ///
+    /// Returns `Poll::Ready(Err(RecvError::Disconnected))` once the channel is empty and
+    /// every sender has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::task::noop_waker_ref;
+    /// use std::task::{Context, Poll};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// let mut cx = Context::from_waker(noop_waker_ref());
+    /// assert!(receiver.poll_recv(&mut cx).is_pending());
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(Ok(1)));
+    /// ```
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>>
+    where
+        T: Send + 'static,
+    {
+        self.poll_stream(cx)
+            .map(|value| value.ok_or(RecvError::Disconnected))
+    }
+
+    /// Polls the receiver's stream, creating it on first use.
+    fn poll_stream(&self, cx: &mut Context<'_>) -> Poll<Option<T>>
+    where
+        T: Send + 'static,
+    {
+        if self.is_retired() {
+            return Poll::Ready(None);
+        }
+        let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        let stream = stream.get_or_insert_with(|| Box::new(self.receiver.clone().into_stream()));
+        let polled = Pin::new(stream).poll_next(cx);
+        if let Poll::Ready(Some(_)) = polled {
+            self.shared.observe_watermarks(&self.receiver);
+        }
+        polled
+    }
+
+    /// Asynchronously collects messages until none arrives for `idle`.
+    ///
+    /// This is the async version of [`recv_quiescent`](OverwriteReceiver::recv_quiescent).
+    pub async fn recv_quiescent_async(&self, idle: Duration) -> Vec<T> {
+        let mut batch: Vec<T> = self.drain().collect();
+        if self.is_retired() {
+            return batch;
+        }
+        loop {
+            let mut recv = self.receiver.recv_async();
+            let mut timeout = futures_timer::Delay::new(idle);
+            let received = std::future::poll_fn(|cx| {
+                if let Poll::Ready(result) = Pin::new(&mut recv).poll(cx) {
+                    return Poll::Ready(result.ok());
+                }
+                Pin::new(&mut timeout).poll(cx).map(|()| None)
+            })
+            .await;
+            match received {
+                Some(value) => {
+                    batch.push(value);
+                    batch.extend(self.drain());
+                }
+                None => return batch,
+            }
+        }
+    }
+}
+
+impl<T> Deref for OverwriteReceiver<T> {
+    type Target = Receiver<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.receiver
+    }
+}
+
+/// Yields messages as they arrive, ending once every sender has been dropped and the
+/// channel is empty.
+///
+/// See [`conflate`](OverwriteReceiver::conflate) for skipping over backlogs.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+/// use futures::executor::block_on;
+/// use futures::StreamExt;
+///
+/// let (sender, receiver) = bounded(2);
+/// sender.send_overwrite(1).unwrap();
+/// sender.send_overwrite(2).unwrap();
+/// sender.send_overwrite(3).unwrap();
+/// drop(sender);
+///
+/// assert_eq!(block_on(receiver.collect::<Vec<_>>()), vec![2, 3]);
+/// ```
+impl<T: Send + 'static> Stream for OverwriteReceiver<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = &mut *self;
+        let mut value = match this.poll_stream(cx) {
+            Poll::Ready(Some(value)) => value,
+            other => return other,
+        };
+        if this.conflate {
+            while let Ok(newer) = this.receiver.try_recv() {
+                value = newer;
+            }
+        }
+        this.heartbeat();
+        Poll::Ready(Some(value))
+    }
+}
+
+impl<T> Drop for OverwriteReceiver<T> {
+    fn drop(&mut self) {
+        if self.shared.receiver_count.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+        let pending: Vec<T> = self.receiver.drain().collect();
+        if let Some(on_orphaned) = &self.shared.on_orphaned
+            && !pending.is_empty()
+        {
+            on_orphaned(pending);
+        }
+    }
+}
+
+/// Marks a send as in flight for as long as it is alive, including when an async send is
+/// cancelled mid-way.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl<'a> InFlight<'a> {
+    fn enter(counter: &'a AtomicUsize) -> Self {
+        counter.fetch_add(1, Ordering::AcqRel);
+        Self(counter)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl<T, E> OverwriteSender<Result<T, E>> {
+    /// Sends `Ok(value)` with overwrite semantics.
+    ///
+    /// See [`send_overwrite`](OverwriteSender::send_overwrite) for the returned values.
+    #[allow(clippy::type_complexity)]
+    pub fn send_ok(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<Result<T, E>>>, SendOverwriteError<Result<T, E>>> {
+        self.send_overwrite(Ok(value))
+    }
+
+    /// Sends `Err(error)` with overwrite semantics.
+    ///
+    /// Errors are only protected from eviction if the channel was built with
+    /// [`protect_errors`](Builder::protect_errors).
+    #[allow(clippy::type_complexity)]
+    pub fn send_err(
+        &self,
+        error: E,
+    ) -> Result<Option<Vec<Result<T, E>>>, SendOverwriteError<Result<T, E>>> {
+        self.send_overwrite(Err(error))
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Sends a value, overwriting old messages if the channel is at capacity.
+    ///
+    /// This method will never block. If the channel is at capacity, it will remove
+    /// old messages from the front of the queue until there's space for the new message.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to send through the channel
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages that were overwritten (removed from the channel)
+    /// - `Err(SendOverwriteError<T>)` - The channel is disconnected, or the send was rejected
+    ///   because nothing could be evicted, see [`SendOverwriteError::Rejected`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    ///
+    /// // Send without overwriting
+    /// assert_eq!(sender.send_overwrite(1).unwrap(), None);
+    /// assert_eq!(sender.send_overwrite(2).unwrap(), None);
+    ///
+    /// // This will overwrite the first message
+    /// let overwritten = sender.send_overwrite(3).unwrap();
+    /// assert_eq!(overwritten, Some(vec![1]));
+    /// ```
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let _sending = self.shared.lock_sends();
+        self.send_overwrite_locked(value)
+    }
+
+    /// Asynchronously sends a value, overwriting old messages if the channel is at capacity.
+    ///
+    /// This is the async version of `send_overwrite`. Like its synchronous counterpart,
+    /// this method will never block due to a full channel - it will instead remove old
+    /// messages to make space.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to send through the channel
+    ///
+    /// # Returns
+    ///
+    /// A future that resolves to:
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages that were overwritten (removed from the channel)
+    /// - `Err(SendOverwriteError<T>)` - The channel is disconnected, or the send was rejected
+    ///   because nothing could be evicted, see [`SendOverwriteError::Rejected`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::executor::block_on;
+    ///
+    /// let (sender, receiver) = bounded(1);
+    ///
+    /// block_on(async {
+    ///     // Send without overwriting
+    ///     assert_eq!(sender.send_overwrite_async(1).await.unwrap(), None);
+    ///     
+    ///     // This will overwrite the first message
+    ///     let overwritten = sender.send_overwrite_async(2).await.unwrap();
+    ///     assert_eq!(overwritten, Some(vec![1]));
+    /// });
+    /// ```
+    ///
+    /// # Cancellation
+    ///
+    /// The future only waits for concurrent sends to finish, without blocking the executor
+    /// thread, then claims the messages it evicts and queues its own in a single step under
+    /// the channel's send lock. Dropping it while it waits sends nothing, and there is no
+    /// point at which it can be dropped halfway: the overwritten messages are always
+    /// returned in queue order, oldest first, however async sends race.
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        // No await point follows taking the lock, so a cancellation cannot interrupt an
+        // eviction
+        let _sending = self.shared.lock_sends_async().await;
+        self.send_overwrite_locked(value)
+    }
+
+    /// Polls a send with overwrite semantics, for custom executors and event loops.
+    ///
+    /// Overwriting never waits for room, only for a concurrent send holding the channel's
+    /// send lock. Rather than blocking on it, this registers the task of `cx` to be woken
+    /// once the lock is released and returns `Poll::Pending`, leaving the value in `slot`
+    /// for the next poll. Once the lock is taken, the value is moved out of `slot` and sent
+    /// like [`send_overwrite`](OverwriteSender::send_overwrite) would.
+    ///
+    /// It exists so that hand-written futures and reactors can treat the channel like any
+    /// other pollable resource without boxing the future of
+    /// [`send_overwrite_async`](OverwriteSender::send_overwrite_async).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `slot` is empty, such as when polled again after returning `Poll::Ready`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::task::noop_waker_ref;
+    /// use std::task::{Context, Poll};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// let mut cx = Context::from_waker(noop_waker_ref());
+    ///
+    /// let mut slot = Some(1);
+    /// assert_eq!(sender.poll_send_overwrite(&mut cx, &mut slot), Poll::Ready(Ok(None)));
+    /// assert_eq!(slot, None);
+    ///
+    /// let mut slot = Some(2);
+    /// assert_eq!(
+    ///     sender.poll_send_overwrite(&mut cx, &mut slot),
+    ///     Poll::Ready(Ok(Some(vec![1])))
+    /// );
+    /// assert_eq!(receiver.recv().unwrap(), 2);
+    /// ```
+    pub fn poll_send_overwrite(
+        &self,
+        cx: &mut Context<'_>,
+        slot: &mut Option<T>,
+    ) -> Poll<Result<Option<Vec<T>>, SendOverwriteError<T>>> {
+        assert!(slot.is_some(), "polled a send without a value");
+        let _sending = match self.shared.poll_lock_sends(cx) {
+            Poll::Ready(sending) => sending,
+            Poll::Pending => return Poll::Pending,
+        };
+        let value = slot.take().expect("checked above");
+        Poll::Ready(self.send_overwrite_locked(value))
+    }
+
+    /// Sends a value with overwrite semantics, dropping any overwritten messages instead of
+    /// returning them.
+    ///
+    /// For large payloads that are of no use once overwritten, this skips collecting them
+    /// into a vector. The overwritten messages are dropped before this returns.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(usize)` - The message was sent, overwriting this many messages
+    /// - `Err(SendOverwriteError<T>)` - The channel is disconnected, or the send was rejected
+    ///   because nothing could be evicted, see [`SendOverwriteError::Rejected`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// assert_eq!(sender.send_overwrite_discard(vec![0u8; 1 << 20]).unwrap(), 0);
+    /// assert_eq!(sender.send_overwrite_discard(vec![1u8; 1 << 20]).unwrap(), 1);
+    /// assert_eq!(receiver.recv().unwrap()[0], 1);
+    /// ```
+    pub fn send_overwrite_discard(&self, value: T) -> Result<usize, SendOverwriteError<T>> {
+        let _sending = self.shared.lock_sends();
+        self.send_overwrite_with(value, drop)
+    }
+
+    /// Sends a value with overwrite semantics without ever waiting, dropping it on failure.
+    ///
+    /// This is meant for contexts that must not block or handle errors, such as `Drop`
+    /// implementations. Instead of waiting for a concurrent send to finish, the message is
+    /// dropped, and so are overwritten messages and messages that cannot be sent; every
+    /// dropped message other than an overwritten one is counted in
+    /// [`detached_failures`](OverwriteSender::detached_failures). Nothing is allocated
+    /// unless the channel is [frozen](OverwriteReceiver::freeze) or its queue grows.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{OverwriteSender, bounded};
+    ///
+    /// struct Connection {
+    ///     id: u32,
+    ///     closed: OverwriteSender<u32>,
+    /// }
+    ///
+    /// impl Drop for Connection {
+    ///     fn drop(&mut self) {
+    ///         self.closed.send_overwrite_detached(self.id);
+    ///     }
+    /// }
+    ///
+    /// let (closed, receiver) = bounded(8);
+    /// drop(Connection { id: 7, closed: closed.clone() });
+    /// assert_eq!(receiver.recv().unwrap(), 7);
+    ///
+    /// drop(receiver);
+    /// drop(Connection { id: 8, closed: closed.clone() });
+    /// assert_eq!(closed.detached_failures(), 1);
+    /// ```
+    pub fn send_overwrite_detached(&self, value: T) {
+        let sent = match self.shared.try_lock_sends() {
+            Some(_sending) => self.send_overwrite_with(value, drop).is_ok(),
+            None => false,
+        };
+        if !sent {
+            self.shared
+                .detached_failures
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the number of messages [`send_overwrite_detached`] dropped instead of sending.
+    ///
+    /// [`send_overwrite_detached`]: OverwriteSender::send_overwrite_detached
+    pub fn detached_failures(&self) -> u64 {
+        self.shared.detached_failures.load(Ordering::Relaxed)
+    }
+
+    /// Sends a `Copy` value with overwrite semantics, returning a plain status.
+    ///
+    /// For streams of small values such as counters or event codes, this skips collecting
+    /// overwritten messages and handing the value back through an error, since the caller
+    /// keeps a copy anyway. The send itself costs about as much as
+    /// [`send_overwrite_discard`](OverwriteSender::send_overwrite_discard), as taking the
+    /// send lock dominates either way; the `send_overwrite_copy` benchmark compares them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{SendStatus, bounded};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// assert_eq!(sender.send_overwrite_copy(1u64), SendStatus::Sent);
+    /// assert_eq!(sender.send_overwrite_copy(2u64), SendStatus::Overwrote(1));
+    ///
+    /// drop(receiver);
+    /// assert_eq!(sender.send_overwrite_copy(3u64), SendStatus::Disconnected);
+    /// ```
+    pub fn send_overwrite_copy(&self, value: T) -> SendStatus
+    where
+        T: Copy,
+    {
+        let _sending = self.shared.lock_sends();
+        match self.send_overwrite_with(value, drop) {
+            Ok(0) => SendStatus::Sent,
+            Ok(evictions) => SendStatus::Overwrote(evictions),
+            Err(SendOverwriteError::Disconnected(_)) => SendStatus::Disconnected,
+            Err(SendOverwriteError::Rejected(_)) => SendStatus::Rejected,
+            Err(SendOverwriteError::Oversized(_)) => SendStatus::Oversized,
+            Err(SendOverwriteError::Contended(_)) => SendStatus::Contended,
+        }
+    }
+
+    /// Sends every value in order with overwrite semantics, as one atomic batch with respect
+    /// to other senders.
+    ///
+    /// On failure the error holds the value that failed along with every value after it.
+    pub(crate) fn send_overwrite_batch(
+        &self,
+        values: Vec<T>,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        let _sending = self.shared.lock_sends();
+        self.send_batch_locked(values)
+    }
+
+    /// Sends a batch only if all of it fits once older messages are evicted, otherwise
+    /// rejecting the whole batch without evicting anything.
+    ///
+    /// This suits multi-part messages that are useless if partially delivered. The batch
+    /// is sent in order, as one atomic batch with respect to other senders, and its parts
+    /// are never evicted by one another. A batch larger than the channel's capacity is
+    /// always rejected. The channel's policy, eviction budget and
+    /// [protected](Builder::protect) messages are taken into account, and if any part is
+    /// oversized the batch fails with [`SendOverwriteError::Oversized`]. A send can only be
+    /// cut short by [`SendOverwriteError::Contended`], should receivers keep racing it.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The batch was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The batch was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<Vec<T>>)` - The batch could not be sent and is handed back
+    ///   whole
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(3);
+    /// sender.send_overwrite("ping").unwrap();
+    /// sender.send_all_or_nothing(vec!["1/2", "2/2"]).unwrap();
+    ///
+    /// let overwritten = sender.send_all_or_nothing(vec!["1/2'", "2/2'"]).unwrap();
+    /// assert_eq!(overwritten, Some(vec!["ping", "1/2"]));
+    ///
+    /// // Four parts can never fit, so nothing is evicted
+    /// let error = sender.send_all_or_nothing(vec!["a", "b", "c", "d"]).unwrap_err();
+    /// assert_eq!(error.into_inner(), vec!["a", "b", "c", "d"]);
+    /// assert_eq!(receiver.len(), 3);
+    /// ```
+    pub fn send_all_or_nothing(
+        &self,
+        batch: Vec<T>,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        let _sending = self.shared.lock_sends();
+        if self.is_orphaned() {
+            return Err(SendOverwriteError::Disconnected(batch));
+        }
+        if batch.iter().any(|value| self.shared.is_oversized(value)) {
+            return Err(SendOverwriteError::Oversized(batch));
+        }
+        if !self.batch_fits_locked(batch.len()) {
+            return Err(SendOverwriteError::Rejected(batch));
+        }
+        self.send_batch_locked(batch)
+    }
+
+    /// Returns whether `n` messages can be queued at once, evicting older messages as
+    /// needed, while holding the send lock.
+    fn batch_fits_locked(&self, n: usize) -> bool {
+        let len = self.sender.len();
+        let capacity = self.regular_capacity(len);
+        if n > capacity {
+            return false;
+        }
+        let excess = (len + n).saturating_sub(capacity);
+        if excess == 0 {
+            return true;
+        }
+        if self.shared.policy.load() == OverflowPolicy::RejectNew
+            || !self.can_claim_evictions(excess)
+        {
+            return false;
+        }
+        len - self.shared.protected_locked(len) >= excess
+    }
+
+    /// Sends every value in order while holding the send lock.
+    ///
+    /// See [`send_overwrite_batch`](OverwriteSender::send_overwrite_batch).
+    fn send_batch_locked(
+        &self,
+        values: Vec<T>,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<Vec<T>>> {
+        let mut drained = Vec::new();
+        let mut values = values.into_iter();
+        while let Some(value) = values.next() {
+            match self.send_overwrite_locked(value) {
+                Ok(Some(overwritten)) => drained.extend(overwritten),
+                Ok(None) => (),
+                Err(error) => {
+                    return Err(error.map(|value| {
+                        let mut unsent = vec![value];
+                        unsent.extend(values);
+                        unsent
+                    }));
+                }
+            }
+        }
+        Ok(if drained.is_empty() {
+            None
+        } else {
+            Some(drained)
+        })
+    }
+
+    /// Returns a [`BufferedSender`] that stages up to `n` messages locally before flushing
+    /// them to the channel in one batch.
+    ///
+    /// A batch size of zero is treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(8);
+    /// let mut buffered = sender.buffered(16);
+    /// for i in 0..4 {
+    ///     buffered.send_overwrite(i).unwrap();
+    /// }
+    /// buffered.flush().unwrap();
+    /// assert_eq!(receiver.len(), 4);
+    /// ```
+    pub fn buffered(&self, n: usize) -> BufferedSender<T> {
+        BufferedSender::new(self.clone(), n)
+    }
+
+    /// Attempts to send a value without overwriting, failing if the channel is full.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(())` - The message was sent
+    /// - `Err(TrySendError::Full(T))` - The channel is at capacity
+    /// - `Err(TrySendError::Disconnected(T))` - The channel is disconnected
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume::TrySendError;
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, _receiver) = bounded(1);
+    /// sender.try_send(1).unwrap();
+    /// assert!(matches!(sender.try_send(2), Err(TrySendError::Full(2))));
+    /// ```
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let _sending = self.shared.lock_sends();
+        if self.is_orphaned() {
+            return Err(TrySendError::Disconnected(value));
+        }
+        if self.shared.is_frozen() {
+            let mut frozen = self.shared.lock_frozen();
+            if self.sender.len() + frozen.len() >= self.shared.capacity.regular() {
+                return Err(TrySendError::Full(value));
+            }
+            frozen.push_back(value);
+            return Ok(());
+        }
+        // Only this sender can fill the channel while the lock is held
+        if self.sender.len() >= self.shared.capacity.regular() {
+            return Err(TrySendError::Full(value));
+        }
+        if let Some(bucket) = &self.shared.token_bucket
+            && !bucket.try_take(self.shared.clock.now())
+            && !self.sender.is_empty()
+        {
+            return Err(TrySendError::Full(value));
+        }
+        self.shared.tap(&value);
+        let protected = self.shared.is_protected(&value);
+        self.sender.try_send(value)?;
+        self.shared.queued_locked(protected);
+        self.shared.sent.add(1);
+        self.shared.observe_watermarks_locked(&self.receiver);
+        Ok(())
+    }
+
+    /// Returns `true` if every receiver of the channel has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.is_orphaned()
+    }
+
+    /// Returns how many more messages the queue may grow by right now, or `None` if the
+    /// channel has no [token bucket](Builder::token_bucket).
+    pub fn available_tokens(&self) -> Option<usize> {
+        self.shared
+            .token_bucket
+            .as_ref()
+            .map(|bucket| bucket.available(self.shared.clock.now()))
+    }
+
+    /// Returns `true` if both senders belong to the same channel.
+    pub fn same_channel(&self, other: &OverwriteSender<T>) -> bool {
+        self.shared.id == other.shared.id
+    }
+
+    /// Returns the id of this sender handle.
+    ///
+    /// Each clone of a sender has its own id; see [`SenderId`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::collections::HashSet;
+    ///
+    /// let (sender, receiver) = bounded::<u8>(1);
+    /// let clone = sender.clone();
+    ///
+    /// let mut seen = HashSet::new();
+    /// assert!(seen.insert(sender.id()));
+    /// assert!(seen.insert(clone.id()));
+    /// assert!(!seen.insert(sender.id()));
+    ///
+    /// assert_eq!(sender.channel_id(), receiver.channel_id());
+    /// ```
+    pub fn id(&self) -> SenderId {
+        self.id
+    }
+
+    /// Returns the id of the channel this sender belongs to.
+    pub fn channel_id(&self) -> ChannelId {
+        self.shared.id
+    }
+
+    /// Returns the number of senders connected to the channel.
+    pub fn sender_count(&self) -> usize {
+        self.shared.sender_count.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of receivers connected to the channel.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.receiver_count.load(Ordering::Acquire)
+    }
+
+    /// Converts this sender into the underlying flume `Sender<T>`.
+    ///
+    /// This is an escape hatch for APIs that require a flume sender. The returned sender has
+    /// no overwrite semantics: its `send` blocks while the channel is full, and it keeps the
+    /// channel connected for receivers like any other sender. The underlying queue is
+    /// bounded at the [maximum capacity](OverwriteSender::max_capacity), so the returned
+    /// sender may fill the channel beyond its current [`capacity`](OverwriteSender::capacity).
+    ///
+    /// The returned sender takes this one's place among the channel's senders: it counts in
+    /// [`sender_count`](OverwriteSender::sender_count), and since the channel cannot tell
+    /// when it is dropped, the teardown that follows the last sender, which disconnects
+    /// taps and eviction streams and starts [lingering](Builder::linger), never runs.
+    /// Neither does the channel see what the returned sender queues, so while such messages
+    /// are queued, [protected](Builder::protect) messages may be miscounted when planning
+    /// sends with [`simulate_send`](OverwriteSender::simulate_send).
+    pub fn into_inner(self) -> Sender<T> {
+        // Dropping this sender must not tear the channel down while the returned one lives
+        self.shared.sender_count.fetch_add(1, Ordering::AcqRel);
+        self.sender.clone()
+    }
+
+    /// Returns the maximum number of messages the channel can hold.
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity.get()
+    }
+
+    /// Returns the largest capacity the channel can be resized to, see
+    /// [`Builder::max_capacity`].
+    pub fn max_capacity(&self) -> usize {
+        self.shared.capacity.max()
+    }
+
+    /// Returns the number of bytes reserved for messages when the channel was built, see
+    /// [`Builder::preallocate`], or `0` for a channel that allocates as it grows.
+    ///
+    /// This counts the queue and the buffer for messages sent while the channel is frozen.
+    /// Only the messages themselves are counted, not any heap data they own, nor any room
+    /// beyond the maximum capacity the queue may have rounded its allocation up to.
+    pub fn allocated_bytes(&self) -> usize {
+        self.shared.reserved
+    }
+
+    /// Changes the maximum number of messages the channel can hold, returning the messages
+    /// evicted to shrink it, oldest first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero or exceeds [`max_capacity`](OverwriteSender::max_capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    ///
+    /// let (sender, receiver) = Builder::new(2).max_capacity(4).build();
+    /// for i in 0..4 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    ///
+    /// assert!(sender.set_capacity(4).is_empty());
+    /// sender.send_overwrite(4).unwrap();
+    /// assert_eq!(receiver.len(), 3);
+    ///
+    /// assert_eq!(sender.set_capacity(1), vec![2, 3]);
+    /// assert_eq!(receiver.recv().unwrap(), 4);
+    /// ```
+    #[track_caller]
+    pub fn set_capacity(&self, cap: usize) -> Vec<T> {
+        assert!(
+            cap <= self.max_capacity(),
+            "capacity {cap} exceeds the channel's maximum of {}",
+            self.max_capacity()
+        );
+        let cap = Capacity::expect(cap);
+        let _sending = self.shared.lock_sends();
+        self.resize_locked(cap)
+    }
+
+    /// Marks `n` slots of the channel as burst headroom, which only
+    /// [`send_burst`](OverwriteSender::send_burst) may use.
+    ///
+    /// Regular sends then overwrite as if the channel held `n` fewer messages, so a known
+    /// periodic burst finds room without evicting anything. Reserving replaces any previous
+    /// reservation, and `reserve_burst(0)` releases it. Messages already queued in the
+    /// reserve stay there until received.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is not less than the channel's [`capacity`](OverwriteSender::capacity),
+    /// since regular sends need at least one slot.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.reserve_burst(2);
+    /// sender.send_overwrite("tick 1").unwrap();
+    /// sender.send_overwrite("tick 2").unwrap();
+    /// assert_eq!(sender.send_overwrite("tick 3").unwrap(), Some(vec!["tick 1"]));
+    ///
+    /// // The burst fits in the reserve
+    /// assert_eq!(sender.send_burst("burst 1").unwrap(), None);
+    /// assert_eq!(sender.send_burst("burst 2").unwrap(), None);
+    /// assert_eq!(receiver.len(), 4);
+    ///
+    /// // A regular send then replaces the oldest message only
+    /// assert_eq!(sender.send_overwrite("tick 4").unwrap(), Some(vec!["tick 2"]));
+    /// ```
+    #[track_caller]
+    pub fn reserve_burst(&self, n: usize) {
+        let _sending = self.shared.lock_sends();
+        assert!(
+            n < self.capacity(),
+            "burst reserve {n} leaves no room in a channel of capacity {}",
+            self.capacity()
+        );
+        self.shared.capacity.set_reserved(n);
+    }
+
+    /// Returns the number of slots reserved for bursts.
+    pub fn burst_reserve(&self) -> usize {
+        self.shared.capacity.reserved()
+    }
+
+    /// Sends a value with overwrite semantics, using the slots reserved with
+    /// [`reserve_burst`](OverwriteSender::reserve_burst) before evicting anything.
+    ///
+    /// Once the whole channel is full, a burst send evicts the oldest messages like
+    /// [`send_overwrite`](OverwriteSender::send_overwrite).
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_burst(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let _sending = self.shared.lock_sends();
+        let mut drained = Vec::new();
+        self.send_overwrite_within(value, self.capacity(), |evicted| drained.push(evicted))?;
+        Ok(if drained.is_empty() {
+            None
+        } else {
+            Some(drained)
+        })
+    }
+
+    /// Changes the capacity while holding the send lock, evicting the oldest messages that
+    /// no longer fit.
+    fn resize_locked(&self, cap: Capacity) -> Vec<T> {
+        let from = self.shared.capacity.swap(cap);
+        let mut evicted = Vec::new();
+        while self.sender.len() > cap.get() {
+            match self.receiver.try_recv() {
+                Ok(oldest) => evicted.push(oldest),
+                Err(_) => break,
+            }
+        }
+        self.shared.count_evictions(evicted.len());
+        self.shared.count_classes(&evicted);
+        if self.shared.on_capacity_change.is_some() && from != cap.get() {
+            self.shared.defer(Deferred::CapacityChange(CapacityChange {
+                from,
+                to: cap.get(),
+            }));
+        }
+        evicted
+    }
+
+    /// Resizes an adaptive channel once the current window calls for it, while holding the
+    /// send lock.
+    fn adapt_locked(&self) {
+        if let Some(adaptive) = &self.shared.adaptive
+            && let Some(target) = adaptive.observe(
+                self.shared.clock.now(),
+                self.sender.len(),
+                self.shared.evicted.get(),
+                self.capacity(),
+                self.max_capacity(),
+            )
+        {
+            self.resize_locked(Capacity::expect(target));
+        }
+    }
+
+    /// Returns the number of messages in the channel.
+    ///
+    /// Unlike the raw flume length, this counts a message being sent as soon as it has
+    /// evicted an older one, so a channel under sustained overwrite keeps reporting itself
+    /// as full instead of flickering below capacity. Messages staged by a [`BufferedSender`]
+    /// are counted too, since they take a slot on its next flush. The result never exceeds
+    /// [`capacity`](OverwriteSender::capacity).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, _receiver) = bounded(4);
+    /// sender.send_overwrite(1).unwrap();
+    /// assert_eq!(sender.len(), 1);
+    /// assert_eq!(sender.remaining(), 3);
+    ///
+    /// let mut buffered = sender.clone().buffered(8);
+    /// buffered.send_overwrite(2).unwrap();
+    /// assert_eq!(sender.len(), 2);
+    /// assert_eq!(sender.remaining(), 2);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.shared.len(&self.receiver)
+    }
+
+    /// Returns `true` if the channel holds no messages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the next send will overwrite an older message.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.shared.capacity.regular()
+    }
+
+    /// Returns how many more messages can be sent before the channel starts overwriting.
+    ///
+    /// Slots reserved with [`reserve_burst`](OverwriteSender::reserve_burst) are not
+    /// counted.
+    pub fn remaining(&self) -> usize {
+        self.shared.capacity.regular().saturating_sub(self.len())
+    }
+
+    /// Returns a snapshot of the channel's state.
+    ///
+    /// See [`Stats`] for the reported fields.
+    pub fn stats(&self) -> Stats {
+        self.shared.stats(&self.receiver)
+    }
+
+    /// Returns the channel's current [`OverflowPolicy`].
+    pub fn policy(&self) -> OverflowPolicy {
+        self.shared.policy.load()
+    }
+
+    /// Switches the channel to `policy`, returning the previous one.
+    ///
+    /// The change applies to every endpoint of the channel from the next send on, without
+    /// recreating the channel. Messages already queued are left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{OverflowPolicy, bounded};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// sender.send_overwrite(1).unwrap();
+    ///
+    /// let previous = sender.swap_policy(OverflowPolicy::RejectNew);
+    /// assert_eq!(previous, OverflowPolicy::DropOldest);
+    /// assert!(sender.send_overwrite(2).unwrap_err().is_rejected());
+    ///
+    /// sender.swap_policy(OverflowPolicy::DropOldest);
+    /// assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+    /// assert_eq!(receiver.recv().unwrap(), 3);
+    /// ```
+    pub fn swap_policy(&self, policy: OverflowPolicy) -> OverflowPolicy {
+        self.shared.policy.swap(policy)
+    }
+
+    /// Returns `true` if messages are queued and the consumer has not sent a
+    /// [`heartbeat`](OverwriteReceiver::heartbeat) for at least `threshold`.
+    ///
+    /// The channel's creation counts as the first heartbeat. An empty channel is never
+    /// stalled, since an idle consumer has nothing to receive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// assert!(!sender.consumer_stalled(Duration::ZERO));
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// assert!(sender.consumer_stalled(Duration::ZERO));
+    /// assert!(!sender.consumer_stalled(Duration::from_secs(60)));
+    ///
+    /// receiver.recv().unwrap();
+    /// receiver.heartbeat();
+    /// assert!(!sender.consumer_stalled(Duration::ZERO));
+    /// ```
+    pub fn consumer_stalled(&self, threshold: Duration) -> bool {
+        !self.is_empty()
+            && self
+                .shared
+                .watchdog
+                .since_heartbeat(self.shared.clock.now())
+                >= threshold
+    }
+
+    /// Formats the messages currently queued, oldest first, as a debug list.
+    ///
+    /// This is meant for test assertions: the queue is briefly drained and refilled while
+    /// other sends wait, so receivers may momentarily find the channel empty but never
+    /// observe a reordering of the dumped messages.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(2);
+    /// sender.send_overwrite(1).unwrap();
+    /// sender.send_overwrite(2).unwrap();
+    /// sender.send_overwrite(3).unwrap();
+    ///
+    /// assert_eq!(sender.dump(), "[2, 3]");
+    /// assert_eq!(receiver.recv().unwrap(), 2);
+    /// ```
+    pub fn dump(&self) -> String
+    where
+        T: fmt::Debug,
+    {
+        self.with_queue(|queued| format!("{queued:?}"))
+    }
+
+    /// Returns how many messages a send would evict right now.
+    ///
+    /// This is zero when the message fits, but also when the send would fail, for example
+    /// because it would be [rejected](SendOverwriteError::Rejected); use
+    /// [`simulate_send`](OverwriteSender::simulate_send) to tell these apart. Other senders
+    /// and receivers may change the outcome before the next send.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, _receiver) = bounded(2);
+    /// sender.send_overwrite(1).unwrap();
+    /// assert_eq!(sender.would_evict(), 0);
+    /// sender.send_overwrite(2).unwrap();
+    /// assert_eq!(sender.would_evict(), 1);
+    /// ```
+    pub fn would_evict(&self) -> usize {
+        if self.is_orphaned() {
+            return 0;
+        }
+        let _sending = self.shared.lock_sends();
+        self.plan_locked().unwrap_or(0)
+    }
+
+    /// Computes what sending `value` would do, without sending it.
+    ///
+    /// The returned plan tells how many messages the send would displace, so a producer
+    /// can decide before committing, for instance to merge locally instead of evicting.
+    /// The plan accounts for the channel's [policy](OverflowPolicy),
+    /// [eviction budget](Builder::eviction_budget) and [protected](Builder::protect)
+    /// messages, but other senders and receivers may change the outcome before the next
+    /// send. The queue is left untouched, so receivers are never held up.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{EvictionPlan, bounded};
+    ///
+    /// let (sender, _receiver) = bounded(2);
+    /// assert_eq!(sender.simulate_send(&1), EvictionPlan::Fits);
+    ///
+    /// sender.send_overwrite(1).unwrap();
+    /// sender.send_overwrite(2).unwrap();
+    /// assert_eq!(sender.simulate_send(&3), EvictionPlan::Evicts(1));
+    ///
+    /// // Nothing was sent or evicted
+    /// assert_eq!(sender.len(), 2);
+    /// ```
+    pub fn simulate_send(&self, value: &T) -> EvictionPlan {
+        if self.is_orphaned() {
+            return EvictionPlan::Disconnected;
+        }
+        // Otherwise, how many messages a send evicts does not depend on the message itself
+        if self.shared.is_oversized(value) {
+            return EvictionPlan::Oversized;
+        }
+        let _sending = self.shared.lock_sends();
+        match self.plan_locked() {
+            Some(0) => EvictionPlan::Fits,
+            Some(evictions) => EvictionPlan::Evicts(evictions),
+            None => EvictionPlan::Rejected,
+        }
+    }
+
+    /// Returns how many queued messages a send would evict, or `None` if it would be
+    /// rejected, while holding the send lock.
+    fn plan_locked(&self) -> Option<usize> {
+        let len = self.sender.len();
+        let excess = (len + 1).saturating_sub(self.regular_capacity(len));
+        if excess == 0 {
+            return Some(0);
+        }
+        if self.shared.policy.load() == OverflowPolicy::RejectNew
+            || !self.can_claim_evictions(excess)
+            || len - self.shared.protected_locked(len) < excess
+        {
+            return None;
+        }
+        Some(excess)
+    }
+
+    /// Removes and returns every queued message matching `pred`, oldest first.
+    ///
+    /// This withdraws work that became obsolete before the consumer got to it, such as the
+    /// messages of a cancelled job. The remaining messages keep their order; cancelled
+    /// messages are not counted as evicted. The queue is briefly drained and refilled while
+    /// other sends wait, so receivers may momentarily find the channel empty but never
+    /// observe a reordering.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.send_overwrite(("job 1", "step 1")).unwrap();
+    /// sender.send_overwrite(("job 2", "step 1")).unwrap();
+    /// sender.send_overwrite(("job 1", "step 2")).unwrap();
+    ///
+    /// let cancelled = sender.cancel_where(|(job, _)| *job == "job 1");
+    /// assert_eq!(cancelled, vec![("job 1", "step 1"), ("job 1", "step 2")]);
+    ///
+    /// assert_eq!(receiver.recv().unwrap(), ("job 2", "step 1"));
+    /// assert!(receiver.is_empty());
+    /// ```
+    pub fn cancel_where<F>(&self, mut pred: F) -> Vec<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.with_queue(|queued| {
+            let (cancelled, kept) = std::mem::take(queued)
+                .into_iter()
+                .partition(|value| pred(value));
+            *queued = kept;
+            cancelled
+        })
+    }
+
+    /// Runs `f` on the queued messages, oldest first, then requeues whatever `f` left in
+    /// the vector.
+    ///
+    /// The queue is drained and refilled while other sends wait, so receivers may
+    /// momentarily find the channel empty. Messages removed by `f` are not counted as
+    /// evicted.
+    pub(crate) fn with_queue<R>(&self, f: impl FnOnce(&mut Vec<T>) -> R) -> R {
+        let _sending = self.shared.lock_sends();
+        let mut queued: Vec<T> = self.receiver.drain().collect();
+        let result = f(&mut queued);
+        self.requeue_locked(queued);
+        result
+    }
+
+    /// Sends a value, replacing any queued message of the same enum variant instead of
+    /// appending behind it.
+    ///
+    /// Variants are compared with [`std::mem::discriminant`], so for enum message types the
+    /// channel keeps at most the latest message of each kind sent this way. Queued messages
+    /// of other variants keep their order, and the new message is appended at the back. If
+    /// no message was replaced and the channel is full, the oldest message is overwritten as
+    /// with [`send_overwrite`](OverwriteSender::send_overwrite).
+    ///
+    /// The queue is briefly drained and refilled while other sends wait, so receivers may
+    /// momentarily find the channel empty but never observe a reordering.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without replacing or overwriting anything
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   replaced messages of the same variant, followed by any message overwritten to make room
+    /// - `Err(SendOverwriteError<T>)` - The channel is disconnected, or the send was rejected
+    ///   because nothing could be evicted, see [`SendOverwriteError::Rejected`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Command {
+    ///     Move(i32, i32),
+    ///     Zoom(f32),
+    /// }
+    ///
+    /// let (sender, receiver) = bounded(8);
+    /// sender.send_conflate_variant(Command::Move(0, 0)).unwrap();
+    /// sender.send_conflate_variant(Command::Zoom(1.0)).unwrap();
+    ///
+    /// let replaced = sender.send_conflate_variant(Command::Move(5, 5)).unwrap();
+    /// assert_eq!(replaced, Some(vec![Command::Move(0, 0)]));
+    ///
+    /// assert_eq!(receiver.recv().unwrap(), Command::Zoom(1.0));
+    /// assert_eq!(receiver.recv().unwrap(), Command::Move(5, 5));
+    /// ```
+    pub fn send_conflate_variant(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let _sending = self.shared.lock_sends();
+        if self.is_orphaned() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        if self.shared.is_oversized(&value) {
+            return Err(SendOverwriteError::Oversized(value));
+        }
+        let variant = std::mem::discriminant(&value);
+        let queued: Vec<T> = self.receiver.drain().collect();
+        if let Some(identical) = &self.shared.skip_identical {
+            let mut same_variant = queued
+                .iter()
+                .filter(|queued| std::mem::discriminant(*queued) == variant)
+                .peekable();
+            if same_variant.peek().is_some() && same_variant.all(|queued| identical(queued, &value))
+            {
+                self.requeue_locked(queued);
+                return Ok(None);
+            }
+        }
+        let (mut replaced, kept): (Vec<T>, Vec<T>) = queued
+            .into_iter()
+            .partition(|queued| std::mem::discriminant(queued) == variant);
+        self.shared.count_evictions(replaced.len());
+        self.shared.count_classes(&replaced);
+        self.requeue_locked(kept);
+        match self.send_overwrite_locked(value)? {
+            Some(overwritten) => replaced.extend(overwritten),
+            None if replaced.is_empty() => return Ok(None),
+            None => (),
+        }
+        Ok(Some(replaced))
+    }
+
+    /// Removes the oldest queued message, counting it as evicted.
+    pub(crate) fn evict_oldest(&self) -> Option<T> {
+        let _sending = self.shared.lock_sends();
+        let oldest = self.receiver.try_recv().ok()?;
+        self.shared.count_evictions(1);
+        self.shared.count_classes(std::slice::from_ref(&oldest));
+        self.shared.check_strict(std::slice::from_ref(&oldest));
+        Some(oldest)
+    }
+
+    /// The body of [`send_overwrite`](OverwriteSender::send_overwrite), run while holding the
+    /// send lock so that no other sender can fill the slots freed by eviction.
+    fn send_overwrite_locked(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut drained = Vec::new();
+        self.send_overwrite_with(value, |evicted| drained.push(evicted))?;
+        Ok(if drained.is_empty() {
+            None
+        } else {
+            Some(drained)
+        })
+    }
+
+    /// Sends with overwrite semantics while holding the send lock, handing every evicted
+    /// message to `evict` and returning how many there were.
+    ///
+    /// A token of the token bucket, if any, is only taken once the message is queued, so a
+    /// failed send leaves it for the next one. Sends buffered while the channel is frozen
+    /// take theirs when released.
+    fn send_overwrite_with(
+        &self,
+        value: T,
+        evict: impl FnMut(T),
+    ) -> Result<usize, SendOverwriteError<T>> {
+        let len = self.sender.len();
+        let mut capacity = self.regular_capacity(len);
+        let now = self.shared.clock.now();
+        let bucket = self
+            .shared
+            .token_bucket
+            .as_ref()
+            .filter(|_| !self.shared.is_frozen());
+        let granted = bucket.is_some_and(|bucket| bucket.available(now) > 0);
+        if bucket.is_some() && !granted {
+            // Without a token the queue may not grow, so the message replaces the oldest
+            capacity = capacity.min(len.max(1));
+        }
+        let evictions = self.send_overwrite_within(value, capacity, evict)?;
+        if let Some(bucket) = bucket.filter(|_| granted) {
+            // Only sends hold the bucket's tokens, so the one seen above is still there
+            bucket.try_take(now);
+        }
+        Ok(evictions)
+    }
+
+    /// Returns how many of the `len` queued messages regular sends keep: the slots outside
+    /// the burst reserve, or all of them once a burst has used the reserve, so that a
+    /// regular send replaces a single message instead of evicting the burst.
+    fn regular_capacity(&self, len: usize) -> usize {
+        self.shared.capacity.regular().max(len.min(self.capacity()))
+    }
+
+    /// Like [`send_overwrite_with`](OverwriteSender::send_overwrite_with), evicting so that
+    /// the queue fits in `capacity` slots.
+    fn send_overwrite_within(
+        &self,
+        value: T,
+        capacity: usize,
+        mut evict: impl FnMut(T),
+    ) -> Result<usize, SendOverwriteError<T>> {
+        if self.is_orphaned() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        if self.shared.is_oversized(&value) {
+            return Err(SendOverwriteError::Oversized(value));
+        }
+        if self.shared.is_frozen() {
+            return self.buffer_frozen(value, capacity, &mut evict);
+        }
+        let excess = (self.sender.len() + 1).saturating_sub(capacity);
+        if excess > 0 && self.shared.policy.load() == OverflowPolicy::RejectNew {
+            return Err(SendOverwriteError::Rejected(value));
+        }
+        let _in_flight = InFlight::enter(&self.shared.in_flight);
+        let evictions = match &self.shared.protect {
+            Some(protect) if excess > 0 => {
+                // Protected messages stay in place, so the queue is rebuilt without the
+                // oldest unprotected ones
+                let queued: Vec<T> = self.receiver.drain().collect();
+                let needed = (queued.len() + 1).saturating_sub(capacity);
+                let evictable = queued.iter().filter(|queued| !protect(queued)).count();
+                if evictable < needed || !self.claim_evictions(needed) {
+                    self.requeue_locked(queued);
+                    return Err(SendOverwriteError::Rejected(value));
+                }
+                let mut remaining = needed;
+                let (evicted, kept): (Vec<T>, Vec<T>) = queued.into_iter().partition(|queued| {
+                    let evict = remaining > 0 && !protect(queued);
+                    remaining -= usize::from(evict);
+                    evict
+                });
+                self.shared.count_evictions(evicted.len());
+                self.shared.count_classes(&evicted);
+                self.requeue_locked(kept);
+                self.shared.check_strict(&evicted);
+                let evictions = evicted.len();
+                evicted.into_iter().for_each(&mut evict);
+                evictions
+            }
+            None if excess > 0
+                && let Some(transform) = &self.shared.evict_transform =>
+            {
+                if !self.claim_evictions(excess) {
+                    return Err(SendOverwriteError::Rejected(value));
+                }
+                self.evict_transformed(transform, excess, &mut evict)
+            }
+            _ => {
+                if excess > 0 && !self.claim_evictions(excess) {
+                    return Err(SendOverwriteError::Rejected(value));
+                }
+                let mut evictions = 0;
+                let mut attempts = 0;
+                while self.sender.len() >= capacity {
+                    // This sender keeps the channel connected, so the receiving side cannot
+                    // report a disconnection: check for dropped receivers directly
+                    if self.is_orphaned() {
+                        return Err(SendOverwriteError::Disconnected(value));
+                    }
+                    match self.receiver.try_recv() {
+                        Ok(old_value) => {
+                            self.shared.count_evictions(1);
+                            self.shared.count_classes(std::slice::from_ref(&old_value));
+                            self.shared.check_strict(std::slice::from_ref(&old_value));
+                            evict(old_value);
+                            evictions += 1;
+                            attempts = 0;
+                        }
+                        Err(_) => {
+                            // A receiver took the message first
+                            attempts += 1;
+                            if attempts >= MAX_EVICTION_ATTEMPTS {
+                                return Err(SendOverwriteError::Contended(value));
+                            }
+                            std::hint::spin_loop();
+                        }
+                    }
+                }
+                evictions
+            }
+        };
+        self.shared.tap(&value);
+        let protected = self.shared.is_protected(&value);
+        self.sender.send(value)?;
+        self.shared.queued_locked(protected);
+        self.shared.sent.add(1);
+        if evictions > 0 {
+            self.shared.check_stalled_locked();
+        }
+        self.adapt_locked();
+        self.shared.observe_watermarks_locked(&self.receiver);
+        Ok(evictions)
+    }
+
+    /// Sets a message sent while the channel is frozen aside, while holding the send lock.
     ///
-    /// let (sender, receiver) = bounded(1);
+    /// Like [`try_send`](OverwriteSender::try_send), the queue and the side queue together
+    /// hold at most `capacity` messages under [`OverflowPolicy::RejectNew`]. Otherwise, the
+    /// queue is left as is and the side queue holds at most `capacity` messages: past that,
+    /// its oldest unprotected message would be evicted on release anyway, so it is handed to
+    /// `evict` right away. Having never been queued, it is counted neither as sent nor as
+    /// evicted. The messages kept are counted as sent, tapped and take their tokens once
+    /// released.
+    fn buffer_frozen(
+        &self,
+        value: T,
+        capacity: usize,
+        evict: &mut impl FnMut(T),
+    ) -> Result<usize, SendOverwriteError<T>> {
+        let mut frozen = self.shared.lock_frozen();
+        if self.shared.policy.load() == OverflowPolicy::RejectNew {
+            if self.sender.len() + frozen.len() >= capacity {
+                return Err(SendOverwriteError::Rejected(value));
+            }
+            frozen.push_back(value);
+            return Ok(0);
+        }
+        let mut evictions = 0;
+        if frozen.len() >= capacity {
+            let unprotected = frozen.iter().position(|buffered| {
+                self.shared
+                    .protect
+                    .as_ref()
+                    .is_none_or(|protect| !protect(buffered))
+            });
+            let Some(oldest) = unprotected.and_then(|index| frozen.remove(index)) else {
+                return Err(SendOverwriteError::Rejected(value));
+            };
+            evict(oldest);
+            evictions = 1;
+        }
+        frozen.push_back(value);
+        Ok(evictions)
+    }
+
+    /// Frees `excess` slots while holding the send lock, handing the oldest messages to the
+    /// eviction transform, and returns how many slots were freed.
     ///
-    /// block_on(async {
-    ///     // Send without overwriting
-    ///     assert_eq!(sender.send_overwrite_async(1).await.unwrap(), None);
-    ///     
-    ///     // This will overwrite the first message
-    ///     let overwritten = sender.send_overwrite_async(2).await.unwrap();
-    ///     assert_eq!(overwritten, Some(vec![1]));
-    /// });
-    /// ```
-    pub async fn send_overwrite_async(&self, value: T) -> Result<Option<Vec<T>>, SendError<T>> {
-        if let Some(capacity) = self.sender.capacity() {
-            let mut drained = Vec::new();
-            while self.sender.len() >= capacity {
-                if let Ok(old_value) = self.receiver.recv_async().await {
-                    drained.push(old_value);
+    /// See [`Builder::on_evict_transform`].
+    fn evict_transformed(
+        &self,
+        transform: &EvictTransform<T>,
+        excess: usize,
+        evict: &mut impl FnMut(T),
+    ) -> usize {
+        let mut queued: VecDeque<T> = self.receiver.drain().collect();
+        let mut representatives = Vec::new();
+        let mut evicted = Vec::new();
+        // Classes and senders of the messages the transform dropped, which are evicted too
+        let mut dropped = Vec::new();
+        let mut dropped_senders = Vec::new();
+        let mut freed = 0;
+        while freed < excess {
+            let Some(oldest) = queued.pop_front() else {
+                break;
+            };
+            let class = self
+                .shared
+                .classify
+                .as_ref()
+                .map(|classify| classify(&oldest));
+            let sender = self.shared.attribute.map(|attribute| attribute(&oldest));
+            if let Some(representative) = transform(oldest) {
+                // The representative keeps the slot, the next oldest message makes room
+                match queued.pop_front() {
+                    Some(next) => {
+                        representatives.push(representative);
+                        evicted.push(next);
+                    }
+                    None => evicted.push(representative),
                 }
-            }
-            self.sender.send_async(value).await?;
-            Ok(if drained.is_empty() {
-                None
             } else {
-                Some(drained)
-            })
-        } else {
-            self.sender.send_async(value).await?;
-            Ok(None)
+                dropped.extend(class);
+                dropped_senders.extend(sender);
+            }
+            freed += 1;
         }
+        representatives.extend(queued);
+        self.requeue_locked(representatives);
+        self.shared.count_evictions(freed);
+        self.shared.count_classes(&evicted);
+        self.shared.tally_classes(dropped);
+        self.shared.tally_senders(dropped_senders);
+        self.shared.check_strict(&evicted);
+        evicted.into_iter().for_each(evict);
+        freed
+    }
+
+    /// Claims evictions from the eviction budget, if any, returning `false` once it is spent.
+    fn claim_evictions(&self, evictions: usize) -> bool {
+        self.shared
+            .eviction_budget
+            .as_ref()
+            .is_none_or(|budget| budget.try_claim(evictions as u64, self.shared.clock.now()))
+    }
+
+    /// Puts previously drained messages back in order, while holding the send lock.
+    ///
+    /// Requeued messages are not counted as sent again. Should the channel have been filled
+    /// in the meantime through [`into_inner`](OverwriteSender::into_inner), the oldest
+    /// messages are evicted to make room.
+    fn requeue_locked(&self, values: Vec<T>) {
+        self.shared
+            .requeue_locked(&self.sender, &self.receiver, values);
+    }
+
+    /// Returns whether the eviction budget, if any, has room for `evictions`, without
+    /// claiming them.
+    fn can_claim_evictions(&self, evictions: usize) -> bool {
+        self.shared
+            .eviction_budget
+            .as_ref()
+            .is_none_or(|budget| budget.can_claim(evictions as u64, self.shared.clock.now()))
+    }
+
+    /// Returns `true` once every receiver of the channel has been dropped.
+    fn is_orphaned(&self) -> bool {
+        self.shared.receiver_count.load(Ordering::Acquire) == 0
     }
 }
 
@@ -251,6 +3491,27 @@ mod test {
         assert_eq!(receiver.try_recv().unwrap(), 3);
     }
 
+    #[test]
+    fn test_send_overwrite_racing_receivers() {
+        let (sender, receiver) = bounded(1);
+        let receivers: Vec<_> = (0..2)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || while receiver.recv().is_ok() {})
+            })
+            .collect();
+        for i in 0..10_000 {
+            match sender.send_overwrite(i) {
+                Ok(_) | Err(SendOverwriteError::Contended(_)) => (),
+                Err(error) => panic!("unexpected error: {error:?}"),
+            }
+        }
+        drop(sender);
+        receivers
+            .into_iter()
+            .for_each(|receiver| receiver.join().unwrap());
+    }
+
     #[test]
     fn test_send_overwrite_multiple_overwrites() {
         let (sender, receiver) = bounded(2);
@@ -318,6 +3579,693 @@ mod test {
         assert_eq!(block_on(receiver.recv_async()).unwrap(), 2);
     }
 
+    #[test]
+    fn test_on_orphaned_receives_pending_messages() {
+        use std::sync::Mutex;
+        let orphaned = Arc::new(Mutex::new(Vec::new()));
+        let sink = orphaned.clone();
+        let (sender, receiver) = Builder::new(2)
+            .on_orphaned(move |pending| sink.lock().unwrap().push(pending))
+            .build();
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        sender.send_overwrite(3).unwrap();
+        let receiver2 = receiver.clone();
+        drop(receiver);
+        assert!(orphaned.lock().unwrap().is_empty());
+        drop(receiver2);
+        assert_eq!(*orphaned.lock().unwrap(), vec![vec![2, 3]]);
+        assert_eq!(sender.send_overwrite(4).unwrap_err().into_inner(), 4);
+    }
+
+    #[test]
+    fn test_on_orphaned_skipped_when_empty() {
+        use std::sync::atomic::AtomicBool;
+        let called = Arc::new(AtomicBool::new(false));
+        let flag = called.clone();
+        let (sender, receiver) = Builder::new(2)
+            .on_orphaned(move |_: Vec<i32>| flag.store(true, Ordering::SeqCst))
+            .build();
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(receiver.recv().unwrap(), 1);
+        drop(receiver);
+        assert!(!called.load(Ordering::SeqCst));
+        assert!(block_on(sender.send_overwrite_async(2)).is_err());
+    }
+
+    #[test]
+    fn test_debug_reports_state_not_contents() {
+        let (sender, receiver) = Builder::new(2).name("events").build();
+        sender.send_overwrite("secret").unwrap();
+        sender.send_overwrite("secret").unwrap();
+        sender.send_overwrite("secret").unwrap();
+        let expected = r#"{ name: Some("events"), capacity: 2, len: 2, sent: 3, evicted: 1 }"#;
+        assert_eq!(format!("{sender:?}"), format!("OverwriteSender {expected}"));
+        assert_eq!(
+            format!("{receiver:?}"),
+            format!("OverwriteReceiver {expected}")
+        );
+    }
+
+    #[test]
+    fn test_len_capacity_remaining() {
+        let (sender, receiver) = bounded(3);
+        assert_eq!(sender.capacity(), 3);
+        assert!(receiver.is_empty());
+        assert_eq!(receiver.remaining(), 3);
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(sender.len(), 2);
+        assert_eq!(receiver.remaining(), 1);
+        sender.send_overwrite(3).unwrap();
+        sender.send_overwrite(4).unwrap();
+        assert!(sender.is_full());
+        assert_eq!(receiver.len(), 3);
+        assert_eq!(receiver.remaining(), 0);
+    }
+
+    #[test]
+    fn test_len_counts_in_flight_sends() {
+        let (sender, receiver) = bounded(2);
+        sender.send_overwrite(1).unwrap();
+        let in_flight = InFlight::enter(&sender.shared.in_flight);
+        assert_eq!(receiver.len(), 2);
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(receiver.len(), 2);
+        drop(in_flight);
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.recv().unwrap(), 1);
+        assert_eq!(receiver.len(), 1);
+    }
+
+    #[test]
+    fn test_delegated_sender_methods() {
+        let (sender, receiver) = bounded(1);
+        let other = sender.clone();
+        let (unrelated, _unrelated_receiver) = bounded::<i32>(1);
+        assert!(sender.same_channel(&other));
+        assert!(!sender.same_channel(&unrelated));
+        assert_eq!(sender.sender_count(), 2);
+        assert_eq!(sender.receiver_count(), 1);
+        sender.try_send(1).unwrap();
+        assert!(matches!(sender.try_send(2), Err(TrySendError::Full(2))));
+        assert_eq!(sender.stats().sent, 1);
+        drop(receiver);
+        assert!(sender.is_disconnected());
+        assert!(matches!(
+            sender.try_send(3),
+            Err(TrySendError::Disconnected(3))
+        ));
+    }
+
+    #[test]
+    fn test_endpoint_identity() {
+        let (sender, receiver) = bounded::<i32>(1);
+        let (other_sender, other_receiver) = bounded::<i32>(1);
+        let sender_clone = sender.clone();
+        let receiver_clone = receiver.clone();
+        assert_ne!(sender.id(), sender_clone.id());
+        assert_ne!(receiver.id(), receiver_clone.id());
+        assert!(receiver.same_channel(&receiver_clone));
+        assert!(!receiver.same_channel(&other_receiver));
+        assert_eq!(sender_clone.channel_id(), receiver_clone.channel_id());
+        assert_ne!(sender.channel_id(), other_sender.channel_id());
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let (sender, receiver) = bounded(2);
+        let inner = sender.into_inner();
+        inner.send(1).unwrap();
+        assert_eq!(receiver.recv().unwrap(), 1);
+        drop(inner);
+        assert!(receiver.is_disconnected());
+    }
+
+    #[test]
+    fn test_dump_preserves_queue() {
+        let (sender, receiver) = bounded(3);
+        assert_eq!(sender.dump(), "[]");
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(sender.dump(), "[1, 2]");
+        assert_eq!(receiver.stats().len, 2);
+        assert_eq!(receiver.try_recv().unwrap(), 1);
+        assert_eq!(receiver.try_recv().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_send_conflate_variant() {
+        #[derive(Debug, PartialEq)]
+        enum Event {
+            Position(u32),
+            Command(&'static str),
+        }
+        let (sender, receiver) = bounded(3);
+        sender.send_overwrite(Event::Command("a")).unwrap();
+        assert_eq!(
+            sender.send_conflate_variant(Event::Position(1)).unwrap(),
+            None
+        );
+        sender.send_overwrite(Event::Command("b")).unwrap();
+        let replaced = sender.send_conflate_variant(Event::Position(2)).unwrap();
+        assert_eq!(replaced, Some(vec![Event::Position(1)]));
+        assert_eq!(sender.stats().evicted, 1);
+        // Every queued message of the variant is replaced, not only the latest one
+        receiver.recv().unwrap();
+        sender.send_overwrite(Event::Command("c")).unwrap();
+        assert_eq!(
+            sender.send_conflate_variant(Event::Command("d")).unwrap(),
+            Some(vec![Event::Command("b"), Event::Command("c")])
+        );
+        assert_eq!(receiver.recv().unwrap(), Event::Position(2));
+        assert_eq!(receiver.recv().unwrap(), Event::Command("d"));
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn test_send_conflate_variant_overwrites_when_full() {
+        #[derive(Debug, PartialEq)]
+        enum Event {
+            A(u8),
+            B(u8),
+        }
+        let (sender, receiver) = bounded(2);
+        sender.send_overwrite(Event::A(1)).unwrap();
+        sender.send_overwrite(Event::A(2)).unwrap();
+        let overwritten = sender.send_conflate_variant(Event::B(1)).unwrap();
+        assert_eq!(overwritten, Some(vec![Event::A(1)]));
+        assert_eq!(receiver.recv().unwrap(), Event::A(2));
+        assert_eq!(receiver.recv().unwrap(), Event::B(1));
+    }
+
+    #[test]
+    fn test_skip_identical_replacements_keeps_queue() {
+        #[derive(Debug, PartialEq)]
+        enum Event {
+            A(u8),
+            B(u8),
+        }
+        let (sender, receiver) = Builder::new(4).skip_identical_replacements().build();
+        sender.send_conflate_variant(Event::A(1)).unwrap();
+        sender.send_conflate_variant(Event::B(1)).unwrap();
+        assert_eq!(sender.send_conflate_variant(Event::A(1)).unwrap(), None);
+        assert_eq!(sender.dump(), "[A(1), B(1)]");
+        assert_eq!(
+            sender.send_conflate_variant(Event::A(2)).unwrap(),
+            Some(vec![Event::A(1)])
+        );
+        assert_eq!(sender.dump(), "[B(1), A(2)]");
+        assert_eq!(receiver.stats().evicted, 1);
+    }
+
+    #[test]
+    fn test_cancel_where_frees_slots() {
+        let (sender, receiver) = bounded(3);
+        sender.send_overwrite_batch(vec![1, 2, 3]).unwrap();
+        assert_eq!(sender.cancel_where(|value| value % 2 == 1), vec![1, 3]);
+        assert_eq!(sender.cancel_where(|_| false), Vec::<i32>::new());
+        assert_eq!(sender.send_overwrite(4).unwrap(), None);
+        assert_eq!(sender.send_overwrite(5).unwrap(), None);
+        assert_eq!(receiver.stats().evicted, 0);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2, 4, 5]);
+    }
+
+    #[test]
+    fn test_inspect_after_senders_dropped() {
+        let (sender, receiver) = bounded(2);
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(receiver.inspect(|queued| queued.len()), Some(1));
+        drop(sender);
+        assert_eq!(receiver.inspect(|queued| queued.len()), None);
+        assert_eq!(receiver.recv(), Ok(1));
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn test_strict_allows_sends_without_eviction() {
+        let (sender, receiver) = Builder::new(1).strict().build();
+        for i in 0..3 {
+            sender.send_overwrite(i).unwrap();
+            assert_eq!(receiver.recv(), Ok(i));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "strict channel `unnamed` evicted 1 message(s): [1]")]
+    fn test_strict_panics_on_protected_eviction() {
+        let (sender, _receiver) = Builder::new(2)
+            .strict()
+            .protect(|value| *value == 0)
+            .build();
+        sender.send_overwrite_batch(vec![0, 1]).unwrap();
+        // Explicit removals are not evictions
+        assert_eq!(sender.cancel_where(|_| false), Vec::<i32>::new());
+        sender.send_overwrite(2).unwrap();
+    }
+
+    #[test]
+    fn test_simulate_send_matches_send() {
+        let (sender, _receiver) = Builder::new(3).protect(|value| *value == 0).build();
+        sender.send_overwrite_batch(vec![1, 0, 2]).unwrap();
+        assert_eq!(sender.simulate_send(&3), EvictionPlan::Evicts(1));
+        assert_eq!(sender.would_evict(), 1);
+        assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+
+        sender.send_overwrite_batch(vec![0, 0]).unwrap();
+        assert_eq!(sender.dump(), "[0, 0, 0]");
+        assert_eq!(sender.simulate_send(&4), EvictionPlan::Rejected);
+        assert_eq!(sender.would_evict(), 0);
+
+        let (sender, receiver) = bounded(1);
+        sender.send_overwrite(1).unwrap();
+        sender.swap_policy(OverflowPolicy::RejectNew);
+        assert_eq!(sender.simulate_send(&2), EvictionPlan::Rejected);
+        assert_eq!(sender.would_evict(), 0);
+        drop(receiver);
+        assert_eq!(sender.simulate_send(&2), EvictionPlan::Disconnected);
+    }
+
+    #[test]
+    fn test_plan_follows_protected_messages_through_the_queue() {
+        let (sender, receiver) = Builder::new(3).protect(|value| *value == 0).build();
+        sender.send_overwrite_batch(vec![0, 0, 1]).unwrap();
+        assert_eq!(sender.simulate_send(&2), EvictionPlan::Evicts(1));
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(sender.simulate_send(&3), EvictionPlan::Evicts(1));
+        assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![2]));
+
+        // Receiving and cancelling move protected messages out of the plan
+        assert_eq!(receiver.recv().unwrap(), 0);
+        assert_eq!(sender.cancel_where(|value| *value == 3), vec![3]);
+        sender.send_overwrite_batch(vec![4, 5]).unwrap();
+        assert_eq!(sender.dump(), "[0, 4, 5]");
+        assert_eq!(sender.would_evict(), 1);
+        assert!(sender.send_all_or_nothing(vec![6, 7]).is_ok());
+        assert_eq!(sender.dump(), "[0, 6, 7]");
+        assert_eq!(sender.simulate_send(&8), EvictionPlan::Evicts(1));
+    }
+
+    #[test]
+    fn test_linger_waits_for_drain() {
+        let (sender, receiver) = Builder::new(4).linger(Duration::from_secs(30)).build();
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        let clone = sender.clone();
+        drop(sender);
+        assert_eq!(clone.sender_count(), 1);
+        drop(clone);
+        thread::sleep(Duration::from_millis(20));
+        assert!(!receiver.is_disconnected());
+        assert_eq!(receiver.recv().unwrap(), 1);
+        assert_eq!(receiver.recv().unwrap(), 2);
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Err(flume::RecvTimeoutError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_linger_period_elapses() {
+        let (sender, receiver) = Builder::new(4).linger(Duration::from_millis(10)).build();
+        sender.send_overwrite(1).unwrap();
+        drop(sender);
+        thread::sleep(Duration::from_millis(100));
+        assert!(receiver.is_disconnected());
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_linger_period_follows_the_clock() {
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(4)
+            .clock(clock.clone())
+            .linger(Duration::from_secs(30))
+            .build();
+        sender.send_overwrite(1).unwrap();
+        drop(sender);
+        thread::sleep(Duration::from_millis(20));
+        assert!(!receiver.is_disconnected());
+
+        clock.advance(Duration::from_secs(30));
+        while !receiver.is_disconnected() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_no_linger_when_empty() {
+        let (sender, receiver) = Builder::<i32>::new(4)
+            .linger(Duration::from_secs(30))
+            .build();
+        drop(sender);
+        assert!(receiver.is_disconnected());
+    }
+
+    #[test]
+    fn test_endpoints_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<OverwriteSender<u32>>();
+        assert_send_sync::<OverwriteReceiver<u32>>();
+    }
+
+    #[test]
+    fn test_stream_yields_in_order() {
+        use futures::StreamExt;
+        let (sender, mut receiver) = bounded(4);
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(block_on(receiver.next()), Some(1));
+        assert_eq!(block_on(receiver.next()), Some(2));
+        sender.send_overwrite(3).unwrap();
+        drop(sender);
+        assert_eq!(block_on(receiver.next()), Some(3));
+        assert_eq!(block_on(receiver.next()), None);
+    }
+
+    #[test]
+    fn test_stream_conflates_backlog() {
+        use futures::StreamExt;
+        let (sender, receiver) = bounded(8);
+        let mut receiver = receiver.conflate(true);
+        for i in 0..5 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert_eq!(block_on(receiver.next()), Some(4));
+        assert!(receiver.is_empty());
+        let mut clone = receiver.clone();
+        sender.send_overwrite(5).unwrap();
+        sender.send_overwrite(6).unwrap();
+        assert_eq!(block_on(clone.next()), Some(6));
+        // Plain receives are not conflated
+        sender.send_overwrite(7).unwrap();
+        sender.send_overwrite(8).unwrap();
+        assert_eq!(receiver.recv().unwrap(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "channel capacity must be non-zero")]
+    fn test_zero_capacity_panics() {
+        let _ = bounded::<u8>(0);
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_consumer_stalled_hook_runs_once_per_stall() {
+        let clock = MockClock::new();
+        let stalls = Arc::new(Mutex::new(Vec::new()));
+        let reported = stalls.clone();
+        let (sender, receiver) = Builder::new(1)
+            .clock(clock.clone())
+            .on_consumer_stalled(Duration::from_secs(5), move |silence| {
+                reported.lock().unwrap().push(silence)
+            })
+            .build();
+        sender.send_overwrite_batch(vec![0, 1]).unwrap();
+        clock.advance(Duration::from_secs(5));
+        assert!(sender.consumer_stalled(Duration::from_secs(5)));
+        sender.send_overwrite_batch(vec![2, 3]).unwrap();
+        assert_eq!(*stalls.lock().unwrap(), vec![Duration::from_secs(5)]);
+
+        receiver.recv().unwrap();
+        receiver.heartbeat();
+        clock.advance(Duration::from_secs(6));
+        sender.send_overwrite_batch(vec![4, 5]).unwrap();
+        assert_eq!(stalls.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_recv_spin_parks_after_spinning() {
+        let (sender, receiver) = bounded(1);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            sender.send_overwrite(1).unwrap();
+        });
+        assert_eq!(receiver.recv_spin(10), Ok(1));
+        handle.join().unwrap();
+        assert_eq!(receiver.recv_spin(0), Err(RecvError::Disconnected));
+    }
+
+    #[test]
+    fn test_poll_recv_wakes_on_send() {
+        use futures::task::{ArcWake, waker};
+
+        struct Flag(std::sync::atomic::AtomicBool);
+        impl ArcWake for Flag {
+            fn wake_by_ref(flag: &Arc<Self>) {
+                flag.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let (sender, receiver) = bounded(2);
+        let flag = Arc::new(Flag(Default::default()));
+        let waker = waker(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        assert!(receiver.poll_recv(&mut cx).is_pending());
+        assert_eq!(
+            sender.poll_send_overwrite(&mut cx, &mut Some(1)),
+            Poll::Ready(Ok(None))
+        );
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert_eq!(receiver.poll_recv(&mut cx), Poll::Ready(Ok(1)));
+        drop(sender);
+        assert_eq!(
+            receiver.poll_recv(&mut cx),
+            Poll::Ready(Err(RecvError::Disconnected))
+        );
+    }
+
+    #[test]
+    fn test_poll_send_waits_for_the_send_lock() {
+        use futures::task::{ArcWake, waker};
+
+        struct Flag(std::sync::atomic::AtomicBool);
+        impl ArcWake for Flag {
+            fn wake_by_ref(flag: &Arc<Self>) {
+                flag.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let (sender, receiver) = bounded(1);
+        let flag = Arc::new(Flag(Default::default()));
+        let waker = waker(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        // A concurrent send holds the lock, so the poll neither blocks nor loses the value
+        let sending = sender.shared.lock_sends();
+        let mut slot = Some(1);
+        assert!(sender.poll_send_overwrite(&mut cx, &mut slot).is_pending());
+        assert_eq!(slot, Some(1));
+        assert!(!flag.0.load(Ordering::SeqCst));
+
+        drop(sending);
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert_eq!(
+            sender.poll_send_overwrite(&mut cx, &mut slot),
+            Poll::Ready(Ok(None))
+        );
+        assert_eq!(slot, None);
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_reject_new_policy_applies_to_clones() {
+        let (sender, receiver) = Builder::new(2).policy(OverflowPolicy::RejectNew).build();
+        let clone = sender.clone();
+        sender.send_overwrite_batch(vec![1, 2]).unwrap();
+        assert_eq!(clone.send_overwrite(3).unwrap_err().into_inner(), 3);
+        assert_eq!(receiver.stats().evicted, 0);
+
+        assert_eq!(
+            sender.swap_policy(OverflowPolicy::DropOldest),
+            OverflowPolicy::RejectNew
+        );
+        assert_eq!(clone.policy(), OverflowPolicy::DropOldest);
+        assert_eq!(clone.send_overwrite(3).unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_eviction_budget_window() {
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(2)
+            .clock(clock.clone())
+            .eviction_budget(2, Duration::from_secs(1))
+            .build();
+        sender.send_overwrite_batch(vec![0, 1, 2, 3]).unwrap();
+        let error = sender.send_overwrite(4).unwrap_err();
+        assert!(error.is_rejected());
+        assert_eq!(error.into_inner(), 4);
+        assert_eq!(receiver.stats().evicted, 2);
+
+        clock.advance(Duration::from_millis(999));
+        assert!(sender.send_overwrite(4).is_err());
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(sender.send_overwrite(4).unwrap(), Some(vec![2]));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_recv_quiescent_async_waits_for_idle() {
+        let (sender, receiver) = bounded(8);
+        let producer = thread::spawn(move || {
+            for i in 0..4 {
+                sender.send_overwrite(i).unwrap();
+                thread::sleep(Duration::from_millis(2));
+            }
+            // Keep the channel connected past the idle period
+            thread::sleep(Duration::from_millis(100));
+        });
+        let batch = block_on(receiver.recv_quiescent_async(Duration::from_millis(50)));
+        assert_eq!(batch, vec![0, 1, 2, 3]);
+        producer.join().unwrap();
+        assert!(receiver.recv_quiescent(Duration::from_secs(10)).is_empty());
+    }
+
+    #[test]
+    fn test_protected_messages_are_not_evicted() {
+        let (sender, receiver) = Builder::<Result<u32, u32>>::new(3).protect_errors().build();
+        sender.send_ok(0).unwrap();
+        sender.send_err(1).unwrap();
+        sender.send_ok(2).unwrap();
+        assert_eq!(sender.send_err(3).unwrap(), Some(vec![Ok(0)]));
+        assert_eq!(sender.send_err(4).unwrap(), Some(vec![Ok(2)]));
+        // Only errors are left, so nothing can be evicted
+        assert!(sender.send_ok(5).unwrap_err().is_rejected());
+        assert_eq!(receiver.stats().evicted, 2);
+        assert_eq!(
+            receiver.drain().collect::<Vec<_>>(),
+            vec![Err(1), Err(3), Err(4)]
+        );
+    }
+
+    #[test]
+    fn test_max_message_weight_boundary() {
+        let (sender, receiver) = Builder::new(2)
+            .max_message_weight(4, |value: &&str| value.len())
+            .build();
+        sender.send_overwrite("").unwrap();
+        sender.send_overwrite("four").unwrap();
+        assert_eq!(sender.simulate_send(&"fifth"), EvictionPlan::Oversized);
+        assert!(sender.send_overwrite("fifth").unwrap_err().is_oversized());
+        assert_eq!(
+            sender.send_overwrite_discard("fifth"),
+            Err(SendOverwriteError::Oversized("fifth"))
+        );
+        // Nothing was evicted for the oversized messages
+        assert_eq!(receiver.stats().evicted, 0);
+        assert_eq!(sender.send_overwrite("last").unwrap(), Some(vec![""]));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec!["four", "last"]);
+    }
+
+    #[test]
+    fn test_adaptive_capacity_grows_and_shrinks() {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let sink = changes.clone();
+        let (sender, receiver) = Builder::new(2)
+            .adaptive_capacity(4, Duration::ZERO)
+            .on_capacity_change(move |change| sink.lock().unwrap().push(change))
+            .build();
+        for i in 0..6 {
+            sender.send_overwrite(i).unwrap();
+        }
+        // Grown to the maximum, and no further
+        assert_eq!(sender.capacity(), 4);
+        assert_eq!(receiver.len(), 4);
+
+        receiver.drain().for_each(drop);
+        for i in 6..9 {
+            sender.send_overwrite(i).unwrap();
+            assert_eq!(receiver.recv().unwrap(), i);
+        }
+        // Back to the built capacity, and no further
+        assert_eq!(sender.capacity(), 2);
+        let changes: Vec<_> = changes
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|change| (change.from, change.to))
+            .collect();
+        assert_eq!(changes, vec![(2, 4), (4, 2)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity 3 exceeds the channel's maximum of 2")]
+    fn test_set_capacity_above_maximum_panics() {
+        let (sender, _receiver) = bounded::<()>(2);
+        sender.set_capacity(3);
+    }
+
+    #[test]
+    fn test_send_all_or_nothing_protected() {
+        let (sender, receiver) = Builder::new(3).protect(|value| *value < 10).build();
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(20).unwrap();
+        sender.send_overwrite(2).unwrap();
+
+        // Only one message can be evicted to make room
+        let error = sender.send_all_or_nothing(vec![30, 31]).unwrap_err();
+        assert!(error.is_rejected());
+        assert_eq!(error.into_inner(), vec![30, 31]);
+        assert_eq!(receiver.stats().evicted, 0);
+
+        assert_eq!(
+            sender.send_all_or_nothing(vec![30]).unwrap(),
+            Some(vec![20])
+        );
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2, 30]);
+    }
+
+    #[test]
+    fn test_evict_transform_progressive() {
+        // Halving until the value is too small to keep
+        let (sender, receiver) = Builder::new(2)
+            .on_evict_transform(|value: u32| (value >= 4).then_some(value / 2))
+            .build();
+        sender.send_overwrite(8).unwrap();
+        sender.send_overwrite(9).unwrap();
+        assert_eq!(sender.send_overwrite(10).unwrap(), Some(vec![9]));
+        assert_eq!(sender.send_overwrite(11).unwrap(), Some(vec![10]));
+        // 2 is dropped by the transform, so nothing is returned
+        assert_eq!(sender.send_overwrite(12).unwrap(), None);
+        assert_eq!(receiver.stats().evicted, 3);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![11, 12]);
+
+        // With no message behind it, the representative itself is evicted
+        let (sender, _receiver) = Builder::new(1).on_evict_transform(Some).build();
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(sender.send_overwrite(2).unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_tap_receives_clones() {
+        let (sender, receiver) = Builder::new(2).name("events").build();
+        sender.send_overwrite(0).unwrap();
+        let tap = receiver.tap(4);
+        assert_eq!(tap.stats().name.as_deref(), Some("events.tap"));
+        sender.send_overwrite(1).unwrap();
+        sender.try_send(2).unwrap_err();
+        sender.send_overwrite_batch(vec![3, 4]).unwrap();
+        assert_eq!(tap.drain().collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![3, 4]);
+
+        drop(sender);
+        assert!(tap.is_disconnected());
+    }
+
+    #[test]
+    fn test_tap_detaches_when_dropped() {
+        let (sender, receiver) = bounded(2);
+        let tap = receiver.tap(1);
+        drop(tap);
+        sender.send_overwrite(1).unwrap();
+        assert!(sender.shared.lock_taps().is_empty());
+        drop(sender);
+        assert!(receiver.tap(1).is_disconnected());
+    }
+
     #[test]
     fn test_send_overwrite_concurrent() {
         let (sender, receiver) = bounded(2);
@@ -368,4 +4316,191 @@ mod test {
             assert_eq!(*got, vec![3, 4]);
         }
     }
+
+    #[test]
+    fn test_into_inner_keeps_the_channel_up() {
+        let (sender, receiver) = bounded(2);
+        let tap = receiver.tap(2);
+        let raw = sender.into_inner();
+        assert!(!tap.is_disconnected());
+
+        raw.send(1).unwrap();
+        assert_eq!(receiver.sender_count(), 1);
+        assert_eq!(receiver.recv().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_send_overwrite_detached_never_waits() {
+        let (sender, receiver) = bounded(1);
+        let sending = sender.shared.lock_sends();
+        sender.send_overwrite_detached(1);
+        drop(sending);
+        sender.send_overwrite_detached(2);
+        sender.send_overwrite_detached(3);
+
+        assert_eq!(sender.detached_failures(), 1);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn test_async_send_awaits_the_send_lock() {
+        use futures::FutureExt;
+        use futures::task::noop_waker_ref;
+
+        let (sender, receiver) = bounded(1);
+        let sending = sender.shared.lock_sends();
+        let mut send = Box::pin(sender.send_overwrite_async(1));
+        // Pending instead of blocking the executor thread on the held lock
+        let mut cx = Context::from_waker(noop_waker_ref());
+        assert!(send.poll_unpin(&mut cx).is_pending());
+
+        // A cancelled send leaves the lock to the next one
+        drop(send);
+        drop(sending);
+        assert_eq!(block_on(sender.send_overwrite_async(2)).unwrap(), None);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_contended_sync_and_async_sends_keep_every_message() {
+        let (sender, receiver) = bounded(8);
+        let producers: Vec<_> = (0..4u64)
+            .map(|producer| {
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    let mut overwritten = Vec::new();
+                    for seq in 0..500u64 {
+                        let value = (producer, seq);
+                        let evicted = if producer % 2 == 0 {
+                            sender.send_overwrite(value)
+                        } else {
+                            block_on(sender.send_overwrite_async(value))
+                        };
+                        overwritten.extend(evicted.unwrap().unwrap_or_default());
+                    }
+                    overwritten
+                })
+            })
+            .collect();
+        let mut seen: Vec<(u64, u64)> = producers
+            .into_iter()
+            .flat_map(|producer| producer.join().unwrap())
+            .collect();
+        seen.extend(receiver.drain());
+
+        // Every message is either still queued or was handed back exactly once
+        assert_eq!(seen.len(), 2000);
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen.len(), 2000);
+        assert_eq!(sender.stats().evicted, 1992);
+    }
+
+    #[test]
+    fn test_hooks_run_after_the_send_lock_is_released() {
+        let handle = Arc::new(std::sync::OnceLock::<OverwriteSender<i32>>::new());
+        let hooked = handle.clone();
+        let (sender, receiver) = Builder::new(4)
+            .watermarks(0, 2)
+            .on_watermark(move |watermark| {
+                if watermark == Watermark::High {
+                    hooked.get().unwrap().send_overwrite(-1).unwrap();
+                }
+            })
+            .build();
+        handle.set(sender.clone()).unwrap();
+
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2, -1]);
+    }
+
+    /// How a producer drives one of its async sends.
+    #[derive(Clone, Copy, Debug)]
+    enum Drive {
+        /// Drops the future before polling it.
+        Cancel,
+        /// Polls the future once, dropping it if it waits for the send lock.
+        PollOnce,
+        /// Runs the future to completion.
+        Complete,
+    }
+
+    fn in_queue_order(messages: &[(usize, usize)]) -> bool {
+        let mut last = BTreeMap::new();
+        messages
+            .iter()
+            .all(|&(producer, seq)| last.insert(producer, seq).is_none_or(|last| last < seq))
+    }
+
+    proptest::proptest! {
+        #![proptest_config(proptest::test_runner::Config::with_cases(64))]
+
+        #[test]
+        fn test_eviction_order_under_racing_and_cancelled_async_sends(
+            cap in 1..=4usize,
+            plans in proptest::collection::vec(
+                proptest::collection::vec(
+                    proptest::prop_oneof![
+                        proptest::strategy::Just(Drive::Cancel),
+                        proptest::strategy::Just(Drive::PollOnce),
+                        proptest::strategy::Just(Drive::Complete),
+                    ],
+                    1..100,
+                ),
+                1..4,
+            ),
+        ) {
+            use futures::FutureExt;
+            use futures::task::noop_waker_ref;
+
+            let (sender, receiver) = bounded(cap);
+            let producers: Vec<_> = plans
+                .into_iter()
+                .enumerate()
+                .map(|(producer, plan)| {
+                    let sender = sender.clone();
+                    thread::spawn(move || {
+                        let (mut evicted, mut cancelled, mut sent) = (Vec::new(), Vec::new(), 0);
+                        for (seq, drive) in plan.into_iter().enumerate() {
+                            let mut send = Box::pin(sender.send_overwrite_async((producer, seq)));
+                            let result = match drive {
+                                Drive::Cancel => None,
+                                Drive::PollOnce => {
+                                    let mut cx = Context::from_waker(noop_waker_ref());
+                                    match send.poll_unpin(&mut cx) {
+                                        Poll::Ready(result) => Some(result),
+                                        Poll::Pending => None,
+                                    }
+                                }
+                                Drive::Complete => Some(block_on(send)),
+                            };
+                            let Some(result) = result else {
+                                cancelled.push((producer, seq));
+                                continue;
+                            };
+                            let batch = result.unwrap().unwrap_or_default();
+                            assert!(in_queue_order(&batch), "batch out of queue order: {batch:?}");
+                            evicted.extend(batch);
+                            sent += 1;
+                        }
+                        (evicted, cancelled, sent)
+                    })
+                })
+                .collect();
+            drop(sender);
+
+            let received: Vec<_> = receiver.iter().collect();
+            proptest::prop_assert!(in_queue_order(&received));
+            let (mut evicted, mut cancelled, mut sent) = (0, Vec::new(), 0);
+            for producer in producers {
+                let (batch, dropped, count) = producer.join().unwrap();
+                evicted += batch.len();
+                cancelled.extend(dropped);
+                sent += count;
+            }
+            proptest::prop_assert_eq!(evicted + received.len(), sent);
+            proptest::prop_assert!(received.iter().all(|message| !cancelled.contains(message)));
+        }
+    }
 }
@@ -0,0 +1,282 @@
+//! Deterministic interleaving of several producers through logical clocks.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, SenderId, bounded};
+use flume::{RecvError, TryRecvError};
+use std::cmp::{Ordering as CmpOrdering, Reverse};
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+/// Creates an overwrite channel of capacity `cap` whose receiver reorders up to `window`
+/// messages by logical time.
+///
+/// Every [`OrderedSender`] clone keeps a Lamport clock and stamps each message with it. The
+/// [`ReorderReceiver`] buffers up to `window` queued messages and delivers the one with the
+/// lowest stamp first, breaking ties by sender id. Messages that were queued together are
+/// therefore delivered in the same order however the producers' sends interleaved, and
+/// overwrites do not change the order of the survivors.
+///
+/// # Panics
+///
+/// Panics if `cap` or `window` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded_ordered;
+///
+/// let (left, receiver) = bounded_ordered(4, 4);
+/// let right = left.clone();
+///
+/// // The right producer happens to get ahead
+/// right.send_overwrite("right 1").unwrap();
+/// right.send_overwrite("right 2").unwrap();
+/// left.send_overwrite("left 1").unwrap();
+///
+/// // Stamps 1, 1 and 2: the tie is broken by sender id, the clone's being higher
+/// assert_eq!(*receiver.recv().unwrap(), "left 1");
+/// assert_eq!(*receiver.recv().unwrap(), "right 1");
+/// assert_eq!(*receiver.recv().unwrap(), "right 2");
+/// ```
+#[track_caller]
+pub fn bounded_ordered<T>(cap: usize, window: usize) -> (OrderedSender<T>, ReorderReceiver<T>) {
+    assert!(window > 0, "reorder window must be non-zero");
+    let (sender, receiver) = bounded(cap);
+    let sender = OrderedSender {
+        sender,
+        clock: AtomicU64::new(0),
+    };
+    let receiver = ReorderReceiver {
+        receiver,
+        window,
+        pending: Mutex::new(BinaryHeap::new()),
+    };
+    (sender, receiver)
+}
+
+/// A message sent by an [`OrderedSender`], along with its logical timestamp.
+#[derive(Clone, Debug)]
+pub struct Stamped<T> {
+    stamp: u64,
+    sender: SenderId,
+    value: T,
+}
+
+impl<T> Stamped<T> {
+    /// Returns the Lamport timestamp the message was sent with.
+    pub fn stamp(&self) -> u64 {
+        self.stamp
+    }
+
+    /// Returns the id of the sender clone that sent the message.
+    pub fn sender(&self) -> SenderId {
+        self.sender
+    }
+
+    /// Returns the message.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    fn key(&self) -> (u64, SenderId) {
+        (self.stamp, self.sender)
+    }
+}
+
+impl<T> Deref for Stamped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> PartialEq for Stamped<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl<T> Eq for Stamped<T> {}
+
+impl<T> PartialOrd for Stamped<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders messages by stamp, then by sender id.
+impl<T> Ord for Stamped<T> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The sending half of a [`bounded_ordered`] channel.
+///
+/// Each clone has a clock of its own, starting from the clock of the sender it was cloned
+/// from.
+pub struct OrderedSender<T> {
+    sender: OverwriteSender<Stamped<T>>,
+    clock: AtomicU64,
+}
+
+impl<T> OrderedSender<T> {
+    /// Stamps a value with the next tick of this sender's clock and sends it with overwrite
+    /// semantics.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let message = Stamped {
+            stamp: self.clock.fetch_add(1, Ordering::AcqRel) + 1,
+            sender: self.sender.id(),
+            value,
+        };
+        let overwritten = self
+            .sender
+            .send_overwrite(message)
+            .map_err(|error| error.map(Stamped::into_inner))?;
+        Ok(overwritten
+            .map(|overwritten| overwritten.into_iter().map(Stamped::into_inner).collect()))
+    }
+
+    /// Advances this sender's clock past `stamp`, so that its next message is ordered after
+    /// an event it has observed.
+    ///
+    /// This is the receive rule of Lamport clocks: a producer that reacts to a message,
+    /// from this channel or elsewhere, should observe its stamp before sending.
+    pub fn observe(&self, stamp: u64) {
+        self.clock.fetch_max(stamp, Ordering::AcqRel);
+    }
+
+    /// Returns the stamp of the last message this sender sent or observed.
+    pub fn clock(&self) -> u64 {
+        self.clock.load(Ordering::Acquire)
+    }
+
+    /// Returns the underlying sender.
+    pub fn sender(&self) -> &OverwriteSender<Stamped<T>> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for OrderedSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            clock: AtomicU64::new(self.clock()),
+        }
+    }
+}
+
+impl<T> fmt::Debug for OrderedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderedSender")
+            .field("clock", &self.clock())
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+/// The receiving half of a [`bounded_ordered`] channel.
+///
+/// Buffered messages have left the channel: they are no longer counted by its length and
+/// can no longer be overwritten.
+pub struct ReorderReceiver<T> {
+    receiver: OverwriteReceiver<Stamped<T>>,
+    window: usize,
+    pending: Mutex<BinaryHeap<Reverse<Stamped<T>>>>,
+}
+
+impl<T> ReorderReceiver<T> {
+    /// Fills the reorder buffer from the channel without waiting, then takes its earliest
+    /// message.
+    fn take_earliest(&self, pending: &mut BinaryHeap<Reverse<Stamped<T>>>) -> Option<Stamped<T>> {
+        while pending.len() < self.window {
+            match self.receiver.try_recv() {
+                Ok(message) => pending.push(Reverse(message)),
+                Err(_) => break,
+            }
+        }
+        pending.pop().map(|Reverse(message)| message)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BinaryHeap<Reverse<Stamped<T>>>> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Receives the earliest of the queued messages, blocking until one arrives.
+    ///
+    /// Only messages already queued are reordered; the receiver does not wait for the
+    /// window to fill. Returns an error once the channel and the buffer are empty and every
+    /// sender has been dropped.
+    pub fn recv(&self) -> Result<Stamped<T>, RecvError> {
+        let mut pending = self.lock();
+        if let Some(message) = self.take_earliest(&mut pending) {
+            return Ok(message);
+        }
+        pending.push(Reverse(self.receiver.recv()?));
+        Ok(self
+            .take_earliest(&mut pending)
+            .expect("a message was just buffered"))
+    }
+
+    /// Receives the earliest of the queued messages without blocking.
+    pub fn try_recv(&self) -> Result<Stamped<T>, TryRecvError> {
+        let mut pending = self.lock();
+        match self.take_earliest(&mut pending) {
+            Some(message) => Ok(message),
+            None if self.receiver.is_disconnected() => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns the number of messages waiting in the reorder buffer.
+    pub fn buffered(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns the underlying receiver.
+    pub fn receiver(&self) -> &OverwriteReceiver<Stamped<T>> {
+        &self.receiver
+    }
+}
+
+impl<T> fmt::Debug for ReorderReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReorderReceiver")
+            .field("window", &self.window)
+            .field("buffered", &self.buffered())
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_window_limits_reordering() {
+        let (early, receiver) = bounded_ordered(8, 2);
+        let late = early.clone();
+        late.observe(10);
+        late.send_overwrite("late").unwrap();
+        late.send_overwrite("later").unwrap();
+        early.send_overwrite("early").unwrap();
+        assert_eq!(late.clock(), 12);
+
+        // "early" is outside the window when the first message is delivered
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok())
+            .map(|message| (message.stamp(), message.into_inner()))
+            .collect();
+        assert_eq!(received, vec![(11, "late"), (1, "early"), (12, "later")]);
+    }
+}
@@ -0,0 +1,178 @@
+//! Pumps between overwrite channels and byte streams, such as the stdin and stdout of
+//! processes connected by a pipe.
+//!
+//! Messages travel as length-delimited frames: a 4-byte big-endian length followed by the
+//! message encoded by a [`Codec`]. The codec is pluggable, so any serde format can be used
+//! by implementing it with, say, `serde_json` or `bincode`. On the reading side, frames are
+//! sent into an overwrite channel, so a consumer slower than the producing process only
+//! ever loses the oldest messages, and the pipe itself never backs up.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use flume_overwrite::bounded;
+//! use flume_overwrite::pipe::{self, Codec};
+//! use std::io;
+//!
+//! struct Utf8;
+//!
+//! impl Codec<String> for Utf8 {
+//!     fn encode(&self, value: &String, frame: &mut Vec<u8>) -> io::Result<()> {
+//!         frame.extend_from_slice(value.as_bytes());
+//!         Ok(())
+//!     }
+//!
+//!     fn decode(&self, frame: &[u8]) -> io::Result<String> {
+//!         String::from_utf8(frame.to_vec()).map_err(io::Error::other)
+//!     }
+//! }
+//!
+//! // The producing process writes its messages to stdout
+//! let (sender, receiver) = bounded(8);
+//! for status in ["starting", "ready", "busy"] {
+//!     sender.send_overwrite(status.to_string()).unwrap();
+//! }
+//! drop(sender);
+//! let mut stdout = Vec::new();
+//! pipe::write_from(&receiver, &mut stdout, &Utf8).unwrap();
+//!
+//! // The consuming process reads them from stdin, keeping only the latest
+//! let (sender, receiver) = bounded(1);
+//! assert_eq!(pipe::read_into(stdout.as_slice(), &sender, &Utf8).unwrap(), 3);
+//! assert_eq!(receiver.recv().unwrap(), "busy");
+//! ```
+
+use crate::{OverwriteReceiver, OverwriteSender};
+use std::io::{self, Read, Write};
+
+/// Encodes messages into frames and decodes them back.
+pub trait Codec<T> {
+    /// Appends the encoding of `value` to `frame`, which is empty.
+    fn encode(&self, value: &T, frame: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Decodes a message from a whole frame.
+    fn decode(&self, frame: &[u8]) -> io::Result<T>;
+
+    /// Returns the largest frame accepted when reading, which guards against allocating
+    /// for a corrupted length. Defaults to 16 MiB.
+    fn max_frame_len(&self) -> usize {
+        16 << 20
+    }
+}
+
+/// Reads frames from `reader` until the end of the stream, sending the decoded messages to
+/// `sender` with overwrite semantics, and returns the number of messages read.
+///
+/// Overwritten messages are dropped. Reading stops early, without error, once every
+/// receiver of the channel has been dropped.
+///
+/// # Errors
+///
+/// Returns an error if reading fails, if the stream ends in the middle of a frame, or if a
+/// frame is too long or cannot be decoded.
+pub fn read_into<T, R, C>(mut reader: R, sender: &OverwriteSender<T>, codec: &C) -> io::Result<u64>
+where
+    R: Read,
+    C: Codec<T> + ?Sized,
+{
+    let mut frame = Vec::new();
+    let mut read = 0;
+    loop {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(read),
+            Err(error) => return Err(error),
+        }
+        let len = u32::from_be_bytes(len) as usize;
+        if len > codec.max_frame_len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {len} bytes exceeds the maximum"),
+            ));
+        }
+        frame.resize(len, 0);
+        reader.read_exact(&mut frame)?;
+        let value = codec.decode(&frame)?;
+        read += 1;
+        if sender.send_overwrite_discard(value).is_err() && sender.is_disconnected() {
+            return Ok(read);
+        }
+    }
+}
+
+/// Receives messages from `receiver` until every sender has been dropped and the channel
+/// is drained, writing each to `writer` as a frame, and returns the number of messages
+/// written.
+///
+/// The writer is flushed after every frame, so the reading side sees each message as soon
+/// as it is received.
+///
+/// # Errors
+///
+/// Returns an error if a message cannot be encoded or is longer than `u32::MAX` bytes, or
+/// if writing fails, for instance because the reading process exited.
+pub fn write_from<T, W, C>(
+    receiver: &OverwriteReceiver<T>,
+    mut writer: W,
+    codec: &C,
+) -> io::Result<u64>
+where
+    W: Write,
+    C: Codec<T> + ?Sized,
+{
+    let mut frame = Vec::new();
+    let mut written = 0;
+    while let Ok(value) = receiver.recv() {
+        frame.clear();
+        codec.encode(&value, &mut frame)?;
+        let len = u32::try_from(frame.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("message of {} bytes does not fit in a frame", frame.len()),
+            )
+        })?;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&frame)?;
+        writer.flush()?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    struct Be;
+
+    impl Codec<u32> for Be {
+        fn encode(&self, value: &u32, frame: &mut Vec<u8>) -> io::Result<()> {
+            frame.extend_from_slice(&value.to_be_bytes());
+            Ok(())
+        }
+
+        fn decode(&self, frame: &[u8]) -> io::Result<u32> {
+            let bytes = frame.try_into().map_err(io::Error::other)?;
+            Ok(u32::from_be_bytes(bytes))
+        }
+
+        fn max_frame_len(&self) -> usize {
+            4
+        }
+    }
+
+    #[test]
+    fn test_truncated_and_oversized_frames() {
+        let (sender, receiver) = bounded(4);
+        let truncated = [0, 0, 0, 4, 0, 0];
+        let error = read_into(&truncated[..], &sender, &Be).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let oversized = [0, 0, 0, 5, 0, 0, 0, 0, 0];
+        let error = read_into(&oversized[..], &sender, &Be).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(receiver.is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+//! Multi-stage pipelines of workers connected by overwrite channels.
+
+use crate::{Builder, Capacity, Controller, OverwriteReceiver, OverwriteSender, Stats};
+use std::fmt;
+use std::thread::{self, JoinHandle};
+
+/// Connects a pipeline to the sender its last stage feeds, spawning its workers, and
+/// returns the sender feeding its first stage.
+type Connect<In, Out> =
+    Box<dyn FnOnce(OverwriteSender<Out>, &mut Vec<Stage>) -> OverwriteSender<In>>;
+
+/// A spawned stage: its worker and a report on the channel feeding it.
+struct Stage {
+    stats: Box<dyn Fn() -> Stats + Send + Sync>,
+    worker: JoinHandle<()>,
+}
+
+/// A builder for a chain of worker threads connected by overwrite channels.
+///
+/// Each [`stage`](Pipeline::stage) is a worker applying a function to every message of the
+/// channel in front of it and sending the result to the next stage with overwrite
+/// semantics. A stage slower than its producer thus loses the oldest messages of its own
+/// queue rather than holding up the stages upstream. Nothing is spawned until
+/// [`build`](Pipeline::build).
+///
+/// `In` is the type of the messages sent into the pipeline and `Out` the type of those
+/// coming out of its last stage.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::Pipeline;
+///
+/// let (head, tail, stages) = Pipeline::new()
+///     .stage(4, |line: &str| line.len())
+///     .stage(4, |len| len * 2)
+///     .build();
+///
+/// head.send_overwrite("decode").unwrap();
+/// assert_eq!(tail.recv().unwrap(), 12);
+///
+/// // Dropping the head shuts the workers down in order
+/// drop(head);
+/// assert!(tail.recv().is_err());
+/// let stats = stages.join().unwrap();
+/// assert_eq!(stats.len(), 2);
+/// assert!(stats.iter().all(|stage| stage.sent == 1));
+/// ```
+pub struct Pipeline<In, Out> {
+    connect: Connect<In, Out>,
+    tail: Option<Capacity>,
+}
+
+impl<T: Send + 'static> Pipeline<T, T> {
+    /// Creates a pipeline without stages.
+    pub fn new() -> Self {
+        Self {
+            connect: Box::new(|sender, _| sender),
+            tail: None,
+        }
+    }
+}
+
+impl<T: Send + 'static> Default for Pipeline<T, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<In: Send + 'static, Out: Send + 'static> Pipeline<In, Out> {
+    /// Appends a stage whose worker applies `f` to every message, fed by a channel holding
+    /// at most `cap` messages.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn stage<U, F>(self, cap: usize, f: F) -> Pipeline<In, U>
+    where
+        U: Send + 'static,
+        F: FnMut(Out) -> U + Send + 'static,
+    {
+        self.stage_with(Builder::new(cap), f)
+    }
+
+    /// Appends a stage whose worker applies `f` to every message, fed by a channel built by
+    /// `builder`.
+    ///
+    /// This sets the stage's own overwrite policy, eviction hooks or name; named stages are
+    /// easier to tell apart in [`PipelineStages::stats`].
+    pub fn stage_with<U, F>(self, builder: Builder<Out>, mut f: F) -> Pipeline<In, U>
+    where
+        U: Send + 'static,
+        F: FnMut(Out) -> U + Send + 'static,
+    {
+        let cap = builder.cap;
+        let connect = self.connect;
+        Pipeline {
+            connect: Box::new(move |output: OverwriteSender<U>, stages: &mut Vec<Stage>| {
+                let (sender, receiver) = builder.build();
+                let controller = Controller::new(&sender);
+                let head = connect(sender, stages);
+                let worker = thread::spawn(move || {
+                    while let Ok(value) = receiver.recv() {
+                        if let Err(err) = output.send_overwrite(f(value))
+                            && err.is_disconnected()
+                        {
+                            break;
+                        }
+                        receiver.heartbeat();
+                    }
+                });
+                stages.push(Stage {
+                    stats: Box::new(move || controller.stats()),
+                    worker,
+                });
+                head
+            }),
+            tail: Some(cap),
+        }
+    }
+
+    /// Spawns a worker per stage and returns the sender feeding the first stage, the
+    /// receiver of the last stage's results and a handle on the stages.
+    ///
+    /// The last stage sends into a channel with the same capacity as its own. Dropping
+    /// every sender of the head shuts the stages down one after the other, and dropping
+    /// every receiver of the tail does the same from the other end.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pipeline has no stage.
+    #[track_caller]
+    pub fn build(self) -> (OverwriteSender<In>, OverwriteReceiver<Out>, PipelineStages) {
+        let cap = self.tail.expect("a pipeline needs at least one stage");
+        let (output, tail) = Builder::with_capacity(cap).build();
+        let mut stages = Vec::new();
+        let head = (self.connect)(output, &mut stages);
+        (head, tail, PipelineStages { stages })
+    }
+}
+
+impl<In, Out> fmt::Debug for Pipeline<In, Out> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("tail", &self.tail)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The running stages of a pipeline, as returned by [`Pipeline::build`].
+pub struct PipelineStages {
+    stages: Vec<Stage>,
+}
+
+impl PipelineStages {
+    /// Returns a snapshot of the channel feeding each stage, first stage first.
+    ///
+    /// A stage whose channel keeps evicting is the bottleneck of the pipeline.
+    pub fn stats(&self) -> Vec<Stats> {
+        self.stages.iter().map(|stage| (stage.stats)()).collect()
+    }
+
+    /// Returns `true` once every worker has exited.
+    pub fn is_finished(&self) -> bool {
+        self.stages.iter().all(|stage| stage.worker.is_finished())
+    }
+
+    /// Waits for every worker to exit and returns the final [`stats`](Self::stats).
+    ///
+    /// Returns the panic of the first stage that panicked, in stage order, if any did.
+    pub fn join(self) -> thread::Result<Vec<Stats>> {
+        let mut stats = Vec::with_capacity(self.stages.len());
+        let mut panicked = None;
+        for stage in self.stages {
+            if let Err(payload) = stage.worker.join() {
+                panicked.get_or_insert(payload);
+            }
+            stats.push((stage.stats)());
+        }
+        match panicked {
+            Some(payload) => Err(payload),
+            None => Ok(stats),
+        }
+    }
+}
+
+impl fmt::Debug for PipelineStages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineStages")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dropping_tail_stops_every_stage() {
+        let (head, tail, stages) = Pipeline::new()
+            .stage_with(Builder::new(2).name("parse"), |n: u32| n + 1)
+            .stage(2, |n| n * 10)
+            .build();
+        head.send_overwrite(1).unwrap();
+        assert_eq!(tail.recv().unwrap(), 20);
+
+        drop(tail);
+        // The last worker notices on its next send, and the first one on the send after
+        while !stages.is_finished() {
+            if head.send_overwrite(0).is_err() {
+                break;
+            }
+            thread::sleep(crate::LINGER_POLL_INTERVAL);
+        }
+        let stats = stages.join().unwrap();
+        assert_eq!(stats[0].name.as_deref(), Some("parse"));
+        assert!(head.is_disconnected());
+    }
+}
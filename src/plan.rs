@@ -0,0 +1,119 @@
+//! The outcome a send would have, computed without sending.
+
+use std::collections::VecDeque;
+
+/// What sending a message would do to the channel, as returned by
+/// [`OverwriteSender::simulate_send`](crate::OverwriteSender::simulate_send).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EvictionPlan {
+    /// The message fits without evicting anything.
+    Fits,
+    /// The message would evict this many queued messages, the oldest ones that are not
+    /// [protected](crate::Builder::protect).
+    Evicts(usize),
+    /// The send would be rejected with
+    /// [`SendOverwriteError::Rejected`](crate::SendOverwriteError::Rejected).
+    Rejected,
+    /// The send would be rejected with
+    /// [`SendOverwriteError::Oversized`](crate::SendOverwriteError::Oversized).
+    Oversized,
+    /// The send would fail because every receiver has been dropped.
+    Disconnected,
+}
+
+impl EvictionPlan {
+    /// Returns how many messages the send would evict.
+    pub fn evictions(&self) -> usize {
+        match self {
+            Self::Evicts(evictions) => *evictions,
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if the send would succeed.
+    pub fn succeeds(&self) -> bool {
+        matches!(self, Self::Fits | Self::Evicts(_))
+    }
+}
+
+/// The positions of the [protected](crate::Builder::protect) messages in the queue, so that
+/// sends can be planned without draining it.
+///
+/// Messages are numbered in the order they are queued. They only ever leave the queue from
+/// the front, or all at once when it is drained to be refilled, so the queue holds the last
+/// `len` messages queued, and protected messages numbered before those have left it.
+pub(crate) struct Protected {
+    queued: u64,
+    positions: VecDeque<u64>,
+}
+
+impl Protected {
+    pub(crate) fn new() -> Self {
+        Self {
+            queued: 0,
+            positions: VecDeque::new(),
+        }
+    }
+
+    /// Records a message queued while holding the send lock.
+    pub(crate) fn queued(&mut self, protected: bool) {
+        if protected {
+            self.positions.push_back(self.queued);
+        }
+        self.queued += 1;
+    }
+
+    /// Returns how many of the `len` messages in the queue are protected.
+    pub(crate) fn count(&mut self, len: usize) -> usize {
+        let front = self.queued.saturating_sub(len as u64);
+        while self
+            .positions
+            .front()
+            .is_some_and(|&position| position < front)
+        {
+            self.positions.pop_front();
+        }
+        self.positions.len().min(len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plan_outcomes() {
+        assert_eq!(EvictionPlan::Fits.evictions(), 0);
+        assert!(EvictionPlan::Fits.succeeds());
+        assert_eq!(EvictionPlan::Evicts(2).evictions(), 2);
+        assert!(EvictionPlan::Evicts(2).succeeds());
+        for failed in [
+            EvictionPlan::Rejected,
+            EvictionPlan::Oversized,
+            EvictionPlan::Disconnected,
+        ] {
+            assert!(!failed.succeeds());
+            assert_eq!(failed.evictions(), 0);
+        }
+    }
+
+    #[test]
+    fn test_protected_positions_leave_from_the_front() {
+        let mut protected = Protected::new();
+        for value in [true, false, true, false] {
+            protected.queued(value);
+        }
+        assert_eq!(protected.count(4), 2);
+        // Receiving the oldest message takes a protected one out
+        assert_eq!(protected.count(3), 1);
+        assert_eq!(protected.count(2), 1);
+
+        // Draining and refilling the queue numbers the requeued messages anew
+        for value in [false, true] {
+            protected.queued(value);
+        }
+        assert_eq!(protected.count(2), 1);
+        assert_eq!(protected.count(0), 0);
+    }
+}
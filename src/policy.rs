@@ -0,0 +1,86 @@
+//! What a full channel does with a new message.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How a full channel makes room for a new message.
+///
+/// Set with [`Builder::policy`](crate::Builder::policy) and switched at runtime with
+/// [`OverwriteSender::swap_policy`](crate::OverwriteSender::swap_policy).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Evict the oldest queued messages to make room for the new one.
+    #[default]
+    DropOldest,
+    /// Keep the queued messages and reject the new one with
+    /// [`SendOverwriteError::Rejected`](crate::SendOverwriteError::Rejected).
+    RejectNew,
+}
+
+impl OverflowPolicy {
+    const fn to_u8(self) -> u8 {
+        match self {
+            Self::DropOldest => 0,
+            Self::RejectNew => 1,
+        }
+    }
+
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::DropOldest,
+            _ => Self::RejectNew,
+        }
+    }
+}
+
+/// An [`OverflowPolicy`] that can be swapped while the channel is in use.
+pub(crate) struct AtomicPolicy(AtomicU8);
+
+impl AtomicPolicy {
+    pub(crate) fn new(policy: OverflowPolicy) -> Self {
+        Self(AtomicU8::new(policy.to_u8()))
+    }
+
+    pub(crate) fn load(&self) -> OverflowPolicy {
+        OverflowPolicy::from_u8(self.0.load(Ordering::Acquire))
+    }
+
+    pub(crate) fn swap(&self, policy: OverflowPolicy) -> OverflowPolicy {
+        OverflowPolicy::from_u8(self.0.swap(policy.to_u8(), Ordering::AcqRel))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Builder, SendOverwriteError};
+
+    #[test]
+    fn test_swap_returns_the_previous_policy() {
+        let policy = AtomicPolicy::new(OverflowPolicy::default());
+        assert_eq!(policy.load(), OverflowPolicy::DropOldest);
+        assert_eq!(
+            policy.swap(OverflowPolicy::RejectNew),
+            OverflowPolicy::DropOldest
+        );
+        assert_eq!(
+            policy.swap(OverflowPolicy::RejectNew),
+            OverflowPolicy::RejectNew
+        );
+        assert_eq!(policy.load(), OverflowPolicy::RejectNew);
+    }
+
+    #[test]
+    fn test_policy_decides_who_is_dropped() {
+        let (sender, receiver) = Builder::new(1).policy(OverflowPolicy::RejectNew).build();
+        sender.send_overwrite(1).unwrap();
+        assert!(matches!(
+            sender.send_overwrite(2),
+            Err(SendOverwriteError::Rejected(2))
+        ));
+
+        sender.swap_policy(OverflowPolicy::DropOldest);
+        assert_eq!(sender.send_overwrite(3).unwrap(), Some(vec![1]));
+        assert_eq!(receiver.recv().unwrap(), 3);
+    }
+}
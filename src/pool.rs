@@ -0,0 +1,238 @@
+//! Overwrite channels drawing from one shared capacity budget.
+
+use crate::{Builder, Controller, OverwriteReceiver, OverwriteSender, SendOverwriteError};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A capacity budget shared by several named overwrite channels.
+///
+/// Every channel created from the pool counts towards the same total of `cap` messages.
+/// When the pool is exhausted, the oldest message of the channel with the largest backlog
+/// is evicted, whichever channel is sending. This bounds the total memory held by many
+/// per-client channels while letting a busy client use what idle ones leave free, and makes
+/// the client that has fallen furthest behind pay for the overflow.
+///
+/// Cloning a pool returns another handle to the same budget.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::SharedPool;
+///
+/// let pool = SharedPool::new(3);
+/// let (alice, alice_rx) = pool.channel("alice");
+/// let (bob, bob_rx) = pool.channel("bob");
+///
+/// alice.send_overwrite("a1").unwrap();
+/// alice.send_overwrite("a2").unwrap();
+/// bob.send_overwrite("b1").unwrap();
+///
+/// // The pool is exhausted, and alice has the largest backlog
+/// assert_eq!(bob.send_overwrite("b2").unwrap(), Some(vec!["a1"]));
+///
+/// assert_eq!(alice_rx.recv().unwrap(), "a2");
+/// assert_eq!(bob_rx.len(), 2);
+/// ```
+pub struct SharedPool<T> {
+    inner: Arc<PoolInner<T>>,
+}
+
+struct PoolInner<T> {
+    cap: usize,
+    /// The channels drawing from the pool, which do not keep them connected.
+    channels: Mutex<Vec<Controller<T>>>,
+}
+
+impl<T> SharedPool<T> {
+    /// Creates a pool holding at most `cap` messages across all of its channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn new(cap: usize) -> Self {
+        assert!(cap > 0, "pool capacity must be non-zero");
+        Self {
+            inner: Arc::new(PoolInner {
+                cap,
+                channels: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Creates a channel named `name` drawing from the pool.
+    ///
+    /// A single channel may take up the whole pool.
+    pub fn channel(&self, name: impl Into<String>) -> (PoolSender<T>, OverwriteReceiver<T>) {
+        let (sender, receiver) = Builder::new(self.inner.cap).name(name).build();
+        self.inner.lock().push(Controller::new(&sender));
+        let sender = PoolSender {
+            sender,
+            pool: self.clone(),
+        };
+        (sender, receiver)
+    }
+
+    /// Returns the total number of messages queued across all channels.
+    pub fn len(&self) -> usize {
+        self.inner.len(&self.inner.lock())
+    }
+
+    /// Returns `true` if no channel holds any messages.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total capacity shared by all channels.
+    pub fn capacity(&self) -> usize {
+        self.inner.cap
+    }
+
+    /// Returns the number of channels still drawing from the pool.
+    pub fn channel_count(&self) -> usize {
+        let mut channels = self.inner.lock();
+        PoolInner::prune(&mut channels);
+        channels.len()
+    }
+}
+
+impl<T> PoolInner<T> {
+    /// Locks the channel list, which also serializes sends across the pool.
+    fn lock(&self) -> MutexGuard<'_, Vec<Controller<T>>> {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn len(&self, channels: &[Controller<T>]) -> usize {
+        channels.iter().map(|channel| channel.len()).sum()
+    }
+
+    /// Forgets channels that can no longer hold messages: those without receivers, whose
+    /// queues were dropped with them, and those without senders that have been drained.
+    fn prune(channels: &mut Vec<Controller<T>>) {
+        channels.retain(|channel| {
+            channel.receiver_count() > 0 && !(channel.is_disconnected() && channel.is_empty())
+        });
+    }
+}
+
+impl<T> Clone for SharedPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SharedPool<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedPool")
+            .field("capacity", &self.capacity())
+            .field("len", &self.len())
+            .field("channels", &self.channel_count())
+            .finish()
+    }
+}
+
+/// The sending half of a channel created by [`SharedPool::channel`].
+pub struct PoolSender<T> {
+    sender: OverwriteSender<T>,
+    pool: SharedPool<T>,
+}
+
+impl<T> PoolSender<T> {
+    /// Sends a value, evicting the oldest message of the pool's largest backlog if the pool
+    /// is exhausted.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten, which may belong to other channels of the pool
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut channels = self.pool.inner.lock();
+        if self.sender.is_disconnected() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        PoolInner::prune(&mut channels);
+        let mut drained = Vec::new();
+        while self.pool.inner.len(&channels) >= self.pool.inner.cap {
+            let largest = channels
+                .iter()
+                .rev()
+                .max_by_key(|channel| channel.len())
+                .and_then(|channel| channel.evict_oldest());
+            match largest {
+                Some(evicted) => drained.push(evicted),
+                None => break,
+            }
+        }
+        // Still holding the lock, so no other channel takes the freed slots
+        match self.sender.send_overwrite(value)? {
+            Some(overwritten) => drained.extend(overwritten),
+            None if drained.is_empty() => return Ok(None),
+            None => (),
+        }
+        Ok(Some(drained))
+    }
+
+    /// Asynchronously sends a value.
+    ///
+    /// This is the async version of [`send_overwrite`](PoolSender::send_overwrite).
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.send_overwrite(value)
+    }
+
+    /// Returns the pool this channel draws from.
+    pub fn pool(&self) -> &SharedPool<T> {
+        &self.pool
+    }
+
+    /// Returns the underlying sender, which bypasses the pool's budget.
+    pub fn sender(&self) -> &OverwriteSender<T> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for PoolSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for PoolSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoolSender")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dropped_channels_release_their_share() {
+        let pool = SharedPool::new(2);
+        let (first, first_rx) = pool.channel("first");
+        let (second, second_rx) = pool.channel("second");
+        first.send_overwrite(1).unwrap();
+        first.send_overwrite(2).unwrap();
+        assert_eq!(pool.len(), 2);
+
+        drop(first_rx);
+        assert_eq!(pool.channel_count(), 1);
+        assert_eq!(second.send_overwrite(3).unwrap(), None);
+        assert_eq!(second.send_overwrite(4).unwrap(), None);
+        assert_eq!(second.send_overwrite(5).unwrap(), Some(vec![3]));
+        assert!(first.send_overwrite(6).unwrap_err().is_disconnected());
+        assert_eq!(second_rx.stats().evicted, 1);
+    }
+}
@@ -0,0 +1,142 @@
+//! Receive-side prefetching into a consumer-local buffer.
+
+use crate::OverwriteReceiver;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// A batch of messages moved out of a channel by [`OverwriteReceiver::prefetch`].
+///
+/// Messages are served by [`recv_local`](Prefetch::recv_local), oldest first, without
+/// touching the channel. Messages still buffered when the batch is dropped are put back
+/// at the front of the channel, ahead of anything sent since, so an early `break` out of
+/// a consumer loop loses nothing.
+pub struct Prefetch<'a, T> {
+    receiver: &'a OverwriteReceiver<T>,
+    buffer: VecDeque<T>,
+}
+
+impl<T> Prefetch<'_, T> {
+    /// Returns the next prefetched message, or `None` once the batch is exhausted.
+    pub fn recv_local(&mut self) -> Option<T> {
+        self.buffer.pop_front()
+    }
+
+    /// Returns the number of prefetched messages not yet received.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns `true` once every prefetched message has been received.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+}
+
+impl<T> Iterator for Prefetch<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.recv_local()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.buffer.len(), Some(self.buffer.len()))
+    }
+}
+
+impl<T> ExactSizeIterator for Prefetch<'_, T> {}
+
+impl<T> fmt::Debug for Prefetch<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Prefetch")
+            .field("channel", &self.receiver.channel_id())
+            .field("len", &self.buffer.len())
+            .finish()
+    }
+}
+
+impl<T> Drop for Prefetch<'_, T> {
+    fn drop(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let shared = &self.receiver.shared;
+        let _sending = shared.lock_sends();
+        // Without a sender the leftovers cannot be put back, and go with the batch
+        let Some(sender) = shared.weak_sender.upgrade() else {
+            return;
+        };
+        let mut leftovers: Vec<T> = self.buffer.drain(..).collect();
+        leftovers.extend(self.receiver.receiver.drain());
+        shared.requeue_locked(&sender, &self.receiver.receiver, leftovers);
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Moves up to `n` of the oldest queued messages into a consumer-local buffer.
+    ///
+    /// The batch is taken in a single pass while sends wait, and the returned [`Prefetch`]
+    /// then serves the messages with [`recv_local`](Prefetch::recv_local) without touching
+    /// shared state, which suits tight consumer loops. Prefetched messages are gone from
+    /// the channel as far as other receivers and [`len`](Self::len) are concerned. Like any
+    /// receive, it records a [`heartbeat`](Self::heartbeat).
+    ///
+    /// The batch is empty if nothing is queued; this never blocks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    ///
+    /// let (sender, receiver) = bounded(8);
+    /// for i in 0..5 {
+    ///     sender.send_overwrite(i).unwrap();
+    /// }
+    ///
+    /// let mut batch = receiver.prefetch(3);
+    /// assert_eq!(receiver.len(), 2);
+    /// let mut sum = 0;
+    /// while let Some(value) = batch.recv_local() {
+    ///     sum += value;
+    /// }
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn prefetch(&self, n: usize) -> Prefetch<'_, T> {
+        let buffer = {
+            let _sending = self.shared.lock_sends();
+            if self.is_retired() {
+                VecDeque::new()
+            } else if self.receiver.len() <= n {
+                self.receiver.drain().collect()
+            } else {
+                self.receiver.try_iter().take(n).collect()
+            }
+        };
+        self.heartbeat();
+        Prefetch {
+            receiver: self,
+            buffer,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    #[test]
+    fn test_leftovers_return_to_the_front() {
+        let (sender, receiver) = bounded(4);
+        for i in 0..3 {
+            sender.send_overwrite(i).unwrap();
+        }
+
+        let mut batch = receiver.prefetch(2);
+        assert_eq!(batch.recv_local(), Some(0));
+        sender.send_overwrite(3).unwrap();
+        drop(batch);
+
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(receiver.stats().evicted, 0);
+    }
+}
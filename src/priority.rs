@@ -0,0 +1,199 @@
+//! Priority-aware eviction over a single shared capacity.
+
+use crate::{LaneReceiver, OverwriteSender, SendOverwriteError, bounded};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Creates a channel of `levels` priorities sharing a total capacity of `cap` messages.
+///
+/// Unlike [`bounded_overwrite_lanes`](crate::bounded_overwrite_lanes), where every lane has
+/// its own capacity, the priorities here compete for the same `cap` slots. When the channel
+/// is full, the oldest message of the lowest priority is evicted first, so low priority
+/// traffic gives way to high priority traffic. Priority `0` is the highest.
+///
+/// Each priority is kept in its own sub-queue, so finding the message to evict costs at most
+/// one check per priority level, however large the capacity. The receiving half is a
+/// [`LaneReceiver`] with one lane per priority, draining higher priorities first.
+///
+/// # Panics
+///
+/// Panics if `cap` or `levels` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded_priority;
+///
+/// const HIGH: usize = 0;
+/// const LOW: usize = 1;
+///
+/// let (sender, receiver) = bounded_priority(2, 2);
+/// sender.send_priority(LOW, "sample").unwrap();
+/// sender.send_priority(HIGH, "alarm 1").unwrap();
+///
+/// // The channel is full, so the low priority message gives way
+/// assert_eq!(sender.send_priority(HIGH, "alarm 2").unwrap(), Some(vec!["sample"]));
+///
+/// // Nothing of lower or equal priority is left to evict for a low priority message
+/// assert!(sender.send_priority(LOW, "sample 2").unwrap_err().is_rejected());
+///
+/// assert_eq!(receiver.recv().unwrap(), "alarm 1");
+/// assert_eq!(receiver.recv().unwrap(), "alarm 2");
+/// ```
+#[track_caller]
+pub fn bounded_priority<T>(cap: usize, levels: usize) -> (PrioritySender<T>, LaneReceiver<T>) {
+    assert!(levels > 0, "at least one priority level is required");
+    // Every sub-queue can hold the whole capacity, which the sender enforces in total
+    let (senders, receivers) = (0..levels).map(|_| bounded(cap)).unzip();
+    let sender = PrioritySender {
+        levels: senders,
+        cap,
+        send_lock: Arc::new(Mutex::new(())),
+    };
+    (sender, LaneReceiver::new(receivers))
+}
+
+/// The sending half of a [`bounded_priority`] channel.
+pub struct PrioritySender<T> {
+    levels: Vec<OverwriteSender<T>>,
+    cap: usize,
+    /// Serializes sends across clones, so that the total capacity is never exceeded.
+    send_lock: Arc<Mutex<()>>,
+}
+
+impl<T> PrioritySender<T> {
+    /// Sends a value at a priority, evicting the oldest message of the lowest priority if
+    /// the channel is full.
+    ///
+    /// Only messages of the same or a lower priority than `value` are ever evicted.
+    ///
+    /// # Arguments
+    ///
+    /// * `priority` - The priority of the message, `0` being the highest
+    /// * `value` - The value to send
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the message that was overwritten
+    /// - `Err(SendOverwriteError<T>)` - All receivers have been dropped, or the channel is
+    ///   full of messages of a higher priority
+    ///
+    /// # Panics
+    ///
+    /// Panics if `priority` is out of range.
+    pub fn send_priority(
+        &self,
+        priority: usize,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let level = &self.levels[priority];
+        let _sending = self.send_lock.lock().unwrap_or_else(|e| e.into_inner());
+        if level.is_disconnected() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        let mut drained = Vec::new();
+        while self.len() >= self.cap {
+            let lowest = self.levels[priority..]
+                .iter()
+                .rev()
+                .find(|level| !level.is_empty());
+            match lowest {
+                Some(lowest) => drained.extend(lowest.evict_oldest()),
+                None => return Err(SendOverwriteError::Rejected(value)),
+            }
+        }
+        match level.send_overwrite(value)? {
+            Some(overwritten) => drained.extend(overwritten),
+            None if drained.is_empty() => return Ok(None),
+            None => (),
+        }
+        Ok(Some(drained))
+    }
+
+    /// Asynchronously sends a value at a priority.
+    ///
+    /// This is the async version of [`send_priority`](PrioritySender::send_priority).
+    pub async fn send_priority_async(
+        &self,
+        priority: usize,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        self.send_priority(priority, value)
+    }
+
+    /// Returns the total number of messages queued across all priorities.
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(|level| level.len()).sum()
+    }
+
+    /// Returns `true` if no messages are queued.
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(|level| level.is_empty())
+    }
+
+    /// Returns the total capacity shared by all priorities.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Returns the number of priority levels.
+    pub fn level_count(&self) -> usize {
+        self.levels.len()
+    }
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            levels: self.levels.clone(),
+            cap: self.cap,
+            send_lock: self.send_lock.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for PrioritySender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrioritySender")
+            .field("capacity", &self.cap)
+            .field("levels", &self.levels)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_priority_evicts_lowest_oldest_first() {
+        let (sender, receiver) = bounded_priority(3, 3);
+        sender.send_priority(1, "mid 1").unwrap();
+        sender.send_priority(2, "low 1").unwrap();
+        sender.send_priority(2, "low 2").unwrap();
+        assert_eq!(
+            sender.send_priority(0, "high").unwrap(),
+            Some(vec!["low 1"])
+        );
+        assert_eq!(
+            sender.send_priority(1, "mid 2").unwrap(),
+            Some(vec!["low 2"])
+        );
+        assert_eq!(
+            sender.send_priority(1, "mid 3").unwrap(),
+            Some(vec!["mid 1"])
+        );
+        assert_eq!(sender.len(), 3);
+        let received: Vec<_> = std::iter::from_fn(|| receiver.try_recv().ok()).collect();
+        assert_eq!(received, vec!["high", "mid 2", "mid 3"]);
+    }
+
+    #[test]
+    fn test_priority_disconnected() {
+        let (sender, receiver) = bounded_priority(1, 2);
+        drop(receiver);
+        assert!(sender.send_priority(1, 1).unwrap_err().is_disconnected());
+    }
+}
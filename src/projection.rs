@@ -0,0 +1,182 @@
+//! Projections folding a channel's events into a state.
+
+use crate::counter::Counter;
+use crate::{OverwriteReceiver, Subscription};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Starts a consumer maintaining a projection of the events received on `receiver`,
+/// starting from `initial` and applying `fold` to each event in order.
+///
+/// The consumer runs on a thread of its own until the returned [`Projection`] is dropped
+/// or the channel is disconnected. Events overwritten before the consumer got to them are
+/// never folded in, so the projection counts them: a projection that has
+/// [lost](Projection::lost) events is only an approximation of the event stream, which
+/// readers can check with [`is_approximate`](Projection::is_approximate). The count is
+/// only accurate if the projection owns the channel's sole receiver, since messages taken
+/// by other receivers are not counted.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{bounded, project};
+///
+/// let (sender, receiver) = bounded(2);
+/// for deposit in [10, 20, 30] {
+///     sender.send_overwrite(deposit).unwrap();
+/// }
+///
+/// let balance = project(receiver, 0, |balance, deposit| *balance += deposit);
+/// drop(sender);
+///
+/// // The first deposit was overwritten before the projection started
+/// assert_eq!(balance.lost(), 1);
+/// assert!(balance.is_approximate());
+/// assert_eq!(balance.into_state(), 50);
+/// ```
+pub fn project<T, S, F>(receiver: OverwriteReceiver<T>, initial: S, mut fold: F) -> Projection<S>
+where
+    T: Send + 'static,
+    S: Send + 'static,
+    F: FnMut(&mut S, T) + Send + 'static,
+{
+    let shared = receiver.shared.clone();
+    let evicted = shared.evicted.get();
+    let folded = Arc::new(Mutex::new(Folded {
+        state: initial,
+        applied: 0,
+        lost: Counter::since(evicted, 0),
+        evicted,
+    }));
+    let projecting = folded.clone();
+    let subscription = Subscription::spawn(receiver, move |event| {
+        let mut folded = projecting.lock().unwrap_or_else(|e| e.into_inner());
+        fold(&mut folded.state, event);
+        folded.applied += 1;
+        // Read after the event was received, so that every event lost before it is counted
+        let evicted = shared.evicted.get();
+        folded.lost += Counter::since(evicted, folded.evicted);
+        folded.evicted = evicted;
+    });
+    Projection {
+        folded,
+        subscription,
+    }
+}
+
+/// A state maintained by folding a channel's events, returned by [`project`].
+///
+/// Dropping the projection stops its consumer.
+pub struct Projection<S> {
+    folded: Arc<Mutex<Folded<S>>>,
+    subscription: Subscription,
+}
+
+struct Folded<S> {
+    state: S,
+    applied: u64,
+    lost: u64,
+    /// The channel's eviction count when `lost` was last brought up to date.
+    evicted: u64,
+}
+
+impl<S> Projection<S> {
+    /// Returns a snapshot of the state.
+    pub fn state(&self) -> S
+    where
+        S: Clone,
+    {
+        self.lock().state.clone()
+    }
+
+    /// Calls `f` with the state, without cloning it.
+    pub fn with_state<R>(&self, f: impl FnOnce(&S) -> R) -> R {
+        f(&self.lock().state)
+    }
+
+    /// Returns the number of events folded into the state.
+    pub fn applied(&self) -> u64 {
+        self.lock().applied
+    }
+
+    /// Returns the number of events overwritten before the consumer received them, among
+    /// those that came before the projection started or before the last event folded in.
+    pub fn lost(&self) -> u64 {
+        self.lock().lost
+    }
+
+    /// Returns `true` if events were lost, so that the state only approximates the event
+    /// stream.
+    pub fn is_approximate(&self) -> bool {
+        self.lost() > 0
+    }
+
+    /// Returns `true` once the consumer has exited, as it does when the channel is
+    /// disconnected and drained.
+    pub fn is_finished(&self) -> bool {
+        self.subscription.is_finished()
+    }
+
+    /// Waits for the consumer to fold in every event up to the channel's disconnection,
+    /// and returns the final state.
+    ///
+    /// Should `fold` have panicked, the consumer stopped there, and the state is returned
+    /// as the panic left it.
+    pub fn into_state(self) -> S {
+        self.subscription.join();
+        // The consumer's handle on the state went away with its thread
+        let Ok(folded) = Arc::try_unwrap(self.folded) else {
+            unreachable!("the consumer has exited");
+        };
+        folded.into_inner().unwrap_or_else(|e| e.into_inner()).state
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Folded<S>> {
+        self.folded.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Projection<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let folded = self.lock();
+        f.debug_struct("Projection")
+            .field("state", &folded.state)
+            .field("applied", &folded.applied)
+            .field("lost", &folded.lost)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    #[test]
+    fn test_lost_events_are_counted_as_they_are_folded() {
+        let (sender, receiver) = bounded(1);
+        let (gate, opened) = flume::bounded::<()>(0);
+        let log = project(receiver, Vec::new(), move |log, event| {
+            let _ = opened.recv();
+            log.push(event);
+        });
+        assert!(!log.is_approximate());
+
+        // The consumer holds the first event while the next ones overwrite each other
+        sender.send_overwrite(1).unwrap();
+        while !sender.is_empty() {
+            std::thread::yield_now();
+        }
+        for event in 2..5 {
+            sender.send_overwrite(event).unwrap();
+        }
+        gate.send(()).unwrap();
+        gate.send(()).unwrap();
+        while log.applied() < 2 {
+            std::thread::yield_now();
+        }
+        assert_eq!(log.lost(), 2);
+        drop(sender);
+        assert_eq!(log.into_state(), vec![1, 4]);
+    }
+}
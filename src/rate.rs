@@ -0,0 +1,185 @@
+//! Paced delivery of messages to a consumer.
+
+use crate::OverwriteReceiver;
+use flume::{RecvError, TryRecvError};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A receiver delivering at most a fixed number of messages per second, created with
+/// [`OverwriteReceiver::rate_limited`].
+///
+/// Between deliveries the channel keeps absorbing messages and overwriting the oldest, so
+/// each delivery hands over the oldest message still queued when the consumer's turn comes.
+/// This suits forwarding to rate-limited downstream APIs, where a backlog would only go
+/// stale.
+pub struct RateLimited<T> {
+    receiver: OverwriteReceiver<T>,
+    interval: Duration,
+    /// The earliest time the next message may be delivered.
+    next: Mutex<Option<Instant>>,
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Paces delivery to at most `max_per_sec` messages per second.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_per_sec` is not a positive, finite number.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let (sender, receiver) = bounded(1);
+    /// let receiver = receiver.rate_limited(100.0);
+    ///
+    /// let start = Instant::now();
+    /// for i in 0..3 {
+    ///     sender.send_overwrite(i).unwrap();
+    ///     receiver.recv().unwrap();
+    /// }
+    /// // The first message is delivered at once, the others 10ms apart
+    /// assert!(start.elapsed() >= Duration::from_millis(20));
+    /// ```
+    #[track_caller]
+    pub fn rate_limited(self, max_per_sec: f64) -> RateLimited<T> {
+        assert!(
+            max_per_sec > 0.0 && max_per_sec.is_finite(),
+            "rate must be positive and finite"
+        );
+        RateLimited {
+            receiver: self,
+            interval: Duration::from_secs_f64(1.0 / max_per_sec),
+            next: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> RateLimited<T> {
+    /// Returns how long the consumer must wait before its next delivery.
+    fn wait(&self) -> Duration {
+        let next = *self.next.lock().unwrap_or_else(|e| e.into_inner());
+        next.map_or(Duration::ZERO, |next| {
+            next.saturating_duration_since(self.receiver.shared.clock.now())
+        })
+    }
+
+    /// Records a delivery, starting the wait for the next one.
+    fn delivered(&self) {
+        let next = self.receiver.shared.clock.now() + self.interval;
+        *self.next.lock().unwrap_or_else(|e| e.into_inner()) = Some(next);
+        self.receiver.heartbeat();
+    }
+
+    /// Waits for the consumer's turn, then receives a message, blocking until one arrives.
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let wait = self.wait();
+        if !wait.is_zero() {
+            self.receiver.shared.clock.sleep(wait);
+        }
+        let value = self.receiver.recv()?;
+        self.delivered();
+        Ok(value)
+    }
+
+    /// Asynchronously waits for the consumer's turn, then receives a message.
+    ///
+    /// This is the async version of [`recv`](RateLimited::recv).
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        let wait = self.wait();
+        if !wait.is_zero() {
+            self.receiver.shared.clock.sleep_async(wait).await;
+        }
+        let value = self.receiver.recv_async().await?;
+        self.delivered();
+        Ok(value)
+    }
+
+    /// Receives a message if it is the consumer's turn and one is queued, without waiting.
+    ///
+    /// Returns [`TryRecvError::Empty`] while the consumer must still wait.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        if !self.wait().is_zero() {
+            return Err(TryRecvError::Empty);
+        }
+        let value = self.receiver.try_recv()?;
+        self.delivered();
+        Ok(value)
+    }
+
+    /// Returns the minimum time between two deliveries.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the underlying receiver, which is not paced.
+    pub fn receiver(&self) -> &OverwriteReceiver<T> {
+        &self.receiver
+    }
+
+    /// Removes the pacing, returning the underlying receiver.
+    pub fn into_inner(self) -> OverwriteReceiver<T> {
+        self.receiver
+    }
+}
+
+impl<T> fmt::Debug for RateLimited<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimited")
+            .field("interval", &self.interval)
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_delivers_latest_after_waiting() {
+        use crate::{Builder, MockClock};
+        use flume::TryRecvError;
+        use std::time::Duration;
+
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(1).clock(clock.clone()).build();
+        let receiver = receiver.rate_limited(1.0);
+
+        sender.send_overwrite(0).unwrap();
+        assert_eq!(receiver.try_recv(), Ok(0));
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        // Waiting advances the mock clock by exactly the interval
+        assert_eq!(receiver.recv(), Ok(2));
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+        assert_eq!(receiver.receiver().stats().evicted, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "test-util")]
+    fn test_async_wait_follows_the_clock() {
+        use crate::{Builder, MockClock};
+        use futures::executor::block_on;
+        use std::time::Duration;
+
+        let clock = MockClock::new();
+        let (sender, receiver) = Builder::new(1).clock(clock.clone()).build();
+        let receiver = receiver.rate_limited(0.5);
+
+        sender.send_overwrite(0).unwrap();
+        assert_eq!(block_on(receiver.recv_async()), Ok(0));
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+        sender.send_overwrite(1).unwrap();
+
+        // Waiting for the next turn advances the mock clock instead of real time
+        assert_eq!(block_on(receiver.recv_async()), Ok(1));
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+    }
+}
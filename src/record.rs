@@ -0,0 +1,517 @@
+//! Recording of a channel's traffic to a file, and its deterministic replay.
+//!
+//! A recording starts with a header holding the channel's capacity, followed by one entry
+//! per event: its kind, the nanoseconds since recording started and the length of the
+//! encoded message, then the message itself. Integers are little-endian.
+
+use crate::{Builder, OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use flume::{RecvError, TryRecvError};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Identifies a recording file.
+const MAGIC: &[u8; 4] = b"FOVR";
+const VERSION: u8 = 1;
+
+const SEND: u8 = 0;
+const RECV: u8 = 1;
+const EVICT: u8 = 2;
+
+/// How long a recording receiver waits for a recorded send before checking the channel
+/// again, in case a message came from an unrecorded sender.
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A message that can be written to and read back from a recording.
+pub trait Recordable: Sized {
+    /// Appends the encoded message to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a message from the bytes written by [`encode`](Recordable::encode),
+    /// returning `None` if they are invalid.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! recordable_int {
+    ($($int:ty),*) => {
+        $(
+            impl Recordable for $int {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    bytes.try_into().ok().map(Self::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+recordable_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Recordable for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl Recordable for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+/// Something that happened to a message of a recorded channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<T> {
+    /// The message was sent.
+    Send(T),
+    /// The message was received.
+    Recv(T),
+    /// The message was evicted by the send recorded before it.
+    Evict(T),
+}
+
+/// An event of a recording, along with when it happened.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry<T> {
+    /// The time since recording started, measured on the channel's [`Clock`](crate::Clock).
+    pub at: Duration,
+    /// What happened.
+    pub event: Event<T>,
+}
+
+/// The file shared by both halves of a recorded channel.
+struct Recorder {
+    log: Mutex<Log>,
+    /// Signalled after every recorded send, and when a recording sender is dropped.
+    sent: Condvar,
+    start: Instant,
+}
+
+struct Log {
+    file: BufWriter<File>,
+    /// The first error met while writing, reported by the next flush.
+    error: Option<io::Error>,
+}
+
+impl Recorder {
+    fn lock(&self) -> MutexGuard<'_, Log> {
+        self.log.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.start)
+    }
+}
+
+impl Log {
+    /// Appends an entry whose message is already encoded.
+    fn write_encoded(&mut self, kind: u8, at: Duration, encoded: &[u8]) {
+        let file = &mut self.file;
+        let result = file
+            .write_all(&[kind])
+            .and_then(|()| file.write_all(&(at.as_nanos() as u64).to_le_bytes()))
+            .and_then(|()| file.write_all(&(encoded.len() as u32).to_le_bytes()))
+            .and_then(|()| file.write_all(encoded));
+        if let Err(error) = result {
+            self.error.get_or_insert(error);
+        }
+    }
+
+    fn write<T: Recordable>(&mut self, kind: u8, at: Duration, value: &T) {
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded);
+        self.write_encoded(kind, at, &encoded);
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => self.file.flush(),
+        }
+    }
+}
+
+impl<T: Recordable> Builder<T> {
+    /// Creates the channel in record mode, logging every send, receive and eviction made
+    /// through the returned halves to a compact binary file at `path`.
+    ///
+    /// The file can be read back with [`Replay::open`] to reproduce the same sequence into
+    /// a fresh channel, so that a consumer bug seen once can be debugged deterministically.
+    /// Sends and receives are serialized while recording, so that the file holds them in
+    /// the order they took effect. Messages sent or received through the underlying
+    /// endpoints are not recorded.
+    ///
+    /// Replay uses a plain channel of the same capacity, so channels relying on other
+    /// builder options, such as [`protect`](Builder::protect), may not replay faithfully;
+    /// [`Replay::run`] reports where they diverge.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or its header written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::Builder;
+    /// use flume_overwrite::record::Replay;
+    ///
+    /// let path = std::env::temp_dir().join("flume-overwrite-record-doctest.bin");
+    /// let (sender, receiver) = Builder::<u32>::new(2).record(&path).unwrap();
+    /// sender.send_overwrite(1).unwrap();
+    /// sender.send_overwrite(2).unwrap();
+    /// sender.send_overwrite(3).unwrap();
+    /// assert_eq!(receiver.recv().unwrap(), 2);
+    /// sender.flush().unwrap();
+    ///
+    /// let mut consumed = Vec::new();
+    /// let replay = Replay::<u32>::open(&path).unwrap();
+    /// replay.run(|value| consumed.push(value)).unwrap();
+    /// assert_eq!(consumed, vec![2]);
+    /// # std::fs::remove_file(path).unwrap();
+    /// ```
+    pub fn record(
+        self,
+        path: impl AsRef<Path>,
+    ) -> io::Result<(RecordingSender<T>, RecordingReceiver<T>)> {
+        let capacity = self.cap.get();
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&(capacity as u64).to_le_bytes())?;
+        let (sender, receiver) = self.build();
+        let recorder = Arc::new(Recorder {
+            log: Mutex::new(Log { file, error: None }),
+            sent: Condvar::new(),
+            start: sender.shared.clock.now(),
+        });
+        let sender = RecordingSender {
+            sender,
+            recorder: recorder.clone(),
+        };
+        let receiver = RecordingReceiver { receiver, recorder };
+        Ok((sender, receiver))
+    }
+}
+
+/// The sending half of a channel created with [`Builder::record`].
+pub struct RecordingSender<T> {
+    sender: OverwriteSender<T>,
+    recorder: Arc<Recorder>,
+}
+
+impl<T: Recordable> RecordingSender<T> {
+    /// Sends a value with overwrite semantics, recording the send and any eviction.
+    ///
+    /// Failed sends are not recorded.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten
+    /// - `Err(SendOverwriteError<T>)` - The message could not be sent
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let mut encoded = Vec::new();
+        value.encode(&mut encoded);
+        let mut log = self.recorder.lock();
+        let overwritten = self.sender.send_overwrite(value)?;
+        let at = self.recorder.elapsed(self.sender.shared.clock.now());
+        log.write_encoded(SEND, at, &encoded);
+        for evicted in overwritten.iter().flatten() {
+            log.write(EVICT, at, evicted);
+        }
+        drop(log);
+        self.recorder.sent.notify_all();
+        Ok(overwritten)
+    }
+
+    /// Writes buffered entries to the file, reporting the first error met while recording.
+    pub fn flush(&self) -> io::Result<()> {
+        self.recorder.lock().flush()
+    }
+}
+
+impl<T> RecordingSender<T> {
+    /// Returns the underlying sender, whose sends are not recorded.
+    pub fn sender(&self) -> &OverwriteSender<T> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for RecordingSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<T> Drop for RecordingSender<T> {
+    fn drop(&mut self) {
+        // Lets a waiting receiver notice once the last sender is gone
+        let _log = self.recorder.lock();
+        self.recorder.sent.notify_all();
+    }
+}
+
+impl<T> fmt::Debug for RecordingSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingSender")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+/// The receiving half of a channel created with [`Builder::record`].
+pub struct RecordingReceiver<T> {
+    receiver: OverwriteReceiver<T>,
+    recorder: Arc<Recorder>,
+}
+
+impl<T: Recordable> RecordingReceiver<T> {
+    /// Receives a message and records it, blocking until one arrives.
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut log = self.recorder.lock();
+        loop {
+            match self.try_recv_locked(&mut log) {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Disconnected) => return Err(RecvError::Disconnected),
+                Err(TryRecvError::Empty) => {
+                    log = self
+                        .recorder
+                        .sent
+                        .wait_timeout(log, RECV_POLL_INTERVAL)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                }
+            }
+        }
+    }
+
+    /// Receives a message if one is queued and records it, without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        self.try_recv_locked(&mut self.recorder.lock())
+    }
+
+    fn try_recv_locked(&self, log: &mut Log) -> Result<T, TryRecvError> {
+        let value = self.receiver.try_recv()?;
+        let at = self.recorder.elapsed(self.receiver.shared.clock.now());
+        log.write(RECV, at, &value);
+        self.receiver.heartbeat();
+        Ok(value)
+    }
+}
+
+impl<T> RecordingReceiver<T> {
+    /// Returns the underlying receiver, whose receives are not recorded.
+    pub fn receiver(&self) -> &OverwriteReceiver<T> {
+        &self.receiver
+    }
+}
+
+impl<T> fmt::Debug for RecordingReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordingReceiver")
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+/// A recording read back from a file written in record mode, see [`Builder::record`].
+#[derive(Clone, Debug)]
+pub struct Replay<T> {
+    capacity: usize,
+    entries: Vec<Entry<T>>,
+}
+
+impl<T: Recordable> Replay<T> {
+    /// Reads the recording at `path`.
+    ///
+    /// A recording cut short, as left by a process that crashed while recording, is read
+    /// up to its last complete entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, is not a recording, or holds a message
+    /// that cannot be decoded.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0; 13];
+        file.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a flume-overwrite recording",
+            ));
+        }
+        let capacity = u64::from_le_bytes(header[5..].try_into().unwrap()) as usize;
+        let mut entries = Vec::new();
+        let mut prefix = [0; 13];
+        while read_exact_or_eof(&mut file, &mut prefix)? {
+            let at = Duration::from_nanos(u64::from_le_bytes(prefix[1..9].try_into().unwrap()));
+            let len = u32::from_le_bytes(prefix[9..].try_into().unwrap()) as usize;
+            let mut encoded = vec![0; len];
+            if !read_exact_or_eof(&mut file, &mut encoded)? {
+                break;
+            }
+            let value = T::decode(&encoded)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "undecodable message"))?;
+            let event = match prefix[0] {
+                SEND => Event::Send(value),
+                RECV => Event::Recv(value),
+                EVICT => Event::Evict(value),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "unknown event kind",
+                    ));
+                }
+            };
+            entries.push(Entry { at, event });
+        }
+        Ok(Self { capacity, entries })
+    }
+}
+
+impl<T> Replay<T> {
+    /// Returns the capacity of the recorded channel.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the recorded entries, in the order they happened.
+    pub fn entries(&self) -> &[Entry<T>] {
+        &self.entries
+    }
+
+    /// Reproduces the recording into a fresh channel, handing every message the consumer
+    /// received to `consume`, in order.
+    ///
+    /// Each recorded send is made again, and each recorded receive takes the next message
+    /// from the channel, without waiting. Timing is not reproduced: only the order of events
+    /// matters to the channel.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Divergence`] as soon as the fresh channel evicts or delivers a different
+    /// message than the recorded one.
+    pub fn run(&self, mut consume: impl FnMut(T)) -> Result<(), Divergence>
+    where
+        T: Clone + PartialEq,
+    {
+        let (sender, receiver) = bounded(self.capacity);
+        let mut expected_evictions = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let diverged = Divergence { entry: index };
+            match &entry.event {
+                Event::Send(value) => {
+                    let evicted = sender.send_overwrite(value.clone()).map_err(|_| diverged)?;
+                    expected_evictions = evicted.unwrap_or_default();
+                    expected_evictions.reverse();
+                }
+                Event::Evict(value) => {
+                    if expected_evictions.pop().as_ref() != Some(value) {
+                        return Err(diverged);
+                    }
+                }
+                Event::Recv(value) => {
+                    if !expected_evictions.is_empty() {
+                        return Err(diverged);
+                    }
+                    match receiver.try_recv() {
+                        Ok(received) if received == *value => consume(received),
+                        _ => return Err(diverged),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads exactly `buf.len()` bytes, returning `false` if the reader ends first.
+fn read_exact_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error),
+    }
+}
+
+/// An error returned by [`Replay::run`] when the fresh channel does not behave as the
+/// recorded one did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The index of the first entry that could not be reproduced.
+    pub entry: usize,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "replay diverged from the recording at entry {}",
+            self.entry
+        )
+    }
+}
+
+impl Error for Divergence {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_replays_concurrent_recording() {
+        let path =
+            std::env::temp_dir().join(format!("flume-overwrite-record-{}.bin", std::process::id()));
+        let (sender, receiver) = Builder::<String>::new(3).record(&path).unwrap();
+        let consumer = thread::spawn(move || {
+            let mut consumed = Vec::new();
+            while let Ok(value) = receiver.recv() {
+                consumed.push(value);
+            }
+            consumed
+        });
+        for i in 0..200 {
+            sender.send_overwrite(i.to_string()).unwrap();
+        }
+        sender.flush().unwrap();
+        drop(sender);
+        let consumed = consumer.join().unwrap();
+
+        let replay = Replay::<String>::open(&path).unwrap();
+        assert_eq!(replay.capacity(), 3);
+        let sends = replay
+            .entries()
+            .iter()
+            .filter(|entry| matches!(entry.event, Event::Send(_)))
+            .count();
+        assert_eq!(sends, 200);
+
+        let mut replayed = Vec::new();
+        replay.run(|value| replayed.push(value)).unwrap();
+        assert_eq!(replayed, consumed);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+//! Replacement of a channel's consumer under live senders.
+
+use crate::{OverwriteReceiver, OverwriteSender, ReceiverId};
+use flume::r#async::RecvFut;
+use flume::{Receiver, RecvError, TryRecvError};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+impl<T> OverwriteSender<T> {
+    /// Returns a fresh receiver for the channel, retiring every existing receiver.
+    ///
+    /// This restarts a crashed or wedged consumer without recreating the senders feeding
+    /// it: the new consumer picks up the queued messages, while the old one finishes what
+    /// it already took. The receive methods of a retired receiver report the channel as
+    /// disconnected, its `Stream` ends, and so do its other consumer loops, such as
+    /// [`for_each_resilient`](OverwriteReceiver::for_each_resilient). A receive already
+    /// blocked when the receiver is retired may still take one last message, and the flume
+    /// receiver reached by dereferencing is not retired at all. Retired receivers keep the
+    /// channel connected until they are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use futures::StreamExt;
+    /// use futures::executor::block_on;
+    ///
+    /// let (sender, mut wedged) = bounded(4);
+    /// sender.send_overwrite("job 1").unwrap();
+    ///
+    /// let restarted = sender.replace_receiver();
+    /// assert!(wedged.is_retired());
+    /// assert_eq!(block_on(wedged.next()), None);
+    /// assert!(wedged.try_recv().is_err());
+    ///
+    /// assert_eq!(restarted.recv().unwrap(), "job 1");
+    /// ```
+    pub fn replace_receiver(&self) -> OverwriteReceiver<T> {
+        let epoch = self.shared.receiver_epoch.fetch_add(1, Ordering::AcqRel) + 1;
+        self.shared.receiver_count.fetch_add(1, Ordering::AcqRel);
+        OverwriteReceiver {
+            id: ReceiverId::next(),
+            receiver: self.receiver.clone(),
+            shared: self.shared.clone(),
+            conflate: false,
+            epoch,
+            selected: AtomicU64::new(self.shared.evicted.get()),
+            stream: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Returns `true` once a sender has replaced this receiver with
+    /// [`replace_receiver`](OverwriteSender::replace_receiver).
+    ///
+    /// Clones of a retired receiver are retired too.
+    pub fn is_retired(&self) -> bool {
+        self.epoch < self.shared.receiver_epoch.load(Ordering::Acquire)
+    }
+
+    /// Receives a message if one is queued, without blocking.
+    ///
+    /// This shadows flume's `try_recv`, and fails as disconnected once the receiver is
+    /// [retired](OverwriteReceiver::is_retired).
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let value = self.live().ok_or(TryRecvError::Disconnected)?.try_recv()?;
+        self.shared.observe_watermarks(&self.receiver);
+        Ok(value)
+    }
+
+    /// Receives a message, waiting asynchronously until one is available.
+    ///
+    /// This shadows flume's `recv_async`, and fails as disconnected once the receiver is
+    /// [retired](OverwriteReceiver::is_retired).
+    pub fn recv_async(&self) -> RecvFuture<'_, T> {
+        let recv = match self.live() {
+            Some(receiver) => receiver.recv_async(),
+            // A channel without senders, so the future fails right away
+            None => flume::bounded(0).1.into_recv_async(),
+        };
+        RecvFuture {
+            receiver: self,
+            recv,
+        }
+    }
+
+    /// Takes every queued message, oldest first.
+    ///
+    /// This shadows flume's `drain`, and takes nothing once the receiver is
+    /// [retired](OverwriteReceiver::is_retired).
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        // Draining takes the messages right away, iterating only hands them out
+        let drained = self.live().map(Receiver::drain);
+        self.shared.observe_watermarks(&self.receiver);
+        drained.into_iter().flatten()
+    }
+
+    /// Returns an iterator over the messages queued, ending once the channel is empty.
+    ///
+    /// This shadows flume's `try_iter`, see [`try_recv`](OverwriteReceiver::try_recv).
+    pub fn try_iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.try_recv().ok())
+    }
+
+    /// Returns an iterator blocking for each message, ending once the channel is
+    /// disconnected and drained.
+    ///
+    /// This shadows flume's `iter`, see [`recv`](OverwriteReceiver::recv).
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    /// Returns the flume receiver, unless this receiver is retired.
+    pub(crate) fn live(&self) -> Option<&Receiver<T>> {
+        (!self.is_retired()).then_some(&self.receiver)
+    }
+}
+
+/// The future returned by [`OverwriteReceiver::recv_async`].
+pub struct RecvFuture<'a, T> {
+    receiver: &'a OverwriteReceiver<T>,
+    recv: RecvFut<'a, T>,
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let received = Pin::new(&mut self.recv).poll(cx);
+        if let Poll::Ready(Ok(_)) = received {
+            self.receiver
+                .shared
+                .observe_watermarks(&self.receiver.receiver);
+        }
+        received
+    }
+}
+
+impl<T> fmt::Debug for RecvFuture<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecvFuture")
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+    use flume::{RecvError, RecvTimeoutError, TryRecvError};
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn test_replacing_revives_an_orphaned_channel() {
+        let (sender, receiver) = bounded(2);
+        drop(receiver);
+        assert!(sender.is_disconnected());
+
+        let receiver = sender.replace_receiver();
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(receiver.recv().unwrap(), 1);
+        assert!(!receiver.clone().is_retired());
+    }
+
+    #[test]
+    fn test_retired_receiver_receives_nothing() {
+        let (sender, retired) = bounded(4);
+        sender.send_overwrite(1).unwrap();
+        let receiver = sender.replace_receiver();
+        sender.send_overwrite(2).unwrap();
+
+        assert_eq!(retired.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(retired.recv(), Err(RecvError::Disconnected));
+        assert_eq!(
+            retired.recv_timeout(Duration::ZERO),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert_eq!(block_on(retired.recv_async()), Err(RecvError::Disconnected));
+        assert_eq!(retired.drain().count(), 0);
+        assert_eq!(retired.try_iter().count(), 0);
+        assert_eq!(retired.iter().count(), 0);
+        assert_eq!(retired.take_backlog(), Vec::<i32>::new());
+        assert_eq!(retired.recv_spin(10), Err(RecvError::Disconnected));
+        assert!(retired.try_recv_message().is_none());
+
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
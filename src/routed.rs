@@ -0,0 +1,380 @@
+//! Sticky routing of keyed messages to per-shard receivers.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+type KeyHasher<T> = dyn Fn(&T) -> u64 + Send + Sync;
+
+/// Creates a channel that routes messages to shards by key.
+///
+/// The channel is split into `shards` independent overwrite channels, each holding at most
+/// `cap` messages. The `key` function extracts a key from every message; messages with equal
+/// keys are always routed to the same shard, so per-key ordering is preserved while each
+/// shard overwrites its own oldest messages under load.
+///
+/// Returns the sender together with one receiver per shard, indexed by shard number. A shard
+/// count of zero is treated as one. Shards can be added and removed later, see
+/// [`RoutedSender::register`].
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::routed;
+///
+/// let (sender, receivers) = routed(4, 16, |(user, _): &(u32, &str)| *user);
+///
+/// sender.send_overwrite((7, "login")).unwrap();
+/// sender.send_overwrite((7, "logout")).unwrap();
+///
+/// let shard = &receivers[sender.shard_for(&(7, ""))];
+/// assert_eq!(shard.recv().unwrap(), (7, "login"));
+/// assert_eq!(shard.recv().unwrap(), (7, "logout"));
+/// ```
+pub fn routed<T, K, F>(
+    shards: usize,
+    cap: usize,
+    key: F,
+) -> (RoutedSender<T>, Vec<OverwriteReceiver<T>>)
+where
+    K: Hash,
+    F: Fn(&T) -> K + Send + Sync + 'static,
+{
+    let (senders, receivers): (Vec<_>, _) = (0..shards.max(1)).map(|_| bounded(cap)).unzip();
+    let sender = RoutedSender {
+        table: Arc::new(RwLock::new(Table {
+            next: senders.len(),
+            shards: senders.into_iter().enumerate().collect(),
+        })),
+        cap,
+        hasher: Arc::new(move |value: &T| {
+            let mut hasher = DefaultHasher::new();
+            key(value).hash(&mut hasher);
+            hasher.finish()
+        }),
+    };
+    (sender, receivers)
+}
+
+/// The sending half of a [`routed`] channel.
+///
+/// The routing table is shared between clones and can change while messages flow: shards
+/// join with [`register`](RoutedSender::register) and leave with
+/// [`unregister`](RoutedSender::unregister). Keys are assigned to shards by rendezvous
+/// hashing, so a change only moves the keys the joining shard takes over or the leaving
+/// shard gave up, and their queued messages move along with them, keeping per-key order.
+pub struct RoutedSender<T> {
+    table: Arc<RwLock<Table<T>>>,
+    cap: usize,
+    hasher: Arc<KeyHasher<T>>,
+}
+
+/// The shards of a [`routed`] channel, by shard number.
+struct Table<T> {
+    shards: BTreeMap<usize, OverwriteSender<T>>,
+    /// The number given to the next shard registered.
+    next: usize,
+}
+
+impl<T> Table<T> {
+    /// Returns the number of the shard a key hashing to `hash` is routed to.
+    fn route(&self, hash: u64) -> usize {
+        *self
+            .shards
+            .keys()
+            .max_by_key(|&&shard| weight(hash, shard))
+            .expect("the last shard cannot be unregistered")
+    }
+
+    /// Returns the shard a key hashing to `hash` is routed to.
+    fn shard(&self, hash: u64) -> &OverwriteSender<T> {
+        &self.shards[&self.route(hash)]
+    }
+}
+
+/// Returns the score of `shard` for a key hashing to `hash`; the key is routed to the shard
+/// scoring highest.
+fn weight(hash: u64, shard: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (hash, shard).hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<T> RoutedSender<T> {
+    /// Sends a value to the shard its key maps to, overwriting that shard's oldest message
+    /// if it is at capacity.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains
+    ///   the messages that were overwritten in its shard
+    /// - `Err(SendOverwriteError<T>)` - The shard's receivers have all been dropped
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let hash = (self.hasher)(&value);
+        self.read().shard(hash).send_overwrite(value)
+    }
+
+    /// Asynchronously sends a value to the shard its key maps to.
+    ///
+    /// This is the async version of [`send_overwrite`](RoutedSender::send_overwrite). The
+    /// shard is picked when the send starts, so a send racing a change of the routing table
+    /// may still land on the shard its key was routed to before.
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let hash = (self.hasher)(&value);
+        let shard = self.read().shard(hash).clone();
+        shard.send_overwrite_async(value).await
+    }
+
+    /// Returns the number of the shard `value` is routed to.
+    pub fn shard_for(&self, value: &T) -> usize {
+        self.read().route((self.hasher)(value))
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.read().shards.len()
+    }
+
+    /// Returns the numbers of the shards, in ascending order.
+    pub fn shards(&self) -> Vec<usize> {
+        self.read().shards.keys().copied().collect()
+    }
+
+    /// Returns the sender for a single shard, or `None` if there is no shard `shard`.
+    pub fn shard(&self, shard: usize) -> Option<OverwriteSender<T>> {
+        self.read().shards.get(&shard).cloned()
+    }
+
+    /// Adds a shard to the routing table, returning its number and receiver.
+    ///
+    /// Shards are numbered in the order they join, and numbers are never reused. The keys
+    /// routed to the new shard from now on take their queued messages along, in order, so
+    /// that its receiver sees each key's messages in the order they were sent. Should they
+    /// not all fit in the new shard, the oldest are overwritten.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::routed;
+    ///
+    /// let (sender, receivers) = routed(1, 16, |key: &u32| *key);
+    /// for key in 0..8 {
+    ///     sender.send_overwrite(key).unwrap();
+    /// }
+    ///
+    /// let (shard, receiver) = sender.register();
+    /// assert_eq!(shard, 1);
+    ///
+    /// // The keys the new shard took over moved with their messages
+    /// let moved: Vec<u32> = receiver.try_iter().collect();
+    /// assert!(moved.iter().all(|key| sender.shard_for(key) == 1));
+    /// assert_eq!(receivers[0].len() + moved.len(), 8);
+    /// ```
+    pub fn register(&self) -> (usize, OverwriteReceiver<T>) {
+        let (sender, receiver) = bounded(self.cap);
+        let mut table = self.write();
+        let shard = table.next;
+        table.next += 1;
+        let moved: Vec<T> = table
+            .shards
+            .values()
+            .flat_map(|other| {
+                other.cancel_where(|value| {
+                    let hash = (self.hasher)(value);
+                    weight(hash, shard) > weight(hash, table.route(hash))
+                })
+            })
+            .collect();
+        for value in moved {
+            let _ = sender.send_overwrite_discard(value);
+        }
+        table.shards.insert(shard, sender);
+        (shard, receiver)
+    }
+
+    /// Removes shard `shard` from the routing table, returning `false` if there is no such
+    /// shard, or if it is the last one, which cannot be removed.
+    ///
+    /// The messages still queued in the shard are routed again, in order, to the shards
+    /// that take over its keys, overwriting their oldest messages if need be. The shard's
+    /// receivers are then disconnected, unless senders for the shard obtained with
+    /// [`shard`](RoutedSender::shard) are still around.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::routed;
+    ///
+    /// let (sender, receivers) = routed(2, 16, |key: &u32| *key);
+    /// for key in 0..8 {
+    ///     sender.send_overwrite(key).unwrap();
+    /// }
+    ///
+    /// assert!(sender.unregister(1));
+    /// assert!(!sender.unregister(0));
+    /// assert_eq!(sender.shards(), vec![0]);
+    ///
+    /// // Shard 0 took over the keys of shard 1, whose receiver is now disconnected
+    /// assert_eq!(receivers[0].len(), 8);
+    /// assert!(receivers[1].recv().is_err());
+    /// ```
+    pub fn unregister(&self, shard: usize) -> bool {
+        let mut table = self.write();
+        if table.shards.len() == 1 {
+            return false;
+        }
+        let Some(removed) = table.shards.remove(&shard) else {
+            return false;
+        };
+        for value in removed.cancel_where(|_| true) {
+            let hash = (self.hasher)(&value);
+            let _ = table.shard(hash).send_overwrite_discard(value);
+        }
+        true
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, Table<T>> {
+        self.table.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Table<T>> {
+        self.table.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> Clone for RoutedSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            cap: self.cap,
+            hasher: self.hasher.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for RoutedSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RoutedSender")
+            .field("shards", &self.read().shards)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_routed_keeps_key_order() {
+        let (sender, receivers) = routed(3, 64, |(key, _): &(u8, u32)| *key);
+        for seq in 0..10 {
+            for key in 0..5 {
+                sender.send_overwrite((key, seq)).unwrap();
+            }
+        }
+        for key in 0..5 {
+            let shard = &receivers[sender.shard_for(&(key, 0))];
+            let seqs: Vec<u32> = shard
+                .try_iter()
+                .filter(|(k, _)| *k == key)
+                .map(|(_, seq)| seq)
+                .collect();
+            assert!(seqs.is_empty() || seqs.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn test_routed_overwrites_per_shard() {
+        let (sender, receivers) = routed(2, 1, |(key, _): &(u32, u32)| *key);
+        let first = sender.shard_for(&(0, 0));
+        let other = (1..)
+            .find(|key| sender.shard_for(&(*key, 0)) != first)
+            .unwrap();
+        assert_eq!(sender.send_overwrite((0, 0)).unwrap(), None);
+        assert_eq!(sender.send_overwrite((other, 0)).unwrap(), None);
+        assert_eq!(sender.send_overwrite((0, 1)).unwrap(), Some(vec![(0, 0)]));
+        let drained = block_on(sender.send_overwrite_async((other, 1))).unwrap();
+        assert_eq!(drained, Some(vec![(other, 0)]));
+        assert_eq!(receivers[first].recv().unwrap(), (0, 1));
+        assert_eq!(receivers[1 - first].recv().unwrap(), (other, 1));
+    }
+
+    /// Asserts that every key's messages are queued in the shard it is routed to, in order.
+    fn assert_routed(
+        sender: &RoutedSender<(u32, u32)>,
+        receivers: &[OverwriteReceiver<(u32, u32)>],
+    ) {
+        let mut seen = BTreeMap::new();
+        for (shard, receiver) in receivers.iter().enumerate() {
+            for (key, seq) in receiver.try_iter() {
+                assert_eq!(sender.shard_for(&(key, 0)), shard);
+                let last = seen.insert(key, seq);
+                assert!(last.is_none_or(|last| last < seq));
+            }
+        }
+        assert_eq!(seen.len(), 20);
+    }
+
+    #[test]
+    fn test_register_moves_keys_with_their_messages() {
+        let (sender, mut receivers) = routed(2, 64, |(key, _): &(u32, u32)| *key);
+        for seq in 0..3 {
+            for key in 0..20 {
+                sender.send_overwrite((key, seq)).unwrap();
+            }
+        }
+        let (shard, receiver) = sender.register();
+        assert_eq!((shard, sender.shard_count()), (2, 3));
+        assert!(!receiver.is_empty());
+        receivers.push(receiver);
+
+        // Later messages follow their keys to the new shard
+        for key in 0..20 {
+            sender.send_overwrite((key, 3)).unwrap();
+        }
+        assert_routed(&sender, &receivers);
+    }
+
+    #[test]
+    fn test_unregister_reroutes_the_backlog() {
+        let (sender, receivers) = routed(3, 64, |(key, _): &(u32, u32)| *key);
+        for seq in 0..3 {
+            for key in 0..20 {
+                sender.send_overwrite((key, seq)).unwrap();
+            }
+        }
+        assert!(sender.unregister(1));
+        assert!(!sender.unregister(1));
+        assert_eq!(sender.shards(), vec![0, 2]);
+        assert!(sender.shard(1).is_none());
+        assert_eq!(receivers[1].recv(), Err(flume::RecvError::Disconnected));
+
+        let (shard, receiver) = sender.register();
+        assert_eq!(shard, 3);
+        let receivers = [
+            receivers[0].clone(),
+            bounded(1).1,
+            receivers[2].clone(),
+            receiver,
+        ];
+        assert_routed(&sender, &receivers);
+    }
+
+    #[test]
+    fn test_routed_zero_shards() {
+        let (sender, receivers) = routed(0, 2, |value: &u32| *value);
+        assert_eq!(sender.shard_count(), 1);
+        assert_eq!(receivers.len(), 1);
+        sender.send_overwrite(42).unwrap();
+        assert_eq!(receivers[0].recv().unwrap(), 42);
+    }
+}
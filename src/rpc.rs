@@ -0,0 +1,230 @@
+//! Best-effort request/response over an overwrite channel.
+
+use crate::{OverwriteReceiver, OverwriteSender, Stats, bounded};
+use flume::{RecvError, TryRecvError};
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::ops::Deref;
+
+/// Why a [`Caller::call`] got no response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallError {
+    /// The request was dropped from the full queue to make room for a newer one.
+    Superseded,
+    /// The request was dropped by the responder without an answer, or the responder is gone.
+    Closed,
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Superseded => f.write_str("request was superseded by a newer one"),
+            CallError::Closed => f.write_str("request was not answered"),
+        }
+    }
+}
+
+impl Error for CallError {}
+
+/// Creates a request/response channel whose queue holds at most `cap` pending requests.
+///
+/// When a call finds the queue full, the oldest unserviced request is dropped and its
+/// caller gets [`CallError::Superseded`] rather than an answer. This suits best-effort
+/// queries such as autocomplete, where only the latest requests are worth answering.
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{CallError, rpc};
+/// use futures::executor::block_on;
+///
+/// let (caller, responder) = rpc::<String, usize>(1);
+///
+/// // The user keeps typing before the first query is serviced
+/// let first = caller.call("fl".to_string());
+/// let second = caller.call("flume".to_string());
+///
+/// let request = responder.recv().unwrap();
+/// let matches = request.len();
+/// request.respond(matches);
+///
+/// assert_eq!(block_on(first), Err(CallError::Superseded));
+/// assert_eq!(block_on(second), Ok(5));
+/// ```
+#[track_caller]
+pub fn rpc<Req, Resp>(cap: usize) -> (Caller<Req, Resp>, Responder<Req, Resp>) {
+    let (sender, receiver) = bounded(cap);
+    (Caller { sender }, Responder { receiver })
+}
+
+/// A request received by a [`Responder`], along with the means to answer it.
+///
+/// Dropping it without calling [`respond`](Request::respond) fails the call with
+/// [`CallError::Closed`].
+pub struct Request<Req, Resp> {
+    request: Req,
+    reply: flume::Sender<Result<Resp, CallError>>,
+}
+
+impl<Req, Resp> Request<Req, Resp> {
+    /// Answers the request.
+    ///
+    /// Does nothing if the caller has stopped waiting.
+    pub fn respond(self, response: Resp) {
+        let _ = self.reply.send(Ok(response));
+    }
+
+    /// Returns `true` if the caller has stopped waiting, in which case the request need
+    /// not be serviced.
+    pub fn is_abandoned(&self) -> bool {
+        self.reply.is_disconnected()
+    }
+
+    /// Returns the request.
+    pub fn into_inner(self) -> Req {
+        self.request
+    }
+}
+
+impl<Req, Resp> Deref for Request<Req, Resp> {
+    type Target = Req;
+
+    fn deref(&self) -> &Req {
+        &self.request
+    }
+}
+
+impl<Req: fmt::Debug, Resp> fmt::Debug for Request<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Request")
+            .field("request", &self.request)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The calling half of an [`rpc`] channel.
+pub struct Caller<Req, Resp> {
+    sender: OverwriteSender<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Caller<Req, Resp> {
+    /// Queues `request` and returns a future resolving to its response.
+    ///
+    /// The request is queued immediately, and any request it supersedes fails right away
+    /// with [`CallError::Superseded`]. The future resolves to [`CallError::Closed`] if the
+    /// responder is gone.
+    pub fn call(
+        &self,
+        request: Req,
+    ) -> impl Future<Output = Result<Resp, CallError>> + use<Req, Resp> {
+        let (reply, response) = flume::bounded(1);
+        let queued = match self.sender.send_overwrite(Request { request, reply }) {
+            Ok(superseded) => {
+                for request in superseded.into_iter().flatten() {
+                    let _ = request.reply.send(Err(CallError::Superseded));
+                }
+                true
+            }
+            Err(_) => false,
+        };
+        async move {
+            if !queued {
+                return Err(CallError::Closed);
+            }
+            response
+                .recv_async()
+                .await
+                .unwrap_or(Err(CallError::Closed))
+        }
+    }
+
+    /// Returns a snapshot of the request queue, where `evicted` counts superseded requests.
+    pub fn stats(&self) -> Stats {
+        self.sender.stats()
+    }
+}
+
+impl<Req, Resp> Clone for Caller<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<Req, Resp> fmt::Debug for Caller<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Caller")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// The responding half of an [`rpc`] channel.
+pub struct Responder<Req, Resp> {
+    receiver: OverwriteReceiver<Request<Req, Resp>>,
+}
+
+impl<Req, Resp> Responder<Req, Resp> {
+    /// Receives the oldest pending request, blocking until one arrives.
+    ///
+    /// Returns an error once no request is pending and every caller has been dropped.
+    pub fn recv(&self) -> Result<Request<Req, Resp>, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Receives the oldest pending request without blocking.
+    pub fn try_recv(&self) -> Result<Request<Req, Resp>, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Asynchronously receives the oldest pending request.
+    pub async fn recv_async(&self) -> Result<Request<Req, Resp>, RecvError> {
+        self.receiver.recv_async().await
+    }
+
+    /// Returns a snapshot of the request queue.
+    pub fn stats(&self) -> Stats {
+        self.receiver.stats()
+    }
+}
+
+impl<Req, Resp> Clone for Responder<Req, Resp> {
+    fn clone(&self) -> Self {
+        Self {
+            receiver: self.receiver.clone(),
+        }
+    }
+}
+
+impl<Req, Resp> fmt::Debug for Responder<Req, Resp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Responder")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_unanswered_calls_are_closed() {
+        let (caller, responder) = rpc::<u32, u32>(2);
+        let dropped = caller.call(1);
+        let pending = caller.call(2);
+        drop(responder.recv().unwrap());
+        assert_eq!(block_on(dropped), Err(CallError::Closed));
+
+        drop(responder);
+        assert_eq!(block_on(pending), Err(CallError::Closed));
+        assert_eq!(block_on(caller.call(3)), Err(CallError::Closed));
+    }
+}
@@ -0,0 +1,194 @@
+//! Coalescing of consecutive equal messages into runs.
+
+use crate::{OverwriteSender, SendOverwriteError};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Set in a run's count once the count has been read or the run dropped, after which the
+/// sender starts a new run instead of extending it.
+const SEALED: u64 = 1 << 63;
+
+/// The previous message sent and the count of the run it started.
+type LastRun<T> = Option<(T, Arc<AtomicU64>)>;
+
+/// A message along with the number of consecutive times it was sent.
+///
+/// Sent by [`CoalesceRuns`]. The count keeps growing while the run is queued; reading it
+/// with [`count`](Run::count) or [`into_parts`](Run::into_parts) fixes it, so that equal
+/// messages sent afterwards start a new run rather than being lost.
+pub struct Run<T> {
+    /// Only `None` once taken by `into_parts`.
+    value: Option<T>,
+    count: Arc<AtomicU64>,
+}
+
+impl<T> Run<T> {
+    /// Returns the message.
+    pub fn value(&self) -> &T {
+        self.value
+            .as_ref()
+            .expect("value is only taken on consumption")
+    }
+
+    /// Returns how many consecutive times the message was sent, ending the run.
+    pub fn count(&self) -> u64 {
+        self.count.fetch_or(SEALED, Ordering::AcqRel) & !SEALED
+    }
+
+    /// Consumes the run, returning the message and how many consecutive times it was sent.
+    pub fn into_parts(mut self) -> (T, u64) {
+        let count = self.count();
+        let value = self
+            .value
+            .take()
+            .expect("value is only taken on consumption");
+        (value, count)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Run<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Run")
+            .field("value", self.value())
+            .field("count", &(self.count.load(Ordering::Acquire) & !SEALED))
+            .finish()
+    }
+}
+
+impl<T> Drop for Run<T> {
+    fn drop(&mut self) {
+        self.count.fetch_or(SEALED, Ordering::AcqRel);
+    }
+}
+
+/// A sender that stores consecutive equal messages once, with a run count.
+///
+/// Sending a message equal to the previous one, while that one is still queued, increments
+/// the run count of the queued message instead of taking up another slot. Bursts of
+/// identical events are thereby compressed without losing their multiplicity, which the
+/// receiver reads from each [`Run`]. Clones share the previous message, so runs coalesce
+/// across clones.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{CoalesceRuns, bounded};
+///
+/// let (sender, receiver) = bounded(2);
+/// let sender = CoalesceRuns::new(sender);
+///
+/// for event in ["click", "click", "click", "scroll"] {
+///     sender.send_overwrite(event).unwrap();
+/// }
+///
+/// assert_eq!(receiver.recv().unwrap().into_parts(), ("click", 3));
+/// assert_eq!(receiver.recv().unwrap().into_parts(), ("scroll", 1));
+/// ```
+pub struct CoalesceRuns<T> {
+    sender: OverwriteSender<Run<T>>,
+    last: Arc<Mutex<LastRun<T>>>,
+}
+
+impl<T: PartialEq + Clone> CoalesceRuns<T> {
+    /// Wraps a sender of runs.
+    pub fn new(sender: OverwriteSender<Run<T>>) -> Self {
+        Self {
+            sender,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sends a value, extending the queued run if it equals the previous value and
+    /// otherwise starting a new run with overwrite semantics.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The run was extended, or a new run was sent without overwriting
+    /// - `Ok(Some(Vec<Run<T>>))` - A new run was sent and the returned vector contains the
+    ///   runs that were overwritten
+    /// - `Err(SendOverwriteError<Run<T>>)` - The run could not be sent
+    #[allow(clippy::type_complexity)]
+    pub fn send_overwrite(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<Run<T>>>, SendOverwriteError<Run<T>>> {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((previous, count)) = &*last
+            && *previous == value
+            && count
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                    (count & SEALED == 0).then_some(count + 1)
+                })
+                .is_ok()
+        {
+            return Ok(None);
+        }
+        let count = Arc::new(AtomicU64::new(1));
+        let run = Run {
+            value: Some(value.clone()),
+            count: count.clone(),
+        };
+        let overwritten = self.sender.send_overwrite(run)?;
+        *last = Some((value, count));
+        Ok(overwritten)
+    }
+
+    /// Returns the underlying sender.
+    pub fn sender(&self) -> &OverwriteSender<Run<T>> {
+        &self.sender
+    }
+}
+
+impl<T> Clone for CoalesceRuns<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            last: self.last.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for CoalesceRuns<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoalesceRuns")
+            .field("sender", &self.sender)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    #[test]
+    fn test_received_run_is_not_extended() {
+        let (sender, receiver) = bounded(4);
+        let sender = CoalesceRuns::new(sender);
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(1).unwrap();
+        let run = receiver.recv().unwrap();
+        assert_eq!(run.count(), 2);
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(run.count(), 2);
+        assert_eq!(receiver.recv().unwrap().into_parts(), (1, 1));
+    }
+
+    #[test]
+    fn test_dropped_run_is_not_extended() {
+        let (sender, receiver) = bounded(1);
+        let sender = CoalesceRuns::new(sender);
+        sender.send_overwrite(1).unwrap();
+        drop(receiver.recv().unwrap());
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(receiver.len(), 1);
+
+        // An evicted run is dropped with the returned vector
+        let overwritten = sender.send_overwrite(2).unwrap().unwrap();
+        assert_eq!(overwritten[0].value(), &1);
+        drop(overwritten);
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(receiver.recv().unwrap().into_parts(), (1, 1));
+    }
+}
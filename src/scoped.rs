@@ -0,0 +1,90 @@
+//! Scoped producer threads.
+
+use crate::OverwriteSender;
+use std::panic;
+use std::thread;
+
+/// Runs `f` on `n` scoped producer threads, each with its own clone of `sender`, and
+/// returns their results in thread order.
+///
+/// `f` receives the index of its thread and its sender. Since the threads are scoped, `f`
+/// may borrow data from the caller's stack, which makes stress-producing into a channel
+/// straightforward. Every thread is joined before this returns; if any panicked, the panic
+/// of the first one, in thread order, is resumed on the caller's thread once all are done.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{bounded, scope_producers};
+///
+/// let readings = vec![1, 2, 3, 4];
+/// let (sender, receiver) = bounded(16);
+///
+/// let sent = scope_producers(&sender, 2, |index, sender| {
+///     // Each producer sends every other reading, borrowing the vector
+///     let mine: Vec<_> = readings.iter().skip(index).step_by(2).collect();
+///     for reading in &mine {
+///         sender.send_overwrite(**reading).unwrap();
+///     }
+///     mine.len()
+/// });
+///
+/// assert_eq!(sent, vec![2, 2]);
+/// assert_eq!(receiver.len(), 4);
+/// ```
+pub fn scope_producers<T, R, F>(sender: &OverwriteSender<T>, n: usize, f: F) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+    F: Fn(usize, &OverwriteSender<T>) -> R + Sync,
+{
+    let f = &f;
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..n)
+            .map(|index| {
+                let sender = sender.clone();
+                scope.spawn(move || f(index, &sender))
+            })
+            .collect();
+        let mut results = Vec::with_capacity(n);
+        let mut panicked = None;
+        for handle in handles {
+            match handle.join() {
+                Ok(result) => results.push(result),
+                Err(payload) => {
+                    panicked.get_or_insert(payload);
+                }
+            }
+        }
+        if let Some(payload) = panicked {
+            panic::resume_unwind(payload);
+        }
+        results
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    #[test]
+    fn test_propagates_first_panic() {
+        let (sender, receiver) = bounded(4);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            scope_producers(&sender, 3, |index, sender| {
+                sender.send_overwrite(index).unwrap();
+                if index > 0 {
+                    panic!("producer {index} failed");
+                }
+            })
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<String>().unwrap(),
+            "producer 1 failed"
+        );
+        // Every producer ran to completion before the panic was resumed
+        assert_eq!(receiver.len(), 3);
+    }
+}
@@ -0,0 +1,157 @@
+//! Waiting on several overwrite channels at once, reporting their losses.
+
+use crate::OverwriteReceiver;
+use crate::counter::Counter;
+use std::future::poll_fn;
+use std::sync::atomic::Ordering;
+use std::task::{Context, Poll};
+
+/// The message yielded by [`select_overwrite`] on a pair of receivers, telling which one
+/// it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Select2<A, B> {
+    /// A message from the first receiver.
+    A(A),
+    /// A message from the second receiver.
+    B(B),
+}
+
+/// The message yielded by [`select_overwrite`] on three receivers, telling which one it
+/// came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Select3<A, B, C> {
+    /// A message from the first receiver.
+    A(A),
+    /// A message from the second receiver.
+    B(B),
+    /// A message from the third receiver.
+    C(C),
+}
+
+/// The outcome of a [`select_overwrite`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selected<M, const N: usize> {
+    /// The message received, tagged with the receiver it came from.
+    pub message: M,
+    /// The number of messages evicted from each channel, in the order of the receivers,
+    /// since the receiver last took part in a select.
+    pub evicted: [u64; N],
+}
+
+/// Receivers [`select_overwrite`] can wait on: tuples of two or three receiver references.
+pub trait Select {
+    /// The message type, telling which receiver the message came from.
+    type Message;
+    /// The per-channel eviction counts.
+    type Evicted;
+
+    /// Polls the receivers in order, returning the first message available, or `None` once
+    /// every receiver is disconnected and drained.
+    #[doc(hidden)]
+    fn poll_select(&self, cx: &mut Context<'_>) -> Poll<Option<Self::Message>>;
+
+    #[doc(hidden)]
+    fn take_evicted(&self) -> Self::Evicted;
+}
+
+/// Returns the number of messages evicted from the channel since `receiver` last took part
+/// in a select.
+fn take_evicted<T>(receiver: &OverwriteReceiver<T>) -> u64 {
+    let total = receiver.shared.evicted.get();
+    Counter::since(total, receiver.selected.swap(total, Ordering::Relaxed))
+}
+
+macro_rules! impl_select {
+    ($select:ident, $n:literal, $($index:tt $ty:ident),+) => {
+        impl<'a, $($ty),+> Select for ($(&'a OverwriteReceiver<$ty>,)+)
+        where
+            $($ty: Send + 'static),+
+        {
+            type Message = $select<$($ty),+>;
+            type Evicted = [u64; $n];
+
+            fn poll_select(&self, cx: &mut Context<'_>) -> Poll<Option<Self::Message>> {
+                let mut open = false;
+                $(
+                    match self.$index.poll_stream(cx) {
+                        Poll::Ready(Some(value)) => return Poll::Ready(Some($select::$ty(value))),
+                        Poll::Ready(None) => {}
+                        Poll::Pending => open = true,
+                    }
+                )+
+                if open { Poll::Pending } else { Poll::Ready(None) }
+            }
+
+            fn take_evicted(&self) -> [u64; $n] {
+                [$(take_evicted(self.$index)),+]
+            }
+        }
+    };
+}
+
+impl_select!(Select2, 2, 0 A, 1 B);
+impl_select!(Select3, 3, 0 A, 1 B, 2 C);
+
+/// Waits for a message on any of several receivers, reporting which one yielded it along
+/// with the losses of every channel since the last select.
+///
+/// Event loops juggling several lossy inputs learn both what to handle next and how much
+/// of each input they missed meanwhile. Receivers are polled in order, so an earlier
+/// receiver that always has messages ready takes precedence over later ones. The select
+/// resolves to `None` once every receiver is disconnected and drained.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::{Select2, bounded, select_overwrite};
+/// use futures::executor::block_on;
+///
+/// let (mouse, mouse_events) = bounded(1);
+/// let (keys, key_events) = bounded(8);
+///
+/// mouse.send_overwrite((1, 1)).unwrap();
+/// mouse.send_overwrite((4, 2)).unwrap();
+/// keys.send_overwrite('q').unwrap();
+///
+/// let selected = block_on(select_overwrite((&mouse_events, &key_events))).unwrap();
+/// assert_eq!(selected.message, Select2::A((4, 2)));
+/// assert_eq!(selected.evicted, [1, 0]);
+///
+/// let selected = block_on(select_overwrite((&mouse_events, &key_events))).unwrap();
+/// assert_eq!(selected.message, Select2::B('q'));
+/// assert_eq!(selected.evicted, [0, 0]);
+/// ```
+pub async fn select_overwrite<S, const N: usize>(receivers: S) -> Option<Selected<S::Message, N>>
+where
+    S: Select<Evicted = [u64; N]>,
+{
+    let message = poll_fn(|cx| receivers.poll_select(cx)).await?;
+    Some(Selected {
+        message,
+        evicted: receivers.take_evicted(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_select_skips_disconnected_receivers() {
+        let (first, a) = bounded::<u8>(1);
+        let (second, b) = bounded::<u16>(1);
+        let (third, c) = bounded::<u32>(1);
+        drop(first);
+        third.send_overwrite(3).unwrap();
+        third.send_overwrite(4).unwrap();
+
+        let selected = block_on(select_overwrite((&a, &b, &c))).unwrap();
+        assert_eq!(selected.message, Select3::C(4));
+        assert_eq!(selected.evicted, [0, 0, 1]);
+
+        drop((second, third));
+        assert_eq!(block_on(select_overwrite((&a, &b, &c))), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! The lock serializing the sending side of a channel.
+
+use flume::{Receiver, Sender};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering, fence};
+use std::task::{Context, Poll, Waker};
+
+/// A lock serializing the operations that modify a channel's queue from the sending side.
+///
+/// Overwriting takes several steps, evicting the oldest messages before queueing the new
+/// one, and conflating sends drain and requeue the queue. Without the lock, a concurrent
+/// send could take the slot freed by an eviction, or be reordered by a requeue. The lock is
+/// a single token passed around through a flume channel, so async senders await it instead
+/// of blocking their executor thread, while sync senders block as they would on a mutex.
+/// Pollers that find the lock taken leave their waker to be woken once it is released.
+pub(crate) struct SendLock {
+    release: Sender<()>,
+    acquire: Receiver<()>,
+    /// Whether `pollers` may hold wakers, so releasing skips its lock otherwise.
+    polled: AtomicBool,
+    pollers: Mutex<Vec<Waker>>,
+}
+
+impl SendLock {
+    pub(crate) fn new() -> Self {
+        let (release, acquire) = flume::bounded(1);
+        release.send(()).expect("the lock holds both ends");
+        Self {
+            release,
+            acquire,
+            polled: AtomicBool::new(false),
+            pollers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Blocks until the lock is acquired.
+    pub(crate) fn lock(&self) -> SendLockGuard<'_> {
+        self.acquire.recv().expect("the lock holds both ends");
+        SendLockGuard { lock: self }
+    }
+
+    /// Waits asynchronously until the lock is acquired.
+    ///
+    /// Dropping the future before it completes leaves the lock to the next waiter.
+    pub(crate) async fn lock_async(&self) -> SendLockGuard<'_> {
+        self.acquire
+            .recv_async()
+            .await
+            .expect("the lock holds both ends");
+        SendLockGuard { lock: self }
+    }
+
+    /// Acquires the lock if no one holds it.
+    pub(crate) fn try_lock(&self) -> Option<SendLockGuard<'_>> {
+        self.acquire.try_recv().ok()?;
+        Some(SendLockGuard { lock: self })
+    }
+
+    /// Acquires the lock if no one holds it, or registers the task of `cx` to be woken once
+    /// it is released.
+    pub(crate) fn poll_lock(&self, cx: &mut Context<'_>) -> Poll<SendLockGuard<'_>> {
+        if let Some(guard) = self.try_lock() {
+            return Poll::Ready(guard);
+        }
+        {
+            let mut pollers = self.pollers.lock().unwrap_or_else(|e| e.into_inner());
+            if !pollers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                pollers.push(cx.waker().clone());
+            }
+            self.polled.store(true, Ordering::SeqCst);
+        }
+        // The holder may have released the lock before seeing the waker; paired with the
+        // fence in `wake_pollers`, either it sees the waker or this sees the token
+        fence(Ordering::SeqCst);
+        match self.try_lock() {
+            Some(guard) => Poll::Ready(guard),
+            None => Poll::Pending,
+        }
+    }
+
+    /// Wakes every task that polled the lock while it was taken.
+    fn wake_pollers(&self) {
+        fence(Ordering::SeqCst);
+        if !self.polled.load(Ordering::SeqCst) {
+            return;
+        }
+        let pollers = {
+            let mut pollers = self.pollers.lock().unwrap_or_else(|e| e.into_inner());
+            self.polled.store(false, Ordering::SeqCst);
+            std::mem::take(&mut *pollers)
+        };
+        pollers.into_iter().for_each(Waker::wake);
+    }
+}
+
+/// Holds a [`SendLock`], releasing it when dropped, including while unwinding.
+pub(crate) struct SendLockGuard<'a> {
+    lock: &'a SendLock,
+}
+
+impl Drop for SendLockGuard<'_> {
+    fn drop(&mut self) {
+        // The token was taken by this guard, so there is always room to put it back
+        let _ = self.lock.release.try_send(());
+        self.lock.wake_pollers();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use futures::FutureExt;
+    use futures::executor::block_on;
+
+    #[test]
+    fn test_waiters_take_turns() {
+        let lock = SendLock::new();
+        let held = lock.lock();
+        assert!(lock.try_lock().is_none());
+
+        // An async waiter stays pending instead of blocking, and cancelling it loses nothing
+        assert!(lock.lock_async().now_or_never().is_none());
+        drop(held);
+        let held = block_on(lock.lock_async());
+        assert!(lock.try_lock().is_none());
+        drop(held);
+        assert!(lock.try_lock().is_some());
+    }
+
+    #[test]
+    fn test_release_wakes_pollers() {
+        use futures::task::noop_waker_ref;
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicUsize;
+        use std::task::Wake;
+
+        struct Count(AtomicUsize);
+        impl Wake for Count {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let lock = SendLock::new();
+        let count = Arc::new(Count(AtomicUsize::new(0)));
+        let waker = Waker::from(count.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let held = lock.lock();
+        assert!(lock.poll_lock(&mut cx).is_pending());
+        // Polling again from the same task does not wake it twice
+        assert!(lock.poll_lock(&mut cx).is_pending());
+        drop(held);
+        assert_eq!(count.0.load(Ordering::SeqCst), 1);
+
+        let held = lock.poll_lock(&mut Context::from_waker(noop_waker_ref()));
+        assert!(held.is_ready());
+        drop(held);
+        assert_eq!(count.0.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,150 @@
+//! A control plane delivering typed signals ahead of the data.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError};
+use flume::{RecvError, Selector};
+use std::future::{Future, poll_fn};
+use std::pin::Pin;
+use std::task::Poll;
+
+/// A control signal, sent with [`OverwriteSender::signal`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Control {
+    /// The consumer should stop.
+    Shutdown,
+    /// An application-defined signal.
+    Custom(u32),
+}
+
+/// A message received with [`OverwriteReceiver::recv_message`]: either a control signal or
+/// data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message<T> {
+    /// A control signal, delivered ahead of any queued data.
+    Control(Control),
+    /// A data message.
+    Data(T),
+}
+
+impl<T> OverwriteSender<T> {
+    /// Sends a control signal, delivered ahead of every queued data message.
+    ///
+    /// Signals travel in a queue of their own, which is unbounded: they are never
+    /// overwritten, never evict data, and do not count towards the capacity or the
+    /// [`Stats`](crate::Stats). Receivers only see them through
+    /// [`recv_message`](OverwriteReceiver::recv_message) and its variants, so a single
+    /// channel carries both the data and the instructions about it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the signal if every receiver has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::{Control, Message, bounded};
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// sender.send_overwrite("frame 1").unwrap();
+    /// sender.send_overwrite("frame 2").unwrap();
+    /// sender.signal(Control::Shutdown).unwrap();
+    ///
+    /// assert_eq!(receiver.recv_message(), Ok(Message::Control(Control::Shutdown)));
+    /// assert_eq!(receiver.recv_message(), Ok(Message::Data("frame 1")));
+    /// ```
+    pub fn signal(&self, control: Control) -> Result<(), SendOverwriteError<Control>> {
+        if self.is_orphaned() {
+            return Err(SendOverwriteError::Disconnected(control));
+        }
+        let _ = self.shared.signals.0.send(control);
+        Ok(())
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Receives the next control signal, or the next data message if no signal is pending,
+    /// blocking until either is available.
+    ///
+    /// See [`OverwriteSender::signal`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once no signal is pending, the channel is empty and every sender
+    /// has been dropped.
+    pub fn recv_message(&self) -> Result<Message<T>, RecvError> {
+        let receiver = self.live().ok_or(RecvError::Disconnected)?;
+        if let Some(message) = self.try_recv_signal() {
+            return Ok(message);
+        }
+        Selector::new()
+            .recv(&self.shared.signals.1, |control| {
+                control.map(Message::Control)
+            })
+            .recv(receiver, |value| {
+                let value = value?;
+                self.shared.observe_watermarks(receiver);
+                Ok(Message::Data(value))
+            })
+            .wait()
+    }
+
+    /// Receives the next control signal, or the next data message, without waiting.
+    pub fn try_recv_message(&self) -> Option<Message<T>> {
+        self.live()?;
+        self.try_recv_signal()
+            .or_else(|| self.try_recv().ok().map(Message::Data))
+    }
+
+    /// Waits asynchronously for the next control signal, or the next data message if no
+    /// signal is pending.
+    ///
+    /// This is the async version of [`recv_message`](OverwriteReceiver::recv_message).
+    pub async fn recv_message_async(&self) -> Result<Message<T>, RecvError> {
+        let receiver = self.live().ok_or(RecvError::Disconnected)?;
+        if let Some(message) = self.try_recv_signal() {
+            return Ok(message);
+        }
+        let mut signal = self.shared.signals.1.recv_async();
+        let mut data = receiver.recv_async();
+        poll_fn(|cx| {
+            if let Poll::Ready(Ok(control)) = Pin::new(&mut signal).poll(cx) {
+                return Poll::Ready(Ok(Message::Control(control)));
+            }
+            Pin::new(&mut data).poll(cx).map(|value| {
+                let value = value?;
+                self.shared.observe_watermarks(receiver);
+                Ok(Message::Data(value))
+            })
+        })
+        .await
+    }
+
+    fn try_recv_signal(&self) -> Option<Message<T>> {
+        self.shared.signals.1.try_recv().ok().map(Message::Control)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn test_signal_wakes_waiting_receiver() {
+        let (sender, receiver) = bounded::<u8>(1);
+        let waiting = thread::spawn(move || block_on(receiver.recv_message_async()));
+        sender.signal(Control::Custom(7)).unwrap();
+        assert_eq!(
+            waiting.join().unwrap(),
+            Ok(Message::Control(Control::Custom(7)))
+        );
+        assert!(
+            sender
+                .signal(Control::Shutdown)
+                .unwrap_err()
+                .is_disconnected()
+        );
+    }
+}
@@ -0,0 +1,470 @@
+//! Oversized messages split into chunks, reassembled on receive.
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use flume::{RecvError, TryRecvError};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A message that can be split into chunks and joined back, for [`bounded_split`].
+///
+/// Sizes are in whatever unit the chunk size is given in, such as bytes or elements.
+pub trait Split: Sized {
+    /// Returns the size of the message.
+    fn size(&self) -> usize;
+
+    /// Splits the message at `at`, leaving the first `at` units in place and returning the
+    /// rest.
+    fn split_off(&mut self, at: usize) -> Self;
+
+    /// Appends a chunk split off after this one.
+    fn append(&mut self, chunk: Self);
+}
+
+impl<T> Split for Vec<T> {
+    fn size(&self) -> usize {
+        self.len()
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        Vec::split_off(self, at)
+    }
+
+    fn append(&mut self, mut chunk: Self) {
+        Vec::append(self, &mut chunk);
+    }
+}
+
+/// Creates an overwrite channel of `cap` slots whose messages are split into chunks of at
+/// most `chunk_size` units, each occupying a slot.
+///
+/// The receiver joins the chunks back, so a large message costs several slots but arrives
+/// whole. Eviction is all-or-nothing: a message whose first chunk is overwritten loses its
+/// remaining chunks along with it, so no slot is spent on a message that can no longer be
+/// delivered. The channel's [`Stats`](crate::Stats) count chunks rather than messages.
+///
+/// # Panics
+///
+/// Panics if `cap` or `chunk_size` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded_split;
+///
+/// let (sender, receiver) = bounded_split(4, 2);
+///
+/// sender.send_overwrite(vec![1, 2, 3]).unwrap();
+/// assert_eq!(sender.slots(), 2);
+///
+/// // Needing three slots, the next message evicts the first one as a whole
+/// let evicted = sender.send_overwrite(vec![4, 5, 6, 7, 8]).unwrap();
+/// assert_eq!(evicted, Some(vec![vec![1, 2, 3]]));
+/// assert_eq!(receiver.recv().unwrap(), vec![4, 5, 6, 7, 8]);
+/// ```
+#[track_caller]
+pub fn bounded_split<T: Split>(
+    cap: usize,
+    chunk_size: usize,
+) -> (SplitSender<T>, SplitReceiver<T>) {
+    assert!(chunk_size > 0, "chunk size must be non-zero");
+    let (sender, receiver) = bounded(cap);
+    let sender = SplitSender {
+        sender,
+        chunk_size,
+        messages: Arc::new(AtomicU64::new(0)),
+    };
+    let receiver = SplitReceiver {
+        receiver,
+        partial: Mutex::new(None),
+    };
+    (sender, receiver)
+}
+
+/// A chunk of a message, numbered among the chunks of its message.
+struct Chunk<T> {
+    message: u64,
+    index: usize,
+    count: usize,
+    part: T,
+}
+
+impl<T> Chunk<T> {
+    fn is_last(&self) -> bool {
+        self.index + 1 == self.count
+    }
+}
+
+/// The sending half of a [`bounded_split`] channel.
+pub struct SplitSender<T> {
+    sender: OverwriteSender<Chunk<T>>,
+    chunk_size: usize,
+    messages: Arc<AtomicU64>,
+}
+
+impl<T: Split> SplitSender<T> {
+    /// Splits a value into chunks and sends them with overwrite semantics, evicting whole
+    /// messages to make room.
+    ///
+    /// The chunks are sent while other sends wait, so the chunks of a message are never
+    /// interleaved with those of another. Like
+    /// [`send_all_or_nothing`](OverwriteSender::send_all_or_nothing), the whole message is
+    /// checked to fit before its first chunk is sent, so a send that fails is never left
+    /// half queued.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The message was sent without overwriting any existing messages
+    /// - `Ok(Some(Vec<T>))` - The message was sent and the returned vector contains the
+    ///   messages that were overwritten, joined back. A message the receiver had started to
+    ///   reassemble is dropped along with its remaining chunks instead.
+    /// - `Err(SendOverwriteError<T>)` - The channel is disconnected, the message needs more
+    ///   chunks than the channel has slots, see [`SendOverwriteError::Oversized`], or room
+    ///   cannot be made for all of its chunks, see [`SendOverwriteError::Rejected`]. A
+    ///   receiver dropped while the chunks are being sent fails the send with the part of
+    ///   the message that was not sent yet.
+    pub fn send_overwrite(&self, mut value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let sender = &self.sender;
+        let _sending = sender.shared.lock_sends();
+        if sender.is_orphaned() {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        let count = value.size().div_ceil(self.chunk_size).max(1);
+        if count > sender.capacity() {
+            return Err(SendOverwriteError::Oversized(value));
+        }
+        if !sender.batch_fits_locked(count) {
+            return Err(SendOverwriteError::Rejected(value));
+        }
+        let message = self.messages.fetch_add(1, Ordering::Relaxed);
+        let mut evicted = Vec::new();
+        for index in 0..count {
+            let rest = (index + 1 < count).then(|| value.split_off(self.chunk_size));
+            let chunk = Chunk {
+                message,
+                index,
+                count,
+                part: value,
+            };
+            match sender.send_overwrite_locked(chunk) {
+                Ok(overwritten) => evicted.extend(overwritten.into_iter().flatten()),
+                Err(err) => {
+                    return Err(err.map(|chunk| {
+                        let mut unsent = chunk.part;
+                        if let Some(rest) = rest {
+                            unsent.append(rest);
+                        }
+                        unsent
+                    }));
+                }
+            }
+            self.evict_remaining_chunks(&mut evicted);
+            match rest {
+                Some(rest) => value = rest,
+                None => break,
+            }
+        }
+        let evicted = join_evicted(evicted);
+        Ok((!evicted.is_empty()).then_some(evicted))
+    }
+
+    /// Returns the number of slots the messages in the channel occupy.
+    pub fn slots(&self) -> usize {
+        self.sender.len()
+    }
+
+    /// Returns the number of slots the channel has.
+    pub fn capacity(&self) -> usize {
+        self.sender.capacity()
+    }
+
+    /// Returns the largest number of units a chunk holds.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Evicts the chunks left queued of the last message overwritten, while holding the
+    /// send lock.
+    ///
+    /// The chunks of a message are queued together, so they are at the head of the queue,
+    /// unless a receiver took them meanwhile: the first chunk of the next message is then
+    /// put back in front of the queue.
+    fn evict_remaining_chunks(&self, evicted: &mut Vec<Chunk<T>>) {
+        let Some(last) = evicted.last().filter(|last| !last.is_last()) else {
+            return;
+        };
+        let message = last.message;
+        let mut remaining = 0;
+        while let Ok(chunk) = self.sender.receiver.try_recv() {
+            if chunk.message != message {
+                let mut queued = vec![chunk];
+                queued.extend(self.sender.receiver.drain());
+                self.sender.requeue_locked(queued);
+                break;
+            }
+            let is_last = chunk.is_last();
+            evicted.push(chunk);
+            remaining += 1;
+            if is_last {
+                break;
+            }
+        }
+        self.sender.shared.count_evictions(remaining);
+    }
+}
+
+impl<T> Clone for SplitSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            chunk_size: self.chunk_size,
+            messages: self.messages.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SplitSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SplitSender")
+            .field("chunk_size", &self.chunk_size)
+            .field("slots", &self.sender.len())
+            .field("capacity", &self.sender.capacity())
+            .finish()
+    }
+}
+
+/// Joins evicted chunks back into messages, dropping those whose first chunk was already
+/// received.
+fn join_evicted<T: Split>(evicted: Vec<Chunk<T>>) -> Vec<T> {
+    let mut messages = Vec::new();
+    let mut joining: Option<T> = None;
+    for chunk in evicted {
+        let is_last = chunk.is_last();
+        if chunk.index == 0 {
+            joining = Some(chunk.part);
+        } else if let Some(message) = &mut joining {
+            message.append(chunk.part);
+        }
+        if is_last && let Some(message) = joining.take() {
+            messages.push(message);
+        }
+    }
+    messages
+}
+
+/// The receiving half of a [`bounded_split`] channel.
+///
+/// The receiver keeps the chunks of the message it is reassembling, so it cannot be
+/// cloned: competing receivers would each get a share of the chunks.
+pub struct SplitReceiver<T> {
+    receiver: OverwriteReceiver<Chunk<T>>,
+    partial: Mutex<Option<Partial<T>>>,
+}
+
+/// The chunks of a message received so far.
+struct Partial<T> {
+    message: u64,
+    next: usize,
+    value: T,
+}
+
+impl<T: Split> SplitReceiver<T> {
+    /// Receives a whole message, blocking until all of its chunks have arrived.
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut partial = self.lock();
+        loop {
+            let chunk = self.receiver.recv()?;
+            if let Some(message) = reassemble(&mut partial, chunk) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Receives a whole message, waiting asynchronously until all of its chunks have
+    /// arrived.
+    ///
+    /// This is the async version of [`recv`](SplitReceiver::recv).
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        loop {
+            let chunk = self.receiver.recv_async().await?;
+            if let Some(message) = reassemble(&mut self.lock(), chunk) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Receives a whole message if all of its chunks have arrived, without blocking.
+    ///
+    /// Chunks of a message still arriving are kept for the next receive.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut partial = self.lock();
+        loop {
+            let chunk = self.receiver.try_recv()?;
+            if let Some(message) = reassemble(&mut partial, chunk) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Returns the number of slots the messages in the channel occupy, not counting the
+    /// chunks already received of a message being reassembled.
+    pub fn slots(&self) -> usize {
+        self.receiver.len()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Partial<T>>> {
+        self.partial.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> fmt::Debug for SplitReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let partial = self.partial.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("SplitReceiver")
+            .field("reassembling", &partial.is_some())
+            .field("receiver", &self.receiver)
+            .finish()
+    }
+}
+
+/// Adds a chunk to the message being reassembled, returning the message once complete.
+///
+/// A chunk that does not follow the partial message means the rest of it was evicted, so
+/// the partial message is dropped, and so is a stray chunk that does not start one.
+fn reassemble<T: Split>(partial: &mut Option<Partial<T>>, chunk: Chunk<T>) -> Option<T> {
+    let is_last = chunk.is_last();
+    match partial {
+        Some(joining) if joining.message == chunk.message && joining.next == chunk.index => {
+            joining.value.append(chunk.part);
+            joining.next += 1;
+        }
+        _ if chunk.index == 0 => {
+            *partial = Some(Partial {
+                message: chunk.message,
+                next: 1,
+                value: chunk.part,
+            });
+        }
+        _ => {
+            *partial = None;
+            return None;
+        }
+    }
+    if is_last {
+        partial.take().map(|joining| joining.value)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_eviction_takes_whole_messages() {
+        let (sender, receiver) = bounded_split(3, 1);
+        sender.send_overwrite(vec![1, 2, 3]).unwrap();
+        let evicted = sender.send_overwrite(vec![4, 5]).unwrap();
+        assert_eq!(evicted, Some(vec![vec![1, 2, 3]]));
+        assert_eq!(sender.slots(), 2);
+        assert_eq!(receiver.receiver.stats().evicted, 3);
+        assert!(
+            sender
+                .send_overwrite(vec![0; 4])
+                .unwrap_err()
+                .is_oversized()
+        );
+    }
+
+    #[test]
+    fn test_rejected_message_queues_no_chunk() {
+        use crate::OverflowPolicy;
+
+        let (sender, receiver) = bounded_split(3, 1);
+        sender.send_overwrite(vec![1]).unwrap();
+        sender.sender.swap_policy(OverflowPolicy::RejectNew);
+
+        // Only two of the three chunks would fit without evicting
+        let error = sender.send_overwrite(vec![2, 3, 4]).unwrap_err();
+        assert_eq!(error.into_inner(), vec![2, 3, 4]);
+        assert_eq!(sender.slots(), 1);
+        assert_eq!(receiver.try_recv().unwrap(), vec![1]);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_message_missing_chunks_is_dropped() {
+        let chunk = |message, index, count| Chunk {
+            message,
+            index,
+            count,
+            part: vec![message],
+        };
+        let mut partial = None;
+        assert_eq!(reassemble(&mut partial, chunk(0, 0, 3)), None);
+        assert_eq!(reassemble(&mut partial, chunk(1, 0, 1)), Some(vec![1]));
+
+        // A chunk of a message whose start was evicted is stray
+        assert_eq!(reassemble(&mut partial, chunk(2, 1, 2)), None);
+        assert!(partial.is_none());
+    }
+
+    #[test]
+    fn test_remaining_chunks_taken_by_receiver() {
+        let (sender, receiver) = bounded_split(4, 1);
+        sender.send_overwrite(vec![0, 0]).unwrap();
+        sender.send_overwrite(vec![1, 1]).unwrap();
+
+        // A send evicted the first chunk of a message, and the receiver took the second
+        let sending = sender.sender.shared.lock_sends();
+        let mut evicted = vec![sender.sender.receiver.try_recv().unwrap()];
+        receiver.receiver.try_recv().unwrap();
+        sender.evict_remaining_chunks(&mut evicted);
+        drop(sending);
+
+        // The next message is left whole, in place
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(sender.slots(), 2);
+        assert_eq!(receiver.try_recv().unwrap(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_eviction_races_with_receiver() {
+        let (sender, receiver) = bounded_split(4, 1);
+        let consumer = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            while let Ok(message) = receiver.recv() {
+                received.push(message);
+                // Falls behind now and then, so that sends evict
+                for _ in 0..(received.len() % 7) * 50 {
+                    std::hint::spin_loop();
+                }
+            }
+            received
+        });
+        let mut evicted = Vec::new();
+        for message in 0..5000 {
+            let overwritten = sender.send_overwrite(vec![message; 3]).unwrap();
+            evicted.extend(overwritten.unwrap_or_default());
+        }
+        drop(sender);
+
+        // Messages arrive or are evicted whole, and never both
+        let received = consumer.join().unwrap();
+        let mut seen: Vec<u32> = received
+            .iter()
+            .chain(&evicted)
+            .map(|message| message[0])
+            .collect();
+        for message in received.iter().chain(&evicted) {
+            assert_eq!(message, &vec![message[0]; 3]);
+        }
+        seen.sort_unstable();
+        let before = seen.len();
+        seen.dedup();
+        assert_eq!(seen.len(), before);
+    }
+}
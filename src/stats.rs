@@ -0,0 +1,101 @@
+//! Point-in-time statistics describing the state of a channel.
+
+use crate::{SenderId, Summary, WaitTimes};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A snapshot of a channel's state, as returned by `stats()` on either endpoint.
+///
+/// `Stats` describes the channel without exposing its contents, which makes it suitable
+/// for logging and diagnostics. With the `serde` feature enabled it also implements
+/// `serde::Serialize`, so it can be exported as-is from a debug endpoint.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::Builder;
+///
+/// let (sender, _receiver) = Builder::new(2).name("telemetry").build();
+/// sender.send_overwrite(1).unwrap();
+/// sender.send_overwrite(2).unwrap();
+/// sender.send_overwrite(3).unwrap();
+///
+/// let stats = sender.stats();
+/// assert_eq!(stats.len, 2);
+/// assert_eq!(stats.sent, 3);
+/// assert_eq!(stats.evicted, 1);
+/// assert_eq!(stats.to_string(), "telemetry: 2/2 queued, 3 sent, 1 evicted");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Stats {
+    /// The name given to the channel with `Builder::name`, if any.
+    pub name: Option<String>,
+    /// The maximum number of messages the channel can hold.
+    pub capacity: usize,
+    /// The number of messages currently queued.
+    pub len: usize,
+    /// The number of messages successfully sent through the channel.
+    pub sent: u64,
+    /// The number of queued messages removed to make room for newer ones.
+    pub evicted: u64,
+    /// The evicted messages counted per class, for channels built with
+    /// [`Builder::classify_evictions`](crate::Builder::classify_evictions); empty otherwise.
+    pub evicted_by_class: BTreeMap<&'static str, u64>,
+    /// The evicted messages counted per sender clone that sent them, for channels built
+    /// with [`Builder::attribute_evictions`](crate::Builder::attribute_evictions); empty
+    /// otherwise.
+    pub evicted_by_sender: BTreeMap<SenderId, u64>,
+    /// The summary of the evicted messages, for channels built with
+    /// [`Builder::aggregate_evictions`](crate::Builder::aggregate_evictions); `None`
+    /// otherwise.
+    pub evicted_summary: Option<Summary>,
+    /// How long blocking receives waited for a message.
+    ///
+    /// Only the blocking receives of [`OverwriteReceiver`](crate::OverwriteReceiver) are
+    /// measured, not async receives nor those made on the underlying flume receiver.
+    pub recv_wait: WaitTimes,
+    /// The capacity last requested by a receiver with
+    /// [`OverwriteReceiver::request_capacity`](crate::OverwriteReceiver::request_capacity),
+    /// if any.
+    pub requested_capacity: Option<usize>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}/{} queued, {} sent, {} evicted",
+            self.name.as_deref().unwrap_or("<unnamed>"),
+            self.len,
+            self.capacity,
+            self.sent,
+            self.evicted
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display_unnamed() {
+        let stats = Stats {
+            name: None,
+            capacity: 4,
+            len: 1,
+            sent: 7,
+            evicted: 0,
+            evicted_by_class: BTreeMap::new(),
+            evicted_by_sender: BTreeMap::new(),
+            evicted_summary: None,
+            recv_wait: WaitTimes::default(),
+            requested_capacity: None,
+        };
+        assert_eq!(
+            stats.to_string(),
+            "<unnamed>: 1/4 queued, 7 sent, 0 evicted"
+        );
+    }
+}
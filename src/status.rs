@@ -0,0 +1,71 @@
+//! The outcome of a send of a `Copy` value.
+
+/// The outcome of [`OverwriteSender::send_overwrite_copy`](crate::OverwriteSender::send_overwrite_copy).
+///
+/// Since the value is `Copy`, the caller still holds it whatever the outcome, so unlike
+/// [`SendOverwriteError`](crate::SendOverwriteError) the status does not hand it back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SendStatus {
+    /// The message was sent without overwriting anything.
+    Sent,
+    /// The message was sent, overwriting this many messages.
+    Overwrote(usize),
+    /// All receivers have been dropped.
+    Disconnected,
+    /// The channel is full and cannot evict anything to make room, see
+    /// [`SendOverwriteError::Rejected`](crate::SendOverwriteError::Rejected).
+    Rejected,
+    /// The message weighs more than the channel accepts, see
+    /// [`SendOverwriteError::Oversized`](crate::SendOverwriteError::Oversized).
+    Oversized,
+    /// Eviction could not make progress, see
+    /// [`SendOverwriteError::Contended`](crate::SendOverwriteError::Contended).
+    Contended,
+}
+
+impl SendStatus {
+    /// Returns `true` if the message was sent.
+    pub fn is_sent(&self) -> bool {
+        matches!(self, Self::Sent | Self::Overwrote(_))
+    }
+
+    /// Returns how many messages the send overwrote.
+    pub fn overwritten(&self) -> usize {
+        match self {
+            Self::Overwrote(count) => *count,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Builder, bounded};
+
+    #[test]
+    fn test_status_of_each_outcome() {
+        let (sender, receiver) = Builder::new(1)
+            .max_message_weight(10, |v: &u32| *v as usize)
+            .build();
+        assert_eq!(sender.send_overwrite_copy(1), SendStatus::Sent);
+        let overwrote = sender.send_overwrite_copy(2);
+        assert_eq!(overwrote, SendStatus::Overwrote(1));
+        assert!(overwrote.is_sent());
+        assert_eq!(overwrote.overwritten(), 1);
+
+        let oversized = sender.send_overwrite_copy(11);
+        assert_eq!(oversized, SendStatus::Oversized);
+        assert!(!oversized.is_sent());
+        assert_eq!(oversized.overwritten(), 0);
+
+        drop(receiver);
+        assert_eq!(sender.send_overwrite_copy(3), SendStatus::Disconnected);
+
+        let (sender, _receiver) = bounded(1);
+        sender.swap_policy(crate::OverflowPolicy::RejectNew);
+        sender.send_overwrite_copy(1);
+        assert_eq!(sender.send_overwrite_copy(2), SendStatus::Rejected);
+    }
+}
@@ -0,0 +1,140 @@
+//! Consumers whose lifetime is tied to a guard.
+
+use crate::OverwriteReceiver;
+use flume::Selector;
+use std::fmt;
+use std::thread::{self, JoinHandle};
+
+/// A guard for a consumer started with [`OverwriteReceiver::subscribe_scoped`].
+///
+/// Dropping the guard stops the consumer: a message being handled is handled to completion,
+/// no further messages are taken, and the consumer's receiver handle is released. The drop
+/// blocks until the consumer thread has exited, unless it happens on that thread itself.
+#[must_use = "dropping the subscription immediately stops the consumer"]
+pub struct Subscription {
+    stop: Option<flume::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Subscription {
+    pub(crate) fn spawn<T, F>(receiver: OverwriteReceiver<T>, mut f: F) -> Self
+    where
+        T: Send + 'static,
+        F: FnMut(T) + Send + 'static,
+    {
+        let (stop, stopped) = flume::bounded::<()>(0);
+        let handle = thread::spawn(move || {
+            // Stopping takes precedence over messages that are already queued
+            while let Some(value) = Selector::new()
+                .recv(&stopped, |_| None)
+                .recv(&receiver, |result| result.ok())
+                .wait()
+            {
+                f(value);
+            }
+        });
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+
+    /// Stops the consumer, waiting for it to exit.
+    ///
+    /// This is equivalent to dropping the guard.
+    pub fn unsubscribe(self) {}
+
+    /// Waits for the consumer to exit on its own, once the channel is disconnected and
+    /// drained or the handler panicked.
+    pub(crate) fn join(mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+
+    /// Returns `true` if the consumer has exited, either because the channel was
+    /// disconnected or because the handler panicked.
+    pub fn is_finished(&self) -> bool {
+        self.handle
+            .as_ref()
+            .is_none_or(|handle| handle.is_finished())
+    }
+}
+
+impl fmt::Debug for Subscription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscription")
+            .field("finished", &self.is_finished())
+            .finish()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(handle) = self.handle.take()
+            && handle.thread().id() != thread::current().id()
+        {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bounded;
+
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_subscription_stops_on_drop() {
+        let (sender, receiver) = bounded(4);
+        let (seen_tx, seen_rx) = flume::unbounded();
+        let subscription = receiver.subscribe_scoped(move |value| seen_tx.send(value).unwrap());
+        assert_eq!(sender.receiver_count(), 2);
+
+        sender.send_overwrite(1).unwrap();
+        assert_eq!(seen_rx.recv_timeout(Duration::from_secs(1)), Ok(1));
+
+        drop(subscription);
+        assert_eq!(sender.receiver_count(), 1);
+        sender.send_overwrite(2).unwrap();
+        assert_eq!(receiver.recv(), Ok(2));
+        // The handler, and the sender it owned, have been dropped
+        assert!(seen_rx.recv().is_err());
+    }
+
+    #[test]
+    fn test_subscription_finishes_on_disconnect() {
+        let (sender, receiver) = bounded(4);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let subscription = receiver.subscribe_scoped({
+            let seen = seen.clone();
+            move |value| seen.lock().unwrap().push(value)
+        });
+        drop(receiver);
+        sender.send_overwrite(1).unwrap();
+        sender.send_overwrite(2).unwrap();
+        drop(sender);
+        while !subscription.is_finished() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_subscription_dropped_from_handler() {
+        let (sender, receiver) = bounded(4);
+        let slot = Arc::new(Mutex::new(None));
+        let subscription = receiver.subscribe_scoped({
+            let slot = slot.clone();
+            move |()| drop(slot.lock().unwrap().take())
+        });
+        *slot.lock().unwrap() = Some(subscription);
+        sender.send_overwrite(()).unwrap();
+        while sender.receiver_count() > 1 {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Summaries of evicted messages, so lost data is not entirely lost.
+
+use std::collections::BTreeMap;
+
+/// Folds evicted messages into a [`Summary`], see
+/// [`Builder::aggregate_evictions`](crate::Builder::aggregate_evictions).
+///
+/// [`MinMaxMean`] covers numeric messages; implement this trait for other reductions.
+/// Messages are added under the channel's send lock, so an aggregator must not use the
+/// channel it summarizes.
+pub trait Aggregator<T>: Send {
+    /// Adds an evicted message to the aggregate.
+    fn add(&mut self, evicted: &T);
+
+    /// Returns the summary of the messages added so far.
+    fn summary(&self) -> Summary;
+}
+
+/// Named values summarizing evicted messages, as reported in
+/// [`Stats::evicted_summary`](crate::Stats::evicted_summary).
+///
+/// Values compare by their bit patterns, so a summary always equals itself, even when it
+/// holds a NaN.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary(BTreeMap<&'static str, f64>);
+
+impl Summary {
+    /// Creates an empty summary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the summary with `value` recorded under `name`.
+    pub fn with(mut self, name: &'static str, value: f64) -> Self {
+        self.0.insert(name, value);
+        self
+    }
+
+    /// Returns the value recorded under `name`, if any.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.0.get(name).copied()
+    }
+
+    /// Returns an iterator over the values, in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, f64)> + '_ {
+        self.0.iter().map(|(name, value)| (*name, *value))
+    }
+}
+
+impl PartialEq for Summary {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .iter()
+                .zip(other.iter())
+                .all(|((a, x), (b, y))| a == b && x.to_bits() == y.to_bits())
+    }
+}
+
+impl Eq for Summary {}
+
+/// An [`Aggregator`] of numeric messages, summarizing them as `count`, `min`, `max` and
+/// `mean`.
+///
+/// An empty aggregate only reports its `count`.
+#[derive(Clone, Debug, Default)]
+pub struct MinMaxMean {
+    count: u64,
+    min: f64,
+    max: f64,
+    sum: f64,
+}
+
+impl MinMaxMean {
+    /// Creates an empty aggregate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T> Aggregator<T> for MinMaxMean
+where
+    T: Copy + Into<f64>,
+{
+    fn add(&mut self, evicted: &T) {
+        let value = (*evicted).into();
+        if self.count == 0 {
+            (self.min, self.max) = (value, value);
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    fn summary(&self) -> Summary {
+        let summary = Summary::new().with("count", self.count as f64);
+        if self.count == 0 {
+            return summary;
+        }
+        summary
+            .with("min", self.min)
+            .with("max", self.max)
+            .with("mean", self.sum / self.count as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+
+    struct Last(Option<&'static str>);
+
+    impl Aggregator<&'static str> for Last {
+        fn add(&mut self, evicted: &&'static str) {
+            self.0 = Some(evicted);
+        }
+
+        fn summary(&self) -> Summary {
+            Summary::new().with("last_len", self.0.map_or(0, str::len) as f64)
+        }
+    }
+
+    #[test]
+    fn test_custom_aggregator_sees_every_eviction() {
+        let (sender, receiver) = Builder::new(1).aggregate_evictions(Last(None)).build();
+        assert_eq!(
+            sender.stats().evicted_summary.unwrap().get("last_len"),
+            Some(0.0)
+        );
+        for word in ["a", "bbb", "cc"] {
+            sender.send_overwrite(word).unwrap();
+        }
+        assert_eq!(
+            receiver.stats().evicted_summary.unwrap().get("last_len"),
+            Some(3.0)
+        );
+    }
+}
@@ -0,0 +1,368 @@
+//! Fault injection and connected channel pairs for testing overload handling and protocols.
+//!
+//! Available with the `testing` feature.
+
+use crate::{Builder, Clock, OverwriteReceiver, OverwriteSender, SendOverwriteError, SystemClock};
+use flume::TrySendError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A sender wrapper that injects faults according to a seeded random number generator.
+///
+/// `FaultyChannel<T>` forwards sends to an [`OverwriteSender<T>`], but before each send it
+/// may inject one of the following faults:
+///
+/// - **Disconnects**: the send fails with a `SendError` (or `TrySendError::Disconnected`)
+///   as if every receiver had gone away. The fault is transient and later sends may succeed.
+/// - **Artificial fullness**: the channel is treated as full. `send_overwrite` evicts the
+///   oldest queued message even if there was room, and `try_send` fails with
+///   `TrySendError::Full`.
+/// - **Delays**: the send is held back for a random duration up to a configured maximum.
+///
+/// Every decision is drawn from a generator seeded by the caller, so a given seed and
+/// sequence of calls always injects the same faults. All fault probabilities default to zero.
+///
+/// Delays sleep on the channel's [`Clock`], synchronously or asynchronously. Combined with a `MockClock` from the
+/// `test-util` feature, delays advance virtual time instead of stalling the test.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::bounded;
+/// use flume_overwrite::testing::FaultyChannel;
+///
+/// let (sender, receiver) = bounded(4);
+/// let faulty = FaultyChannel::new(sender, 42).disconnects(0.5);
+///
+/// let failed = (0..100).filter(|i| faulty.send_overwrite(*i).is_err()).count();
+/// assert_eq!(failed as u64, faulty.injected().disconnects);
+/// assert!(failed > 0 && failed < 100);
+/// # drop(receiver);
+/// ```
+pub struct FaultyChannel<T> {
+    sender: OverwriteSender<T>,
+    disconnect_probability: f64,
+    full_probability: f64,
+    delay_probability: f64,
+    max_delay: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<FaultState>,
+}
+
+/// Counts of the faults a [`FaultyChannel`] has injected so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InjectedFaults {
+    /// Sends failed as if the channel was disconnected.
+    pub disconnects: u64,
+    /// Sends that treated the channel as full.
+    pub fulls: u64,
+    /// Sends that were delayed.
+    pub delays: u64,
+}
+
+struct FaultState {
+    rng: SplitMix64,
+    injected: InjectedFaults,
+}
+
+/// The faults drawn for a single send.
+struct Draw {
+    disconnect: bool,
+    full: bool,
+    delay: Option<Duration>,
+}
+
+impl<T> FaultyChannel<T> {
+    /// Wraps `sender`, drawing faults from a generator seeded with `seed`.
+    pub fn new(sender: OverwriteSender<T>, seed: u64) -> Self {
+        Self {
+            sender,
+            disconnect_probability: 0.0,
+            full_probability: 0.0,
+            delay_probability: 0.0,
+            max_delay: Duration::ZERO,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(FaultState {
+                rng: SplitMix64(seed),
+                injected: InjectedFaults::default(),
+            }),
+        }
+    }
+
+    /// Sets the probability, between 0 and 1, of a send failing as if disconnected.
+    pub fn disconnects(mut self, probability: f64) -> Self {
+        self.disconnect_probability = probability;
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, of a send treating the channel as full.
+    pub fn fullness(mut self, probability: f64) -> Self {
+        self.full_probability = probability;
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, of a send being delayed by up to `max`.
+    pub fn delays(mut self, probability: f64, max: Duration) -> Self {
+        self.delay_probability = probability;
+        self.max_delay = max;
+        self
+    }
+
+    /// Sets the clock delays sleep on. Defaults to [`SystemClock`].
+    pub fn clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sends a value with overwrite semantics, subject to injected faults.
+    ///
+    /// Returns the same results as [`OverwriteSender::send_overwrite`].
+    pub fn send_overwrite(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let draw = self.draw();
+        if let Some(delay) = draw.delay {
+            self.clock.sleep(delay);
+        }
+        self.send_drawn(draw, value)
+    }
+
+    /// Asynchronously sends a value with overwrite semantics, subject to injected faults.
+    ///
+    /// Delays are awaited with [`Clock::sleep_async`] rather than blocking the thread.
+    pub async fn send_overwrite_async(
+        &self,
+        value: T,
+    ) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let draw = self.draw();
+        if let Some(delay) = draw.delay {
+            self.clock.sleep_async(delay).await;
+        }
+        self.send_drawn(draw, value)
+    }
+
+    /// Attempts to send a value without overwriting, subject to injected faults.
+    ///
+    /// Returns the same results as [`OverwriteSender::try_send`].
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        let draw = self.draw();
+        if let Some(delay) = draw.delay {
+            self.clock.sleep(delay);
+        }
+        if draw.disconnect {
+            Err(TrySendError::Disconnected(value))
+        } else if draw.full {
+            Err(TrySendError::Full(value))
+        } else {
+            self.sender.try_send(value)
+        }
+    }
+
+    /// Returns the faults injected so far.
+    pub fn injected(&self) -> InjectedFaults {
+        self.lock().injected
+    }
+
+    /// Returns the wrapped sender.
+    pub fn sender(&self) -> &OverwriteSender<T> {
+        &self.sender
+    }
+
+    /// Unwraps the faulty channel, returning the wrapped sender.
+    pub fn into_inner(self) -> OverwriteSender<T> {
+        self.sender
+    }
+
+    fn send_drawn(&self, draw: Draw, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        if draw.disconnect {
+            return Err(SendOverwriteError::Disconnected(value));
+        }
+        let mut drained: Vec<T> = if draw.full {
+            self.sender.evict_oldest().into_iter().collect()
+        } else {
+            Vec::new()
+        };
+        if let Some(overwritten) = self.sender.send_overwrite(value)? {
+            drained.extend(overwritten);
+        }
+        Ok(if drained.is_empty() {
+            None
+        } else {
+            Some(drained)
+        })
+    }
+
+    /// Draws every fault for one send, so the random sequence does not depend on which
+    /// faults are enabled.
+    fn draw(&self) -> Draw {
+        let mut state = self.lock();
+        let disconnect = state.rng.next_f64() < self.disconnect_probability;
+        let full = !disconnect && state.rng.next_f64() < self.full_probability;
+        let delayed = state.rng.next_f64() < self.delay_probability;
+        let delay = self.max_delay.mul_f64(state.rng.next_f64());
+        let delay = delayed.then_some(delay);
+        state.injected.disconnects += disconnect as u64;
+        state.injected.fulls += full as u64;
+        state.injected.delays += delayed as u64;
+        Draw {
+            disconnect,
+            full,
+            delay,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FaultState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<T> fmt::Debug for FaultyChannel<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultyChannel")
+            .field("sender", &self.sender)
+            .field("disconnect_probability", &self.disconnect_probability)
+            .field("full_probability", &self.full_probability)
+            .field("delay_probability", &self.delay_probability)
+            .field("max_delay", &self.max_delay)
+            .field("injected", &self.injected())
+            .finish()
+    }
+}
+
+/// Creates a bidirectional pair of overwrite channels of capacity `cap` connecting two
+/// components, `a` sending `T` to `b` and `b` sending `U` back.
+///
+/// Returns `(a_sender, b_sender, a_receiver, b_receiver)`: `a` keeps the first sender and
+/// the first receiver, and `b` the others. Both directions overwrite their oldest message
+/// when full, so a test can overload either side of a protocol the same way, and each
+/// channel is named after its direction to tell them apart in [`Stats`](crate::Stats).
+///
+/// # Panics
+///
+/// Panics if `cap` is zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::testing::loopback;
+///
+/// let (client, server, replies, requests) = loopback(2);
+///
+/// client.send_overwrite("ping").unwrap();
+/// assert_eq!(requests.recv().unwrap(), "ping");
+/// server.send_overwrite(4).unwrap();
+/// assert_eq!(replies.recv().unwrap(), 4);
+///
+/// assert_eq!(requests.stats().name.as_deref(), Some("loopback a->b"));
+/// ```
+#[track_caller]
+pub fn loopback<T, U>(
+    cap: usize,
+) -> (
+    OverwriteSender<T>,
+    OverwriteSender<U>,
+    OverwriteReceiver<U>,
+    OverwriteReceiver<T>,
+) {
+    let (a_sender, b_receiver) = Builder::new(cap).name("loopback a->b").build();
+    let (b_sender, a_receiver) = Builder::new(cap).name("loopback b->a").build();
+    (a_sender, b_sender, a_receiver, b_receiver)
+}
+
+/// A small, fast generator whose output is stable across releases, which keeps seeded
+/// fault sequences reproducible.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value uniformly distributed in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bounded;
+
+    use futures::executor::block_on;
+
+    fn outcomes(seed: u64) -> Vec<bool> {
+        let (sender, _receiver) = bounded(4);
+        let faulty = FaultyChannel::new(sender, seed).disconnects(0.3);
+        (0..64).map(|i| faulty.send_overwrite(i).is_ok()).collect()
+    }
+
+    #[test]
+    fn test_faults_are_deterministic_per_seed() {
+        assert_eq!(outcomes(7), outcomes(7));
+        assert_ne!(outcomes(7), outcomes(8));
+    }
+
+    #[test]
+    fn test_artificial_fullness_evicts() {
+        let (sender, receiver) = bounded(4);
+        let faulty = FaultyChannel::new(sender, 1).fullness(1.0);
+        assert_eq!(faulty.send_overwrite(1).unwrap(), None);
+        assert_eq!(faulty.send_overwrite(2).unwrap(), Some(vec![1]));
+        assert!(matches!(faulty.try_send(3), Err(TrySendError::Full(3))));
+        assert_eq!(faulty.injected().fulls, 3);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_disabled_faults_pass_through() {
+        let (sender, receiver) = bounded(2);
+        let faulty = FaultyChannel::new(sender, 3);
+        faulty.send_overwrite(1).unwrap();
+        block_on(faulty.send_overwrite_async(2)).unwrap();
+        faulty.try_send(3).unwrap_err();
+        assert_eq!(faulty.injected(), InjectedFaults::default());
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_loopback_directions_overwrite_independently() {
+        let (a, b, from_b, from_a) = loopback(1);
+        a.send_overwrite(1).unwrap();
+        assert_eq!(a.send_overwrite(2).unwrap(), Some(vec![1]));
+        b.send_overwrite("reply").unwrap();
+
+        assert_eq!(from_a.drain().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(from_b.recv().unwrap(), "reply");
+        assert_eq!(from_b.stats().evicted, 0);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_delays_on_mock_clock() {
+        use crate::MockClock;
+        let (sender, _receiver) = bounded(2);
+        let clock = MockClock::new();
+        let faulty = FaultyChannel::new(sender, 5)
+            .delays(1.0, Duration::from_secs(3600))
+            .clock(clock.clone());
+        faulty.send_overwrite(1).unwrap();
+        faulty.send_overwrite(2).unwrap();
+        assert_eq!(faulty.injected().delays, 2);
+        assert!(clock.elapsed() > Duration::ZERO);
+        assert!(clock.elapsed() <= Duration::from_secs(7200));
+    }
+
+    #[test]
+    fn test_delays() {
+        let (sender, receiver) = bounded(2);
+        let faulty = FaultyChannel::new(sender, 5).delays(1.0, Duration::from_millis(2));
+        block_on(faulty.send_overwrite_async(1)).unwrap();
+        faulty.send_overwrite(2).unwrap();
+        assert_eq!(faulty.injected().delays, 2);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), vec![1, 2]);
+    }
+}
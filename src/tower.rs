@@ -0,0 +1,527 @@
+//! A `tower` buffer layer that sheds the oldest requests instead of exerting backpressure.
+//!
+//! [`OverwriteBuffer`] plays the same role as `tower::buffer::Buffer`: it moves a service
+//! behind a queue so that it can be cloned and shared, with a [`Worker`] future driving the
+//! inner service. Unlike `tower::buffer`, the queue is an overwrite channel, so a full buffer
+//! never makes callers wait. The oldest queued request is shed instead and its caller
+//! receives a [`Dropped`] error. This suits best-effort RPC fan-in, where fresh requests
+//! matter more than complete ones.
+//!
+//! This module is only available with the `tower` feature.
+
+use crate::{OverwriteReceiver, OverwriteSender, Stats, bounded};
+use futures_core::Stream;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// The error type returned by an [`OverwriteBuffer`].
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// A type-erased [`Worker`], as handed to the spawn function of [`OverwriteBufferLayer`].
+pub type BoxWorker = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type Spawn = Arc<dyn Fn(BoxWorker) + Send + Sync>;
+
+/// A queued request, along with the channel its response future is sent back on.
+struct Message<Req, F> {
+    request: Req,
+    tx: flume::Sender<Result<F, BoxError>>,
+}
+
+/// The error returned to a caller whose request was shed to make room for a newer one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dropped;
+
+impl fmt::Display for Dropped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("request was dropped from a full overwrite buffer")
+    }
+}
+
+impl Error for Dropped {}
+
+/// The error returned once the [`Worker`] of an [`OverwriteBuffer`] has stopped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("overwrite buffer worker has stopped")
+    }
+}
+
+impl Error for Closed {}
+
+/// The error returned once the inner service of an [`OverwriteBuffer`] has failed.
+///
+/// A service whose `poll_ready` fails can no longer be used, so the error is shared by every
+/// request queued at that point and every request made afterwards.
+#[derive(Clone, Debug)]
+pub struct ServiceError(Arc<BoxError>);
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "buffered service failed: {}", self.0)
+    }
+}
+
+impl Error for ServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&**self.0)
+    }
+}
+
+/// The failure of the inner service, shared between the buffer handles and the worker.
+type Failure = Arc<Mutex<Option<ServiceError>>>;
+
+/// A cloneable handle to a service running behind an overwrite queue.
+///
+/// Calls are queued and processed in order by the associated [`Worker`]. When the queue is
+/// full, the oldest queued request is shed and its response future resolves to a [`Dropped`]
+/// error; `poll_ready` only fails once the worker has stopped or the service has failed.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::tower::{Dropped, OverwriteBuffer};
+/// use futures::executor::block_on;
+/// use std::future::{Ready, ready};
+/// use std::task::{Context, Poll};
+/// use tower_service::Service;
+///
+/// struct Double;
+///
+/// impl Service<u32> for Double {
+///     type Response = u32;
+///     type Error = Dropped;
+///     type Future = Ready<Result<u32, Dropped>>;
+///
+///     fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Dropped>> {
+///         Poll::Ready(Ok(()))
+///     }
+///
+///     fn call(&mut self, request: u32) -> Self::Future {
+///         ready(Ok(request * 2))
+///     }
+/// }
+///
+/// let (mut buffer, worker) = OverwriteBuffer::pair(Double, 1);
+///
+/// // The worker has not run yet, so the second request sheds the first
+/// let first = buffer.call(1);
+/// let second = buffer.call(2);
+///
+/// let worker = std::thread::spawn(move || block_on(worker));
+/// assert!(block_on(first).unwrap_err().is::<Dropped>());
+/// assert_eq!(block_on(second).unwrap(), 4);
+///
+/// drop(buffer);
+/// worker.join().unwrap();
+/// ```
+pub struct OverwriteBuffer<Req, F> {
+    sender: OverwriteSender<Message<Req, F>>,
+    failure: Failure,
+}
+
+impl<Req, F> OverwriteBuffer<Req, F>
+where
+    Req: Send + 'static,
+    F: Send + 'static,
+{
+    /// Creates a buffer around `service`, spawning its worker with `spawn`.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The service to move behind the buffer
+    /// * `capacity` - The maximum number of queued requests before the oldest is shed
+    /// * `spawn` - Runs the worker future to completion, e.g. `|worker| { tokio::spawn(worker); }`
+    pub fn new<S>(service: S, capacity: usize, spawn: impl FnOnce(BoxWorker)) -> Self
+    where
+        S: Service<Req, Future = F> + Send + 'static,
+        S::Error: Into<BoxError>,
+    {
+        let (buffer, worker) = Self::pair(service, capacity);
+        spawn(Box::pin(worker));
+        buffer
+    }
+
+    /// Creates a buffer around `service` along with the worker that must be run for requests
+    /// to be processed.
+    ///
+    /// # Arguments
+    ///
+    /// * `service` - The service to move behind the buffer
+    /// * `capacity` - The maximum number of queued requests before the oldest is shed
+    ///
+    /// # Returns
+    ///
+    /// A tuple containing the buffer and its worker. The worker completes once every buffer
+    /// handle has been dropped and the queue is empty, or once the service fails.
+    pub fn pair<S>(service: S, capacity: usize) -> (Self, Worker<S, Req>)
+    where
+        S: Service<Req, Future = F>,
+        S::Error: Into<BoxError>,
+    {
+        let (sender, receiver) = bounded(capacity);
+        let failure = Failure::default();
+        let worker = Worker {
+            service,
+            receiver,
+            failure: failure.clone(),
+        };
+        (Self { sender, failure }, worker)
+    }
+}
+
+impl<Req, F> OverwriteBuffer<Req, F> {
+    /// Returns a snapshot of the request queue, where `evicted` counts shed requests.
+    pub fn stats(&self) -> Stats {
+        self.sender.stats()
+    }
+
+    fn error(&self) -> BoxError {
+        match &*self.failure.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(error) => error.clone().into(),
+            None => Closed.into(),
+        }
+    }
+}
+
+impl<Req, F> Clone for OverwriteBuffer<Req, F> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            failure: self.failure.clone(),
+        }
+    }
+}
+
+impl<Req, F> fmt::Debug for OverwriteBuffer<Req, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverwriteBuffer")
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl<Req, F, Rsp, E> Service<Req> for OverwriteBuffer<Req, F>
+where
+    F: Future<Output = Result<Rsp, E>> + 'static,
+    E: Into<BoxError>,
+{
+    type Response = Rsp;
+    type Error = BoxError;
+    type Future = ResponseFuture<F>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        if self.sender.is_disconnected() {
+            Poll::Ready(Err(self.error()))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn call(&mut self, request: Req) -> ResponseFuture<F> {
+        let (tx, rx) = flume::bounded(1);
+        match self.sender.send_overwrite(Message { request, tx }) {
+            Ok(shed) => {
+                for message in shed.into_iter().flatten() {
+                    let _ = message.tx.send(Err(Dropped.into()));
+                }
+                ResponseFuture {
+                    state: State::Queued(rx.into_recv_async()),
+                }
+            }
+            Err(_) => ResponseFuture {
+                state: State::Failed(Some(self.error())),
+            },
+        }
+    }
+}
+
+/// The future driving the inner service of an [`OverwriteBuffer`].
+///
+/// Returned by [`OverwriteBuffer::pair`]; it must be spawned on an executor for the buffer to
+/// make progress.
+pub struct Worker<S: Service<Req>, Req> {
+    service: S,
+    receiver: OverwriteReceiver<Message<Req, S::Future>>,
+    failure: Failure,
+}
+
+impl<S: Service<Req>, Req> Worker<S, Req>
+where
+    S::Error: Into<BoxError>,
+{
+    /// Records the failure of the service and fails every queued request with it.
+    fn fail(&mut self, error: S::Error) {
+        let error = ServiceError(Arc::new(error.into()));
+        *self.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.clone());
+        for message in self.receiver.drain() {
+            let _ = message.tx.send(Err(error.clone().into()));
+        }
+    }
+}
+
+// The inner service is never pinned, only the receiver is, and that is `Unpin`.
+impl<S: Service<Req>, Req> Unpin for Worker<S, Req> {}
+
+impl<S, Req> Future for Worker<S, Req>
+where
+    S: Service<Req>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    Req: Send + 'static,
+{
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            // Requests stay in the queue, where they can be shed, until the service is ready.
+            match self.service.poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) => {
+                    self.fail(error);
+                    return Poll::Ready(());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+            match Pin::new(&mut self.receiver).poll_next(cx) {
+                Poll::Ready(Some(message)) => {
+                    // Skip requests whose caller has already given up on the response
+                    if !message.tx.is_disconnected() {
+                        let future = self.service.call(message.request);
+                        let _ = message.tx.send(Ok(future));
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S: Service<Req>, Req> fmt::Debug for Worker<S, Req> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker")
+            .field("receiver", &self.receiver)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The response future of an [`OverwriteBuffer`].
+pub struct ResponseFuture<F: 'static> {
+    state: State<F>,
+}
+
+enum State<F: 'static> {
+    /// Waiting for the worker to call the inner service.
+    Queued(flume::r#async::RecvFut<'static, Result<F, BoxError>>),
+    /// Waiting for the inner service to respond.
+    Called(Pin<Box<F>>),
+    /// Failed before the request was queued.
+    Failed(Option<BoxError>),
+}
+
+impl<F, Rsp, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Rsp, E>> + 'static,
+    E: Into<BoxError>,
+{
+    type Output = Result<Rsp, BoxError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        loop {
+            match &mut self.state {
+                State::Queued(rx) => match Pin::new(rx).poll(cx) {
+                    Poll::Ready(Ok(Ok(future))) => self.state = State::Called(Box::pin(future)),
+                    Poll::Ready(Ok(Err(error))) => return Poll::Ready(Err(error)),
+                    // The worker stopped before processing the request
+                    Poll::Ready(Err(_)) => return Poll::Ready(Err(Closed.into())),
+                    Poll::Pending => return Poll::Pending,
+                },
+                State::Called(future) => return future.as_mut().poll(cx).map_err(Into::into),
+                State::Failed(error) => {
+                    return Poll::Ready(Err(error.take().expect("polled after completion")));
+                }
+            }
+        }
+    }
+}
+
+impl<F: 'static> fmt::Debug for ResponseFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseFuture").finish_non_exhaustive()
+    }
+}
+
+/// A [`Layer`] wrapping services in an [`OverwriteBuffer`].
+///
+/// The layer is analogous to `tower::buffer::BufferLayer`, but as this crate does not depend
+/// on a runtime it is given a function spawning each buffer's worker.
+///
+/// # Examples
+///
+/// ```rust
+/// use flume_overwrite::tower::OverwriteBufferLayer;
+///
+/// let layer = OverwriteBufferLayer::<String>::new(64, |worker| {
+///     std::thread::spawn(move || futures::executor::block_on(worker));
+/// });
+/// ```
+pub struct OverwriteBufferLayer<Req> {
+    capacity: usize,
+    spawn: Spawn,
+    _request: PhantomData<fn(Req)>,
+}
+
+impl<Req> OverwriteBufferLayer<Req> {
+    /// Creates a layer whose buffers hold up to `capacity` requests.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of queued requests before the oldest is shed
+    /// * `spawn` - Runs each worker future to completion
+    pub fn new(capacity: usize, spawn: impl Fn(BoxWorker) + Send + Sync + 'static) -> Self {
+        Self {
+            capacity,
+            spawn: Arc::new(spawn),
+            _request: PhantomData,
+        }
+    }
+}
+
+impl<S, Req> Layer<S> for OverwriteBufferLayer<Req>
+where
+    S: Service<Req> + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    Req: Send + 'static,
+{
+    type Service = OverwriteBuffer<Req, S::Future>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        OverwriteBuffer::new(service, self.capacity, |worker| (self.spawn)(worker))
+    }
+}
+
+impl<Req> Clone for OverwriteBufferLayer<Req> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            spawn: self.spawn.clone(),
+            _request: PhantomData,
+        }
+    }
+}
+
+impl<Req> fmt::Debug for OverwriteBufferLayer<Req> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverwriteBufferLayer")
+            .field("capacity", &self.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::executor::block_on;
+    use futures::future::poll_fn;
+    use std::future::{Ready, ready};
+
+    /// Echoes requests back, failing `poll_ready` once `healthy` is cleared.
+    struct Echo {
+        healthy: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    impl Service<u32> for Echo {
+        type Response = u32;
+        type Error = BoxError;
+        type Future = Ready<Result<u32, BoxError>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+            if self.healthy.load(std::sync::atomic::Ordering::SeqCst) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Ready(Err("unhealthy".into()))
+            }
+        }
+
+        fn call(&mut self, request: u32) -> Self::Future {
+            ready(Ok(request))
+        }
+    }
+
+    fn echo() -> (Echo, Arc<std::sync::atomic::AtomicBool>) {
+        let healthy = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        (
+            Echo {
+                healthy: healthy.clone(),
+            },
+            healthy,
+        )
+    }
+
+    #[test]
+    fn test_buffer_sheds_oldest() {
+        let (service, _) = echo();
+        let (mut buffer, worker) = OverwriteBuffer::pair(service, 2);
+        let responses: Vec<_> = (0..5).map(|i| buffer.call(i)).collect();
+        assert_eq!(buffer.stats().evicted, 3);
+        drop(buffer);
+        block_on(worker);
+
+        let results: Vec<_> = responses.into_iter().map(block_on).collect();
+        for result in &results[..3] {
+            assert!(result.as_ref().unwrap_err().is::<Dropped>());
+        }
+        assert_eq!(results[3].as_ref().unwrap(), &3);
+        assert_eq!(results[4].as_ref().unwrap(), &4);
+    }
+
+    #[test]
+    fn test_buffer_closed_without_worker() {
+        let (service, _) = echo();
+        let (mut buffer, worker) = OverwriteBuffer::pair(service, 2);
+        let queued = buffer.call(1);
+        drop(worker);
+        assert!(block_on(queued).unwrap_err().is::<Closed>());
+        let ready = block_on(poll_fn(|cx| buffer.poll_ready(cx)));
+        assert!(ready.unwrap_err().is::<Closed>());
+        assert!(block_on(buffer.call(2)).unwrap_err().is::<Closed>());
+    }
+
+    #[test]
+    fn test_buffer_service_failure() {
+        let (service, healthy) = echo();
+        let (mut buffer, worker) = OverwriteBuffer::pair(service, 2);
+        let queued = buffer.call(1);
+        healthy.store(false, std::sync::atomic::Ordering::SeqCst);
+        block_on(worker);
+
+        let error = block_on(queued).unwrap_err();
+        assert!(error.is::<ServiceError>());
+        assert_eq!(error.to_string(), "buffered service failed: unhealthy");
+        let ready = block_on(poll_fn(|cx| buffer.poll_ready(cx)));
+        assert!(ready.unwrap_err().is::<ServiceError>());
+    }
+
+    #[test]
+    fn test_layer_spawns_worker() {
+        let layer = OverwriteBufferLayer::new(4, |worker| {
+            std::thread::spawn(move || block_on(worker));
+        });
+        let (service, _) = echo();
+        let mut buffer = layer.layer(service);
+        assert_eq!(block_on(buffer.call(7)).unwrap(), 7);
+    }
+}
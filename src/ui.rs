@@ -0,0 +1,226 @@
+//! An inbox for UI event loops, fed from any thread and drained once per frame.
+//!
+//! Immediate-mode GUI frameworks such as egui or iced redraw in frames, and only need the
+//! latest state of each widget, progress bar or status line when they do. A [`UiInbox`]
+//! conflates updates by key, so a background task reporting progress a thousand times
+//! between two frames leaves a single message behind, and wakes the UI loop through a
+//! callback, such as egui's `Context::request_repaint`, so it draws the update promptly.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use flume_overwrite::ui::UiInbox;
+//! use std::sync::Arc;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::thread;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Update {
+//!     Progress(&'static str, u32),
+//!     Status(&'static str),
+//! }
+//!
+//! let repaints = Arc::new(AtomicUsize::new(0));
+//! let requested = repaints.clone();
+//! let inbox = UiInbox::new(
+//!     16,
+//!     |update: &Update| match update {
+//!         Update::Progress(task, _) => Some(*task),
+//!         Update::Status(_) => None,
+//!     },
+//!     move || {
+//!         requested.fetch_add(1, Ordering::Relaxed);
+//!     },
+//! );
+//!
+//! let worker = inbox.clone();
+//! thread::spawn(move || {
+//!     for percent in 0..=100 {
+//!         worker.send_from_any_thread(Update::Progress("download", percent)).unwrap();
+//!     }
+//!     worker.send_from_any_thread(Update::Status("done")).unwrap();
+//! })
+//! .join()
+//! .unwrap();
+//!
+//! // One repaint was requested, and the frame only sees the latest progress
+//! assert_eq!(repaints.load(Ordering::Relaxed), 1);
+//! assert_eq!(
+//!     inbox.drain_on_frame(),
+//!     vec![Update::Progress("download", 100), Update::Status("done")],
+//! );
+//! ```
+
+use crate::{OverwriteReceiver, OverwriteSender, SendOverwriteError, bounded};
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+type SameKey<T> = dyn Fn(&T, &T) -> bool + Send + Sync;
+type Waker = dyn Fn() + Send + Sync;
+
+/// An inbox conflating updates by key and waking a UI loop to drain them, see the
+/// [module documentation](self).
+///
+/// Clones share the inbox, so one can be handed to every thread producing updates while
+/// the UI thread keeps another to drain it.
+pub struct UiInbox<T> {
+    sender: OverwriteSender<T>,
+    receiver: OverwriteReceiver<T>,
+    same_key: Arc<SameKey<T>>,
+    waker: Arc<Waker>,
+    wake_pending: Arc<AtomicBool>,
+}
+
+impl<T> UiInbox<T> {
+    /// Creates an inbox holding at most `cap` updates.
+    ///
+    /// The `key` function extracts the key of every update: an update replaces the pending
+    /// one with the same `Some` key, while updates keyed `None`, such as one-off
+    /// notifications, are never conflated and only overwritten when the inbox is full. The
+    /// `waker` is called when an update arrives in an inbox that was drained since the last
+    /// call, so the UI loop is woken once per frame however many updates arrive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cap` is zero.
+    #[track_caller]
+    pub fn new<K, F, W>(cap: usize, key: F, waker: W) -> Self
+    where
+        K: PartialEq,
+        F: Fn(&T) -> Option<K> + Send + Sync + 'static,
+        W: Fn() + Send + Sync + 'static,
+    {
+        let (sender, receiver) = bounded(cap);
+        Self {
+            sender,
+            receiver,
+            same_key: Arc::new(move |queued: &T, value: &T| {
+                key(value).is_some_and(|key_of_value| key(queued) == Some(key_of_value))
+            }),
+            waker: Arc::new(waker),
+            wake_pending: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Queues an update, replacing the pending update with the same key and waking the UI
+    /// loop if it has not been woken since it last drained the inbox.
+    ///
+    /// The update goes to the back of the inbox, so updates are drained in the order they
+    /// were last changed. Should the inbox still be full, its oldest update is overwritten.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` - The update was queued without replacing or overwriting anything
+    /// - `Ok(Some(Vec<T>))` - The update was queued and the returned vector contains the
+    ///   update it replaced, followed by any update overwritten to make room
+    ///
+    /// # Errors
+    ///
+    /// The inbox keeps its own receiver, so sends never fail as disconnected; the error is
+    /// kept for parity with [`OverwriteSender::send_overwrite`].
+    pub fn send_from_any_thread(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let replaced = self.send_conflated(value)?;
+        if !self.wake_pending.swap(true, Ordering::AcqRel) {
+            (self.waker)();
+        }
+        Ok(replaced)
+    }
+
+    /// Takes every pending update, oldest first, and rearms the waker.
+    ///
+    /// Meant to be called at the start of each frame. An update sent while the frame is
+    /// drawn wakes the UI loop again, so it is picked up by the next frame.
+    pub fn drain_on_frame(&self) -> Vec<T> {
+        // Rearmed before draining, so an update racing with the drain still wakes the loop
+        self.wake_pending.store(false, Ordering::Release);
+        self.receiver.drain().collect()
+    }
+
+    /// Returns the number of pending updates.
+    pub fn len(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Returns `true` if no update is pending.
+    pub fn is_empty(&self) -> bool {
+        self.receiver.is_empty()
+    }
+
+    fn send_conflated(&self, value: T) -> Result<Option<Vec<T>>, SendOverwriteError<T>> {
+        let sender = &self.sender;
+        let _sending = sender.shared.lock_sends();
+        let (mut replaced, kept): (Vec<T>, Vec<T>) = sender
+            .receiver
+            .drain()
+            .partition(|queued| (self.same_key)(queued, &value));
+        sender.shared.count_evictions(replaced.len());
+        sender.shared.count_classes(&replaced);
+        sender.requeue_locked(kept);
+        match sender.send_overwrite_locked(value)? {
+            Some(overwritten) => replaced.extend(overwritten),
+            None if replaced.is_empty() => return Ok(None),
+            None => (),
+        }
+        Ok(Some(replaced))
+    }
+}
+
+impl<T> Clone for UiInbox<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            same_key: self.same_key.clone(),
+            waker: self.waker.clone(),
+            wake_pending: self.wake_pending.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for UiInbox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UiInbox")
+            .field("pending", &self.len())
+            .field("wake_pending", &self.wake_pending.load(Ordering::Acquire))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_wakes_once_per_frame() {
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let count = wakes.clone();
+        let inbox = UiInbox::new(
+            2,
+            |(widget, _): &(u32, u32)| Some(*widget),
+            move || {
+                count.fetch_add(1, Ordering::Relaxed);
+            },
+        );
+
+        assert_eq!(inbox.send_from_any_thread((1, 10)).unwrap(), None);
+        assert_eq!(
+            inbox.send_from_any_thread((1, 11)).unwrap(),
+            Some(vec![(1, 10)])
+        );
+        inbox.send_from_any_thread((2, 20)).unwrap();
+        assert_eq!(wakes.load(Ordering::Relaxed), 1);
+
+        // A third widget overwrites the oldest update
+        assert_eq!(
+            inbox.send_from_any_thread((3, 30)).unwrap(),
+            Some(vec![(1, 11)])
+        );
+        assert_eq!(inbox.drain_on_frame(), vec![(2, 20), (3, 30)]);
+        assert_eq!(inbox.sender.stats().evicted, 2);
+
+        inbox.send_from_any_thread((2, 21)).unwrap();
+        assert_eq!(wakes.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,158 @@
+//! Distribution of the time receivers spend blocked.
+
+use crate::OverwriteReceiver;
+use flume::{RecvError, RecvTimeoutError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Power-of-two microsecond buckets: bucket `i` counts waits shorter than `2^i` µs and at
+/// least `2^(i - 1)` µs, the last one everything longer.
+const BUCKETS: usize = 40;
+
+/// The distribution of the time blocking receives waited for a message, as reported in
+/// [`Stats::recv_wait`](crate::Stats::recv_wait).
+///
+/// Percentiles are upper bounds, accurate to a factor of two. A consumer whose waits are
+/// long is faster than its producers, so a full queue comes from bursts; one that hardly
+/// ever waits is the bottleneck.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WaitTimes {
+    /// The number of blocking receives measured.
+    pub count: u64,
+    /// The median wait.
+    pub p50: Duration,
+    /// The 99th percentile wait.
+    pub p99: Duration,
+}
+
+/// A lock-free histogram of waits.
+pub(crate) struct WaitHistogram {
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl WaitHistogram {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    pub(crate) fn record(&self, wait: Duration) {
+        let micros = u64::try_from(wait.as_micros()).unwrap_or(u64::MAX);
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> WaitTimes {
+        let counts: Vec<u64> = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.load(Ordering::Relaxed))
+            .collect();
+        let count: u64 = counts.iter().sum();
+        let percentile = |q: u64| {
+            // The rank of the percentile, rounded up so that p99 of one wait is that wait
+            let rank = (count * q).div_ceil(100);
+            let mut seen = 0;
+            for (bucket, n) in counts.iter().enumerate() {
+                seen += n;
+                if seen >= rank && seen > 0 {
+                    return Duration::from_micros((1u64 << bucket).saturating_sub(1));
+                }
+            }
+            Duration::ZERO
+        };
+        WaitTimes {
+            count,
+            p50: percentile(50),
+            p99: percentile(99),
+        }
+    }
+}
+
+impl<T> OverwriteReceiver<T> {
+    /// Receives a message, blocking until one is available, and measures the wait.
+    ///
+    /// This behaves like flume's `recv`, which it shadows, except that it fails as
+    /// disconnected once the receiver is [retired](OverwriteReceiver::is_retired), and
+    /// records how long it waited
+    /// in [`Stats::recv_wait`](crate::Stats::recv_wait), on the channel's
+    /// [`Clock`](crate::Clock).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error once the channel is empty and every sender has been dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use flume_overwrite::bounded;
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, receiver) = bounded(4);
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(20));
+    ///     sender.send_overwrite("late").unwrap();
+    /// });
+    ///
+    /// assert_eq!(receiver.recv(), Ok("late"));
+    /// let waits = receiver.stats().recv_wait;
+    /// assert_eq!(waits.count, 1);
+    /// assert!(waits.p99 >= Duration::from_millis(10));
+    /// ```
+    pub fn recv(&self) -> Result<T, RecvError> {
+        self.measure_wait(|| self.live().ok_or(RecvError::Disconnected)?.recv())
+    }
+
+    /// Receives a message, blocking for at most `timeout`, and measures the wait.
+    ///
+    /// See [`recv`](OverwriteReceiver::recv). Timeouts are measured too.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        self.measure_wait(|| {
+            let receiver = self.live().ok_or(RecvTimeoutError::Disconnected)?;
+            receiver.recv_timeout(timeout)
+        })
+    }
+
+    /// Receives a message, blocking until `deadline` at the latest, and measures the wait.
+    ///
+    /// See [`recv`](OverwriteReceiver::recv). Timeouts are measured too.
+    pub fn recv_deadline(&self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.measure_wait(|| {
+            let receiver = self.live().ok_or(RecvTimeoutError::Disconnected)?;
+            receiver.recv_deadline(deadline)
+        })
+    }
+
+    fn measure_wait<R>(&self, recv: impl FnOnce() -> R) -> R {
+        let started = self.shared.clock.now();
+        let result = recv();
+        self.shared.observe_watermarks(&self.receiver);
+        let waited = self.shared.clock.now().saturating_duration_since(started);
+        self.shared.recv_waits.record(waited);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_bound_the_waits() {
+        let histogram = WaitHistogram::new();
+        assert_eq!(histogram.snapshot(), WaitTimes::default());
+        for _ in 0..98 {
+            histogram.record(Duration::from_micros(3));
+        }
+        histogram.record(Duration::from_millis(5));
+        histogram.record(Duration::from_millis(5));
+
+        let waits = histogram.snapshot();
+        assert_eq!(waits.count, 100);
+        assert_eq!(waits.p50, Duration::from_micros(3));
+        assert_eq!(waits.p99, Duration::from_micros(8191));
+    }
+}
@@ -0,0 +1,305 @@
+//! A single-task waker slot for the async fast paths.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(not(feature = "fast"))]
+use std::sync::{Mutex, TryLockError};
+use std::task::Waker;
+
+/// Nobody is touching the stored waker.
+const IDLE: usize = 0;
+/// A task is storing its waker.
+const REGISTERING: usize = 0b01;
+/// A sender is taking the stored waker to wake it.
+const WAKING: usize = 0b10;
+
+/// Storage for the waker of the one task awaiting a channel.
+///
+/// Registering and waking never wait on each other: an atomic state grants whoever moves
+/// it out of [`IDLE`] sole access to the stored waker, like `AtomicWaker` in
+/// `futures-util`. A wake arriving while a task registers is left for the registering task
+/// to deliver, and a task registering while a wake is under way wakes itself, so no wakeup
+/// is lost. A second task awaiting at the same time is turned away by
+/// [`register`](WakerSlot::register) and must wait through flume instead.
+pub(crate) struct WakerSlot {
+    state: AtomicUsize,
+    waker: Stored,
+}
+
+impl WakerSlot {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(IDLE),
+            waker: Stored::default(),
+        }
+    }
+
+    /// Registers `waker` to be woken by the next [`wake`](WakerSlot::wake), returning
+    /// `false` if the slot is taken by another task.
+    ///
+    /// Callers must check for a message again once registered, since a send made just
+    /// before may not have seen the waker.
+    pub(crate) fn register(&self, waker: &Waker) -> bool {
+        match self
+            .state
+            .compare_exchange(IDLE, REGISTERING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                let registered = self.waker.with(|slot| match slot {
+                    Some(registered) => registered.will_wake(waker),
+                    None => {
+                        *slot = Some(waker.clone());
+                        true
+                    }
+                });
+                self.release();
+                registered
+            }
+            Err(WAKING) => {
+                // The wake under way may not take this waker, so poll again right away
+                waker.wake_by_ref();
+                true
+            }
+            // Another task is registering
+            Err(_) => false,
+        }
+    }
+
+    /// Frees the slot if `waker` is still registered in it.
+    ///
+    /// This waits out a registration or wake under way, which only ever holds the slot for
+    /// a few instructions.
+    pub(crate) fn unregister(&self, waker: &Waker) {
+        while self
+            .state
+            .compare_exchange_weak(IDLE, REGISTERING, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+        self.waker.with(|slot| {
+            if slot
+                .as_ref()
+                .is_some_and(|registered| registered.will_wake(waker))
+            {
+                *slot = None;
+            }
+        });
+        self.release();
+    }
+
+    /// Wakes the registered task, if any, freeing the slot.
+    pub(crate) fn wake(&self) {
+        if self.state.fetch_or(WAKING, Ordering::AcqRel) != IDLE {
+            // A registering task delivers the wake, or another wake is under way
+            return;
+        }
+        let woken = self.waker.with(Option::take);
+        self.state.fetch_and(!WAKING, Ordering::Release);
+        if let Some(woken) = woken {
+            woken.wake();
+        }
+    }
+
+    /// Gives up sole access to the stored waker, delivering any wake that arrived meanwhile.
+    fn release(&self) {
+        if self
+            .state
+            .compare_exchange(REGISTERING, IDLE, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            return;
+        }
+        // The wake left the stored waker to be woken here
+        let woken = self.waker.with(Option::take);
+        self.state.store(IDLE, Ordering::Release);
+        if let Some(woken) = woken {
+            woken.wake();
+        }
+    }
+}
+
+/// The stored waker, only accessed by whoever holds [`WakerSlot`]'s state.
+#[cfg(not(feature = "fast"))]
+#[derive(Default)]
+struct Stored(Mutex<Option<Waker>>);
+
+#[cfg(not(feature = "fast"))]
+impl Stored {
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Waker>) -> R) -> R {
+        // The state grants sole access, so the lock is never contended
+        let mut waker = match self.0.try_lock() {
+            Ok(waker) => waker,
+            Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+            Err(TryLockError::WouldBlock) => unreachable!("the slot's state grants sole access"),
+        };
+        f(&mut waker)
+    }
+}
+
+/// The stored waker, only accessed by whoever holds [`WakerSlot`]'s state.
+#[cfg(feature = "fast")]
+#[derive(Default)]
+struct Stored(std::cell::UnsafeCell<Option<Waker>>);
+
+// SAFETY: the waker is only accessed by whoever moved the slot's state out of `IDLE`, so
+// never from two threads at once.
+#[cfg(feature = "fast")]
+unsafe impl Sync for Stored {}
+
+#[cfg(feature = "fast")]
+impl Stored {
+    fn with<R>(&self, f: impl FnOnce(&mut Option<Waker>) -> R) -> R {
+        // SAFETY: see the `Sync` implementation.
+        f(unsafe { &mut *self.0.get() })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::task::Wake;
+    use std::thread;
+
+    /// A waker counting how often it was woken.
+    #[derive(Default)]
+    struct Wakes(AtomicUsize);
+
+    impl Wake for Wakes {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl Wakes {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    fn counting_waker() -> (Arc<Wakes>, Waker) {
+        let wakes = Arc::new(Wakes::default());
+        (wakes.clone(), Waker::from(wakes))
+    }
+
+    #[test]
+    fn test_wake_frees_the_slot() {
+        let slot = WakerSlot::new();
+        let (wakes, waker) = counting_waker();
+        slot.wake();
+
+        assert!(slot.register(&waker));
+        // Registering again from the same task keeps its place
+        assert!(slot.register(&waker.clone()));
+        slot.wake();
+        slot.wake();
+        assert_eq!(wakes.count(), 1);
+
+        let (other_wakes, other) = counting_waker();
+        assert!(slot.register(&other));
+        slot.wake();
+        assert_eq!((wakes.count(), other_wakes.count()), (1, 1));
+    }
+
+    #[test]
+    fn test_slot_holds_a_single_task() {
+        let slot = WakerSlot::new();
+        let (wakes, waker) = counting_waker();
+        let (other_wakes, other) = counting_waker();
+        assert!(slot.register(&waker));
+        assert!(!slot.register(&other));
+
+        // Only the registered task can free the slot
+        slot.unregister(&other);
+        slot.wake();
+        assert_eq!((wakes.count(), other_wakes.count()), (1, 0));
+
+        assert!(slot.register(&waker));
+        slot.unregister(&waker);
+        assert!(slot.register(&other));
+        slot.wake();
+        assert_eq!((wakes.count(), other_wakes.count()), (1, 1));
+    }
+
+    #[test]
+    fn test_wake_during_registration_is_delivered() {
+        let slot = WakerSlot::new();
+        let (wakes, waker) = counting_waker();
+
+        // A task is midway through registering when a sender wakes the slot
+        slot.state.store(REGISTERING, Ordering::SeqCst);
+        slot.waker.with(|stored| *stored = Some(waker));
+        slot.wake();
+        assert_eq!(wakes.count(), 0);
+        slot.release();
+        assert_eq!(wakes.count(), 1);
+        assert_eq!(slot.state.load(Ordering::SeqCst), IDLE);
+        assert!(slot.waker.with(|stored| stored.is_none()));
+    }
+
+    #[test]
+    fn test_registration_during_wake_polls_again() {
+        let slot = WakerSlot::new();
+        let (wakes, waker) = counting_waker();
+
+        // A sender is midway through waking when the task registers
+        slot.state.store(WAKING, Ordering::SeqCst);
+        assert!(slot.register(&waker));
+        assert_eq!(wakes.count(), 1);
+
+        // Another task registering at the same time is turned away
+        slot.state.store(REGISTERING, Ordering::SeqCst);
+        assert!(!slot.register(&waker));
+        assert_eq!(wakes.count(), 1);
+    }
+
+    #[test]
+    fn test_racing_wakes_are_never_lost() {
+        for _ in 0..1000 {
+            let slot = Arc::new(WakerSlot::new());
+            let ready = Arc::new(AtomicBool::new(false));
+            let (wakes, waker) = counting_waker();
+
+            let sender = {
+                let (slot, ready) = (slot.clone(), ready.clone());
+                thread::spawn(move || {
+                    ready.store(true, Ordering::SeqCst);
+                    slot.wake();
+                })
+            };
+            // Like a receiver, register then check again before waiting
+            assert!(slot.register(&waker));
+            let seen = ready.load(Ordering::SeqCst);
+            sender.join().unwrap();
+            assert!(seen || wakes.count() == 1);
+            slot.unregister(&waker);
+        }
+    }
+
+    #[test]
+    fn test_unregister_races_with_wake() {
+        let slot = Arc::new(WakerSlot::new());
+        let (wakes, waker) = counting_waker();
+        let waking = {
+            let slot = slot.clone();
+            thread::spawn(move || {
+                for _ in 0..10_000 {
+                    slot.wake();
+                }
+            })
+        };
+        for _ in 0..10_000 {
+            if slot.register(&waker) {
+                slot.unregister(&waker);
+            }
+        }
+        waking.join().unwrap();
+
+        // However the calls interleaved, the slot ends up free and idle
+        assert_eq!(slot.state.load(Ordering::SeqCst), IDLE);
+        let (_, other) = counting_waker();
+        assert!(slot.register(&other));
+        assert!(wakes.count() <= 10_000);
+    }
+}
@@ -0,0 +1,101 @@
+//! Detection of consumers that stopped receiving.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+pub(crate) type StalledHook = Box<dyn Fn(Duration) + Send + Sync>;
+
+/// Tracks the last heartbeat of a channel's consumers.
+pub(crate) struct Watchdog {
+    last_heartbeat: Mutex<Instant>,
+    on_stalled: Option<(Duration, StalledHook)>,
+    /// Set once the hook has reported the current stall, until the next heartbeat.
+    reported: AtomicBool,
+}
+
+impl Watchdog {
+    /// Creates a watchdog whose first heartbeat is `now`, the channel's creation.
+    pub(crate) fn new(now: Instant, on_stalled: Option<(Duration, StalledHook)>) -> Self {
+        Self {
+            last_heartbeat: Mutex::new(now),
+            on_stalled,
+            reported: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn beat(&self, now: Instant) {
+        *self
+            .last_heartbeat
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = now;
+        self.reported.store(false, Ordering::Release);
+    }
+
+    pub(crate) fn since_heartbeat(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(
+            *self
+                .last_heartbeat
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        )
+    }
+
+    /// Returns how long the consumer has been silent, once per stall, if it has not sent a
+    /// heartbeat within the stall hook's threshold.
+    ///
+    /// The hook is left to the caller, see [`notify`](Watchdog::notify).
+    pub(crate) fn stalled(&self, now: Instant) -> Option<Duration> {
+        let (threshold, _) = self.on_stalled.as_ref()?;
+        let silence = self.since_heartbeat(now);
+        (silence >= *threshold && !self.reported.swap(true, Ordering::AcqRel)).then_some(silence)
+    }
+
+    pub(crate) fn notify(&self, silence: Duration) {
+        if let Some((_, hook)) = &self.on_stalled {
+            hook(silence);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_stall_is_reported_once_until_the_next_heartbeat() {
+        let start = Instant::now();
+        let threshold = Duration::from_secs(5);
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let seen = reports.clone();
+        let hook: StalledHook = Box::new(move |silence| seen.lock().unwrap().push(silence));
+        let watchdog = Watchdog::new(start, Some((threshold, hook)));
+
+        assert_eq!(watchdog.stalled(start + threshold / 2), None);
+        let silence = watchdog.stalled(start + threshold).unwrap();
+        assert_eq!(silence, threshold);
+        assert_eq!(watchdog.stalled(start + threshold * 2), None);
+        watchdog.notify(silence);
+        assert_eq!(*reports.lock().unwrap(), vec![threshold]);
+
+        // A heartbeat ends the stall, and the next one is reported anew
+        let beat = start + threshold * 3;
+        watchdog.beat(beat);
+        assert_eq!(watchdog.since_heartbeat(beat + threshold), threshold);
+        assert_eq!(watchdog.stalled(beat + threshold), Some(threshold));
+    }
+
+    #[test]
+    fn test_without_a_hook_nothing_stalls() {
+        let start = Instant::now();
+        let watchdog = Watchdog::new(start, None);
+        assert_eq!(watchdog.stalled(start + Duration::from_secs(3600)), None);
+        watchdog.notify(Duration::from_secs(1));
+        // Heartbeats from the past do not make the silence negative
+        assert_eq!(
+            watchdog.since_heartbeat(start - Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+}
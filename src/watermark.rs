@@ -0,0 +1,215 @@
+//! Cooperative backpressure from queue length watermarks.
+
+use crate::OverwriteSender;
+use std::future::poll_fn;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+pub(crate) type WatermarkHook = Box<dyn Fn(Watermark) + Send + Sync>;
+
+/// A watermark crossed by a channel's queue length, as reported to
+/// [`Builder::on_watermark`](crate::Builder::on_watermark).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watermark {
+    /// The queue reached the high watermark: producers should pause.
+    High,
+    /// The queue drained down to the low watermark: producers may resume.
+    Low,
+}
+
+/// Tracks which side of its watermarks a channel is on.
+///
+/// Crossings are observed lazily: on sends, on receives, on consumer heartbeats and
+/// whenever a producer checks whether it should pause. Producers waiting for the channel to
+/// resume are woken by the receive that drains it down to the low watermark.
+pub(crate) struct Watermarks {
+    low: usize,
+    high: usize,
+    hook: Option<WatermarkHook>,
+    paused: AtomicBool,
+    /// The tasks waiting for the channel to resume.
+    waiters: Mutex<Vec<Waker>>,
+    /// Signalled along with the waiting tasks, for threads waiting to resume.
+    resumed: Condvar,
+}
+
+impl Watermarks {
+    pub(crate) fn new(low: usize, high: usize, hook: Option<WatermarkHook>) -> Self {
+        Self {
+            low,
+            high,
+            hook,
+            paused: AtomicBool::new(false),
+            waiters: Mutex::new(Vec::new()),
+            resumed: Condvar::new(),
+        }
+    }
+
+    /// Records the queue's length, running the hook if it crossed a watermark, and returns
+    /// whether producers should pause.
+    pub(crate) fn observe(&self, len: usize) -> bool {
+        if let Some(crossed) = self.cross(len) {
+            self.notify(crossed);
+        }
+        self.is_paused()
+    }
+
+    /// Records the queue's length, returning the watermark it crossed without running the
+    /// hook, for callers holding the send lock.
+    pub(crate) fn cross(&self, len: usize) -> Option<Watermark> {
+        if len >= self.high {
+            (!self.paused.swap(true, Ordering::AcqRel)).then_some(Watermark::High)
+        } else if len <= self.low && self.paused.swap(false, Ordering::AcqRel) {
+            self.wake_waiters();
+            Some(Watermark::Low)
+        } else {
+            None
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    fn lock_waiters(&self) -> MutexGuard<'_, Vec<Waker>> {
+        self.waiters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wakes every producer waiting to resume.
+    ///
+    /// Waiters check whether the channel is paused while holding the lock on the waiting
+    /// tasks, so a producer that has just found it paused is already waiting by the time
+    /// the lock is taken here.
+    fn wake_waiters(&self) {
+        let waiters = std::mem::take(&mut *self.lock_waiters());
+        self.resumed.notify_all();
+        waiters.into_iter().for_each(Waker::wake);
+    }
+
+    pub(crate) fn notify(&self, crossed: Watermark) {
+        if let Some(hook) = &self.hook {
+            hook(crossed);
+        }
+    }
+}
+
+impl<T> OverwriteSender<T> {
+    /// Returns `true` if the queue has reached the high watermark and not yet drained down
+    /// to the low one, see [`Builder::watermarks`](crate::Builder::watermarks).
+    ///
+    /// Always `false` for channels without watermarks.
+    pub fn is_paused(&self) -> bool {
+        self.shared
+            .watermarks
+            .as_ref()
+            .is_some_and(|watermarks| watermarks.observe(self.sender.len()))
+    }
+
+    /// Blocks while the channel [is paused](OverwriteSender::is_paused), returning once the
+    /// queue has drained down to the low watermark.
+    ///
+    /// The producer is woken by the receive that drains the queue, or by a consumer
+    /// [heartbeat](crate::OverwriteReceiver::heartbeat): messages taken through flume's own
+    /// streams, reached by dereferencing the receiver, go unnoticed until then.
+    pub fn wait_resumed(&self) {
+        let Some(watermarks) = &self.shared.watermarks else {
+            return;
+        };
+        while self.is_paused() {
+            let waiters = watermarks.lock_waiters();
+            if watermarks.is_paused() {
+                drop(
+                    watermarks
+                        .resumed
+                        .wait(waiters)
+                        .unwrap_or_else(|e| e.into_inner()),
+                );
+            }
+        }
+    }
+
+    /// Waits asynchronously while the channel [is paused](OverwriteSender::is_paused).
+    ///
+    /// This is the async version of [`wait_resumed`](OverwriteSender::wait_resumed).
+    pub async fn resumed(&self) {
+        let Some(watermarks) = &self.shared.watermarks else {
+            return;
+        };
+        poll_fn(|cx| {
+            if !self.is_paused() {
+                return Poll::Ready(());
+            }
+            let mut waiters = watermarks.lock_waiters();
+            if !watermarks.is_paused() {
+                return Poll::Ready(());
+            }
+            if !waiters.iter().any(|waiter| waiter.will_wake(cx.waker())) {
+                waiters.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+    use futures::executor::block_on;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_resumes_once_drained() {
+        let crossings = Arc::new(Mutex::new(Vec::new()));
+        let sink = crossings.clone();
+        let (sender, receiver) = Builder::new(4)
+            .watermarks(1, 3)
+            .on_watermark(move |watermark| sink.lock().unwrap().push(watermark))
+            .build();
+        for i in 0..3 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert!(sender.is_paused());
+
+        let consumer = thread::spawn(move || {
+            while receiver.len() > 1 {
+                receiver.recv().unwrap();
+            }
+            receiver.heartbeat();
+        });
+        block_on(sender.resumed());
+        consumer.join().unwrap();
+        assert_eq!(
+            *crossings.lock().unwrap(),
+            vec![Watermark::High, Watermark::Low]
+        );
+    }
+
+    #[test]
+    fn test_draining_receive_wakes_waiting_producers() {
+        let (sender, receiver) = Builder::new(4).watermarks(1, 3).build();
+        for i in 0..3 {
+            sender.send_overwrite(i).unwrap();
+        }
+        assert!(sender.is_paused());
+
+        let waiting = sender.clone();
+        let blocked = thread::spawn(move || waiting.wait_resumed());
+        let waiting = sender.clone();
+        let awaiting = thread::spawn(move || block_on(waiting.resumed()));
+        thread::sleep(Duration::from_millis(20));
+        assert!(!blocked.is_finished() && !awaiting.is_finished());
+
+        // No heartbeat: the receive that reaches the low watermark wakes both producers
+        receiver.recv().unwrap();
+        assert!(sender.is_paused());
+        receiver.try_recv().unwrap();
+        blocked.join().unwrap();
+        awaiting.join().unwrap();
+        assert!(!sender.is_paused());
+    }
+}